@@ -0,0 +1,271 @@
+//! 访问控制：静态令牌、按用户派生的令牌与按令牌限流（来自 data.json 的 ServerConfig）
+
+use crate::config_file::{Config, ServerConfig, StaticToken, User};
+use axum::extract::{Request, State};
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// 限流窗口
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+
+/// 令牌对应的访问者
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenInfo {
+    pub name: String,
+    pub rate_limit_per_minute: u32,
+    pub banned: bool,
+    /// 令牌对应的用户，用于按用户生成配置
+    pub user: Option<String>,
+}
+
+/// 请求携带的令牌，鉴权通过后放入请求扩展，供生成代理地址时透传
+#[derive(Debug, Clone)]
+pub struct AccessToken {
+    pub token: String,
+    pub user: Option<String>,
+}
+
+impl AccessToken {
+    /// 透传到代理地址上的查询参数（TVBox 客户端无法设置请求头，只能放在 URL 中）
+    pub fn query(&self) -> String {
+        format!("token={}", urlencoding::encode(&self.token))
+    }
+
+    /// 放在其他查询参数之前的形式：`token=xxx&`，无令牌时为空
+    pub fn query_prefix(token: Option<&AccessToken>) -> String {
+        token
+            .map(|token| format!("{}&", token.query()))
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AccessError {
+    MissingToken,
+    InvalidToken,
+    Banned,
+    RateLimited { retry_after_secs: u64 },
+}
+
+impl AccessError {
+    pub fn message(&self) -> String {
+        match self {
+            AccessError::MissingToken => "Missing access token".to_string(),
+            AccessError::InvalidToken => "Invalid access token".to_string(),
+            AccessError::Banned => "User is banned".to_string(),
+            AccessError::RateLimited { retry_after_secs } => {
+                format!("Rate limit exceeded, retry after {}s", retry_after_secs)
+            }
+        }
+    }
+}
+
+struct Window {
+    started_at: Instant,
+    count: u32,
+}
+
+/// 访问控制；没有配置任何令牌时不启用鉴权
+#[derive(Default)]
+pub struct AccessControl {
+    tokens: HashMap<String, TokenInfo>,
+    windows: Mutex<HashMap<String, Window>>,
+}
+
+impl AccessControl {
+    pub fn from_config(config: &Config) -> Self {
+        Self::new(&config.server_config, &config.user_config.users)
+    }
+
+    pub fn new(server: &ServerConfig, users: &[User]) -> Self {
+        let mut tokens = HashMap::new();
+        for (index, token) in server.tokens.iter().enumerate() {
+            let (token, name, limit, user) = match token {
+                StaticToken::Plain(token) => (token, None, None, None),
+                StaticToken::Detailed {
+                    token,
+                    name,
+                    rate_limit_per_minute,
+                    user,
+                } => (token, name.clone(), *rate_limit_per_minute, user.clone()),
+            };
+            let token = token.trim();
+            if token.is_empty() {
+                continue;
+            }
+            tokens.insert(
+                token.to_string(),
+                TokenInfo {
+                    name: name.unwrap_or_else(|| format!("token-{}", index + 1)),
+                    rate_limit_per_minute: limit.unwrap_or(server.rate_limit_per_minute),
+                    banned: false,
+                    user,
+                },
+            );
+        }
+
+        if !server.token_secret.trim().is_empty() {
+            for user in users {
+                tokens.insert(
+                    derive_user_token(&server.token_secret, &user.username),
+                    TokenInfo {
+                        name: user.username.clone(),
+                        rate_limit_per_minute: server.rate_limit_per_minute,
+                        banned: user.banned,
+                        user: Some(user.username.clone()),
+                    },
+                );
+            }
+        }
+
+        Self {
+            tokens,
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.tokens.is_empty()
+    }
+
+    /// 校验令牌并计入限流
+    pub fn authorize(&self, token: Option<&str>) -> Result<&TokenInfo, AccessError> {
+        let token = token
+            .map(str::trim)
+            .filter(|token| !token.is_empty())
+            .ok_or(AccessError::MissingToken)?;
+        let info = self.tokens.get(token).ok_or(AccessError::InvalidToken)?;
+        if info.banned {
+            return Err(AccessError::Banned);
+        }
+        if info.rate_limit_per_minute == 0 {
+            return Ok(info);
+        }
+
+        let now = Instant::now();
+        let mut windows = self.windows.lock().unwrap();
+        let window = windows.entry(token.to_string()).or_insert(Window {
+            started_at: now,
+            count: 0,
+        });
+        let elapsed = now.saturating_duration_since(window.started_at);
+        if elapsed >= RATE_LIMIT_WINDOW {
+            window.started_at = now;
+            window.count = 0;
+        }
+        if window.count >= info.rate_limit_per_minute {
+            let retry_after = RATE_LIMIT_WINDOW.saturating_sub(elapsed).as_secs().max(1);
+            return Err(AccessError::RateLimited {
+                retry_after_secs: retry_after,
+            });
+        }
+        window.count += 1;
+        Ok(info)
+    }
+}
+
+/// 全部用户令牌（用户名、令牌、是否封禁），供管理员分发
+pub fn user_tokens(server: &ServerConfig, users: &[User]) -> Vec<(String, String, bool)> {
+    if server.token_secret.trim().is_empty() {
+        return Vec::new();
+    }
+    users
+        .iter()
+        .map(|user| {
+            (
+                user.username.clone(),
+                derive_user_token(&server.token_secret, &user.username),
+                user.banned,
+            )
+        })
+        .collect()
+}
+
+/// 由密钥与用户名派生用户令牌：以密钥为键的 HMAC-SHA256
+pub fn derive_user_token(secret: &str, username: &str) -> String {
+    hmac_sha256(secret.trim().as_bytes(), username.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// RFC 2104 HMAC，哈希函数为 SHA-256
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    const BLOCK_LEN: usize = 64;
+    let mut block = [0u8; BLOCK_LEN];
+    if key.len() > BLOCK_LEN {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let pad = |byte: u8| block.map(|b| b ^ byte);
+    let inner = Sha256::new()
+        .chain_update(pad(0x36))
+        .chain_update(message)
+        .finalize();
+    Sha256::new()
+        .chain_update(pad(0x5c))
+        .chain_update(inner)
+        .finalize()
+        .into()
+}
+
+/// 从查询参数 `token` 或 `Authorization: Bearer` 请求头中取令牌，查询参数优先
+pub fn extract_token(query: Option<&str>, authorization: Option<&str>) -> Option<String> {
+    let from_query = query.and_then(|query| {
+        url::form_urlencoded::parse(query.as_bytes())
+            .find(|(key, _)| key == "token")
+            .map(|(_, value)| value.into_owned())
+    });
+    from_query.or_else(|| {
+        authorization
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(|token| token.trim().to_string())
+    })
+}
+
+/// 鉴权中间件：启用访问控制时，除健康检查外的请求都需携带有效令牌
+pub async fn require_token(
+    State(access): State<Arc<AccessControl>>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    if !access.is_enabled() || request.uri().path() == "/" {
+        return next.run(request).await;
+    }
+
+    let authorization = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok());
+    let token = extract_token(request.uri().query(), authorization);
+    match access.authorize(token.as_deref()) {
+        Ok(info) => {
+            let user = info.user.clone();
+            request.extensions_mut().insert(AccessToken {
+                token: token.unwrap_or_default(),
+                user,
+            });
+            next.run(request).await
+        }
+        Err(error) => {
+            let status = match error {
+                AccessError::MissingToken | AccessError::InvalidToken => StatusCode::UNAUTHORIZED,
+                AccessError::Banned => StatusCode::FORBIDDEN,
+                AccessError::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            };
+            let mut response = (status, error.message()).into_response();
+            if let AccessError::RateLimited { retry_after_secs } = error {
+                response
+                    .headers_mut()
+                    .insert(header::RETRY_AFTER, HeaderValue::from(retry_after_secs));
+            }
+            response
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use quantumtv_core::{DnsSettings, HostOverrides, ProxySettings, RequestProfile, Subscription};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
@@ -19,14 +20,96 @@ pub struct Config {
     pub config_file: String,
     #[serde(rename = "ConfigSubscribtion")]
     pub config_subscribtion: ConfigSubscribtion,
+    /// 多个订阅，合并时按优先级去重；为空时使用 PARSES_URL
+    #[serde(rename = "Subscriptions", default)]
+    pub subscriptions: Vec<Subscription>,
     #[serde(rename = "CustomCategories")]
     pub custom_categories: Vec<serde_json::Value>,
     #[serde(rename = "SourceConfig")]
     pub source_config: Vec<SourceConfig>,
+    #[serde(rename = "LiveConfig", default)]
+    pub live_config: Vec<LiveConfig>,
     #[serde(rename = "UserConfig")]
     pub user_config: UserConfig,
     #[serde(rename = "UserPreferences")]
     pub user_preferences: UserPreferences,
+    #[serde(rename = "PlayerConfig", default)]
+    pub player_config: PlayerConfig,
+    #[serde(rename = "ServerConfig", default)]
+    pub server_config: ServerConfig,
+}
+
+/// 服务端访问控制配置，保存在 data.json 的 `ServerConfig` 字段中
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    /// 静态访问令牌
+    pub tokens: Vec<StaticToken>,
+    /// 为 UserConfig.Users 派生用户令牌的密钥，留空则不启用用户令牌
+    pub token_secret: String,
+    /// 允许的跨域来源，留空表示允许任意来源
+    pub cors_origins: Vec<String>,
+    /// 每个令牌每分钟的请求数上限，0 表示不限
+    pub rate_limit_per_minute: u32,
+    /// 视频分片缓存
+    pub segment_cache: SegmentCacheConfig,
+    /// 默认在服务端解密 AES-128 分片并删除播放列表中的密钥标签，可用 `decrypt` 参数按请求覆盖
+    pub decrypt_hls: bool,
+}
+
+/// 视频分片缓存配置：内存层与磁盘层分别按字节数限制
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SegmentCacheConfig {
+    /// 内存层上限（MB）
+    pub memory_mb: u64,
+    /// 磁盘层上限（MB），0 表示不使用磁盘缓存
+    pub disk_mb: u64,
+    /// 磁盘缓存目录
+    pub disk_dir: String,
+    /// 生成缓存键时忽略的查询参数（鉴权签名、过期时间等每次请求都会变化的参数）
+    pub volatile_params: Vec<String>,
+}
+
+impl Default for SegmentCacheConfig {
+    fn default() -> Self {
+        Self {
+            memory_mb: 256,
+            disk_mb: 2048,
+            disk_dir: ".cache/segments".to_string(),
+            volatile_params: [
+                "auth_key", "sign", "token", "expires", "wsSecret", "wsTime", "txSecret", "txTime",
+            ]
+            .into_iter()
+            .map(str::to_string)
+            .collect(),
+        }
+    }
+}
+
+/// 静态令牌，可直接写字符串，也可写对象以单独指定名称、限流与绑定的用户
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum StaticToken {
+    Plain(String),
+    Detailed {
+        token: String,
+        #[serde(default)]
+        name: Option<String>,
+        #[serde(default)]
+        rate_limit_per_minute: Option<u32>,
+        /// 绑定的用户名，按该用户的源可见性与成人内容策略生成配置
+        #[serde(default)]
+        user: Option<String>,
+    },
+}
+
+/// 播放器配置中与服务端相关的部分
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlayerConfig {
+    /// 是否允许代理局域网/本机地址
+    pub allow_lan_sources: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -48,19 +131,59 @@ pub struct SourceConfig {
     pub is_adult: bool,
     pub key: String,
     pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_profile: Option<RequestProfile>,
+    #[serde(default, skip_serializing_if = "HostOverrides::is_empty")]
+    pub hosts: HostOverrides,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// 直播源：M3U 或 TVBox TXT 频道列表
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LiveConfig {
+    pub key: String,
+    pub name: String,
+    pub url: String,
+    /// 拉取列表与检测频道时使用的 User-Agent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ua: Option<String>,
+    /// XMLTV 节目单地址，优先于列表头声明的
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub epg: Option<String>,
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct UserConfig {
     #[serde(rename = "Users")]
     pub users: Vec<User>,
+    #[serde(rename = "Tags", default)]
+    pub tags: Vec<UserTag>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct User {
+    #[serde(default)]
     pub banned: bool,
     pub role: String,
     pub username: String,
+    /// 可见的源 key，优先于 tags
+    #[serde(rename = "enabledApis", default)]
+    pub enabled_apis: Vec<String>,
+    /// 所属标签，多个标签的 enabledApis 取并集
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// 成人内容策略：false 始终过滤，true 默认开启，缺省时由请求参数 `adult` 决定
+    #[serde(rename = "allowAdult", default)]
+    pub allow_adult: Option<bool>,
+}
+
+/// 用户标签，为一组用户统一限制可见的源
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UserTag {
+    pub name: String,
+    #[serde(rename = "enabledApis", default)]
+    pub enabled_apis: Vec<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -80,6 +203,10 @@ pub struct UserPreferences {
     pub site_name: String,
     pub bangumi_proxy_type: String,
     pub bangumi_proxy_url: String,
+    #[serde(default)]
+    pub proxy: ProxySettings,
+    #[serde(default)]
+    pub dns: DnsSettings,
 }
 
 pub static PARSES_FILE: LazyLock<String> = LazyLock::new(|| {
@@ -104,6 +231,18 @@ pub async fn load_source_configs_from_file() -> Result<Vec<SourceConfig>, Box<dy
     Ok(parses.config.source_config)
 }
 
+/// 获取已启用的订阅
+pub async fn load_subscriptions_from_file() -> Result<Vec<Subscription>, Box<dyn std::error::Error>>
+{
+    let parses = load_parses_from_file().await?;
+    Ok(parses
+        .config
+        .subscriptions
+        .into_iter()
+        .filter(|subscription| subscription.enabled)
+        .collect())
+}
+
 /// 过滤 成人
 pub async fn filter_adult_source_configs() -> Result<Vec<SourceConfig>, Box<dyn std::error::Error>>
 {
@@ -1,4 +1,5 @@
-use quantumtv_core::is_adult_source;
+use crate::http_client::client_builder;
+use quantumtv_core::{is_adult_source, ProxyCategory, DEFAULT_USER_AGENT};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -21,6 +22,12 @@ pub struct Site {
     pub filterable: Option<i32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub changeable: Option<i32>,
+    /// 请求该站点时使用的请求头（对象或 JSON 字符串，原样透传）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header: Option<serde_json::Value>,
+    /// 合并多个订阅时记录站点来自哪个订阅
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subscription: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -98,6 +105,8 @@ fn convert_custom_format_to_tvbox(
             quick_search: Some(1),
             filterable: Some(1),
             changeable: None,
+            header: None,
+            subscription: None,
         });
     }
 
@@ -126,9 +135,9 @@ fn convert_custom_format_to_tvbox(
 pub async fn fetch_subscription(url: &str, adult: bool) -> Result<SubscriptionConfig, String> {
     tracing::info!("Fetching subscription from: {}", url);
 
-    let client = reqwest::Client::builder()
+    let client = client_builder(ProxyCategory::Sources)
         .timeout(Duration::from_secs(30))
-        .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
+        .user_agent(DEFAULT_USER_AGENT)
         .build()
         .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
 
@@ -0,0 +1,73 @@
+//! 出站 HTTP 客户端的网络设置（来自 data.json 的 UserPreferences.proxy 与 UserPreferences.dns）
+
+use crate::config_file::Config;
+use quantumtv_core::url_safety::{self, DEFAULT_MAX_REDIRECTS};
+use quantumtv_core::{DnsResolver, DnsSettings, ProxyCategory, ProxySettings};
+use std::sync::{Arc, LazyLock, RwLock};
+use url::Url;
+
+static PROXY_SETTINGS: RwLock<Option<ProxySettings>> = RwLock::new(None);
+static DNS_RESOLVER: LazyLock<Arc<DnsResolver>> = LazyLock::new(|| Arc::new(DnsResolver::new()));
+
+pub fn set_proxy_settings(settings: ProxySettings) -> Result<(), String> {
+    settings.validate()?;
+    DNS_RESOLVER.set_exempt_hosts(settings.proxy_hosts());
+    if let Ok(mut guard) = PROXY_SETTINGS.write() {
+        *guard = Some(settings);
+    }
+    Ok(())
+}
+
+pub fn proxy_settings() -> ProxySettings {
+    PROXY_SETTINGS
+        .read()
+        .ok()
+        .and_then(|guard| guard.clone())
+        .unwrap_or_default()
+}
+
+pub fn set_dns_settings(settings: DnsSettings) {
+    DNS_RESOLVER.configure(settings);
+}
+
+/// 应用配置文件中的代理、域名解析设置与各源的静态解析
+pub fn apply_network_settings(config: &Config) -> Result<(), String> {
+    let preferences = &config.user_preferences;
+    preferences.dns.validate()?;
+    set_proxy_settings(preferences.proxy.clone())?;
+    set_dns_settings(preferences.dns.clone());
+    DNS_RESOLVER.set_allow_private(config.player_config.allow_lan_sources);
+    for source in &config.source_config {
+        DNS_RESOLVER.set_source_hosts(&source.key, &source.hosts);
+    }
+    Ok(())
+}
+
+/// 校验外部传入的地址：协议、字面量与域名解析结果都不能指向局域网（除非开启 allow_lan_sources）
+pub async fn check_remote_url(url: &str) -> Result<Url, String> {
+    url_safety::check_remote_url(url, DNS_RESOLVER.allows_private(), &DNS_RESOLVER).await
+}
+
+/// 逐跳校验目标地址的重定向策略
+pub fn redirect_policy(max_redirects: usize) -> reqwest::redirect::Policy {
+    url_safety::redirect_policy(Arc::clone(&DNS_RESOLVER), max_redirects)
+}
+
+/// 按流量分类配置好代理与域名解析的客户端构建器，所有出站客户端都应由此创建
+///
+/// 源接口与媒体请求的地址来自外部，解析结果与每次重定向都按局域网设置检查
+pub fn client_builder(category: ProxyCategory) -> reqwest::ClientBuilder {
+    // 代理设置在 set_proxy_settings 中已校验，不会走到出错分支
+    let builder = proxy_settings()
+        .apply(reqwest::Client::builder(), category)
+        .unwrap_or_else(|e| {
+            tracing::error!("代理设置无效: {}", e);
+            reqwest::Client::builder()
+        });
+    if category == ProxyCategory::Metadata {
+        return builder.dns_resolver(DNS_RESOLVER.handle(false));
+    }
+    builder
+        .dns_resolver(DNS_RESOLVER.handle(true))
+        .redirect(redirect_policy(DEFAULT_MAX_REDIRECTS))
+}
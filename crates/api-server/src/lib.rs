@@ -1,2 +1,9 @@
+pub mod auth;
 pub mod config_file;
 pub mod config_url;
+pub mod http_client;
+pub mod live;
+pub mod segment_cache;
+pub mod segment_proxy;
+pub mod subscription;
+pub mod user_policy;
@@ -0,0 +1,227 @@
+//! 直播源：合并 data.json 中 LiveConfig 的频道列表，后台检测线路可用性，供 TVBox `lives` 与 M3U / TXT 导出使用
+
+use crate::config_file::LiveConfig;
+use crate::http_client::{check_remote_url, client_builder, redirect_policy};
+use futures_util::StreamExt;
+use quantumtv_core::{parse_live_list, LivePlaylist, ProxyCategory, DEFAULT_USER_AGENT};
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+const LIVE_LIST_TIMEOUT: Duration = Duration::from_secs(30);
+const STREAM_CHECK_TIMEOUT: Duration = Duration::from_secs(8);
+const STREAM_CHECK_CONCURRENCY: usize = 16;
+const MAX_REDIRECTS: usize = 5;
+
+/// 已启用的直播源与最近一次合并、检测后的频道列表
+pub struct LiveService {
+    sources: Vec<LiveConfig>,
+    playlist: RwLock<Arc<LivePlaylist>>,
+}
+
+impl LiveService {
+    pub fn new(sources: Vec<LiveConfig>) -> Self {
+        Self {
+            sources: sources
+                .into_iter()
+                .filter(|source| !source.disabled && !source.url.trim().is_empty())
+                .collect(),
+            playlist: RwLock::new(Arc::new(LivePlaylist::default())),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.sources.is_empty()
+    }
+
+    /// 合并去重、移除失效线路后的频道列表
+    pub fn playlist(&self) -> Arc<LivePlaylist> {
+        self.playlist
+            .read()
+            .map(|guard| guard.clone())
+            .unwrap_or_default()
+    }
+
+    /// 节目单地址：直播源手动指定的优先，其次是列表头声明的
+    pub fn epg_url(&self) -> Option<String> {
+        self.sources
+            .iter()
+            .filter_map(|source| source.epg.as_deref().map(str::trim))
+            .find(|epg| !epg.is_empty())
+            .map(str::to_string)
+            .or_else(|| self.playlist().epg_urls.first().cloned())
+    }
+
+    /// 重新拉取全部直播源并检测线路，返回可用频道数
+    ///
+    /// 尚无频道列表时先发布未检测的合并结果，检测完成后再替换为移除失效线路的列表；
+    /// 检测后没有任何可用频道时（通常是网络故障）保留上一次的结果
+    pub async fn refresh(&self) -> Result<usize, String> {
+        let mut merged = LivePlaylist::default();
+        let mut user_agents: HashMap<String, String> = HashMap::new();
+        for source in &self.sources {
+            match fetch_live_source(source).await {
+                Ok(playlist) => {
+                    if let Some(ua) = source.ua.as_deref().filter(|ua| !ua.trim().is_empty()) {
+                        for channel in playlist.groups.iter().flat_map(|g| g.channels.iter()) {
+                            for url in &channel.urls {
+                                user_agents.insert(url.clone(), ua.to_string());
+                            }
+                        }
+                    }
+                    merged.merge(playlist);
+                }
+                Err(e) => tracing::warn!("Live source {} not loaded: {}", source.name, e),
+            }
+        }
+        if merged.channel_count() == 0 {
+            return Err("没有可用的直播源".to_string());
+        }
+
+        let total = merged.channel_count();
+        if self.playlist().channel_count() == 0 {
+            if let Ok(mut guard) = self.playlist.write() {
+                *guard = Arc::new(merged.clone());
+            }
+        }
+        let client = client_builder(ProxyCategory::Media)
+            .timeout(STREAM_CHECK_TIMEOUT)
+            .redirect(redirect_policy(MAX_REDIRECTS))
+            .build()
+            .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+        let user_agents = Arc::new(user_agents);
+        remove_dead_urls(&mut merged, STREAM_CHECK_CONCURRENCY, |url| {
+            let client = client.clone();
+            let user_agents = user_agents.clone();
+            async move {
+                let ua = user_agents
+                    .get(&url)
+                    .map(String::as_str)
+                    .unwrap_or(DEFAULT_USER_AGENT);
+                check_stream(&client, &url, ua).await
+            }
+        })
+        .await;
+
+        let alive = merged.channel_count();
+        tracing::info!("Live channels checked: {}/{} alive", alive, total);
+        if alive == 0 {
+            return Err("直播频道全部检测失败，保留上次结果".to_string());
+        }
+        if let Ok(mut guard) = self.playlist.write() {
+            *guard = Arc::new(merged);
+        }
+        Ok(alive)
+    }
+
+    /// 启动后立即刷新一次，之后按间隔定期刷新
+    pub fn spawn_refresh_task(self: Arc<Self>, interval: Duration) {
+        if !self.is_enabled() {
+            return;
+        }
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.refresh().await {
+                    tracing::warn!("Live channel refresh failed: {}", e);
+                }
+            }
+        });
+    }
+}
+
+/// 拉取并解析一个直播源，手动指定的节目单地址排在最前
+pub async fn fetch_live_source(source: &LiveConfig) -> Result<LivePlaylist, String> {
+    check_remote_url(&source.url).await?;
+    let user_agent = source
+        .ua
+        .as_deref()
+        .filter(|ua| !ua.trim().is_empty())
+        .unwrap_or(DEFAULT_USER_AGENT);
+    let client = client_builder(ProxyCategory::Sources)
+        .timeout(LIVE_LIST_TIMEOUT)
+        .user_agent(user_agent)
+        .redirect(redirect_policy(MAX_REDIRECTS))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    let response = client
+        .get(&source.url)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch live list: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("HTTP error: {}", response.status()));
+    }
+    let text = response
+        .text()
+        .await
+        .map_err(|e| format!("Failed to read response: {}", e))?;
+
+    let mut playlist = parse_live_list(&text);
+    if let Some(epg) = source
+        .epg
+        .as_deref()
+        .map(str::trim)
+        .filter(|e| !e.is_empty())
+    {
+        playlist.epg_urls.retain(|url| url != epg);
+        playlist.epg_urls.insert(0, epg.to_string());
+    }
+    Ok(playlist)
+}
+
+/// 线路可用：地址允许访问、响应成功且能读到数据
+///
+/// 只检测 http(s) 线路，rtmp、rtsp、rtp、p2p 等协议无法用 HTTP 请求判断，原样保留
+pub async fn check_stream(client: &reqwest::Client, url: &str, user_agent: &str) -> bool {
+    let is_http = url::Url::parse(url)
+        .map(|parsed| matches!(parsed.scheme(), "http" | "https"))
+        .unwrap_or(false);
+    if !is_http {
+        return true;
+    }
+    if check_remote_url(url).await.is_err() {
+        return false;
+    }
+    match client
+        .get(url)
+        .header(reqwest::header::USER_AGENT, user_agent)
+        .send()
+        .await
+    {
+        Ok(mut response) if response.status().is_success() => {
+            matches!(response.chunk().await, Ok(Some(chunk)) if !chunk.is_empty())
+        }
+        _ => false,
+    }
+}
+
+/// 并发检测全部线路，移除失效的线路、频道与空分组
+pub async fn remove_dead_urls<F, Fut>(playlist: &mut LivePlaylist, concurrency: usize, check: F)
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = bool>,
+{
+    let mut urls: Vec<String> = Vec::new();
+    let mut seen = HashSet::new();
+    for channel in playlist.groups.iter().flat_map(|g| g.channels.iter()) {
+        for url in &channel.urls {
+            if seen.insert(url.clone()) {
+                urls.push(url.clone());
+            }
+        }
+    }
+
+    let alive: HashSet<String> = futures_util::stream::iter(urls)
+        .map(|url| {
+            let checked = check(url.clone());
+            async move { (url, checked.await) }
+        })
+        .buffer_unordered(concurrency.max(1))
+        .filter_map(|(url, ok)| async move { ok.then_some(url) })
+        .collect()
+        .await;
+    playlist.retain_urls(|url| alive.contains(url));
+}
@@ -1,28 +1,55 @@
-use axum::{routing::get, Router};
-use moka::future::Cache;
+use axum::http::HeaderValue;
+use axum::{middleware, routing::get, Router};
+use quantumtv_api::auth::{self, AccessControl};
+use quantumtv_api::config_file;
+use quantumtv_api::live::LiveService;
+use quantumtv_api::segment_cache::SegmentCache;
+use quantumtv_api::segment_proxy::prefetch_segment;
+use quantumtv_api::user_policy::UserPolicies;
+use quantumtv_core::{BufferMode, HlsKeyCache, SegmentPrefetcher, SegmentUnwrapStats};
 use std::net::{Ipv4Addr, SocketAddr};
 use std::sync::{Arc, LazyLock};
+use std::time::Duration;
 use tokio::sync::Mutex;
-use tower_http::cors::{Any, CorsLayer};
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
 mod tvbox;
 use crate::tvbox::{
-    get_config_handler, proxy_m3u8_handler, proxy_spider_jar_handler, proxy_ts_handler,
-    CachedSubscription, FailedSources, SpiderInfo,
+    get_config_handler, live_m3u_handler, live_txt_handler, new_subscription_cache,
+    proxy_m3u8_handler, proxy_spider_jar_handler, proxy_ts_handler, segment_cache_stats_handler,
+    FailedSources, SpiderInfo, SubscriptionCache,
 };
 
 static SERVER_IP: LazyLock<String> =
     LazyLock::new(|| std::env::var("SERVER_IP").unwrap_or_else(|_| "127.0.0.1".to_string()));
 
+/// 同时预取的分片数，所有客户端共用
+const PREFETCH_CONCURRENCY: usize = 4;
+
+/// 直播频道重新拉取与检测的间隔
+const LIVE_REFRESH_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
 static BIND_ADDR: LazyLock<String> =
     LazyLock::new(|| std::env::var("BIND_ADDR").unwrap_or_else(|_| "0.0.0.0".to_string()));
 
 #[derive(Clone)]
 struct AppState {
     spider_info: Arc<Mutex<SpiderInfo>>,
-    subscription_cache: Arc<Mutex<Option<CachedSubscription>>>,
+    subscription_cache: SubscriptionCache,
     failed_sources: Arc<Mutex<FailedSources>>,
-    // TS 视频片段缓存（URL -> 片段数据）
-    ts_cache: Cache<String, Vec<u8>>,
+    // TS 视频片段缓存（规范化 URL -> 片段数据）
+    ts_cache: Arc<SegmentCache>,
+    // 按用户的源可见性与成人内容策略
+    user_policies: Arc<UserPolicies>,
+    // 按分片主机统计的去伪装次数
+    unwrap_stats: Arc<SegmentUnwrapStats>,
+    // 按播放列表预取分片
+    prefetcher: Arc<SegmentPrefetcher>,
+    // AES-128 密钥缓存（密钥地址 -> 密钥）
+    hls_keys: Arc<HlsKeyCache>,
+    // 代理播放列表时是否默认解密分片
+    decrypt_hls: bool,
+    // 合并后的直播频道列表
+    live: Arc<LiveService>,
 }
 async fn health_check() -> &'static str {
     "QuantumTV API Server is running"
@@ -41,7 +68,91 @@ async fn main() {
         )
         .init();
 
-    // 2. 初始化应用状态
+    // 2. 读取配置文件：出站网络设置、访问控制与分片缓存
+    // 文件存在却读不出来时不能退回默认配置，否则访问控制会被关闭
+    let config = match config_file::load_parses_from_file().await {
+        Ok(parses) => Some(parses.config),
+        Err(e) if std::path::Path::new(&*config_file::PARSES_FILE).exists() => {
+            tracing::error!(
+                "Config file {} could not be loaded, refusing to start: {}",
+                &*config_file::PARSES_FILE,
+                e
+            );
+            std::process::exit(1);
+        }
+        Err(e) => {
+            tracing::warn!("Config file not loaded, using defaults: {}", e);
+            None
+        }
+    };
+    if let Some(config) = &config {
+        if let Err(e) = quantumtv_api::http_client::apply_network_settings(config) {
+            tracing::warn!("Network settings not applied, using defaults: {}", e);
+        }
+        if std::env::args().any(|arg| arg == "--print-user-tokens") {
+            for (username, token, banned) in
+                auth::user_tokens(&config.server_config, &config.user_config.users)
+            {
+                let note = if banned { " (banned)" } else { "" };
+                println!("{}\t{}{}", username, token, note);
+            }
+            return;
+        }
+    }
+    let access = config
+        .as_ref()
+        .map(AccessControl::from_config)
+        .unwrap_or_default();
+    if !access.is_enabled() {
+        tracing::warn!("No access tokens configured, the API server is open to anyone");
+    }
+    let access = Arc::new(access);
+    let cors_origins = config
+        .as_ref()
+        .map(|config| config.server_config.cors_origins.clone())
+        .unwrap_or_default();
+    let decrypt_hls = config
+        .as_ref()
+        .is_some_and(|config| config.server_config.decrypt_hls);
+    let user_policies = config
+        .as_ref()
+        .map(|config| UserPolicies::new(&config.user_config))
+        .unwrap_or_default();
+    let segment_cache = SegmentCache::new(
+        &config
+            .as_ref()
+            .map(|config| config.server_config.segment_cache.clone())
+            .unwrap_or_default(),
+    );
+
+    let live = Arc::new(LiveService::new(
+        config
+            .as_ref()
+            .map(|config| config.live_config.clone())
+            .unwrap_or_default(),
+    ));
+    live.clone().spawn_refresh_task(LIVE_REFRESH_INTERVAL);
+
+    let ts_cache = Arc::new(segment_cache);
+    let unwrap_stats = Arc::new(SegmentUnwrapStats::new());
+
+    // 按播放列表与实测带宽预取分片，写入分片缓存
+    let prefetcher = {
+        let cache = ts_cache.clone();
+        let stats = unwrap_stats.clone();
+        SegmentPrefetcher::new(PREFETCH_CONCURRENCY, move |url: String| {
+            let cache = cache.clone();
+            let stats = stats.clone();
+            async move { prefetch_segment(&cache, &stats, &url).await }
+        })
+    };
+    if let Some(config) = &config {
+        prefetcher.set_buffer_mode(BufferMode::from_preference(
+            &config.user_preferences.player_buffer_mode,
+        ));
+    }
+
+    // 3. 初始化应用状态
     let state = AppState {
         spider_info: Arc::new(Mutex::new(SpiderInfo {
             buffer: None,
@@ -53,37 +164,56 @@ async fn main() {
             size: 0,
             tried: 0,
         })),
-        subscription_cache: Arc::new(Mutex::new(None)),
+        subscription_cache: new_subscription_cache(),
         failed_sources: Arc::new(Mutex::new(FailedSources {
             sources: std::collections::HashSet::new(),
             last_reset: std::time::SystemTime::now(),
         })),
-        // TS 片段缓存：最大 500 个片段，每个最大 10MB，总共约 5GB
-        ts_cache: Cache::builder()
-            .max_capacity(500)
-            .time_to_live(std::time::Duration::from_secs(3600)) // 1 小时过期
-            .build(),
+        // TS 片段缓存：内存层与磁盘层按字节数限制
+        ts_cache,
+        user_policies: Arc::new(user_policies),
+        unwrap_stats,
+        prefetcher: Arc::new(prefetcher),
+        hls_keys: Arc::new(HlsKeyCache::new()),
+        decrypt_hls,
+        live,
     };
 
-    // 3. 配置 CORS
+    // 4. 配置 CORS
+    let allow_origin = if cors_origins.is_empty() {
+        AllowOrigin::from(Any)
+    } else {
+        AllowOrigin::list(
+            cors_origins
+                .iter()
+                .filter_map(|origin| HeaderValue::from_str(origin.trim()).ok()),
+        )
+    };
     let cors = CorsLayer::new()
-        .allow_origin(Any)
+        .allow_origin(allow_origin)
         .allow_methods(Any)
         .allow_headers(Any);
 
-    // 4. 创建路由
+    // 5. 创建路由
     let app = Router::new()
         .route("/", get(health_check))
         // 注册配置接口路由
         .route("/api/tvbox", get(get_config_handler))
         // M3U8 代理路由（带广告过滤和 URL 重写）
         .route("/api/proxy/m3u8", get(proxy_m3u8_handler))
-        // TS 视频片段代理路由（带缓存加速）
+        // TS 视频片段代理路由（流式转发，支持 Range 与 HEAD，带缓存加速）
         .route("/api/proxy/ts", get(proxy_ts_handler))
+        // 分片缓存统计
+        .route("/api/cache/stats", get(segment_cache_stats_handler))
         // Spider JAR 代理路由
         .route("/api/proxy/spider.jar", get(proxy_spider_jar_handler))
+        // 合并去重、移除失效线路后的直播频道列表
+        .route("/api/live.m3u", get(live_m3u_handler))
+        .route("/api/live.txt", get(live_txt_handler))
+        // 鉴权与限流（健康检查除外），CORS 在外层以便预检请求无需令牌
+        .layer(middleware::from_fn_with_state(access, auth::require_token))
         .layer(cors)
-        // 5. 注入状态
+        // 6. 注入状态
         .with_state(state);
 
     let addr = SocketAddr::from((BIND_ADDR.parse::<Ipv4Addr>().unwrap(), 3000));
@@ -95,7 +225,11 @@ async fn main() {
     tracing::info!("QuantumTV API Server listening on {}", addr);
 
     // 启动服务器
-    axum::serve(listener, app)
-        .await
-        .expect("Failed to start server");
+    // 预取会话在无令牌时按来源地址区分
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .expect("Failed to start server");
 }
@@ -0,0 +1,354 @@
+//! 两级视频分片缓存：按字节数限制的内存层 + 按容量预算 LRU 淘汰的磁盘层
+
+use crate::config_file::SegmentCacheConfig;
+use crate::segment_proxy::{CachedSegment, DEFAULT_SEGMENT_CONTENT_TYPE};
+use axum::body::Bytes;
+use moka::future::Cache;
+use moka::notification::RemovalCause;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+const MB: u64 = 1024 * 1024;
+
+/// 内存层条目的存活时间
+const MEMORY_TTL: Duration = Duration::from_secs(3600);
+
+/// 磁盘缓存文件扩展名
+const SEGMENT_FILE_EXT: &str = "seg";
+
+/// 缓存统计
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SegmentCacheStats {
+    pub memory_hits: u64,
+    pub disk_hits: u64,
+    pub misses: u64,
+    pub memory_evictions: u64,
+    pub disk_evictions: u64,
+    pub memory_bytes: u64,
+    pub memory_entries: u64,
+    pub disk_bytes: u64,
+    pub disk_entries: u64,
+}
+
+#[derive(Default)]
+struct Counters {
+    memory_hits: AtomicU64,
+    disk_hits: AtomicU64,
+    misses: AtomicU64,
+    memory_evictions: AtomicU64,
+    disk_evictions: AtomicU64,
+}
+
+struct DiskEntry {
+    size: u64,
+    last_access: u64,
+}
+
+/// 磁盘层索引，`clock` 单调递增，用作最近访问时间
+#[derive(Default)]
+struct DiskIndex {
+    entries: HashMap<String, DiskEntry>,
+    total_bytes: u64,
+    clock: u64,
+}
+
+impl DiskIndex {
+    fn touch(&mut self, name: &str) -> bool {
+        self.clock += 1;
+        match self.entries.get_mut(name) {
+            Some(entry) => {
+                entry.last_access = self.clock;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn insert(&mut self, name: String, size: u64) {
+        self.clock += 1;
+        let entry = DiskEntry {
+            size,
+            last_access: self.clock,
+        };
+        if let Some(old) = self.entries.insert(name, entry) {
+            self.total_bytes -= old.size;
+        }
+        self.total_bytes += size;
+    }
+
+    fn remove(&mut self, name: &str) {
+        if let Some(old) = self.entries.remove(name) {
+            self.total_bytes -= old.size;
+        }
+    }
+
+    /// 超出预算时依次移出最久未访问的条目，返回需要删除的文件名
+    fn evict_to(&mut self, budget: u64) -> Vec<String> {
+        let mut evicted = Vec::new();
+        while self.total_bytes > budget {
+            let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_access)
+                .map(|(name, _)| name.clone())
+            else {
+                break;
+            };
+            self.remove(&oldest);
+            evicted.push(oldest);
+        }
+        evicted
+    }
+}
+
+struct DiskTier {
+    dir: PathBuf,
+    budget: u64,
+    index: Mutex<DiskIndex>,
+}
+
+pub struct SegmentCache {
+    memory: Cache<String, CachedSegment>,
+    disk: Option<DiskTier>,
+    volatile_params: HashSet<String>,
+    counters: Arc<Counters>,
+}
+
+impl SegmentCache {
+    /// 创建缓存；磁盘目录中已有的分片按修改时间恢复到索引中
+    pub fn new(config: &SegmentCacheConfig) -> Self {
+        let counters = Arc::new(Counters::default());
+        let eviction_counters = counters.clone();
+        let memory = Cache::builder()
+            .max_capacity(config.memory_mb.saturating_mul(MB))
+            .weigher(|_key: &String, segment: &CachedSegment| {
+                u32::try_from(segment.data.len()).unwrap_or(u32::MAX)
+            })
+            .time_to_live(MEMORY_TTL)
+            .eviction_listener(move |_key, _value, cause| {
+                if cause == RemovalCause::Size {
+                    eviction_counters
+                        .memory_evictions
+                        .fetch_add(1, Ordering::Relaxed);
+                }
+            })
+            .build();
+
+        let disk = (config.disk_mb > 0).then(|| {
+            let dir = PathBuf::from(&config.disk_dir);
+            if let Err(e) = std::fs::create_dir_all(&dir) {
+                tracing::warn!("创建分片缓存目录失败 {}: {}", dir.display(), e);
+            }
+            let tier = DiskTier {
+                index: Mutex::new(load_disk_index(&dir)),
+                dir,
+                budget: config.disk_mb.saturating_mul(MB),
+            };
+            tier.evict();
+            tier
+        });
+
+        Self {
+            memory,
+            disk,
+            volatile_params: config
+                .volatile_params
+                .iter()
+                .map(|param| param.trim().to_ascii_lowercase())
+                .filter(|param| !param.is_empty())
+                .collect(),
+            counters,
+        }
+    }
+
+    /// 规范化的缓存键：去掉片段标识与易变的鉴权参数，其余查询参数保持原有顺序
+    pub fn cache_key(&self, url: &str) -> String {
+        let Ok(mut parsed) = url::Url::parse(url) else {
+            return url.to_string();
+        };
+        parsed.set_fragment(None);
+        let kept: Vec<(String, String)> = parsed
+            .query_pairs()
+            .filter(|(key, _)| !self.volatile_params.contains(&key.to_ascii_lowercase()))
+            .map(|(key, value)| (key.into_owned(), value.into_owned()))
+            .collect();
+        if kept.is_empty() {
+            parsed.set_query(None);
+        } else {
+            parsed.query_pairs_mut().clear().extend_pairs(kept);
+        }
+        parsed.to_string()
+    }
+
+    /// 依次查内存层与磁盘层，磁盘命中的分片会放回内存层
+    pub async fn get(&self, url: &str) -> Option<CachedSegment> {
+        let key = self.cache_key(url);
+        if let Some(segment) = self.memory.get(&key).await {
+            self.counters.memory_hits.fetch_add(1, Ordering::Relaxed);
+            return Some(segment);
+        }
+        if let Some(segment) = self.read_disk(&key).await {
+            self.counters.disk_hits.fetch_add(1, Ordering::Relaxed);
+            self.memory.insert(key, segment.clone()).await;
+            return Some(segment);
+        }
+        self.counters.misses.fetch_add(1, Ordering::Relaxed);
+        None
+    }
+
+    /// 是否已缓存，不计入命中统计
+    pub fn contains(&self, url: &str) -> bool {
+        let key = self.cache_key(url);
+        self.memory.contains_key(&key)
+            || self.disk.as_ref().is_some_and(|disk| {
+                disk.index
+                    .lock()
+                    .unwrap()
+                    .entries
+                    .contains_key(&file_name(&key))
+            })
+    }
+
+    /// 同时写入内存层与磁盘层
+    pub async fn insert(&self, url: &str, segment: CachedSegment) {
+        let key = self.cache_key(url);
+        self.write_disk(&key, &segment).await;
+        self.memory.insert(key, segment).await;
+    }
+
+    pub async fn stats(&self) -> SegmentCacheStats {
+        self.memory.run_pending_tasks().await;
+        let (disk_bytes, disk_entries) = self
+            .disk
+            .as_ref()
+            .map(|disk| {
+                let index = disk.index.lock().unwrap();
+                (index.total_bytes, index.entries.len() as u64)
+            })
+            .unwrap_or_default();
+        SegmentCacheStats {
+            memory_hits: self.counters.memory_hits.load(Ordering::Relaxed),
+            disk_hits: self.counters.disk_hits.load(Ordering::Relaxed),
+            misses: self.counters.misses.load(Ordering::Relaxed),
+            memory_evictions: self.counters.memory_evictions.load(Ordering::Relaxed),
+            disk_evictions: self.counters.disk_evictions.load(Ordering::Relaxed),
+            memory_bytes: self.memory.weighted_size(),
+            memory_entries: self.memory.entry_count(),
+            disk_bytes,
+            disk_entries,
+        }
+    }
+
+    async fn read_disk(&self, key: &str) -> Option<CachedSegment> {
+        let disk = self.disk.as_ref()?;
+        let name = file_name(key);
+        if !disk.index.lock().unwrap().touch(&name) {
+            return None;
+        }
+        match tokio::fs::read(disk.dir.join(&name)).await {
+            Ok(raw) => decode_segment(raw),
+            Err(_) => {
+                disk.index.lock().unwrap().remove(&name);
+                None
+            }
+        }
+    }
+
+    async fn write_disk(&self, key: &str, segment: &CachedSegment) {
+        let Some(disk) = self.disk.as_ref() else {
+            return;
+        };
+        let name = file_name(key);
+        let path = disk.dir.join(&name);
+        let temp = path.with_extension("tmp");
+        let raw = encode_segment(segment);
+        let size = raw.len() as u64;
+        if size > disk.budget {
+            return;
+        }
+
+        let written = async {
+            tokio::fs::write(&temp, &raw).await?;
+            tokio::fs::rename(&temp, &path).await
+        }
+        .await;
+        if let Err(e) = written {
+            tracing::warn!("写入分片缓存失败 {}: {}", path.display(), e);
+            let _ = tokio::fs::remove_file(&temp).await;
+            return;
+        }
+
+        disk.index.lock().unwrap().insert(name, size);
+        let evicted = disk.evict();
+        self.counters
+            .disk_evictions
+            .fetch_add(evicted, Ordering::Relaxed);
+    }
+}
+
+impl DiskTier {
+    /// 按预算淘汰最久未访问的分片，返回淘汰数量
+    fn evict(&self) -> u64 {
+        let evicted = self.index.lock().unwrap().evict_to(self.budget);
+        for name in &evicted {
+            let _ = std::fs::remove_file(self.dir.join(name));
+        }
+        evicted.len() as u64
+    }
+}
+
+fn file_name(key: &str) -> String {
+    format!("{:x}.{}", md5::compute(key), SEGMENT_FILE_EXT)
+}
+
+/// 磁盘格式：首行为 Content-Type，其后为分片内容
+fn encode_segment(segment: &CachedSegment) -> Vec<u8> {
+    let mut raw = Vec::with_capacity(segment.content_type.len() + 1 + segment.data.len());
+    raw.extend_from_slice(segment.content_type.as_bytes());
+    raw.push(b'\n');
+    raw.extend_from_slice(&segment.data);
+    raw
+}
+
+fn decode_segment(mut raw: Vec<u8>) -> Option<CachedSegment> {
+    let split = raw.iter().position(|byte| *byte == b'\n')?;
+    let data = raw.split_off(split + 1);
+    let content_type = String::from_utf8_lossy(&raw[..split]).trim().to_string();
+    Some(CachedSegment {
+        content_type: if content_type.is_empty() {
+            DEFAULT_SEGMENT_CONTENT_TYPE.to_string()
+        } else {
+            content_type
+        },
+        data: Bytes::from(data),
+    })
+}
+
+/// 扫描磁盘目录重建索引，按修改时间排列访问顺序
+fn load_disk_index(dir: &Path) -> DiskIndex {
+    let mut files: Vec<(String, u64, SystemTime)> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            if Path::new(&name).extension()? != SEGMENT_FILE_EXT {
+                return None;
+            }
+            let meta = entry.metadata().ok()?;
+            let modified = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            Some((name, meta.len(), modified))
+        })
+        .collect();
+    files.sort_by_key(|(_, _, modified)| *modified);
+
+    let mut index = DiskIndex::default();
+    for (name, size, _) in files {
+        index.insert(name, size);
+    }
+    index
+}
@@ -0,0 +1,234 @@
+//! 分片代理：Range 解析、透传的响应头、伪装分片识别、边转发边缓存的响应体、分片预取与解密
+
+use crate::http_client::{check_remote_url, client_builder};
+use crate::segment_cache::SegmentCache;
+use axum::body::{Body, Bytes};
+use axum::http::header::{self, HeaderName};
+pub use quantumtv_core::byte_range::{parse_range, ByteRange};
+use quantumtv_core::hls_crypto::AES_KEY_LEN;
+use quantumtv_core::segment_sniff::{
+    find_disguised_segment, has_image_header, DisguisedSegment, SNIFF_HEADER_LEN, SNIFF_LIMIT,
+};
+use quantumtv_core::{
+    decrypt_hls_segment, unwrap_disguised_segment, EncryptionMethod, HlsKeyCache, ProxyCategory,
+    SegmentUnwrapStats, DEFAULT_USER_AGENT,
+};
+use std::future::Future;
+use std::time::Duration;
+
+/// 超过该大小的分片只转发不缓存
+pub const MAX_CACHED_SEGMENT_BYTES: usize = 10 * 1024 * 1024;
+
+/// 上游未返回 Content-Type 时使用
+pub const DEFAULT_SEGMENT_CONTENT_TYPE: &str = "video/mp2t";
+
+/// 从上游响应原样透传给客户端的响应头
+pub const PASSTHROUGH_HEADERS: [HeaderName; 6] = [
+    header::CONTENT_TYPE,
+    header::CONTENT_LENGTH,
+    header::CONTENT_RANGE,
+    header::ACCEPT_RANGES,
+    header::LAST_MODIFIED,
+    header::ETAG,
+];
+
+/// 缓存的完整分片
+#[derive(Debug, Clone)]
+pub struct CachedSegment {
+    pub content_type: String,
+    pub data: Bytes,
+}
+
+/// 读取响应开头，识别伪装成图片的分片
+///
+/// 以图片文件头开始时继续读取，直到找到真实分片的起点或达到 [`SNIFF_LIMIT`]；
+/// 返回已读取的内容，转发时作为响应体的开头
+pub async fn sniff_segment(
+    response: &mut reqwest::Response,
+) -> Result<(Vec<u8>, Option<DisguisedSegment>), reqwest::Error> {
+    let mut head = Vec::new();
+    while head.len() < SNIFF_HEADER_LEN {
+        match response.chunk().await? {
+            Some(chunk) => head.extend_from_slice(&chunk),
+            None => break,
+        }
+    }
+    if !has_image_header(&head) {
+        return Ok((head, None));
+    }
+    loop {
+        if let Some(found) = find_disguised_segment(&head) {
+            return Ok((head, Some(found)));
+        }
+        if head.len() >= SNIFF_LIMIT {
+            return Ok((head, None));
+        }
+        match response.chunk().await? {
+            Some(chunk) => head.extend_from_slice(&chunk),
+            None => return Ok((head, None)),
+        }
+    }
+}
+
+/// 把上游响应体以流的形式转发给客户端，`prefix` 为已经读取的开头部分
+///
+/// 提供 `on_complete` 时同时收集内容，完整读完且不超过 [`MAX_CACHED_SEGMENT_BYTES`] 才回调；
+/// 客户端中途断开后仍会读完可缓存的分片，以便下次命中缓存
+pub fn stream_body<F, Fut>(
+    mut response: reqwest::Response,
+    prefix: Bytes,
+    on_complete: Option<F>,
+) -> Body
+where
+    F: FnOnce(Bytes) -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send,
+{
+    let (tx, rx) = tokio::sync::mpsc::channel::<Result<Bytes, std::io::Error>>(16);
+
+    tokio::spawn(async move {
+        let mut collected = on_complete.as_ref().map(|_| Vec::new());
+        let mut client_open = true;
+        let mut pending = (!prefix.is_empty()).then_some(prefix);
+        loop {
+            let next = match pending.take() {
+                Some(prefix) => Ok(Some(prefix)),
+                None => response.chunk().await,
+            };
+            match next {
+                Ok(Some(chunk)) => {
+                    let too_large = collected.as_ref().is_some_and(|buffer: &Vec<u8>| {
+                        buffer.len() + chunk.len() > MAX_CACHED_SEGMENT_BYTES
+                    });
+                    if too_large {
+                        collected = None;
+                    }
+                    if let Some(buffer) = collected.as_mut() {
+                        buffer.extend_from_slice(&chunk);
+                    }
+                    if client_open && tx.send(Ok(chunk)).await.is_err() {
+                        client_open = false;
+                    }
+                    if !client_open && collected.is_none() {
+                        return;
+                    }
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    if client_open {
+                        let _ = tx.send(Err(std::io::Error::other(e))).await;
+                    }
+                    return;
+                }
+            }
+        }
+        drop(tx);
+        if let (Some(data), Some(on_complete)) = (collected, on_complete) {
+            on_complete(Bytes::from(data)).await;
+        }
+    });
+
+    Body::from_stream(futures_util::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|item| (item, rx))
+    }))
+}
+
+/// 完整下载一个分片（或密钥）
+pub async fn download_segment(url: &str) -> Result<CachedSegment, String> {
+    let client = client_builder(ProxyCategory::Media)
+        .connect_timeout(Duration::from_secs(10))
+        .read_timeout(Duration::from_secs(30))
+        .user_agent(DEFAULT_USER_AGENT)
+        .build()
+        .map_err(|e| format!("Failed to create client: {}", e))?;
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch TS: {}", e))?;
+    if !response.status().is_success() {
+        return Err(format!("Upstream error: {}", response.status()));
+    }
+    let content_type = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or(DEFAULT_SEGMENT_CONTENT_TYPE)
+        .to_string();
+    let data = response
+        .bytes()
+        .await
+        .map_err(|e| format!("Failed to read response: {}", e))?;
+    Ok(CachedSegment { content_type, data })
+}
+
+/// 预取一个分片写入缓存，返回下载的字节数；已缓存时返回 0
+pub async fn prefetch_segment(
+    cache: &SegmentCache,
+    unwrap_stats: &SegmentUnwrapStats,
+    url: &str,
+) -> Result<u64, String> {
+    if cache.contains(url) {
+        return Ok(0);
+    }
+    check_remote_url(url).await?;
+
+    let segment = download_segment(url).await?;
+    let size = segment.data.len() as u64;
+    if segment.data.len() > MAX_CACHED_SEGMENT_BYTES {
+        return Ok(size);
+    }
+
+    let (data, disguised) = unwrap_disguised_segment(segment.data.to_vec());
+    let content_type = match disguised {
+        Some(found) => {
+            unwrap_stats.record(url, found.offset);
+            found.format.content_type().to_string()
+        }
+        None => segment.content_type,
+    };
+    cache
+        .insert(
+            url,
+            CachedSegment {
+                content_type,
+                data: Bytes::from(data),
+            },
+        )
+        .await;
+    Ok(size)
+}
+
+/// 取得加密分片的明文
+///
+/// 缓存中保存的是上游的密文（与预取共用），每次响应时解密；密钥按地址缓存
+pub async fn decrypt_segment(
+    cache: &SegmentCache,
+    keys: &HlsKeyCache,
+    method: EncryptionMethod,
+    url: &str,
+    key_uri: &str,
+    iv: &[u8; AES_KEY_LEN],
+) -> Result<CachedSegment, String> {
+    check_remote_url(key_uri).await?;
+    let key = keys
+        .get_or_fetch(key_uri, || async {
+            download_segment(key_uri).await.map(|key| key.data.to_vec())
+        })
+        .await?;
+
+    let encrypted = match cache.get(url).await {
+        Some(segment) => segment,
+        None => {
+            let segment = download_segment(url).await?;
+            if segment.data.len() <= MAX_CACHED_SEGMENT_BYTES {
+                cache.insert(url, segment.clone()).await;
+            }
+            segment
+        }
+    };
+    let data = decrypt_hls_segment(method, &encrypted.data, &key, iv)?;
+    Ok(CachedSegment {
+        content_type: encrypted.content_type,
+        data: Bytes::from(data),
+    })
+}
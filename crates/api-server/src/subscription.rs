@@ -0,0 +1,117 @@
+//! 多订阅：解析订阅列表，按优先级合并各订阅的 TVBox 配置
+
+use crate::config_url::{Site, SubscriptionConfig};
+use quantumtv_core::{merge_subscription_sources, normalize_api_url, stable_hash, Subscription};
+use std::collections::HashSet;
+use std::time::Duration;
+
+/// 未设置自动更新间隔的订阅缓存时长
+pub const DEFAULT_SUBSCRIPTION_TTL: Duration = Duration::from_secs(600);
+
+/// 解析逗号或换行分隔的订阅地址，靠前的优先级高；订阅标识由地址生成，不随顺序变化
+pub fn subscriptions_from_urls(urls: &str) -> Vec<Subscription> {
+    let urls: Vec<&str> = urls
+        .split([',', '\n'])
+        .map(str::trim)
+        .filter(|url| !url.is_empty())
+        .collect();
+    let count = urls.len() as i32;
+    urls.into_iter()
+        .enumerate()
+        .map(|(index, url)| Subscription {
+            key: format!("sub_{}", stable_hash(&normalize_api_url(url))),
+            name: url.to_string(),
+            url: url.to_string(),
+            priority: count - index as i32,
+            enabled: true,
+            update_interval_hours: 0,
+            last_update: 0,
+        })
+        .collect()
+}
+
+/// 订阅配置的缓存时长：设置了自动更新间隔时按间隔，否则 10 分钟
+pub fn subscription_ttl(subscription: &Subscription) -> Duration {
+    match subscription.update_interval_hours {
+        0 => DEFAULT_SUBSCRIPTION_TTL,
+        hours => Duration::from_secs(u64::from(hours) * 3600),
+    }
+}
+
+fn push_unique<T>(
+    target: &mut Vec<T>,
+    seen: &mut HashSet<String>,
+    items: Vec<T>,
+    id: impl Fn(&T) -> String,
+) {
+    for item in items {
+        if seen.insert(id(&item)) {
+            target.push(item);
+        }
+    }
+}
+
+/// 合并多个订阅的配置
+///
+/// 站点按接口地址去重，冲突时以优先级高的订阅为准并记录来源订阅；
+/// spider 取优先级最高的，其他订阅的站点未指定 jar 时写入各自订阅的 spider；
+/// 解析与直播按地址去重后依次追加
+pub fn merge_subscription_configs(
+    configs: Vec<(&Subscription, SubscriptionConfig)>,
+) -> SubscriptionConfig {
+    let mut configs: Vec<_> = configs.into_iter().filter(|(sub, _)| sub.enabled).collect();
+    configs.sort_by_key(|(sub, _)| std::cmp::Reverse(sub.priority));
+
+    let spider = configs.iter().find_map(|(_, config)| config.spider.clone());
+    let mut parses = Vec::new();
+    let mut lives = Vec::new();
+    let mut seen_parses = HashSet::new();
+    let mut seen_lives = HashSet::new();
+    let mut site_sets = Vec::new();
+
+    for (subscription, config) in configs {
+        push_unique(
+            &mut parses,
+            &mut seen_parses,
+            config.parses.unwrap_or_default(),
+            |parse| parse.url.clone(),
+        );
+        push_unique(
+            &mut lives,
+            &mut seen_lives,
+            config.lives.unwrap_or_default(),
+            |live| match live.get("url").and_then(|v| v.as_str()) {
+                Some(url) => normalize_api_url(url),
+                None => live.to_string(),
+            },
+        );
+        let sites = config
+            .sites
+            .unwrap_or_default()
+            .into_iter()
+            .map(|mut site| {
+                if site.jar.is_none() && config.spider != spider {
+                    site.jar = config.spider.clone();
+                }
+                if site.jar.is_some() && site.jar == spider {
+                    site.jar = None;
+                }
+                site
+            })
+            .filter_map(|site| serde_json::to_value(site).ok())
+            .collect();
+        site_sets.push((subscription, sites));
+    }
+
+    let sites: Vec<Site> = merge_subscription_sources(site_sets)
+        .into_iter()
+        .filter_map(|site| serde_json::from_value(site).ok())
+        .collect();
+
+    SubscriptionConfig {
+        spider,
+        sites: Some(sites),
+        parses: (!parses.is_empty()).then_some(parses),
+        lives: (!lives.is_empty()).then_some(lives),
+    }
+}
@@ -1,20 +1,39 @@
-use axum::extract::{Json, Query, State};
-use axum::http::{HeaderMap, StatusCode};
-use axum::response::IntoResponse;
+use axum::body::Bytes;
+use axum::extract::{ConnectInfo, Extension, Json, Query, State};
+use axum::http::{header, HeaderMap, HeaderValue, Method, StatusCode};
+use axum::response::{IntoResponse, Response};
 use base64::Engine;
-use quantumtv_core::is_adult_source;
+use moka::future::Cache;
+use moka::Expiry;
+use quantumtv_core::hls_crypto::{format_iv, parse_iv};
 use quantumtv_core::playback::filter_ads_from_m3_u8;
+use quantumtv_core::playlist::{rewrite_playlist_uris, PlaylistUriKind};
+use quantumtv_core::{
+    decryptable_segments, is_adult_source, segment_encryptions, strip_decryptable_keys,
+    EncryptionMethod, LivePlaylist, ProxyCategory, SegmentEncryption, Subscription,
+    DEFAULT_USER_AGENT, MOBILE_USER_AGENT,
+};
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::sync::LazyLock;
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, Instant, SystemTime};
 
 use crate::{AppState, SERVER_IP};
+use quantumtv_api::auth::AccessToken;
 use quantumtv_api::config_file;
 use quantumtv_api::config_url::{
     determine_site_type, fetch_subscription, Parse, Site, SubscriptionConfig,
 };
+use quantumtv_api::http_client::{check_remote_url, client_builder, redirect_policy};
+use quantumtv_api::segment_proxy::{
+    decrypt_segment, parse_range, sniff_segment, stream_body, CachedSegment,
+    DEFAULT_SEGMENT_CONTENT_TYPE, PASSTHROUGH_HEADERS,
+};
+use quantumtv_api::subscription::{
+    merge_subscription_configs, subscription_ttl, subscriptions_from_urls,
+};
 static PARSES_URL: LazyLock<String> = LazyLock::new(|| {
     std::env::var("PARSES_URL").unwrap_or_else(|_| "http://127.0.0.1".to_string())
 });
@@ -52,14 +71,57 @@ pub struct ConfigParams {
     force_spider_refresh: Option<String>,
     #[serde(rename = "subscriptionUrl")]
     subscription_url: Option<String>,
+    /// 按该用户的源可见性生成配置（令牌已绑定用户时忽略）
+    user: Option<String>,
 }
 
 #[derive(Clone, Debug)]
 pub struct CachedSubscription {
     pub config: SubscriptionConfig,
-    pub cached_at: SystemTime,
+    /// 该订阅的缓存时长
+    pub ttl: Duration,
+}
+
+/// 订阅配置缓存，按（订阅地址，是否包含成人源）区分
+pub type SubscriptionCache = Cache<(String, bool), CachedSubscription>;
+
+/// 订阅配置缓存的最大条目数
+const SUBSCRIPTION_CACHE_CAPACITY: u64 = 64;
+
+/// 订阅配置缓存条目按各自订阅的缓存时长过期
+struct SubscriptionExpiry;
+
+impl Expiry<(String, bool), CachedSubscription> for SubscriptionExpiry {
+    fn expire_after_create(
+        &self,
+        _key: &(String, bool),
+        value: &CachedSubscription,
+        _created_at: Instant,
+    ) -> Option<Duration> {
+        Some(value.ttl)
+    }
+
+    fn expire_after_update(
+        &self,
+        _key: &(String, bool),
+        value: &CachedSubscription,
+        _updated_at: Instant,
+        _duration_until_expiry: Option<Duration>,
+    ) -> Option<Duration> {
+        Some(value.ttl)
+    }
 }
 
+pub fn new_subscription_cache() -> SubscriptionCache {
+    Cache::builder()
+        .max_capacity(SUBSCRIPTION_CACHE_CAPACITY)
+        .expire_after(SubscriptionExpiry)
+        .build()
+}
+
+/// PARSES_FILE 中本地源合并时使用的订阅标识，优先级最高且不写入站点
+const LOCAL_SUBSCRIPTION_KEY: &str = "local";
+
 /// Spider JAR 磁盘缓存元数据
 #[derive(Serialize, Deserialize, Clone, Debug)]
 struct SpiderMetadata {
@@ -229,15 +291,6 @@ fn save_spider_to_disk(info: &SpiderInfo) -> Result<(), String> {
     Ok(())
 }
 
-fn is_private_host(url: &str) -> bool {
-    if url.starts_with("http") {
-        let lower = url.to_lowercase();
-        lower.contains("localhost") || lower.contains("127.") || lower.contains("10.")
-    } else {
-        true
-    }
-}
-
 fn header_value(headers: &HeaderMap, key: &str) -> Option<String> {
     headers
         .get(key)
@@ -309,9 +362,9 @@ async fn fetch_remote(url: &str, timeout_ms: u64, retry_count: usize) -> Option<
 }
 
 async fn fetch_remote_once(url: &str, timeout_ms: u64) -> Result<Vec<u8>, String> {
-    let client = reqwest::Client::builder()
+    let client = client_builder(ProxyCategory::Sources)
         .timeout(Duration::from_millis(timeout_ms))
-        .redirect(reqwest::redirect::Policy::limited(5))
+        .redirect(redirect_policy(5))
         .build()
         .map_err(|e| format!("Failed to create client: {}", e))?;
 
@@ -325,11 +378,11 @@ async fn fetch_remote_once(url: &str, timeout_ms: u64) -> Result<Vec<u8>, String
     let user_agent = if url.contains("github") || url.contains("raw.githubusercontent") {
         "curl/7.68.0"
     } else if url.contains("gitee") || url.contains("gitcode") {
-        "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36"
+        DEFAULT_USER_AGENT
     } else if url.contains("jsdelivr") || url.contains("fastly") {
         "DecoTV/1.0"
     } else {
-        "Mozilla/5.0 (Linux; Android 11; SM-G973F) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/91.0.4472.124 Mobile Safari/537.36"
+        MOBILE_USER_AGENT
     };
 
     headers.insert("User-Agent", user_agent.parse().unwrap());
@@ -505,42 +558,83 @@ async fn get_spider_jar(state: &AppState, force_refresh: bool) -> SpiderInfo {
     info
 }
 
-/// 获取缓存的订阅配置
+/// 获取单个订阅的配置，在该订阅的缓存时长内复用
+async fn cached_subscription_config(
+    state: &AppState,
+    subscription: &Subscription,
+    force_refresh: bool,
+    adult: bool,
+) -> Result<SubscriptionConfig, String> {
+    let cache_key = (subscription.url.clone(), adult);
+    if !force_refresh {
+        if let Some(cached) = state.subscription_cache.get(&cache_key).await {
+            return Ok(cached.config);
+        }
+    }
+
+    let config = fetch_subscription(&subscription.url, adult).await?;
+    state
+        .subscription_cache
+        .insert(
+            cache_key,
+            CachedSubscription {
+                config: config.clone(),
+                ttl: subscription_ttl(subscription),
+            },
+        )
+        .await;
+    Ok(config)
+}
+
+/// 获取合并后的订阅配置
+///
+/// 订阅列表依次取 `subscriptionUrl` 参数、data.json 的 `Subscriptions`、PARSES_URL；
+/// PARSES_FILE 存在时其中的源优先级最高
 async fn get_cached_subscription(
     state: &AppState,
-    url: &str,
+    subscription_url: Option<&str>,
     force_refresh: bool,
     adult: bool,
 ) -> Result<SubscriptionConfig, String> {
-    let mut cache = state.subscription_cache.lock().await;
-
-    // 检查缓存是否有效（10分钟）
-    let cache_valid = if let Some(cached) = cache.as_ref() {
-        SystemTime::now()
-            .duration_since(cached.cached_at)
-            .map(|d| d.as_secs() < 600)
-            .unwrap_or(false)
-    } else {
-        false
+    let file_mode = PathBuf::from(&*config_file::PARSES_FILE).exists();
+    let subscriptions = match subscription_url {
+        Some(urls) => subscriptions_from_urls(urls),
+        None if file_mode => config_file::load_subscriptions_from_file()
+            .await
+            .map_err(|e| format!("加载订阅列表失败: {}", e))?,
+        None => subscriptions_from_urls(&PARSES_URL),
     };
 
-    if force_refresh || !cache_valid {
-        // 优先检查 PARSES_FILE 是否存在
-        let parses_file_path = PathBuf::from(&*config_file::PARSES_FILE);
-        let config = if parses_file_path.exists() {
-            load_subscription_from_file(adult).await?
-        } else {
-            fetch_subscription(url, adult).await?
-        };
+    let local = Subscription {
+        key: LOCAL_SUBSCRIPTION_KEY.to_string(),
+        name: "本地源".to_string(),
+        url: String::new(),
+        priority: i32::MAX,
+        enabled: true,
+        update_interval_hours: 0,
+        last_update: 0,
+    };
+    let mut configs = Vec::new();
+    if file_mode {
+        configs.push((&local, load_subscription_from_file(adult).await?));
+    }
+    for subscription in subscriptions.iter().filter(|s| s.enabled) {
+        match cached_subscription_config(state, subscription, force_refresh, adult).await {
+            Ok(config) => configs.push((subscription, config)),
+            Err(e) => tracing::warn!("Subscription {} not loaded: {}", subscription.url, e),
+        }
+    }
+    if configs.is_empty() {
+        return Err("没有可用的订阅".to_string());
+    }
 
-        *cache = Some(CachedSubscription {
-            config: config.clone(),
-            cached_at: SystemTime::now(),
-        });
-        Ok(config)
-    } else {
-        Ok(cache.as_ref().unwrap().config.clone())
+    let mut config = merge_subscription_configs(configs);
+    for site in config.sites.iter_mut().flatten() {
+        if site.subscription.as_deref() == Some(LOCAL_SUBSCRIPTION_KEY) {
+            site.subscription = None;
+        }
     }
+    Ok(config)
 }
 
 /// 从文件加载配置
@@ -574,6 +668,10 @@ async fn load_subscription_from_file(adult: bool) -> Result<SubscriptionConfig,
                 quick_search: Some(1),
                 filterable: Some(1),
                 changeable: None,
+                header: sc
+                    .request_profile
+                    .and_then(|profile| serde_json::to_value(profile.to_tvbox_header()).ok()),
+                subscription: None,
             }
         })
         .collect();
@@ -614,6 +712,8 @@ fn get_default_config() -> SubscriptionConfig {
             quick_search: Some(1),
             filterable: Some(1),
             changeable: None,
+            header: None,
+            subscription: None,
         }]),
         parses: Some(vec![
             Parse {
@@ -636,22 +736,39 @@ fn get_default_config() -> SubscriptionConfig {
 pub async fn get_config_handler(
     Query(params): Query<ConfigParams>,
     State(state): State<AppState>,
+    token: Option<Extension<AccessToken>>,
     headers: HeaderMap,
 ) -> impl IntoResponse {
-    // 0. 构建 API 服务器地址（用于 M3U8 代理）
-
+    // 0. 构建 API 服务器地址（用于 M3U8 代理），启用鉴权时在代理地址中透传令牌
+    let token = token.map(|Extension(token)| token);
+    let policy = match state.user_policies.resolve(
+        token.as_ref().and_then(|token| token.user.as_deref()),
+        params.user.as_deref(),
+    ) {
+        Ok(policy) => policy,
+        Err(e) => {
+            return (
+                StatusCode::FORBIDDEN,
+                Json(serde_json::json!({ "error": e })),
+            )
+        }
+    };
     let api_base_url = resolve_base_url(&headers);
-    let m3u8_proxy_url = format!("{}/api/proxy/m3u8?url=", api_base_url);
+    let m3u8_proxy_url = format!(
+        "{}/api/proxy/m3u8?{}url=",
+        api_base_url,
+        AccessToken::query_prefix(token.as_ref())
+    );
 
     // 1. 获取订阅配置
-    let subscription_url = params
-        .subscription_url
-        .as_deref()
-        .unwrap_or(PARSES_URL.as_str());
+    let subscription_url = params.subscription_url.as_deref();
 
     let force_refresh = params.force_spider_refresh.as_deref() == Some("1");
-    // adult 参数，默认 false 过滤成人资源
-    let adult = params.adult.unwrap_or(false);
+    // adult 参数，默认 false 过滤成人资源；识别到用户时按其成人内容策略
+    let adult = match policy {
+        Some(policy) => policy.adult_enabled(params.adult),
+        None => params.adult.unwrap_or(false),
+    };
     let mut config =
         match get_cached_subscription(&state, subscription_url, force_refresh, adult).await {
             Ok(cfg) => cfg,
@@ -690,12 +807,16 @@ pub async fn get_config_handler(
     };
 
     // 构建 spider 字符串（使用代理 URL）
-    let spider_proxy_url = format!("{}/api/proxy/spider.jar", api_base_url);
+    let spider_query = token
+        .as_ref()
+        .map(|token| format!("?{}", token.query()))
+        .unwrap_or_default();
+    let spider_proxy_url = format!("{}/api/proxy/spider.jar{}", api_base_url, spider_query);
     let global_spider_jar = format!("{};md5;{}", spider_proxy_url, spider_info.md5);
 
     // 允许 URL 参数覆盖 Spider（仅当是公网地址时）
     let final_spider = if let Some(spider_url) = &params.spider {
-        if spider_url.starts_with("http") && !is_private_host(spider_url) {
+        if check_remote_url(spider_url).await.is_ok() {
             spider_url.clone()
         } else {
             global_spider_jar
@@ -711,6 +832,9 @@ pub async fn get_config_handler(
             sites.retain(|s| !is_adult_source(&s.api) && !is_adult_source(&s.name));
         }
     }
+    if let (Some(policy), Some(sites)) = (policy, config.sites.as_mut()) {
+        sites.retain(|s| policy.allows_site(&s.key));
+    }
 
     let mode = params
         .mode
@@ -730,40 +854,109 @@ pub async fn get_config_handler(
         },
     );
 
-    // 6. 组装响应（仅返回 TVBox 标准字段）
+    // 6. 直播：配置的直播源合并为一个频道列表排在最前（已有频道时才加入），订阅中的直播原样保留在后
+    let mut lives = Vec::new();
+    if state.live.is_enabled() && state.live.playlist().channel_count() > 0 {
+        let mut live = serde_json::json!({
+            "name": "直播",
+            "type": 0,
+            "url": format!("{}/api/live.txt{}", api_base_url, spider_query),
+            "playerType": 1,
+        });
+        if let Some(epg) = state.live.epg_url() {
+            live["epg"] = serde_json::Value::String(epg);
+        }
+        lives.push(live);
+    }
+    lives.extend(config.lives.unwrap_or_default());
+
+    // 7. 组装响应（仅返回 TVBox 标准字段）
     let response = serde_json::json!({
         "spider": final_spider,
         "sites": config.sites.unwrap_or_default(),
         "parses": parses,
-        "lives": config.lives.unwrap_or_default(),
+        "lives": lives,
     });
 
     tracing::info!(
-        "Config generated: spider_success={}, mode={}, adult={}, subscription_url={}",
+        "Config generated: spider_success={}, mode={}, adult={}, user={}, subscription_url={}",
         spider_info.success,
         mode,
         adult,
-        subscription_url
+        token
+            .as_ref()
+            .and_then(|token| token.user.as_deref())
+            .or(params.user.as_deref())
+            .unwrap_or("-"),
+        subscription_url.unwrap_or(PARSES_URL.as_str())
     );
 
     (StatusCode::OK, Json(response))
 }
 
+/// 导出合并后的直播频道列表（M3U）
+pub async fn live_m3u_handler(State(state): State<AppState>) -> Response {
+    live_export_response(
+        &state,
+        "audio/x-mpegurl; charset=utf-8",
+        LivePlaylist::to_m3u,
+    )
+}
+
+/// 导出合并后的直播频道列表（TVBox TXT）
+pub async fn live_txt_handler(State(state): State<AppState>) -> Response {
+    live_export_response(&state, "text/plain; charset=utf-8", LivePlaylist::to_txt)
+}
+
+fn live_export_response(
+    state: &AppState,
+    content_type: &'static str,
+    render: fn(&LivePlaylist) -> String,
+) -> Response {
+    if !state.live.is_enabled() {
+        return (StatusCode::NOT_FOUND, "未配置直播源").into_response();
+    }
+    let playlist = state.live.playlist();
+    if playlist.channel_count() == 0 {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            "直播频道检测中，请稍后再试",
+        )
+            .into_response();
+    }
+    (
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, content_type),
+            (header::CACHE_CONTROL, "public, max-age=300"),
+        ],
+        render(&playlist),
+    )
+        .into_response()
+}
+
 /// M3U8 代理处理器（带广告过滤）
 pub async fn proxy_m3u8_handler(
     Query(params): Query<std::collections::HashMap<String, String>>,
+    State(state): State<AppState>,
+    ConnectInfo(remote): ConnectInfo<SocketAddr>,
+    token: Option<Extension<AccessToken>>,
     headers: HeaderMap,
 ) -> impl IntoResponse {
     let url = match params.get("url") {
         Some(u) => u.clone(), // 克隆以避免生命周期问题
         None => return (StatusCode::BAD_REQUEST, "Missing url parameter".to_string()),
     };
+    if let Err(e) = check_remote_url(&url).await {
+        tracing::warn!("Rejected M3U8 proxy target {}: {}", url, e);
+        return (StatusCode::FORBIDDEN, e);
+    }
 
     tracing::info!("Proxying M3U8: {}", url);
 
-    let client = match reqwest::Client::builder()
+    let client = match client_builder(ProxyCategory::Media)
         .timeout(Duration::from_secs(30))
-        .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
+        .user_agent(DEFAULT_USER_AGENT)
         .build()
     {
         Ok(c) => c,
@@ -802,209 +995,333 @@ pub async fn proxy_m3u8_handler(
         }
     };
 
+    // 解密模式：AES-128 与 TS 的 SAMPLE-AES 分片由 TS 代理解密，播放列表中去掉对应的密钥标签
+    let decrypt = params
+        .get("decrypt")
+        .map(|value| value == "1" || value.eq_ignore_ascii_case("true"))
+        .unwrap_or(state.decrypt_hls);
+
     // 使用 core crate 中的广告过滤函数
     let filtered = filter_ads_from_m3_u8(&content);
 
-    // 重写 M3U8 中的 TS URL 为代理 URL
-    let api_base_url = resolve_base_url(&headers);
-    let proxy_base = format!("{}/api/proxy/ts?url=", api_base_url);
-    let rewritten = rewrite_m3u8_urls(&filtered, &url, &proxy_base);
-
-    // 后台并发预加载前几个 TS 片段（异步，不阻塞响应）
-    let rewritten_clone = rewritten.clone();
-    let url_clone = url.clone();
-    tokio::spawn(async move {
-        preload_ts_segments(&rewritten_clone, &url_clone).await;
-    });
-
-    (StatusCode::OK, rewritten)
-}
-
-/// 重写 M3U8 中的 TS URL 为代理 URL
-fn rewrite_m3u8_urls(m3u8_content: &str, base_url: &str, proxy_base: &str) -> String {
-    let mut result = String::new();
-
-    for line in m3u8_content.lines() {
-        let trimmed = line.trim();
-
-        // 跳过注释行和空行
-        if trimmed.starts_with('#') || trimmed.is_empty() {
-            result.push_str(line);
-            result.push('\n');
-            continue;
+    let encryptions: HashMap<String, SegmentEncryption> = if decrypt {
+        let encryptions: HashMap<String, SegmentEncryption> = decryptable_segments(&filtered, &url)
+            .into_iter()
+            .map(|segment| (segment.url.clone(), segment))
+            .collect();
+        let undecryptable = segment_encryptions(&filtered, &url).len() - encryptions.len();
+        if undecryptable > 0 {
+            tracing::warn!(
+                "fMP4 SAMPLE-AES and SAMPLE-AES-CTR are not decrypted, {} segments left to the player: {}",
+                undecryptable,
+                url
+            );
         }
+        encryptions
+    } else {
+        HashMap::new()
+    };
+    let playlist = if encryptions.is_empty() {
+        filtered.clone()
+    } else {
+        strip_decryptable_keys(&filtered)
+    };
 
-        // 处理 TS URL
-        if trimmed.ends_with(".ts") || trimmed.ends_with(".m3u8") {
-            // 解析为绝对 URL
-            let absolute_url = if trimmed.starts_with("http://") || trimmed.starts_with("https://")
-            {
-                trimmed.to_string()
-            } else {
-                // 相对 URL，需要基于 base_url 解析
-                resolve_relative_url(base_url, trimmed)
-            };
-
-            // 重写为代理 URL
-            let encoded_url = urlencoding::encode(&absolute_url);
-            result.push_str(&format!("{}{}", proxy_base, encoded_url));
-            result.push('\n');
-        } else {
-            result.push_str(line);
-            result.push('\n');
-        }
-    }
+    // 重写 M3U8 中的 TS URL 为代理 URL
+    let api_base_url = resolve_base_url(&headers);
+    let token = token.map(|Extension(token)| token);
+    let token_query = AccessToken::query_prefix(token.as_ref());
+    let decrypt_query = if decrypt { "decrypt=1&" } else { "" };
+    let playlist_proxy_base = format!(
+        "{}/api/proxy/m3u8?{}{}url=",
+        api_base_url, token_query, decrypt_query
+    );
+    let media_proxy_base = format!("{}/api/proxy/ts?{}url=", api_base_url, token_query);
+    let rewritten = rewrite_m3u8_urls(
+        &playlist,
+        &url,
+        &playlist_proxy_base,
+        &media_proxy_base,
+        &encryptions,
+    );
 
-    result
-}
+    // 按媒体播放列表预取播放位置之后的分片（主播放列表会被忽略）
+    let client = prefetch_client(token.as_ref(), remote);
+    state.prefetcher.register_playlist(&client, &url, &filtered);
 
-/// 解析相对 URL
-fn resolve_relative_url(base: &str, relative: &str) -> String {
-    if let Ok(base_url) = url::Url::parse(base) {
-        if let Ok(resolved) = base_url.join(relative) {
-            return resolved.to_string();
-        }
-    }
-    relative.to_string()
+    (StatusCode::OK, rewritten)
 }
 
-/// 并发预加载 TS 片段（前5个）
-async fn preload_ts_segments(m3u8_content: &str, _base_url: &str) {
-    let mut ts_urls = Vec::new();
-
-    for line in m3u8_content.lines() {
-        let trimmed = line.trim();
-        if trimmed.ends_with(".ts") {
-            // 提取原始 URL（从代理 URL 中解码）
-            if let Some(url_param) = trimmed
-                .strip_prefix("http://")
-                .and_then(|s| s.split("url=").nth(1))
-            {
-                if let Ok(decoded) = urlencoding::decode(url_param) {
-                    ts_urls.push(decoded.to_string());
-                    if ts_urls.len() >= 5 {
-                        break;
-                    }
-                }
+/// 重写播放列表中的全部 URI：嵌套播放列表经 M3U8 代理（逐级过滤广告），分片、密钥与初始化分片经 TS 代理
+///
+/// `encryptions` 中的分片附带密钥地址与 IV，由 TS 代理解密后返回
+fn rewrite_m3u8_urls(
+    m3u8_content: &str,
+    base_url: &str,
+    playlist_proxy_base: &str,
+    media_proxy_base: &str,
+    encryptions: &HashMap<String, SegmentEncryption>,
+) -> String {
+    rewrite_playlist_uris(m3u8_content, base_url, |url, kind| {
+        let proxy_base = match kind {
+            PlaylistUriKind::Playlist => playlist_proxy_base,
+            PlaylistUriKind::Media => media_proxy_base,
+        };
+        let mut proxied = format!("{}{}", proxy_base, urlencoding::encode(url));
+        if let Some(encryption) = encryptions.get(url) {
+            proxied.push_str(&format!(
+                "&key={}&iv={}",
+                urlencoding::encode(&encryption.key_uri),
+                format_iv(&encryption.iv)
+            ));
+            if encryption.method != EncryptionMethod::Aes128 {
+                proxied.push_str(&format!("&method={}", encryption.method.as_attribute()));
             }
         }
-    }
-
-    // 并发下载前5个片段
-    let tasks: Vec<_> = ts_urls
-        .into_iter()
-        .map(|url| {
-            tokio::spawn(async move {
-                if let Ok(client) = reqwest::Client::builder()
-                    .timeout(Duration::from_secs(10))
-                    .build()
-                {
-                    let _ = client.get(&url).send().await;
-                }
-            })
-        })
-        .collect();
+        proxied
+    })
+}
 
-    // 等待所有任务完成
-    for task in tasks {
-        let _ = task.await;
+/// 预取会话的客户端标识：有令牌时按令牌区分，否则按来源 IP
+fn prefetch_client(token: Option<&AccessToken>, remote: SocketAddr) -> String {
+    match token {
+        Some(token) if !token.token.is_empty() => format!("token:{}", token.token),
+        _ => format!("ip:{}", remote.ip()),
     }
 }
 
-/// TS 视频片段代理处理器（带缓存加速）
+/// TS 视频片段代理处理器：流式转发并透传 Range/206，支持 HEAD，完整分片写入缓存
 pub async fn proxy_ts_handler(
+    method: Method,
     Query(params): Query<std::collections::HashMap<String, String>>,
     State(state): State<AppState>,
-) -> impl IntoResponse {
+    ConnectInfo(remote): ConnectInfo<SocketAddr>,
+    token: Option<Extension<AccessToken>>,
+    request_headers: HeaderMap,
+) -> Response {
     let url = match params.get("url") {
         Some(u) => u,
-        None => {
-            return (
-                StatusCode::BAD_REQUEST,
-                axum::http::HeaderMap::new(),
-                "Missing url parameter".as_bytes().to_vec(),
-            )
-        }
+        None => return (StatusCode::BAD_REQUEST, "Missing url parameter").into_response(),
     };
+    if let Err(e) = check_remote_url(url).await {
+        tracing::warn!("Rejected TS proxy target {}: {}", url, e);
+        return (StatusCode::FORBIDDEN, e).into_response();
+    }
+    let range = request_headers
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+
+    // 更新播放位置，预取其后的分片
+    if method == Method::GET {
+        let token = token.map(|Extension(token)| token);
+        let client = prefetch_client(token.as_ref(), remote);
+        state.prefetcher.on_segment_request(&client, url);
+    }
+
+    // 解密模式的加密分片：整段下载（或取缓存的密文）解密后再按 Range 响应
+    if let Some(key_uri) = params.get("key") {
+        let Some(iv) = params.get("iv").and_then(|iv| parse_iv(iv)) else {
+            return (StatusCode::BAD_REQUEST, "Missing or invalid iv parameter").into_response();
+        };
+        let encryption_method = match params.get("method") {
+            Some(value) => match EncryptionMethod::from_attribute(value) {
+                Some(encryption_method) => encryption_method,
+                None => {
+                    return (StatusCode::BAD_REQUEST, "Invalid method parameter").into_response()
+                }
+            },
+            None => EncryptionMethod::Aes128,
+        };
+        let keys = &state.hls_keys;
+        return match decrypt_segment(&state.ts_cache, keys, encryption_method, url, key_uri, &iv)
+            .await
+        {
+            Ok(segment) => cached_segment_response(&method, &segment, range.as_deref()),
+            Err(e) => {
+                tracing::warn!("解密分片失败 {}: {}", url, e);
+                (StatusCode::BAD_GATEWAY, e).into_response()
+            }
+        };
+    }
 
     // 检查缓存
-    if let Some(cached_data) = state.ts_cache.get(url).await {
+    if let Some(segment) = state.ts_cache.get(url).await {
         tracing::debug!("TS 缓存: {}", url);
-        let mut headers = axum::http::HeaderMap::new();
-        headers.insert(
-            axum::http::header::CONTENT_TYPE,
-            "video/mp2t".parse().unwrap(),
-        );
-        headers.insert(
-            axum::http::header::CACHE_CONTROL,
-            "public, max-age=432000".parse().unwrap(),
-        );
-        return (StatusCode::OK, headers, cached_data);
+        return cached_segment_response(&method, &segment, range.as_deref());
     }
 
     tracing::debug!("TS cache miss, downloading: {}", url);
 
-    // 下载 TS 片段
-    let client = match reqwest::Client::builder()
-        .timeout(Duration::from_secs(30))
-        .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
+    // 大分片可能下载较久，只限制连接与读取间隔
+    let client = match client_builder(ProxyCategory::Media)
+        .connect_timeout(Duration::from_secs(10))
+        .read_timeout(Duration::from_secs(30))
+        .user_agent(DEFAULT_USER_AGENT)
         .build()
     {
         Ok(c) => c,
         Err(e) => {
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
-                axum::http::HeaderMap::new(),
-                format!("Failed to create client: {}", e).into_bytes(),
+                format!("Failed to create client: {}", e),
             )
+                .into_response()
         }
     };
 
-    let response = match client.get(url).send().await {
+    let mut upstream = client.request(method.clone(), url);
+    if let Some(range) = &range {
+        upstream = upstream.header(header::RANGE, range);
+    }
+    let response = match upstream.send().await {
         Ok(r) => r,
         Err(e) => {
             return (
                 StatusCode::BAD_GATEWAY,
-                axum::http::HeaderMap::new(),
-                format!("Failed to fetch TS: {}", e).into_bytes(),
+                format!("Failed to fetch TS: {}", e),
             )
+                .into_response()
         }
     };
 
-    if !response.status().is_success() {
+    let status = response.status();
+    if !status.is_success() && status != StatusCode::RANGE_NOT_SATISFIABLE {
         return (
             StatusCode::BAD_GATEWAY,
-            axum::http::HeaderMap::new(),
-            format!("Upstream error: {}", response.status()).into_bytes(),
-        );
+            format!("Upstream error: {}", status),
+        )
+            .into_response();
     }
 
-    let data = match response.bytes().await {
-        Ok(d) => d.to_vec(),
-        Err(e) => {
-            return (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                axum::http::HeaderMap::new(),
-                format!("Failed to read response: {}", e).into_bytes(),
-            )
+    let mut headers = HeaderMap::new();
+    for name in PASSTHROUGH_HEADERS {
+        if let Some(value) = response.headers().get(&name) {
+            headers.insert(name, value.clone());
         }
-    };
+    }
+    headers
+        .entry(header::CONTENT_TYPE)
+        .or_insert(HeaderValue::from_static(DEFAULT_SEGMENT_CONTENT_TYPE));
+    headers.insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_static("public, max-age=3600"),
+    );
+
+    if method == Method::HEAD {
+        return (status, headers).into_response();
+    }
+
+    // 完整响应先识别伪装成图片的分片，去掉图片前缀并改用真实的 Content-Type
+    let mut response = response;
+    let mut prefix = Bytes::new();
+    if status == StatusCode::OK {
+        let (head, disguised) = match sniff_segment(&mut response).await {
+            Ok(sniffed) => sniffed,
+            Err(e) => {
+                return (StatusCode::BAD_GATEWAY, format!("Failed to read TS: {}", e))
+                    .into_response()
+            }
+        };
+        prefix = Bytes::from(head);
+        if let Some(found) = disguised {
+            tracing::debug!(
+                "Unwrapped disguised segment ({} bytes prefix): {}",
+                found.offset,
+                url
+            );
+            state.unwrap_stats.record(url, found.offset);
+            prefix = prefix.slice(found.offset..);
+            headers.insert(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static(found.format.content_type()),
+            );
+            let length = headers
+                .get(header::CONTENT_LENGTH)
+                .and_then(|value| value.to_str().ok()?.parse::<usize>().ok())
+                .and_then(|length| length.checked_sub(found.offset));
+            match length {
+                Some(length) => {
+                    headers.insert(header::CONTENT_LENGTH, HeaderValue::from(length));
+                }
+                None => {
+                    headers.remove(header::CONTENT_LENGTH);
+                }
+            }
+        }
+    }
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or(DEFAULT_SEGMENT_CONTENT_TYPE)
+        .to_string();
+
+    // 只缓存完整响应（206 部分内容不缓存）
+    let on_complete = (status == StatusCode::OK).then(|| {
+        let cache = state.ts_cache.clone();
+        let url = url.clone();
+        move |data: Bytes| async move {
+            tracing::debug!("TS cached: {} ({} bytes)", url, data.len());
+            cache
+                .insert(&url, CachedSegment { content_type, data })
+                .await;
+        }
+    });
 
-    // 保存到缓存
-    state.ts_cache.insert(url.clone(), data.clone()).await;
-    tracing::debug!("TS cached: {} ({} bytes)", url, data.len());
+    (status, headers, stream_body(response, prefix, on_complete)).into_response()
+}
 
-    let mut headers = axum::http::HeaderMap::new();
+/// 用缓存的完整分片响应，按需截取 Range
+fn cached_segment_response(
+    method: &Method,
+    segment: &CachedSegment,
+    range: Option<&str>,
+) -> Response {
+    let total = segment.data.len() as u64;
+    let mut headers = HeaderMap::new();
     headers.insert(
-        axum::http::header::CONTENT_TYPE,
-        "video/mp2t".parse().unwrap(),
+        header::CONTENT_TYPE,
+        HeaderValue::from_str(&segment.content_type)
+            .unwrap_or(HeaderValue::from_static(DEFAULT_SEGMENT_CONTENT_TYPE)),
     );
+    headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
     headers.insert(
-        axum::http::header::CACHE_CONTROL,
-        "public, max-age=3600".parse().unwrap(),
+        header::CACHE_CONTROL,
+        HeaderValue::from_static("public, max-age=432000"),
     );
 
-    (StatusCode::OK, headers, data)
+    let (status, body) = match range.map(|range| parse_range(range, total)) {
+        Some(Err(e)) => {
+            if let Ok(value) = HeaderValue::from_str(&format!("bytes */{}", total)) {
+                headers.insert(header::CONTENT_RANGE, value);
+            }
+            return (StatusCode::RANGE_NOT_SATISFIABLE, headers, e).into_response();
+        }
+        Some(Ok(Some(range))) => {
+            if let Ok(value) = HeaderValue::from_str(&range.content_range(total)) {
+                headers.insert(header::CONTENT_RANGE, value);
+            }
+            let body = segment
+                .data
+                .slice(range.start as usize..=range.end as usize);
+            (StatusCode::PARTIAL_CONTENT, body)
+        }
+        _ => (StatusCode::OK, segment.data.clone()),
+    };
+    headers.insert(header::CONTENT_LENGTH, HeaderValue::from(body.len()));
+
+    if *method == Method::HEAD {
+        return (status, headers).into_response();
+    }
+    (status, headers, body).into_response()
+}
+
+/// 分片缓存统计：命中、未命中、淘汰次数与各层占用，以及按分片主机统计的去伪装次数
+pub async fn segment_cache_stats_handler(State(state): State<AppState>) -> impl IntoResponse {
+    let mut stats = serde_json::to_value(state.ts_cache.stats().await).unwrap_or_default();
+    stats["unwrapped_segments"] =
+        serde_json::to_value(state.unwrap_stats.snapshot()).unwrap_or_default();
+    Json(stats)
 }
 
 /// Spider JAR 代理处理器
@@ -0,0 +1,136 @@
+//! 按用户的源可见性与成人内容策略（来自 data.json 的 UserConfig）
+
+use crate::config_file::UserConfig;
+use std::collections::{HashMap, HashSet};
+
+/// 单个用户的策略
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourcePolicy {
+    /// 可见的源 key，`None` 表示不限制
+    pub enabled_apis: Option<HashSet<String>>,
+    pub allow_adult: Option<bool>,
+    pub banned: bool,
+}
+
+impl SourcePolicy {
+    pub fn allows_site(&self, key: &str) -> bool {
+        self.enabled_apis
+            .as_ref()
+            .is_none_or(|enabled| enabled.contains(key))
+    }
+
+    /// 结合请求参数 `adult` 得出是否返回成人源
+    pub fn adult_enabled(&self, requested: Option<bool>) -> bool {
+        match self.allow_adult {
+            Some(false) => false,
+            Some(true) => requested.unwrap_or(true),
+            None => requested.unwrap_or(false),
+        }
+    }
+
+    fn is_restricted(&self) -> bool {
+        self.enabled_apis.is_some() || self.allow_adult == Some(false)
+    }
+}
+
+/// 全部用户的策略
+#[derive(Debug, Default)]
+pub struct UserPolicies {
+    users: HashMap<String, SourcePolicy>,
+    /// 未识别用户时使用的最严格策略，没有任何用户受限时为 `None`
+    anonymous: Option<SourcePolicy>,
+}
+
+impl UserPolicies {
+    /// 用户自身的 enabledApis 优先；为空时取所属标签 enabledApis 的并集；都为空则不限制
+    pub fn new(config: &UserConfig) -> Self {
+        let tags: HashMap<&str, &[String]> = config
+            .tags
+            .iter()
+            .map(|tag| (tag.name.as_str(), tag.enabled_apis.as_slice()))
+            .collect();
+
+        let users = config
+            .users
+            .iter()
+            .map(|user| {
+                let mut enabled: HashSet<String> = user.enabled_apis.iter().cloned().collect();
+                if enabled.is_empty() {
+                    enabled = user
+                        .tags
+                        .iter()
+                        .filter_map(|tag| tags.get(tag.as_str()))
+                        .flat_map(|apis| apis.iter().cloned())
+                        .collect();
+                }
+                let policy = SourcePolicy {
+                    enabled_apis: (!enabled.is_empty()).then_some(enabled),
+                    allow_adult: user.allow_adult,
+                    banned: user.banned,
+                };
+                (user.username.clone(), policy)
+            })
+            .collect();
+        let anonymous = Self::most_restrictive(&users);
+
+        Self { users, anonymous }
+    }
+
+    /// 未封禁用户可见源的交集，任一用户禁止成人内容时同样禁止
+    fn most_restrictive(users: &HashMap<String, SourcePolicy>) -> Option<SourcePolicy> {
+        let active: Vec<&SourcePolicy> = users.values().filter(|policy| !policy.banned).collect();
+        if !active.iter().any(|policy| policy.is_restricted()) {
+            return None;
+        }
+        let enabled_apis = active
+            .iter()
+            .filter_map(|policy| policy.enabled_apis.as_ref())
+            .fold(None, |acc: Option<HashSet<String>>, enabled| match acc {
+                Some(acc) => Some(acc.intersection(enabled).cloned().collect()),
+                None => Some(enabled.clone()),
+            });
+        let allow_adult = active
+            .iter()
+            .any(|policy| policy.allow_adult == Some(false))
+            .then_some(false);
+        Some(SourcePolicy {
+            enabled_apis,
+            allow_adult,
+            banned: false,
+        })
+    }
+
+    pub fn get(&self, username: &str) -> Option<&SourcePolicy> {
+        self.users.get(username)
+    }
+
+    /// 确定调用者的策略：令牌绑定的用户；查询参数 `user` 必须与之一致。
+    /// 令牌未绑定用户时，只有没有任何用户受限才能用 `user` 指定用户，否则使用最严格的策略
+    pub fn resolve(
+        &self,
+        token_user: Option<&str>,
+        requested_user: Option<&str>,
+    ) -> Result<Option<&SourcePolicy>, String> {
+        let requested_user = requested_user
+            .map(str::trim)
+            .filter(|username| !username.is_empty());
+        let username = match (token_user, requested_user) {
+            (Some(token_user), Some(requested)) if requested != token_user => {
+                return Err(format!("User {} does not match the token", requested));
+            }
+            (Some(token_user), _) => token_user,
+            (None, Some(_)) if self.anonymous.is_some() => {
+                return Err("A user-bound token is required to select a user".to_string());
+            }
+            (None, Some(requested)) => requested,
+            (None, None) => return Ok(self.anonymous.as_ref()),
+        };
+        let policy = self
+            .get(username)
+            .ok_or_else(|| format!("Unknown user: {}", username))?;
+        if policy.banned {
+            return Err("User is banned".to_string());
+        }
+        Ok(Some(policy))
+    }
+}
@@ -0,0 +1,105 @@
+#[cfg(test)]
+mod tests {
+    use quantumtv_api::auth::{derive_user_token, extract_token, AccessControl, AccessError};
+    use quantumtv_api::config_file::{ServerConfig, StaticToken, User};
+
+    fn user(username: &str, banned: bool) -> User {
+        User {
+            banned,
+            role: "user".to_string(),
+            username: username.to_string(),
+            enabled_apis: Vec::new(),
+            tags: Vec::new(),
+            allow_adult: None,
+        }
+    }
+
+    #[test]
+    fn access_control_is_disabled_without_tokens() {
+        let access = AccessControl::new(&ServerConfig::default(), &[user("alice", false)]);
+        assert!(!access.is_enabled());
+    }
+
+    #[test]
+    fn static_and_user_tokens_are_accepted_and_banned_users_rejected() {
+        let server: ServerConfig = serde_json::from_value(serde_json::json!({
+            "tokens": ["static-token", {"token": "tv", "name": "living-room"}],
+            "token_secret": "s3cret"
+        }))
+        .unwrap();
+        assert!(matches!(&server.tokens[0], StaticToken::Plain(t) if t == "static-token"));
+
+        let users = [user("alice", false), user("mallory", true)];
+        let access = AccessControl::new(&server, &users);
+
+        assert!(access.authorize(Some("static-token")).is_ok());
+        assert_eq!(access.authorize(Some("tv")).unwrap().name, "living-room");
+        assert_eq!(access.authorize(Some("tv")).unwrap().user, None);
+        assert_eq!(
+            access
+                .authorize(Some(&derive_user_token("s3cret", "alice")))
+                .unwrap()
+                .name,
+            "alice"
+        );
+        assert_eq!(
+            access.authorize(Some(&derive_user_token("s3cret", "mallory"))),
+            Err(AccessError::Banned)
+        );
+        assert_eq!(
+            access.authorize(Some("nope")),
+            Err(AccessError::InvalidToken)
+        );
+        assert_eq!(access.authorize(None), Err(AccessError::MissingToken));
+    }
+
+    #[test]
+    fn user_tokens_are_hmac_sha256_of_the_username() {
+        // RFC 4231 测试用例 2
+        assert_eq!(
+            derive_user_token("Jefe", "what do ya want for nothing?"),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert_eq!(
+            derive_user_token(" Jefe ", "what do ya want for nothing?"),
+            derive_user_token("Jefe", "what do ya want for nothing?")
+        );
+        assert_ne!(
+            derive_user_token("s3cret", "alice"),
+            derive_user_token("s3cret", "bob")
+        );
+    }
+
+    #[test]
+    fn rate_limit_applies_per_token() {
+        let server: ServerConfig = serde_json::from_value(serde_json::json!({
+            "tokens": ["a", {"token": "b", "rate_limit_per_minute": 0}],
+            "rate_limit_per_minute": 2
+        }))
+        .unwrap();
+        let access = AccessControl::new(&server, &[]);
+
+        assert!(access.authorize(Some("a")).is_ok());
+        assert!(access.authorize(Some("a")).is_ok());
+        assert!(matches!(
+            access.authorize(Some("a")),
+            Err(AccessError::RateLimited { .. })
+        ));
+        for _ in 0..5 {
+            assert!(access.authorize(Some("b")).is_ok());
+        }
+    }
+
+    #[test]
+    fn token_is_read_from_query_before_bearer_header() {
+        assert_eq!(
+            extract_token(Some("url=x&token=q%2B1"), Some("Bearer h")).as_deref(),
+            Some("q+1")
+        );
+        assert_eq!(
+            extract_token(Some("url=x"), Some("Bearer h")).as_deref(),
+            Some("h")
+        );
+        assert_eq!(extract_token(None, Some("Basic abc")), None);
+    }
+}
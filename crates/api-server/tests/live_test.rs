@@ -0,0 +1,75 @@
+#[cfg(test)]
+mod tests {
+    use quantumtv_api::config_file::LiveConfig;
+    use quantumtv_api::live::{check_stream, remove_dead_urls, LiveService};
+    use quantumtv_core::parse_live_list;
+
+    fn live_config(key: &str, epg: Option<&str>, disabled: bool) -> LiveConfig {
+        LiveConfig {
+            key: key.to_string(),
+            name: key.to_string(),
+            url: format!("https://live.example.com/{}.m3u", key),
+            ua: None,
+            epg: epg.map(str::to_string),
+            disabled,
+        }
+    }
+
+    #[tokio::test]
+    async fn dead_urls_channels_and_groups_are_removed() {
+        let mut playlist = parse_live_list(
+            "央视,#genre#\n\
+CCTV1,http://dead.example.com/1.m3u8#http://ok.example.com/1.m3u8\n\
+CCTV2,http://dead.example.com/2.m3u8\n\
+地方,#genre#\n\
+本地台,http://dead.example.com/3.m3u8\n",
+        );
+        remove_dead_urls(&mut playlist, 4, |url| async move {
+            url.starts_with("http://ok.")
+        })
+        .await;
+
+        assert_eq!(playlist.groups.len(), 1);
+        assert_eq!(playlist.groups[0].channels.len(), 1);
+        assert_eq!(
+            playlist.groups[0].channels[0].urls,
+            vec!["http://ok.example.com/1.m3u8"]
+        );
+        assert_eq!(
+            playlist.to_txt(),
+            "央视,#genre#\nCCTV1,http://ok.example.com/1.m3u8\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn only_http_streams_are_checked() {
+        let client = reqwest::Client::new();
+        for url in [
+            "rtmp://live.example.com/app/stream",
+            "rtsp://10.0.0.2/live",
+            "rtp://239.0.0.1:5000",
+            "p2p://example/abc",
+        ] {
+            assert!(check_stream(&client, url, "okhttp").await, "{}", url);
+        }
+        assert!(!check_stream(&client, "http://127.0.0.1/live.m3u8", "okhttp").await);
+    }
+
+    #[test]
+    fn disabled_sources_are_ignored_and_configured_epg_wins() {
+        let service = LiveService::new(vec![live_config("off", None, true)]);
+        assert!(!service.is_enabled());
+
+        let service = LiveService::new(vec![
+            live_config("a", None, false),
+            live_config("b", Some("https://epg.example.com/e.xml.gz"), false),
+        ]);
+        assert!(service.is_enabled());
+        assert_eq!(
+            service.epg_url().as_deref(),
+            Some("https://epg.example.com/e.xml.gz")
+        );
+        // 尚未完成首次检测
+        assert_eq!(service.playlist().channel_count(), 0);
+    }
+}
@@ -0,0 +1,89 @@
+#[cfg(test)]
+mod tests {
+    use axum::body::Bytes;
+    use quantumtv_api::config_file::SegmentCacheConfig;
+    use quantumtv_api::segment_cache::SegmentCache;
+    use quantumtv_api::segment_proxy::CachedSegment;
+
+    fn config(name: &str, memory_mb: u64, disk_mb: u64) -> SegmentCacheConfig {
+        let dir = std::env::temp_dir().join(format!("quantumtv-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        SegmentCacheConfig {
+            memory_mb,
+            disk_mb,
+            disk_dir: dir.to_string_lossy().into_owned(),
+            ..Default::default()
+        }
+    }
+
+    fn segment(size: usize) -> CachedSegment {
+        CachedSegment {
+            content_type: "video/mp4".to_string(),
+            data: Bytes::from(vec![7u8; size]),
+        }
+    }
+
+    #[test]
+    fn cache_key_drops_volatile_params_and_fragment() {
+        let cache = SegmentCache::new(&config("key", 1, 0));
+        assert_eq!(
+            cache.cache_key("https://CDN.example.com/a/1.ts?wsSecret=abc&q=720&wsTime=1#x"),
+            "https://cdn.example.com/a/1.ts?q=720"
+        );
+        assert_eq!(
+            cache.cache_key("https://cdn.example.com/a/1.ts?auth_key=1-2-3"),
+            "https://cdn.example.com/a/1.ts"
+        );
+    }
+
+    #[tokio::test]
+    async fn disk_tier_survives_restart_and_counts_hits() {
+        let config = config("restart", 1, 8);
+        let cache = SegmentCache::new(&config);
+        cache
+            .insert("https://cdn.example.com/1.ts?sign=a", segment(1024))
+            .await;
+        assert!(cache
+            .get("https://cdn.example.com/1.ts?sign=b")
+            .await
+            .is_some());
+        assert!(cache.get("https://cdn.example.com/2.ts").await.is_none());
+        drop(cache);
+
+        let restarted = SegmentCache::new(&config);
+        assert!(restarted.contains("https://cdn.example.com/1.ts?token=c"));
+        assert!(!restarted.contains("https://cdn.example.com/2.ts"));
+        let hit = restarted.get("https://cdn.example.com/1.ts").await.unwrap();
+        assert_eq!(hit.content_type, "video/mp4");
+        assert_eq!(hit.data.len(), 1024);
+
+        let stats = restarted.stats().await;
+        assert_eq!((stats.disk_hits, stats.misses), (1, 0));
+        assert_eq!(stats.disk_entries, 1);
+        let _ = std::fs::remove_dir_all(&config.disk_dir);
+    }
+
+    #[tokio::test]
+    async fn disk_tier_evicts_least_recently_used() {
+        let config = config("evict", 0, 1);
+        let cache = SegmentCache::new(&config);
+        let size = 400 * 1024;
+        cache
+            .insert("https://a.example.com/1.ts", segment(size))
+            .await;
+        cache
+            .insert("https://a.example.com/2.ts", segment(size))
+            .await;
+        assert!(cache.get("https://a.example.com/1.ts").await.is_some());
+        cache
+            .insert("https://a.example.com/3.ts", segment(size))
+            .await;
+
+        let stats = cache.stats().await;
+        assert_eq!(stats.disk_evictions, 1);
+        assert!(stats.disk_bytes <= 1024 * 1024);
+        assert!(cache.get("https://a.example.com/2.ts").await.is_none());
+        assert!(cache.get("https://a.example.com/1.ts").await.is_some());
+        let _ = std::fs::remove_dir_all(&config.disk_dir);
+    }
+}
@@ -0,0 +1,96 @@
+#[cfg(test)]
+mod tests {
+    use axum::body::Bytes;
+    use quantumtv_api::segment_proxy::{parse_range, sniff_segment, stream_body, ByteRange};
+
+    #[test]
+    fn single_ranges_are_parsed_and_clamped() {
+        assert_eq!(
+            parse_range("bytes=0-99", 1000),
+            Ok(Some(ByteRange { start: 0, end: 99 }))
+        );
+        assert_eq!(
+            parse_range("bytes=900-", 1000),
+            Ok(Some(ByteRange {
+                start: 900,
+                end: 999
+            }))
+        );
+        assert_eq!(
+            parse_range("bytes=-100", 1000),
+            Ok(Some(ByteRange {
+                start: 900,
+                end: 999
+            }))
+        );
+        assert_eq!(
+            parse_range("bytes=500-5000", 1000),
+            Ok(Some(ByteRange {
+                start: 500,
+                end: 999
+            }))
+        );
+        assert_eq!(
+            ByteRange { start: 0, end: 99 }.content_range(1000),
+            "bytes 0-99/1000"
+        );
+    }
+
+    #[test]
+    fn unsupported_ranges_fall_back_and_out_of_bounds_fail() {
+        assert_eq!(parse_range("bytes=0-1,5-6", 1000), Ok(None));
+        assert_eq!(parse_range("items=0-1", 1000), Ok(None));
+        assert_eq!(parse_range("bytes=9-3", 1000), Ok(None));
+        assert!(parse_range("bytes=1000-", 1000).is_err());
+        assert!(parse_range("bytes=-0", 1000).is_err());
+    }
+
+    #[tokio::test]
+    async fn streamed_body_is_forwarded_and_cached() {
+        let upstream = reqwest::Response::from(http_response(b"segment-data".to_vec()));
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let body = stream_body(
+            upstream,
+            Bytes::new(),
+            Some(move |data: Bytes| async move {
+                let _ = tx.send(data);
+            }),
+        );
+
+        let forwarded = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+        assert_eq!(&forwarded[..], b"segment-data");
+        assert_eq!(&rx.await.unwrap()[..], b"segment-data");
+    }
+
+    #[tokio::test]
+    async fn disguised_segment_is_sniffed_and_prefix_forwarded() {
+        let mut data = b"GIF89a\x01\x00\x01\x00".to_vec();
+        let mut ts = vec![0u8; 188 * 4];
+        for packet in 0..4 {
+            ts[packet * 188] = 0x47;
+        }
+        data.extend_from_slice(&ts);
+
+        let mut upstream = reqwest::Response::from(http_response(data));
+        let (head, found) = sniff_segment(&mut upstream).await.unwrap();
+        let found = found.unwrap();
+        assert_eq!(found.offset, 10);
+        assert_eq!(found.format.content_type(), "video/mp2t");
+
+        let prefix = Bytes::from(head).slice(found.offset..);
+        let body = stream_body(
+            upstream,
+            prefix,
+            None::<fn(Bytes) -> std::future::Ready<()>>,
+        );
+        let forwarded = axum::body::to_bytes(body, usize::MAX).await.unwrap();
+        assert_eq!(&forwarded[..], &ts[..]);
+    }
+
+    fn http_response(body: Vec<u8>) -> axum::http::Response<Vec<u8>> {
+        axum::http::Response::builder()
+            .status(200)
+            .body(body)
+            .unwrap()
+    }
+}
@@ -0,0 +1,132 @@
+#[cfg(test)]
+mod tests {
+    use quantumtv_api::config_url::{Parse, Site, SubscriptionConfig};
+    use quantumtv_api::subscription::{merge_subscription_configs, subscriptions_from_urls};
+
+    fn site(key: &str, name: &str, api: &str) -> Site {
+        Site {
+            key: key.to_string(),
+            name: name.to_string(),
+            site_type: 1,
+            api: api.to_string(),
+            jar: None,
+            is_adult: None,
+            searchable: Some(1),
+            quick_search: Some(1),
+            filterable: Some(1),
+            changeable: None,
+            header: None,
+            subscription: None,
+        }
+    }
+
+    fn config(spider: Option<&str>, sites: Vec<Site>, parse_url: &str) -> SubscriptionConfig {
+        SubscriptionConfig {
+            spider: spider.map(str::to_string),
+            sites: Some(sites),
+            parses: Some(vec![Parse {
+                name: "解析".to_string(),
+                parse_type: 0,
+                url: parse_url.to_string(),
+            }]),
+            lives: None,
+        }
+    }
+
+    #[test]
+    fn urls_are_split_with_earlier_ones_preferred() {
+        let subs = subscriptions_from_urls(
+            " https://a.example.com/1.json ,\nhttps://b.example.com/2.json,, ",
+        );
+        assert_eq!(subs.len(), 2);
+        assert_eq!(subs[0].url, "https://a.example.com/1.json");
+        assert!(subs[0].priority > subs[1].priority);
+        assert_ne!(subs[0].key, subs[1].key);
+
+        let reordered =
+            subscriptions_from_urls("https://b.example.com/2.json\nhttps://a.example.com/1.json");
+        assert_eq!(reordered[1].key, subs[0].key);
+        assert_eq!(reordered[0].key, subs[1].key);
+    }
+
+    #[test]
+    fn sites_are_deduplicated_by_api_with_priority() {
+        let subs =
+            subscriptions_from_urls("https://a.example.com/1.json,https://b.example.com/2.json");
+        let merged = merge_subscription_configs(vec![
+            (
+                &subs[1],
+                config(
+                    Some("low.jar"),
+                    vec![
+                        site(
+                            "x",
+                            "低优先级",
+                            "https://vod.example.com/api.php/provide/vod/",
+                        ),
+                        site("y", "Y", "https://y.example.com/api.php/provide/vod"),
+                    ],
+                    "https://jx.example.com/?url=",
+                ),
+            ),
+            (
+                &subs[0],
+                config(
+                    None,
+                    vec![site(
+                        "x",
+                        "高优先级",
+                        "https://VOD.example.com/api.php/provide/vod",
+                    )],
+                    "https://jx.example.com/?url=",
+                ),
+            ),
+        ]);
+
+        let sites = merged.sites.unwrap();
+        assert_eq!(sites.len(), 2);
+        assert_eq!(sites[0].name, "高优先级");
+        assert_eq!(sites[0].subscription.as_deref(), Some(subs[0].key.as_str()));
+        assert_eq!(sites[1].key, "y");
+        assert_eq!(sites[1].subscription.as_deref(), Some(subs[1].key.as_str()));
+        assert_eq!(merged.spider.as_deref(), Some("low.jar"));
+        assert_eq!(merged.parses.unwrap().len(), 1);
+    }
+
+    #[test]
+    fn spider_sites_keep_their_own_jar() {
+        let subs =
+            subscriptions_from_urls("https://a.example.com/1.json,https://b.example.com/2.json");
+        let merged = merge_subscription_configs(vec![
+            (
+                &subs[0],
+                config(
+                    Some("a.jar"),
+                    vec![site("demo", "A", "csp_Demo"), site("only_a", "A2", "csp_A")],
+                    "https://jx.example.com/?url=",
+                ),
+            ),
+            (
+                &subs[1],
+                config(
+                    Some("b.jar"),
+                    vec![
+                        site("demo", "B", "csp_Demo"),
+                        site("same", "B2", "csp_demo"),
+                    ],
+                    "https://jx.example.com/?url=",
+                ),
+            ),
+        ]);
+
+        let sites = merged.sites.unwrap();
+        assert_eq!(merged.spider.as_deref(), Some("a.jar"));
+        assert_eq!(sites.len(), 4);
+        let by_name = |name: &str| sites.iter().find(|site| site.name == name).unwrap();
+        assert_eq!(by_name("A").jar, None);
+        assert_eq!(by_name("A2").jar, None);
+        assert_eq!(by_name("B").jar.as_deref(), Some("b.jar"));
+        assert_eq!(by_name("B2").jar.as_deref(), Some("b.jar"));
+        assert_ne!(by_name("A").key, by_name("B").key);
+    }
+}
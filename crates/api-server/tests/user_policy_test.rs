@@ -0,0 +1,99 @@
+#[cfg(test)]
+mod tests {
+    use quantumtv_api::auth::AccessControl;
+    use quantumtv_api::config_file::{ServerConfig, UserConfig};
+    use quantumtv_api::user_policy::UserPolicies;
+
+    fn user_config() -> UserConfig {
+        serde_json::from_value(serde_json::json!({
+            "Users": [
+                { "username": "kid", "role": "user", "tags": ["kids", "cartoon"], "allowAdult": false },
+                { "username": "parent", "role": "owner", "allowAdult": true },
+                { "username": "guest", "role": "user", "enabledApis": ["a"], "tags": ["kids"] },
+                { "username": "mallory", "role": "user", "banned": true }
+            ],
+            "Tags": [
+                { "name": "kids", "enabledApis": ["a", "b"] },
+                { "name": "cartoon", "enabledApis": ["c"] }
+            ]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn visible_sites_come_from_user_then_tags() {
+        let policies = UserPolicies::new(&user_config());
+
+        let kid = policies.get("kid").unwrap();
+        assert!(["a", "b", "c"].iter().all(|key| kid.allows_site(key)));
+        assert!(!kid.allows_site("d"));
+
+        let guest = policies.get("guest").unwrap();
+        assert!(guest.allows_site("a"));
+        assert!(!guest.allows_site("b"));
+
+        assert!(policies.get("parent").unwrap().allows_site("anything"));
+    }
+
+    #[test]
+    fn adult_policy_overrides_request_parameter() {
+        let policies = UserPolicies::new(&user_config());
+
+        let kid = policies.get("kid").unwrap();
+        assert!(!kid.adult_enabled(Some(true)));
+
+        let parent = policies.get("parent").unwrap();
+        assert!(parent.adult_enabled(None));
+        assert!(!parent.adult_enabled(Some(false)));
+
+        assert!(!policies.get("guest").unwrap().adult_enabled(None));
+        assert!(policies.get("guest").unwrap().adult_enabled(Some(true)));
+    }
+
+    #[test]
+    fn query_parameter_must_match_token_bound_user() {
+        let config = user_config();
+        let server: ServerConfig = serde_json::from_value(serde_json::json!({
+            "tokens": [{ "token": "tv", "user": "kid" }]
+        }))
+        .unwrap();
+        let access = AccessControl::new(&server, &config.users);
+        let policies = UserPolicies::new(&config);
+
+        let token_user = access.authorize(Some("tv")).unwrap().user.clone();
+        assert!(policies
+            .resolve(token_user.as_deref(), Some("parent"))
+            .is_err());
+        for requested in [None, Some("kid")] {
+            let policy = policies
+                .resolve(token_user.as_deref(), requested)
+                .unwrap()
+                .unwrap();
+            assert_eq!(policy, policies.get("kid").unwrap());
+        }
+
+        // 有用户受限时，不能不带用户令牌而自称某个用户
+        assert!(policies.resolve(None, Some("parent")).is_err());
+        assert!(policies.resolve(None, Some("nobody")).is_err());
+        assert!(policies.resolve(None, Some("mallory")).is_err());
+    }
+
+    #[test]
+    fn anonymous_callers_get_most_restrictive_policy() {
+        let policies = UserPolicies::new(&user_config());
+        let anonymous = policies.resolve(None, Some(" ")).unwrap().unwrap();
+        assert_eq!(policies.resolve(None, None).unwrap(), Some(anonymous));
+        assert!(anonymous.allows_site("a"));
+        assert!(!anonymous.allows_site("b"));
+        assert!(!anonymous.allows_site("c"));
+        assert!(!anonymous.adult_enabled(Some(true)));
+
+        let open: UserConfig = serde_json::from_value(serde_json::json!({
+            "Users": [{ "username": "parent", "role": "owner", "allowAdult": true }]
+        }))
+        .unwrap();
+        let open = UserPolicies::new(&open);
+        assert_eq!(open.resolve(None, None), Ok(None));
+        assert_eq!(open.resolve(None, Some("parent")), Ok(open.get("parent")));
+    }
+}
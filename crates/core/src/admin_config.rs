@@ -1,4 +1,6 @@
 use crate::adult::is_adult_source;
+use crate::mirror::merge_mirror_sources;
+use crate::request_profile::RequestProfile;
 use serde_json::{json, Map, Value};
 use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -37,10 +39,10 @@ fn normalize_admin_config_value(value: Value) -> Result<Value, String> {
             if is_admin_config(&map) {
                 normalize_admin_config_object(&map)
             } else if let Some(sites) = map.get("sites").and_then(|v| v.as_array()) {
-                let sources = normalize_source_config_array(sites, "config");
+                let sources = merge_mirror_sources(normalize_source_config_array(sites, "config"));
                 Ok(build_config_with_sources(sources))
             } else if let Some(api_site) = map.get("api_site").and_then(|v| v.as_object()) {
-                let sources = normalize_api_site_object(api_site);
+                let sources = merge_mirror_sources(normalize_api_site_object(api_site));
                 Ok(build_config_with_sources(sources))
             } else {
                 Err("配置格式错误".to_string())
@@ -106,6 +108,7 @@ fn normalize_admin_config_object(map: &Map<String, Value>) -> Result<Value, Stri
         if let Some(config_file) = config.get("ConfigFile").and_then(|v| v.as_str()) {
             if let Ok(config_file_value) = serde_json::from_str::<Value>(config_file) {
                 if let Some(sources) = extract_sources_from_value(&config_file_value) {
+                    let sources = merge_mirror_sources(sources);
                     set_value(&mut config, "SourceConfig", Value::Array(sources));
                 }
             }
@@ -217,6 +220,7 @@ fn normalize_source_config_item(
     obj.insert("from".to_string(), Value::String(from));
     obj.insert("disabled".to_string(), Value::Bool(disabled));
     obj.insert("is_adult".to_string(), Value::Bool(is_adult));
+    import_tvbox_header(&mut obj);
 
     Some(Value::Object(obj))
 }
@@ -255,7 +259,8 @@ fn normalize_api_site_object(api_site: &Map<String, Value>) -> Vec<Value> {
             .unwrap_or(false);
         let is_adult = existing_adult || is_adult_source(&name);
 
-        let mut obj = Map::new();
+        // 保留镜像、请求策略等附加字段
+        let mut obj = value.as_object().cloned().unwrap_or_default();
         obj.insert("key".to_string(), Value::String(key.clone()));
         obj.insert("name".to_string(), Value::String(name));
         obj.insert("api".to_string(), Value::String(api));
@@ -263,12 +268,27 @@ fn normalize_api_site_object(api_site: &Map<String, Value>) -> Vec<Value> {
         obj.insert("from".to_string(), Value::String("config".to_string()));
         obj.insert("disabled".to_string(), Value::Bool(disabled));
         obj.insert("is_adult".to_string(), Value::Bool(is_adult));
+        import_tvbox_header(&mut obj);
 
         sources.push(Value::Object(obj));
     }
     sources
 }
 
+/// TVBox 站点的 `header` 字段转为 `request_profile`，已有配置时保持不变
+fn import_tvbox_header(obj: &mut Map<String, Value>) {
+    if obj.contains_key("request_profile") {
+        return;
+    }
+    let profile = obj
+        .get("header")
+        .and_then(RequestProfile::from_tvbox_header)
+        .and_then(|profile| serde_json::to_value(profile).ok());
+    if let Some(profile) = profile {
+        obj.insert("request_profile".to_string(), profile);
+    }
+}
+
 fn normalize_custom_categories(items: &[Value], default_from: &str) -> Vec<Value> {
     items
         .iter()
@@ -478,6 +498,46 @@ mod tests {
         assert_eq!(source.get("name").unwrap(), "SiteA");
     }
 
+    #[test]
+    fn parse_from_sites_merges_mirror_hosts() {
+        let input = json!({
+            "sites": [
+                { "name": "SiteA", "api": "http://a.com/api.php/provide/vod" },
+                { "name": "SiteA", "api": "http://a-backup.com/api.php/provide/vod" }
+            ]
+        })
+        .to_string();
+
+        let result = parse_admin_config(&input).unwrap();
+        let sources = result.get("SourceConfig").unwrap().as_array().unwrap();
+        assert_eq!(sources.len(), 1);
+        assert_eq!(
+            sources[0].get("mirrors").unwrap(),
+            &json!(["http://a-backup.com/api.php/provide/vod"])
+        );
+    }
+
+    #[test]
+    fn parse_from_sites_imports_tvbox_header() {
+        let input = json!({
+            "sites": [
+                {
+                    "name": "SiteA",
+                    "api": "http://a.com/api.php/provide/vod",
+                    "header": { "User-Agent": "okhttp/3.12", "Referer": "http://a.com/" }
+                }
+            ]
+        })
+        .to_string();
+
+        let result = parse_admin_config(&input).unwrap();
+        let source = &result.get("SourceConfig").unwrap().as_array().unwrap()[0];
+        assert_eq!(
+            source.get("request_profile").unwrap(),
+            &json!({ "user_agent": "okhttp/3.12", "referer": "http://a.com/" })
+        );
+    }
+
     #[test]
     fn parse_from_api_site_format() {
         let input = json!({
@@ -0,0 +1,63 @@
+/// 字节区间，`end` 包含在内
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl ByteRange {
+    /// `Content-Range` 响应头的值
+    pub fn content_range(&self, total: u64) -> String {
+        format!("bytes {}-{}/{}", self.start, self.end, total)
+    }
+}
+
+/// 解析单段 `Range: bytes=...`
+///
+/// 多段或无法识别的格式返回 `Ok(None)`，按完整内容响应；区间落在内容之外时返回错误（416）
+pub fn parse_range(value: &str, total: u64) -> Result<Option<ByteRange>, String> {
+    let Some(spec) = value.trim().strip_prefix("bytes=") else {
+        return Ok(None);
+    };
+    if spec.contains(',') {
+        return Ok(None);
+    }
+    let Some((start, end)) = spec.trim().split_once('-') else {
+        return Ok(None);
+    };
+    let (start, end) = (start.trim(), end.trim());
+
+    let range = if start.is_empty() {
+        // bytes=-N：最后 N 个字节
+        let Ok(suffix) = end.parse::<u64>() else {
+            return Ok(None);
+        };
+        if suffix == 0 || total == 0 {
+            return Err(format!("Range not satisfiable: {}", value));
+        }
+        ByteRange {
+            start: total.saturating_sub(suffix),
+            end: total - 1,
+        }
+    } else {
+        let Ok(start) = start.parse::<u64>() else {
+            return Ok(None);
+        };
+        let end = if end.is_empty() {
+            u64::MAX
+        } else {
+            match end.parse::<u64>() {
+                Ok(end) if end >= start => end,
+                _ => return Ok(None),
+            }
+        };
+        if start >= total {
+            return Err(format!("Range not satisfiable: {}", value));
+        }
+        ByteRange {
+            start,
+            end: end.min(total - 1),
+        }
+    };
+    Ok(Some(range))
+}
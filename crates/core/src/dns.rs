@@ -0,0 +1,718 @@
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::header::{ACCEPT, CONTENT_TYPE};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+const DOH_TIMEOUT: Duration = Duration::from_secs(5);
+const DNS_MESSAGE_TYPE: &str = "application/dns-message";
+/// DoH 应答缓存的最短时间（秒），避免 TTL 过小时频繁查询
+const MIN_CACHE_TTL_SECS: u64 = 30;
+/// 缓存条目上限，超出后整体清空
+const MAX_CACHE_ENTRIES: usize = 2048;
+const TYPE_A: u16 = 1;
+const TYPE_AAAA: u16 = 28;
+const CLASS_IN: u16 = 1;
+
+/// 本机、局域网、运营商级 NAT（含云厂商元数据地址）、组播及保留地址
+///
+/// IPv4 映射 / 兼容的 IPv6 地址与 NAT64（`64:ff9b::/96`）地址按内嵌的 IPv4 地址判断
+pub fn is_local_or_private_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(addr) => is_local_or_private_ipv4(addr),
+        IpAddr::V6(addr) => {
+            if let Some(embedded) = addr.to_ipv4() {
+                return is_local_or_private_ipv4(embedded);
+            }
+            let segments = addr.segments();
+            if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+                let [a, b] = segments[6].to_be_bytes();
+                let [c, d] = segments[7].to_be_bytes();
+                return is_local_or_private_ipv4(Ipv4Addr::new(a, b, c, d));
+            }
+            addr.is_loopback()
+                || addr.is_unspecified()
+                || addr.is_multicast()
+                || addr.is_unique_local()
+                || addr.is_unicast_link_local()
+                // 本地使用的 NAT64 前缀 64:ff9b:1::/48
+                || segments[..3] == [0x64, 0xff9b, 1]
+                // 已废弃的站点本地地址 fec0::/10
+                || (segments[0] & 0xffc0) == 0xfec0
+                // 文档地址 2001:db8::/32
+                || segments[..2] == [0x2001, 0xdb8]
+        }
+    }
+}
+
+fn is_local_or_private_ipv4(addr: Ipv4Addr) -> bool {
+    let [a, b, c, _] = addr.octets();
+    addr.is_private()
+        || addr.is_loopback()
+        || addr.is_link_local()
+        || addr.is_broadcast()
+        || addr.is_documentation()
+        || addr.is_unspecified()
+        || addr.is_multicast()
+        // 0.0.0.0/8 “本网络”
+        || a == 0
+        // 100.64.0.0/10 运营商级 NAT，云厂商元数据服务（如 100.100.100.200）也在其中
+        || (a == 100 && (b & 0xc0) == 64)
+        // 192.0.0.0/24 IETF 协议分配
+        || (a == 192 && b == 0 && c == 0)
+        // 198.18.0.0/15 基准测试
+        || (a == 198 && (b & 0xfe) == 18)
+        // 240.0.0.0/4 保留
+        || a >= 240
+}
+
+/// DNS 设置，保存在 UserPreferences 的 `dns` 字段中
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DnsSettings {
+    /// DoH 上游地址（RFC 8484），为空时使用系统解析
+    pub doh: Vec<String>,
+    /// 全局静态解析
+    pub hosts: HostOverrides,
+    /// 解析结果缓存时间（秒），为 0 时不缓存
+    pub cache_ttl_secs: u64,
+}
+
+impl Default for DnsSettings {
+    fn default() -> Self {
+        Self {
+            doh: Vec::new(),
+            hosts: HostOverrides::default(),
+            cache_ttl_secs: 300,
+        }
+    }
+}
+
+impl DnsSettings {
+    /// 从 UserPreferences 读取 DNS 设置，缺失或格式错误时使用系统解析
+    pub fn from_preferences(preferences: &Value) -> Self {
+        preferences
+            .get("dns")
+            .and_then(|dns| serde_json::from_value(dns.clone()).ok())
+            .unwrap_or_default()
+    }
+
+    /// 校验 DoH 地址，保存设置前调用
+    pub fn validate(&self) -> Result<(), String> {
+        for upstream in &self.doh {
+            let url = url::Url::parse(upstream.trim())
+                .map_err(|_| format!("DoH 地址格式错误: {}", upstream))?;
+            if url.scheme() != "https" || url.host_str().is_none() {
+                return Err(format!("DoH 地址必须为 https: {}", upstream));
+            }
+        }
+        Ok(())
+    }
+
+    fn doh_upstreams(&self) -> Vec<String> {
+        self.doh
+            .iter()
+            .map(|upstream| upstream.trim().to_string())
+            .filter(|upstream| !upstream.is_empty())
+            .collect()
+    }
+}
+
+/// 静态解析表：域名（支持 `*` 通配）→ IP 或别名域名
+///
+/// 可写为对象 `{"host": "ip"}` / `{"host": ["ip", ...]}`，
+/// 也兼容 TVBox 的 `["host=ip", ...]` 写法
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct HostOverrides(BTreeMap<String, Vec<String>>);
+
+impl<'de> Deserialize<'de> for HostOverrides {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Self::from_value(&Value::deserialize(deserializer)?))
+    }
+}
+
+impl HostOverrides {
+    pub fn from_value(value: &Value) -> Self {
+        let mut hosts = BTreeMap::new();
+        let mut insert = |host: &str, targets: Vec<&str>| {
+            let host = host.trim().trim_end_matches('.').to_ascii_lowercase();
+            let targets: Vec<String> = targets
+                .into_iter()
+                .map(str::trim)
+                .filter(|target| !target.is_empty())
+                .map(str::to_string)
+                .collect();
+            if !host.is_empty() && !targets.is_empty() {
+                hosts.insert(host, targets);
+            }
+        };
+
+        match value {
+            Value::Object(map) => {
+                for (host, targets) in map {
+                    match targets {
+                        Value::String(target) => insert(host, target.split(',').collect()),
+                        Value::Array(items) => {
+                            insert(host, items.iter().filter_map(|v| v.as_str()).collect())
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            Value::Array(items) => {
+                for rule in items.iter().filter_map(|v| v.as_str()) {
+                    if let Some((host, targets)) = rule.split_once('=') {
+                        insert(host, targets.split(',').collect());
+                    }
+                }
+            }
+            _ => {}
+        }
+        Self(hosts)
+    }
+
+    /// 读取源配置的 `hosts` 字段
+    pub fn from_source(source: &Value) -> Self {
+        source
+            .get("hosts")
+            .map(Self::from_value)
+            .unwrap_or_default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// 查找域名的解析目标，精确匹配优先于通配
+    pub fn lookup(&self, host: &str) -> Option<&[String]> {
+        if let Some(targets) = self.0.get(host) {
+            return Some(targets);
+        }
+        self.0
+            .iter()
+            .find(|(pattern, _)| pattern.contains('*') && wildcard_match(pattern, host))
+            .map(|(_, targets)| targets.as_slice())
+    }
+}
+
+fn wildcard_match(pattern: &str, host: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if host.len() < first.len() + last.len() || !host.starts_with(first) || !host.ends_with(last) {
+        return false;
+    }
+    let mut rest = &host[first.len()..host.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+struct CachedAddrs {
+    addrs: Vec<IpAddr>,
+    expires_at: Instant,
+}
+
+#[derive(Default)]
+struct ResolverConfig {
+    settings: DnsSettings,
+    allow_private: bool,
+    /// 不受局域网拦截的域名（如本机代理）
+    exempt_hosts: HashSet<String>,
+}
+
+/// 共享的域名解析器：静态解析 → 缓存 → DoH → 系统解析
+///
+/// 通过 [`DnsResolver::handle`] 接入 reqwest 客户端，
+/// 需要执行局域网策略的客户端会在解析后过滤掉内网地址，防止域名指向内网绕过地址校验
+pub struct DnsResolver {
+    config: RwLock<ResolverConfig>,
+    /// 按源登记的静态解析（源配置的 `hosts` 字段）
+    source_hosts: RwLock<HashMap<String, HostOverrides>>,
+    cache: Mutex<HashMap<String, CachedAddrs>>,
+    doh_client: reqwest::Client,
+}
+
+impl Default for DnsResolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DnsResolver {
+    pub fn new() -> Self {
+        let doh_client = reqwest::Client::builder()
+            .no_proxy()
+            .timeout(DOH_TIMEOUT)
+            .build()
+            .unwrap_or_default();
+        Self {
+            config: RwLock::new(ResolverConfig::default()),
+            source_hosts: RwLock::new(HashMap::new()),
+            cache: Mutex::new(HashMap::new()),
+            doh_client,
+        }
+    }
+
+    /// 更新 DNS 设置并清空缓存
+    pub fn configure(&self, settings: DnsSettings) {
+        self.config.write().unwrap().settings = settings;
+        self.cache.lock().unwrap().clear();
+    }
+
+    pub fn set_allow_private(&self, allow: bool) {
+        self.config.write().unwrap().allow_private = allow;
+    }
+
+    pub fn allows_private(&self) -> bool {
+        self.config.read().unwrap().allow_private
+    }
+
+    pub fn set_exempt_hosts(&self, hosts: impl IntoIterator<Item = String>) {
+        self.config.write().unwrap().exempt_hosts = hosts
+            .into_iter()
+            .map(|host| host.to_ascii_lowercase())
+            .collect();
+    }
+
+    /// 登记源的静态解析，内容未变化时不做任何事
+    pub fn set_source_hosts(&self, source_key: &str, hosts: &HostOverrides) {
+        if self.source_hosts.read().unwrap().get(source_key) == Some(hosts) {
+            return;
+        }
+        let mut source_hosts = self.source_hosts.write().unwrap();
+        if hosts.is_empty() {
+            source_hosts.remove(source_key);
+        } else {
+            source_hosts.insert(source_key.to_string(), hosts.clone());
+        }
+    }
+
+    /// 接入 reqwest 客户端的解析器；`enforce_lan_policy` 为真时按局域网设置过滤解析结果
+    pub fn handle(self: &Arc<Self>, enforce_lan_policy: bool) -> Arc<ResolverHandle> {
+        Arc::new(ResolverHandle {
+            resolver: Arc::clone(self),
+            enforce_lan_policy,
+        })
+    }
+
+    fn override_targets(&self, host: &str) -> Option<Vec<String>> {
+        let config = self.config.read().unwrap();
+        if let Some(targets) = config.settings.hosts.lookup(host) {
+            return Some(targets.to_vec());
+        }
+        let source_hosts = self.source_hosts.read().unwrap();
+        source_hosts
+            .values()
+            .find_map(|hosts| hosts.lookup(host))
+            .map(<[String]>::to_vec)
+    }
+
+    /// 解析域名，静态解析的别名目标不再查静态解析表以免循环
+    pub async fn lookup(&self, host: &str) -> Result<Vec<IpAddr>, String> {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        let Some(targets) = self.override_targets(&host) else {
+            return self.resolve_name(&host).await;
+        };
+
+        let mut addrs = Vec::new();
+        for target in targets {
+            match target.parse::<IpAddr>() {
+                Ok(ip) => addrs.push(ip),
+                Err(_) => addrs.extend(self.resolve_name(&target).await?),
+            }
+        }
+        Ok(addrs)
+    }
+
+    /// 同步解析域名，供重定向策略等同步回调使用：只查静态解析、缓存与系统解析，不走 DoH
+    pub fn lookup_blocking(&self, host: &str) -> Result<Vec<IpAddr>, String> {
+        let host = host.trim_end_matches('.').to_ascii_lowercase();
+        let targets = self
+            .override_targets(&host)
+            .unwrap_or_else(|| vec![host.clone()]);
+
+        let mut addrs = Vec::new();
+        for target in targets {
+            match target.parse::<IpAddr>() {
+                Ok(ip) => addrs.push(ip),
+                Err(_) => addrs.extend(self.resolve_name_blocking(&target)?),
+            }
+        }
+        Ok(addrs)
+    }
+
+    fn resolve_name_blocking(&self, host: &str) -> Result<Vec<IpAddr>, String> {
+        if let Some(addrs) = self.cached(host) {
+            return Ok(addrs);
+        }
+        let resolve = || {
+            (host, 0)
+                .to_socket_addrs()
+                .map(|addrs| addrs.map(|addr| addr.ip()).collect::<Vec<_>>())
+                .map_err(|e| format!("解析 {} 失败: {}", host, e))
+        };
+        // 多线程运行时中让出工作线程，避免阻塞其他任务
+        let addrs = match tokio::runtime::Handle::try_current() {
+            Ok(handle) if handle.runtime_flavor() == tokio::runtime::RuntimeFlavor::MultiThread => {
+                tokio::task::block_in_place(resolve)?
+            }
+            _ => resolve()?,
+        };
+        let cache_ttl_secs = self.config.read().unwrap().settings.cache_ttl_secs;
+        self.store(host, &addrs, cache_ttl_secs);
+        Ok(addrs)
+    }
+
+    /// 按局域网策略过滤解析结果
+    pub fn filter_lan(&self, host: &str, addrs: Vec<IpAddr>) -> Result<Vec<IpAddr>, String> {
+        let config = self.config.read().unwrap();
+        if config.allow_private || config.exempt_hosts.contains(host) {
+            return Ok(addrs);
+        }
+        let public: Vec<IpAddr> = addrs
+            .into_iter()
+            .filter(|ip| !is_local_or_private_ip(*ip))
+            .collect();
+        if public.is_empty() {
+            return Err(format!("{} 解析到局域网地址，已拦截", host));
+        }
+        Ok(public)
+    }
+
+    async fn resolve_name(&self, host: &str) -> Result<Vec<IpAddr>, String> {
+        if let Some(addrs) = self.cached(host) {
+            return Ok(addrs);
+        }
+
+        let (upstreams, cache_ttl_secs) = {
+            let config = self.config.read().unwrap();
+            (
+                config.settings.doh_upstreams(),
+                config.settings.cache_ttl_secs,
+            )
+        };
+
+        for upstream in &upstreams {
+            match self.doh_lookup(upstream, host).await {
+                Ok((addrs, ttl)) if !addrs.is_empty() => {
+                    let ttl = u64::from(ttl).max(MIN_CACHE_TTL_SECS).min(cache_ttl_secs);
+                    self.store(host, &addrs, ttl);
+                    return Ok(addrs);
+                }
+                // 上游失败或无结果时尝试下一个，全部失败后回退到系统解析
+                _ => {}
+            }
+        }
+
+        let addrs: Vec<IpAddr> = tokio::net::lookup_host((host, 0))
+            .await
+            .map_err(|e| format!("解析 {} 失败: {}", host, e))?
+            .map(|addr| addr.ip())
+            .collect();
+        self.store(host, &addrs, cache_ttl_secs);
+        Ok(addrs)
+    }
+
+    async fn doh_lookup(&self, upstream: &str, host: &str) -> Result<(Vec<IpAddr>, u32), String> {
+        let (v4, v6) = tokio::join!(
+            self.doh_query(upstream, host, TYPE_A),
+            self.doh_query(upstream, host, TYPE_AAAA)
+        );
+        match (v4, v6) {
+            (Err(error), Err(_)) => Err(error),
+            (v4, v6) => {
+                let (mut addrs, ttl_v4) = v4.unwrap_or((Vec::new(), u32::MAX));
+                let (addrs_v6, ttl_v6) = v6.unwrap_or((Vec::new(), u32::MAX));
+                addrs.extend(addrs_v6);
+                Ok((addrs, ttl_v4.min(ttl_v6)))
+            }
+        }
+    }
+
+    async fn doh_query(
+        &self,
+        upstream: &str,
+        host: &str,
+        qtype: u16,
+    ) -> Result<(Vec<IpAddr>, u32), String> {
+        let response = self
+            .doh_client
+            .post(upstream)
+            .header(CONTENT_TYPE, DNS_MESSAGE_TYPE)
+            .header(ACCEPT, DNS_MESSAGE_TYPE)
+            .body(encode_query(host, qtype)?)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("HTTP {}", response.status()));
+        }
+        let body = response.bytes().await.map_err(|e| e.to_string())?;
+        parse_response(&body, qtype)
+    }
+
+    fn cached(&self, host: &str) -> Option<Vec<IpAddr>> {
+        let cache = self.cache.lock().unwrap();
+        cache
+            .get(host)
+            .filter(|entry| entry.expires_at > Instant::now())
+            .map(|entry| entry.addrs.clone())
+    }
+
+    fn store(&self, host: &str, addrs: &[IpAddr], ttl_secs: u64) {
+        if ttl_secs == 0 || addrs.is_empty() {
+            return;
+        }
+        let mut cache = self.cache.lock().unwrap();
+        if cache.len() >= MAX_CACHE_ENTRIES && !cache.contains_key(host) {
+            cache.clear();
+        }
+        cache.insert(
+            host.to_string(),
+            CachedAddrs {
+                addrs: addrs.to_vec(),
+                expires_at: Instant::now() + Duration::from_secs(ttl_secs),
+            },
+        );
+    }
+}
+
+/// 接入 reqwest 的解析器句柄
+pub struct ResolverHandle {
+    resolver: Arc<DnsResolver>,
+    enforce_lan_policy: bool,
+}
+
+impl Resolve for ResolverHandle {
+    fn resolve(&self, name: Name) -> Resolving {
+        let resolver = Arc::clone(&self.resolver);
+        let enforce_lan_policy = self.enforce_lan_policy;
+        Box::pin(async move {
+            let host = name.as_str().to_ascii_lowercase();
+            let mut addrs = resolver.lookup(&host).await?;
+            if enforce_lan_policy {
+                addrs = resolver.filter_lan(&host, addrs)?;
+            }
+            let addrs: Addrs = Box::new(addrs.into_iter().map(|ip| SocketAddr::new(ip, 0)));
+            Ok(addrs)
+        })
+    }
+}
+
+/// 构造 DNS 查询报文（RFC 8484 建议 ID 为 0 以便缓存）
+fn encode_query(host: &str, qtype: u16) -> Result<Vec<u8>, String> {
+    let mut message = vec![0, 0, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0];
+    for label in host.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(format!("域名格式错误: {}", host));
+        }
+        message.push(label.len() as u8);
+        message.extend_from_slice(label.as_bytes());
+    }
+    message.push(0);
+    message.extend_from_slice(&qtype.to_be_bytes());
+    message.extend_from_slice(&CLASS_IN.to_be_bytes());
+    Ok(message)
+}
+
+fn read_u16(message: &[u8], pos: usize) -> Result<u16, String> {
+    message
+        .get(pos..pos + 2)
+        .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+        .ok_or_else(|| "DNS 应答被截断".to_string())
+}
+
+fn skip_name(message: &[u8], mut pos: usize) -> Result<usize, String> {
+    loop {
+        let len = *message.get(pos).ok_or("DNS 应答被截断")? as usize;
+        match len {
+            0 => return Ok(pos + 1),
+            len if len & 0xC0 == 0xC0 => return Ok(pos + 2),
+            len => pos += 1 + len,
+        }
+    }
+}
+
+/// 解析 DNS 应答，返回指定类型的地址与最小 TTL；域名不存在时返回空列表
+fn parse_response(message: &[u8], qtype: u16) -> Result<(Vec<IpAddr>, u32), String> {
+    let flags = read_u16(message, 2)?;
+    match flags & 0x000F {
+        0 => {}
+        3 => return Ok((Vec::new(), u32::MAX)),
+        rcode => return Err(format!("DNS 应答错误码 {}", rcode)),
+    }
+    let questions = read_u16(message, 4)?;
+    let answers = read_u16(message, 6)?;
+
+    let mut pos = 12;
+    for _ in 0..questions {
+        pos = skip_name(message, pos)? + 4;
+    }
+
+    let mut addrs = Vec::new();
+    let mut min_ttl = u32::MAX;
+    for _ in 0..answers {
+        pos = skip_name(message, pos)?;
+        let rtype = read_u16(message, pos)?;
+        let class = read_u16(message, pos + 2)?;
+        let ttl =
+            (u32::from(read_u16(message, pos + 4)?) << 16) | u32::from(read_u16(message, pos + 6)?);
+        let len = read_u16(message, pos + 8)? as usize;
+        let data = message
+            .get(pos + 10..pos + 10 + len)
+            .ok_or("DNS 应答被截断")?;
+        pos += 10 + len;
+
+        if rtype != qtype || class != CLASS_IN {
+            continue;
+        }
+        let ip = match (rtype, len) {
+            (TYPE_A, 4) => IpAddr::V4(Ipv4Addr::new(data[0], data[1], data[2], data[3])),
+            (TYPE_AAAA, 16) => {
+                let mut octets = [0u8; 16];
+                octets.copy_from_slice(data);
+                IpAddr::V6(Ipv6Addr::from(octets))
+            }
+            _ => continue,
+        };
+        addrs.push(ip);
+        min_ttl = min_ttl.min(ttl);
+    }
+    Ok((addrs, min_ttl))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn answer_a(query: &[u8], ip: [u8; 4], ttl: u32) -> Vec<u8> {
+        let mut message = query.to_vec();
+        message[2] = 0x81;
+        message[3] = 0x80;
+        message[7] = 2;
+        // CNAME 记录应被跳过
+        message.extend_from_slice(&[0xC0, 12, 0, 5, 0, 1]);
+        message.extend_from_slice(&ttl.to_be_bytes());
+        message.extend_from_slice(&[0, 2, 0xC0, 12]);
+        message.extend_from_slice(&[0xC0, 12, 0, 1, 0, 1]);
+        message.extend_from_slice(&ttl.to_be_bytes());
+        message.extend_from_slice(&[0, 4]);
+        message.extend_from_slice(&ip);
+        message
+    }
+
+    #[test]
+    fn parses_a_records_from_doh_answer() {
+        let query = encode_query("api.example.com", TYPE_A).unwrap();
+        assert_eq!(&query[12..16], &[3, b'a', b'p', b'i']);
+
+        let response = answer_a(&query, [93, 184, 216, 34], 120);
+        let (addrs, ttl) = parse_response(&response, TYPE_A).unwrap();
+        assert_eq!(addrs, vec![IpAddr::from([93, 184, 216, 34])]);
+        assert_eq!(ttl, 120);
+
+        let mut nxdomain = query.clone();
+        nxdomain[3] = 0x83;
+        assert!(parse_response(&nxdomain, TYPE_A).unwrap().0.is_empty());
+        assert!(parse_response(&response[..response.len() - 2], TYPE_A).is_err());
+    }
+
+    #[test]
+    fn host_overrides_accept_object_and_tvbox_rules() {
+        let hosts = HostOverrides::from_value(&json!({
+            "API.example.com": "1.2.3.4",
+            "*.cdn.example.com": ["5.6.7.8", "9.9.9.9"]
+        }));
+        assert_eq!(
+            hosts.lookup("api.example.com"),
+            Some(&["1.2.3.4".to_string()][..])
+        );
+        assert_eq!(
+            hosts.lookup("a.cdn.example.com").map(<[String]>::len),
+            Some(2)
+        );
+        assert_eq!(hosts.lookup("cdn.example.com"), None);
+
+        let tvbox = HostOverrides::from_source(&json!({
+            "hosts": ["cache.ott.*.itv.cmvideo.cn=base-v4-free-mghy.e.cdn.chinamobile.com"]
+        }));
+        assert_eq!(
+            tvbox.lookup("cache.ott.bestlive.itv.cmvideo.cn"),
+            Some(&["base-v4-free-mghy.e.cdn.chinamobile.com".to_string()][..])
+        );
+    }
+
+    #[tokio::test]
+    async fn overrides_resolve_and_lan_results_are_blocked() {
+        let resolver = DnsResolver::new();
+        resolver.set_source_hosts(
+            "a",
+            &HostOverrides::from_value(&json!({
+                "api.example.com": "93.184.216.34",
+                "evil.example.com": "192.168.1.1"
+            })),
+        );
+
+        let addrs = resolver.lookup("API.example.com.").await.unwrap();
+        assert_eq!(addrs, vec![IpAddr::from([93, 184, 216, 34])]);
+
+        let lan = resolver.lookup("evil.example.com").await.unwrap();
+        assert!(resolver
+            .filter_lan("evil.example.com", lan.clone())
+            .is_err());
+
+        resolver.set_allow_private(true);
+        assert_eq!(
+            resolver.filter_lan("evil.example.com", lan).unwrap().len(),
+            1
+        );
+    }
+
+    #[test]
+    fn ipv4_mapped_private_addresses_are_local() {
+        assert!(is_local_or_private_ip("::ffff:10.0.0.1".parse().unwrap()));
+        assert!(!is_local_or_private_ip("::ffff:8.8.8.8".parse().unwrap()));
+        assert!(is_local_or_private_ip("::127.0.0.1".parse().unwrap()));
+        assert!(is_local_or_private_ip(
+            "::ffff:100.100.100.200".parse().unwrap()
+        ));
+    }
+
+    #[test]
+    fn special_purpose_ranges_are_local() {
+        for ip in [
+            "100.100.100.200",
+            "100.64.0.1",
+            "0.1.2.3",
+            "224.0.0.251",
+            "239.255.255.250",
+            "198.18.0.1",
+            "192.0.0.170",
+            "240.0.0.1",
+            "64:ff9b::a9fe:a9fe",
+            "64:ff9b::10.0.0.1",
+            "64:ff9b:1::1",
+            "ff02::1",
+            "fec0::1",
+        ] {
+            assert!(is_local_or_private_ip(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in [
+            "100.128.0.1",
+            "8.8.8.8",
+            "64:ff9b::808:808",
+            "2606:4700::1111",
+        ] {
+            assert!(!is_local_or_private_ip(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+}
@@ -0,0 +1,299 @@
+use crate::hls_crypto::{is_fmp4_playlist, segment_encryptions, SegmentEncryption};
+use crate::playback::filter_ads_from_m3_u8;
+use crate::playlist::{find_uri_attribute, media_segments};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use url::Url;
+
+/// 下载完成后的文件形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DownloadOutput {
+    /// 按顺序拼接成单个文件
+    Ts,
+    /// 保留分片，生成本地播放列表
+    Hls,
+}
+
+/// 下载设置，保存在 UserPreferences 的 `download` 字段中
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DownloadSettings {
+    /// 同时进行的下载任务数
+    pub max_tasks: usize,
+    /// 每个任务同时下载的分片数
+    pub segment_concurrency: usize,
+    /// 所有下载共享的带宽上限（KB/s），0 表示不限
+    pub bandwidth_limit_kbps: u64,
+    pub output: DownloadOutput,
+}
+
+impl Default for DownloadSettings {
+    fn default() -> Self {
+        Self {
+            max_tasks: 2,
+            segment_concurrency: 4,
+            bandwidth_limit_kbps: 0,
+            output: DownloadOutput::Ts,
+        }
+    }
+}
+
+/// 待下载的一个分片
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedSegment {
+    pub url: String,
+    pub duration: f64,
+    /// 加密时的加密方式、密钥地址与 IV
+    pub encryption: Option<SegmentEncryption>,
+}
+
+/// 一集视频的下载计划：去广告后的分片及其解密信息
+#[derive(Debug, Clone, PartialEq)]
+pub struct DownloadPlan {
+    /// fMP4 的初始化分片（`#EXT-X-MAP`）
+    pub init_url: Option<String>,
+    pub segments: Vec<PlannedSegment>,
+}
+
+impl DownloadPlan {
+    /// 由媒体播放列表生成下载计划，广告分片不下载
+    ///
+    /// fMP4 的 SAMPLE-AES 与 SAMPLE-AES-CTR 无法在本地解密，不支持下载
+    pub fn from_playlist(content: &str, base_url: &str) -> Result<Self, String> {
+        let filtered = filter_ads_from_m3_u8(content);
+        let fmp4 = is_fmp4_playlist(&filtered);
+        let mut encryptions: HashMap<String, SegmentEncryption> = HashMap::new();
+        for encryption in segment_encryptions(&filtered, base_url) {
+            if !encryption.method.is_decryptable(fmp4) {
+                return Err(format!(
+                    "不支持下载 {} 加密的视频",
+                    encryption.method.as_attribute()
+                ));
+            }
+            encryptions.insert(encryption.url.clone(), encryption);
+        }
+
+        let segments: Vec<PlannedSegment> = media_segments(&filtered, base_url)
+            .into_iter()
+            .map(|segment| PlannedSegment {
+                encryption: encryptions.remove(&segment.url),
+                url: segment.url,
+                duration: segment.duration,
+            })
+            .collect();
+        if segments.is_empty() {
+            return Err("播放列表中没有分片".to_string());
+        }
+
+        Ok(Self {
+            init_url: init_segment_url(&filtered, base_url),
+            segments,
+        })
+    }
+
+    /// 本地文件的扩展名：有初始化分片时为 fMP4
+    pub fn extension(&self) -> &'static str {
+        if self.init_url.is_some() {
+            "mp4"
+        } else {
+            "ts"
+        }
+    }
+
+    /// 第 `index` 个分片的本地文件名
+    pub fn segment_file_name(&self, index: usize) -> String {
+        format!("{:05}.{}", index, self.extension())
+    }
+
+    /// 引用本地分片的播放列表，分片已解密，不含密钥标签
+    pub fn local_playlist(&self, init_file: Option<&str>) -> String {
+        let target_duration = self
+            .segments
+            .iter()
+            .map(|segment| segment.duration)
+            .fold(1.0, f64::max)
+            .ceil();
+        let mut playlist = format!(
+            "#EXTM3U\n#EXT-X-VERSION:{}\n#EXT-X-TARGETDURATION:{}\n#EXT-X-MEDIA-SEQUENCE:0\n#EXT-X-PLAYLIST-TYPE:VOD\n",
+            if init_file.is_some() { 7 } else { 3 },
+            target_duration
+        );
+        if let Some(init_file) = init_file {
+            playlist.push_str(&format!("#EXT-X-MAP:URI=\"{}\"\n", init_file));
+        }
+        for (index, segment) in self.segments.iter().enumerate() {
+            playlist.push_str(&format!(
+                "#EXTINF:{:.3},\n{}\n",
+                segment.duration,
+                self.segment_file_name(index)
+            ));
+        }
+        playlist.push_str("#EXT-X-ENDLIST\n");
+        playlist
+    }
+}
+
+fn init_segment_url(content: &str, base_url: &str) -> Option<String> {
+    let line = content
+        .lines()
+        .find(|line| line.trim_start().starts_with("#EXT-X-MAP:"))?;
+    let (start, end) = find_uri_attribute(line)?;
+    let uri = &line[start..end];
+    match Url::parse(base_url) {
+        Ok(base) => base.join(uri).ok().map(|url| url.to_string()),
+        Err(_) => Some(uri.to_string()),
+    }
+}
+
+/// 从主播放列表中选出带宽最高的变体流
+pub fn best_variant(content: &str, base_url: &str) -> Option<String> {
+    let mut best: Option<(u64, &str)> = None;
+    let mut bandwidth = None;
+    for line in content.lines().map(str::trim) {
+        if let Some(attributes) = line.strip_prefix("#EXT-X-STREAM-INF:") {
+            bandwidth = Some(
+                attributes
+                    .split(',')
+                    .find_map(|attribute| attribute.trim().strip_prefix("BANDWIDTH="))
+                    .and_then(|value| value.trim().parse::<u64>().ok())
+                    .unwrap_or(0),
+            );
+        } else if !line.is_empty() && !line.starts_with('#') {
+            if let Some(bandwidth) = bandwidth.take() {
+                if best.is_none_or(|(current, _)| bandwidth > current) {
+                    best = Some((bandwidth, line));
+                }
+            }
+        }
+    }
+    let (_, uri) = best?;
+    match Url::parse(base_url) {
+        Ok(base) => base.join(uri).ok().map(|url| url.to_string()),
+        Err(_) => Some(uri.to_string()),
+    }
+}
+
+/// 多个下载任务共享的带宽限制
+///
+/// 每收到一段数据就按限速推后下一段数据的允许时间，超出时等待
+#[derive(Default)]
+pub struct BandwidthLimiter {
+    bytes_per_sec: AtomicU64,
+    next_free: Mutex<Option<Instant>>,
+}
+
+impl BandwidthLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置限速（KB/s），0 表示不限
+    pub fn set_limit_kbps(&self, kbps: u64) {
+        self.bytes_per_sec
+            .store(kbps.saturating_mul(1024), Ordering::Relaxed);
+    }
+
+    /// 登记收到的字节数，超过限速时等待
+    pub async fn consume(&self, bytes: usize) {
+        if let Some(wait) = self.reserve(Instant::now(), bytes) {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// 预留 `bytes` 的传输时间，返回需要等待的时长
+    fn reserve(&self, now: Instant, bytes: usize) -> Option<Duration> {
+        let rate = self.bytes_per_sec.load(Ordering::Relaxed);
+        if rate == 0 {
+            return None;
+        }
+        let cost = Duration::from_secs_f64(bytes as f64 / rate as f64);
+        let mut next_free = self.next_free.lock().unwrap();
+        let start = next_free.filter(|next| *next > now).unwrap_or(now);
+        let end = start + cost;
+        *next_free = Some(end);
+        // 数据已经收到，等到这段数据按限速应当传完的时刻
+        Some(end - now).filter(|wait| !wait.is_zero())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hls_crypto::EncryptionMethod;
+
+    #[test]
+    fn plan_skips_ads_and_keeps_decryption_info() {
+        let playlist = "#EXTM3U\n#EXT-X-MEDIA-SEQUENCE:4\n\
+            #EXT-X-KEY:METHOD=AES-128,URI=\"key\"\n\
+            #EXTINF:5,\nseg4.ts\n\
+            #EXTINF:3,\n/ad/seg5.ts\n\
+            #EXTINF:6.5,\nseg6.ts\n#EXT-X-ENDLIST\n";
+        let plan =
+            DownloadPlan::from_playlist(playlist, "https://cdn.example.com/v/index.m3u8").unwrap();
+        assert_eq!(plan.init_url, None);
+        assert_eq!(plan.segments.len(), 2);
+        let last = &plan.segments[1];
+        assert_eq!(last.url, "https://cdn.example.com/v/seg6.ts");
+        let encryption = last.encryption.as_ref().unwrap();
+        assert_eq!(encryption.key_uri, "https://cdn.example.com/v/key");
+        assert_eq!(encryption.iv, 6u128.to_be_bytes());
+
+        let local = plan.local_playlist(None);
+        assert!(local.contains("#EXT-X-TARGETDURATION:7\n"));
+        assert!(local.contains("#EXTINF:6.500,\n00001.ts\n"));
+        assert!(!local.contains("KEY"));
+        assert!(local.ends_with("#EXT-X-ENDLIST\n"));
+    }
+
+    #[test]
+    fn fmp4_plans_keep_init_segment_and_only_ts_sample_aes_is_accepted() {
+        let fmp4 = "#EXTM3U\n#EXT-X-MAP:URI=\"init.mp4\"\n#EXTINF:4,\nseg0.m4s\n";
+        let plan = DownloadPlan::from_playlist(fmp4, "https://a.example.com/v/index.m3u8").unwrap();
+        assert_eq!(
+            plan.init_url.as_deref(),
+            Some("https://a.example.com/v/init.mp4")
+        );
+        assert_eq!(plan.segment_file_name(0), "00000.mp4");
+
+        let sample_aes = "#EXTM3U\n#EXT-X-KEY:METHOD=SAMPLE-AES,URI=\"k\"\n#EXTINF:4,\ns.ts\n";
+        let plan = DownloadPlan::from_playlist(sample_aes, "https://a.example.com/").unwrap();
+        assert_eq!(
+            plan.segments[0].encryption.as_ref().map(|e| e.method),
+            Some(EncryptionMethod::SampleAes)
+        );
+        let fmp4_sample_aes =
+            sample_aes.replace("#EXTM3U\n", "#EXTM3U\n#EXT-X-MAP:URI=\"i.mp4\"\n");
+        assert!(DownloadPlan::from_playlist(&fmp4_sample_aes, "https://a.example.com/").is_err());
+        let ctr = sample_aes.replace("SAMPLE-AES", "SAMPLE-AES-CTR");
+        assert!(DownloadPlan::from_playlist(&ctr, "https://a.example.com/").is_err());
+    }
+
+    #[test]
+    fn best_variant_has_highest_bandwidth() {
+        let master = "#EXTM3U\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=800000,RESOLUTION=640x360\nsd/index.m3u8\n\
+            #EXT-X-STREAM-INF:RESOLUTION=1920x1080,BANDWIDTH=4000000\nhd/index.m3u8\n";
+        assert_eq!(
+            best_variant(master, "https://a.example.com/v/master.m3u8").as_deref(),
+            Some("https://a.example.com/v/hd/index.m3u8")
+        );
+        assert_eq!(best_variant("#EXTM3U\n#EXTINF:4,\ns.ts\n", ""), None);
+    }
+
+    #[test]
+    fn bandwidth_limiter_spaces_out_chunks() {
+        let limiter = BandwidthLimiter::new();
+        let now = Instant::now();
+        assert_eq!(limiter.reserve(now, 1024 * 1024), None);
+
+        limiter.set_limit_kbps(100);
+        let first = limiter.reserve(now, 50 * 1024).unwrap();
+        assert_eq!(first, Duration::from_millis(500));
+        let second = limiter.reserve(now, 50 * 1024).unwrap();
+        assert_eq!(second, Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,427 @@
+use crate::live::parse_attributes;
+use flate2::read::MultiGzDecoder;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::io::Read;
+
+/// 解压后的节目单上限，避免异常文件占满内存
+const MAX_GUIDE_SIZE: u64 = 256 * 1024 * 1024;
+
+/// XMLTV 中的频道：一个 id 可以有多个显示名称
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EpgChannel {
+    pub id: String,
+    pub names: Vec<String>,
+    pub icon: Option<String>,
+}
+
+/// 一个节目，起止时间为 UTC 秒级时间戳
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EpgProgramme {
+    pub channel: String,
+    pub start: i64,
+    pub stop: i64,
+    pub title: String,
+    pub description: Option<String>,
+    pub category: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EpgGuide {
+    pub channels: Vec<EpgChannel>,
+    pub programmes: Vec<EpgProgramme>,
+}
+
+/// 解析 XMLTV 节目单，gzip 压缩的内容（按文件头识别）先解压
+pub fn parse_xmltv(data: &[u8]) -> Result<EpgGuide, String> {
+    let text = if data.starts_with(&[0x1f, 0x8b]) {
+        let mut decoded = Vec::new();
+        MultiGzDecoder::new(data)
+            .take(MAX_GUIDE_SIZE)
+            .read_to_end(&mut decoded)
+            .map_err(|e| format!("节目单解压失败: {}", e))?;
+        String::from_utf8_lossy(&decoded).into_owned()
+    } else {
+        String::from_utf8_lossy(data).into_owned()
+    };
+    if !text.contains("<tv") {
+        return Err("不是 XMLTV 节目单".to_string());
+    }
+    Ok(parse_xmltv_str(&text))
+}
+
+fn parse_xmltv_str(xml: &str) -> EpgGuide {
+    let mut guide = EpgGuide::default();
+
+    for element in Elements::new(xml, "channel") {
+        let attrs = parse_attributes(element.attrs);
+        let Some(id) = xml_attribute(&attrs, "id") else {
+            continue;
+        };
+        let names = Elements::new(element.inner, "display-name")
+            .map(|name| element_text(name.inner))
+            .filter(|name| !name.is_empty())
+            .collect();
+        let icon = Elements::new(element.inner, "icon")
+            .next()
+            .and_then(|icon| xml_attribute(&parse_attributes(icon.attrs), "src"));
+        guide.channels.push(EpgChannel { id, names, icon });
+    }
+
+    for element in Elements::new(xml, "programme") {
+        let attrs = parse_attributes(element.attrs);
+        let (Some(channel), Some(start)) = (
+            xml_attribute(&attrs, "channel"),
+            xml_attribute(&attrs, "start").and_then(|s| parse_xmltv_time(&s)),
+        ) else {
+            continue;
+        };
+        let stop = xml_attribute(&attrs, "stop")
+            .and_then(|s| parse_xmltv_time(&s))
+            .unwrap_or(start);
+        let child_text = |name: &str| {
+            Elements::new(element.inner, name)
+                .next()
+                .map(|child| element_text(child.inner))
+                .filter(|text| !text.is_empty())
+        };
+        let Some(title) = child_text("title") else {
+            continue;
+        };
+        guide.programmes.push(EpgProgramme {
+            channel,
+            start,
+            stop,
+            title,
+            description: child_text("desc"),
+            category: child_text("category"),
+        });
+    }
+
+    // 缺少结束时间的节目以同频道下一个节目的开始时间结束
+    guide
+        .programmes
+        .sort_by(|a, b| (&a.channel, a.start).cmp(&(&b.channel, b.start)));
+    for index in 0..guide.programmes.len() {
+        if guide.programmes[index].stop > guide.programmes[index].start {
+            continue;
+        }
+        let next_start = guide
+            .programmes
+            .get(index + 1)
+            .filter(|next| next.channel == guide.programmes[index].channel)
+            .map(|next| next.start);
+        if let Some(next_start) = next_start {
+            guide.programmes[index].stop = next_start;
+        }
+    }
+    guide.programmes.retain(|p| p.stop > p.start);
+    guide
+}
+
+/// 解析 XMLTV 时间 `YYYYMMDDhhmmss +hhmm`，秒与时区可省略（省略时区按 UTC）
+pub fn parse_xmltv_time(value: &str) -> Option<i64> {
+    let value = value.trim();
+    let (datetime, offset) = match value.find([' ', '+', '-']) {
+        Some(index) => (&value[..index], value[index..].trim()),
+        None => (value, ""),
+    };
+    if datetime.len() < 12 || !datetime.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let field = |range: std::ops::Range<usize>| datetime.get(range)?.parse::<i64>().ok();
+    let (year, month, day) = (field(0..4)?, field(4..6)?, field(6..8)?);
+    let (hour, minute) = (field(8..10)?, field(10..12)?);
+    let second = field(12..14).unwrap_or(0);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 {
+        return None;
+    }
+
+    let offset_seconds = match offset.as_bytes() {
+        [] => 0,
+        [sign @ (b'+' | b'-'), digits @ ..] if digits.len() == 4 => {
+            let digits = std::str::from_utf8(digits).ok()?;
+            let hours: i64 = digits[..2].parse().ok()?;
+            let minutes: i64 = digits[2..].parse().ok()?;
+            let seconds = hours * 3600 + minutes * 60;
+            if *sign == b'-' {
+                -seconds
+            } else {
+                seconds
+            }
+        }
+        _ => return None,
+    };
+    let local = days_from_civil(year, month, day) * 86_400 + hour * 3600 + minute * 60 + second;
+    Some(local - offset_seconds)
+}
+
+/// 某个本地日期（`YYYY-MM-DD`）在 UTC 下的起止时间戳，`utc_offset_minutes` 为本地时区偏移
+pub fn local_day_bounds(date: &str, utc_offset_minutes: i32) -> Option<(i64, i64)> {
+    let mut parts = date.trim().splitn(3, '-');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: i64 = parts.next()?.parse().ok()?;
+    let day: i64 = parts.next()?.parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let start = days_from_civil(year, month, day) * 86_400 - i64::from(utc_offset_minutes) * 60;
+    Some((start, start + 86_400))
+}
+
+/// 公历日期距 1970-01-01 的天数
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let yoe = year - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// TVBox 配置 `lives` 中声明的 XMLTV 地址
+///
+/// 带 `{name}` / `{date}` 占位符的是按频道查询的接口，不是完整节目单，忽略
+pub fn tvbox_live_epg_urls(lives: &Value) -> Vec<String> {
+    let mut urls: Vec<String> = Vec::new();
+    for live in lives.as_array().into_iter().flatten() {
+        let Some(epg) = live.get("epg").and_then(|v| v.as_str()).map(str::trim) else {
+            continue;
+        };
+        if (epg.starts_with("http://") || epg.starts_with("https://"))
+            && !epg.contains('{')
+            && !urls.iter().any(|url| url == epg)
+        {
+            urls.push(epg.to_string());
+        }
+    }
+    urls
+}
+
+/// 某个元素的属性原文与内容
+struct Element<'a> {
+    attrs: &'a str,
+    inner: &'a str,
+}
+
+/// 按顺序遍历指定名称的元素（不处理同名嵌套，XMLTV 中不存在）
+struct Elements<'a> {
+    xml: &'a str,
+    name: &'a str,
+    position: usize,
+}
+
+impl<'a> Elements<'a> {
+    fn new(xml: &'a str, name: &'a str) -> Self {
+        Self {
+            xml,
+            name,
+            position: 0,
+        }
+    }
+}
+
+impl<'a> Iterator for Elements<'a> {
+    type Item = Element<'a>;
+
+    fn next(&mut self) -> Option<Element<'a>> {
+        let open = format!("<{}", self.name);
+        let close = format!("</{}>", self.name);
+        loop {
+            let start = self.position + self.xml.get(self.position..)?.find(&open)?;
+            let after_name = start + open.len();
+            self.position = after_name;
+            // 跳过名称只是前缀的元素，如 `<channel-info>`
+            match self.xml[after_name..].chars().next() {
+                Some(c) if c.is_whitespace() || c == '>' || c == '/' => {}
+                _ => continue,
+            }
+            let tag_end = after_name + find_tag_end(&self.xml[after_name..])?;
+            let attrs = self.xml[after_name..tag_end].trim_end_matches('/');
+            if self.xml[..tag_end].ends_with('/') {
+                self.position = tag_end + 1;
+                return Some(Element { attrs, inner: "" });
+            }
+            let inner_start = tag_end + 1;
+            let inner_end = inner_start + self.xml[inner_start..].find(&close)?;
+            self.position = inner_end + close.len();
+            return Some(Element {
+                attrs,
+                inner: &self.xml[inner_start..inner_end],
+            });
+        }
+    }
+}
+
+/// 开始标签的 `>` 位置，忽略引号内的字符
+fn find_tag_end(rest: &str) -> Option<usize> {
+    let mut quote: Option<char> = None;
+    for (index, c) in rest.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            (None, '>') => return Some(index),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn xml_attribute(attrs: &[(String, String)], key: &str) -> Option<String> {
+    attrs
+        .iter()
+        .find(|(name, value)| name == key && !value.is_empty())
+        .map(|(_, value)| decode_entities(value))
+}
+
+/// 元素文本：展开 CDATA 并解码实体
+fn element_text(inner: &str) -> String {
+    let text = match inner
+        .trim()
+        .strip_prefix("<![CDATA[")
+        .and_then(|rest| rest.strip_suffix("]]>"))
+    {
+        Some(cdata) => cdata.to_string(),
+        None => decode_entities(inner),
+    };
+    text.trim().to_string()
+}
+
+fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        result.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let decoded = rest.find(';').filter(|end| *end <= 10).and_then(|end| {
+            let entity = &rest[1..end];
+            let c = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                _ => entity
+                    .strip_prefix("#x")
+                    .or_else(|| entity.strip_prefix("#X"))
+                    .map(|hex| u32::from_str_radix(hex, 16))
+                    .or_else(|| entity.strip_prefix('#').map(str::parse::<u32>))
+                    .and_then(|code| code.ok())
+                    .and_then(char::from_u32),
+            }?;
+            Some((c, end))
+        });
+        match decoded {
+            Some((c, end)) => {
+                result.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use serde_json::json;
+    use std::io::Write;
+
+    const GUIDE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE tv SYSTEM "xmltv.dtd">
+<tv generator-info-name="test">
+  <channel id="CCTV1">
+    <display-name lang="zh">CCTV-1 综合</display-name>
+    <display-name>CCTV1</display-name>
+    <icon src="https://logo.example.com/cctv1.png" />
+  </channel>
+  <programme start="20241018200000 +0800" stop="20241018203000 +0800" channel="CCTV1">
+    <title lang="zh">新闻联播</title>
+    <desc>今日要闻 &amp; 天气</desc>
+  </programme>
+  <programme start="20241018203000 +0800" channel="CCTV1">
+    <title><![CDATA[焦点访谈 <特别节目>]]></title>
+  </programme>
+  <programme start="20241018210000 +0800" stop="20241018220000 +0800" channel="CCTV1">
+    <title>电视剧</title>
+    <category>剧集</category>
+  </programme>
+  <programme start="bad" channel="CCTV1"><title>无效</title></programme>
+</tv>"#;
+
+    #[test]
+    fn parses_channels_and_programmes() {
+        let guide = parse_xmltv(GUIDE.as_bytes()).unwrap();
+        assert_eq!(guide.channels.len(), 1);
+        assert_eq!(guide.channels[0].names, vec!["CCTV-1 综合", "CCTV1"]);
+        assert_eq!(
+            guide.channels[0].icon.as_deref(),
+            Some("https://logo.example.com/cctv1.png")
+        );
+
+        assert_eq!(guide.programmes.len(), 3);
+        let news = &guide.programmes[0];
+        assert_eq!(news.title, "新闻联播");
+        assert_eq!(news.description.as_deref(), Some("今日要闻 & 天气"));
+        // 2024-10-18 12:00:00 UTC
+        assert_eq!(news.start, 1_729_252_800);
+        assert_eq!(news.stop - news.start, 1800);
+
+        let focus = &guide.programmes[1];
+        assert_eq!(focus.title, "焦点访谈 <特别节目>");
+        assert_eq!(focus.stop, guide.programmes[2].start);
+        assert_eq!(guide.programmes[2].category.as_deref(), Some("剧集"));
+    }
+
+    #[test]
+    fn gzip_guides_are_decompressed() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(GUIDE.as_bytes()).unwrap();
+        let compressed = encoder.finish().unwrap();
+        let guide = parse_xmltv(&compressed).unwrap();
+        assert_eq!(guide.programmes.len(), 3);
+        assert!(parse_xmltv(b"<html></html>").is_err());
+    }
+
+    #[test]
+    fn times_and_days_respect_offsets() {
+        assert_eq!(parse_xmltv_time("19700101000000 +0000"), Some(0));
+        assert_eq!(parse_xmltv_time("19700101080000 +0800"), Some(0));
+        assert_eq!(parse_xmltv_time("19691231190000 -0500"), Some(0));
+        assert_eq!(parse_xmltv_time("197001010001"), Some(60));
+        assert_eq!(parse_xmltv_time("20240229120000+0000"), Some(1_709_208_000));
+        assert_eq!(parse_xmltv_time("2024"), None);
+
+        // 北京时间 2024-10-18 全天
+        let (start, end) = local_day_bounds("2024-10-18", 480).unwrap();
+        assert_eq!(start, 1_729_180_800);
+        assert_eq!(end - start, 86_400);
+        assert!(local_day_bounds("2024-13-01", 0).is_none());
+    }
+
+    #[test]
+    fn tvbox_lives_only_yield_full_guides() {
+        let lives = json!([
+            { "name": "直播", "url": "https://a.example.com/live.txt", "epg": "https://epg.example.com/e.xml.gz" },
+            { "name": "直播2", "url": "https://b.example.com/live.m3u", "epg": "https://epg.example.com/api/?ch={name}&date={date}" },
+            { "name": "直播3", "url": "https://c.example.com/live.m3u", "epg": "https://epg.example.com/e.xml.gz" }
+        ]);
+        assert_eq!(
+            tvbox_live_epg_urls(&lives),
+            vec!["https://epg.example.com/e.xml.gz"]
+        );
+    }
+}
@@ -0,0 +1,463 @@
+use aes::Aes128;
+use cbc::cipher::{block_padding::Pkcs7, BlockDecryptMut, KeyIvInit};
+use moka::future::Cache;
+use std::future::Future;
+use std::time::Duration;
+use url::Url;
+
+/// AES-128 密钥与 IV 的字节数
+pub const AES_KEY_LEN: usize = 16;
+
+/// 密钥缓存的条目上限
+const MAX_CACHED_KEYS: u64 = 256;
+
+/// 密钥的缓存时长，直播流可能定期轮换密钥
+const KEY_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// 分片的加密方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncryptionMethod {
+    /// 整个分片 AES-128-CBC 加密
+    Aes128,
+    /// 只加密音视频采样（CBC），TS 分片可以在服务端解密
+    SampleAes,
+    /// 只加密音视频采样（CTR），不支持解密，原样交给播放器处理
+    SampleAesCtr,
+}
+
+impl EncryptionMethod {
+    /// 由 `METHOD` 属性解析，`NONE` 与未知方式返回 `None`
+    pub fn from_attribute(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_uppercase().as_str() {
+            "AES-128" => Some(EncryptionMethod::Aes128),
+            "SAMPLE-AES" => Some(EncryptionMethod::SampleAes),
+            "SAMPLE-AES-CTR" => Some(EncryptionMethod::SampleAesCtr),
+            _ => None,
+        }
+    }
+
+    /// `METHOD` 属性值
+    pub fn as_attribute(&self) -> &'static str {
+        match self {
+            EncryptionMethod::Aes128 => "AES-128",
+            EncryptionMethod::SampleAes => "SAMPLE-AES",
+            EncryptionMethod::SampleAesCtr => "SAMPLE-AES-CTR",
+        }
+    }
+
+    /// 能否在服务端解密；fMP4 分片的 SAMPLE-AES 按 CENC 加密，不支持
+    pub fn is_decryptable(&self, fmp4: bool) -> bool {
+        match self {
+            EncryptionMethod::Aes128 => true,
+            EncryptionMethod::SampleAes => !fmp4,
+            EncryptionMethod::SampleAesCtr => false,
+        }
+    }
+}
+
+/// 一个分片的解密信息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SegmentEncryption {
+    /// 分片的绝对地址
+    pub url: String,
+    pub method: EncryptionMethod,
+    /// 密钥的绝对地址
+    pub key_uri: String,
+    /// 标签中的 IV，未给出时为分片的媒体序号
+    pub iv: [u8; AES_KEY_LEN],
+}
+
+/// 当前生效的 `#EXT-X-KEY`
+struct ActiveKey<'a> {
+    line: &'a str,
+    method: EncryptionMethod,
+    uri: String,
+    iv: Option<[u8; AES_KEY_LEN]>,
+}
+
+enum KeyTag<'a> {
+    /// `METHOD=NONE`，之后的分片不再加密
+    None,
+    Key(ActiveKey<'a>),
+    /// 非 identity 的 KEYFORMAT（DRM 等），与解密无关
+    Ignored,
+}
+
+fn parse_key_tag<'a>(line: &'a str, base: Option<&Url>) -> KeyTag<'a> {
+    let Some(list) = line.trim().strip_prefix("#EXT-X-KEY:") else {
+        return KeyTag::Ignored;
+    };
+    let attributes = parse_attributes(list);
+    let get = |name: &str| {
+        attributes
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    };
+    if get("KEYFORMAT").is_some_and(|format| !format.eq_ignore_ascii_case("identity")) {
+        return KeyTag::Ignored;
+    }
+    let method = get("METHOD").unwrap_or_default();
+    if method.eq_ignore_ascii_case("NONE") {
+        return KeyTag::None;
+    }
+    let Some(method) = EncryptionMethod::from_attribute(method) else {
+        return KeyTag::Ignored;
+    };
+    let Some(uri) = get("URI").and_then(|uri| resolve(base, uri)) else {
+        return KeyTag::Ignored;
+    };
+    KeyTag::Key(ActiveKey {
+        line,
+        method,
+        uri,
+        iv: get("IV").and_then(parse_iv),
+    })
+}
+
+/// 拆分属性列表，引号内的逗号不作分隔
+fn parse_attributes(list: &str) -> Vec<(String, String)> {
+    let mut attributes = Vec::new();
+    let mut rest = list.trim();
+    while !rest.is_empty() {
+        let Some((name, after)) = rest.split_once('=') else {
+            break;
+        };
+        let (value, after) = match after.strip_prefix('"') {
+            Some(quoted) => match quoted.split_once('"') {
+                Some((value, after)) => (value, after),
+                None => (quoted, ""),
+            },
+            None => after.split_once(',').unwrap_or((after, "")),
+        };
+        attributes.push((name.trim().to_string(), value.trim().to_string()));
+        rest = after.trim_start_matches(',').trim_start();
+    }
+    attributes
+}
+
+fn resolve(base: Option<&Url>, uri: &str) -> Option<String> {
+    let uri = uri.trim();
+    if uri.is_empty() {
+        return None;
+    }
+    match base {
+        Some(base) => base.join(uri).ok().map(|url| url.to_string()),
+        // 只改写标签、不需要地址时不解析
+        None => Some(uri.to_string()),
+    }
+}
+
+/// 解析 `0x` 开头的十六进制 IV，不足 32 位时左侧补零
+pub fn parse_iv(value: &str) -> Option<[u8; AES_KEY_LEN]> {
+    let value = value.trim();
+    let hex = value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+        .unwrap_or(value);
+    if hex.is_empty() || hex.len() > AES_KEY_LEN * 2 {
+        return None;
+    }
+    let number = u128::from_str_radix(hex, 16).ok()?;
+    Some(number.to_be_bytes())
+}
+
+/// IV 的十六进制表示（带 `0x` 前缀）
+pub fn format_iv(iv: &[u8; AES_KEY_LEN]) -> String {
+    format!("0x{:032x}", u128::from_be_bytes(*iv))
+}
+
+/// 媒体序号对应的默认 IV
+fn sequence_iv(sequence: u64) -> [u8; AES_KEY_LEN] {
+    u128::from(sequence).to_be_bytes()
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum LineKind {
+    Extinf,
+    Segment,
+    Other,
+}
+
+/// 逐行调用 `visit(行, 类型, 当前或下一个分片的媒体序号, 生效的密钥)`
+fn walk_playlist<'a, F>(content: &'a str, base_url: &str, mut visit: F)
+where
+    F: FnMut(&'a str, LineKind, u64, Option<&ActiveKey<'a>>),
+{
+    let base = Url::parse(base_url).ok();
+    let mut sequence = 0u64;
+    let mut key: Option<ActiveKey<'a>> = None;
+    let mut in_segment = false;
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+        let mut kind = LineKind::Other;
+        if let Some(value) = trimmed.strip_prefix("#EXT-X-MEDIA-SEQUENCE:") {
+            sequence = value.trim().parse().unwrap_or(0);
+        } else if trimmed.starts_with("#EXT-X-KEY:") {
+            match parse_key_tag(line, base.as_ref()) {
+                KeyTag::None => key = None,
+                KeyTag::Key(active) => key = Some(active),
+                KeyTag::Ignored => {}
+            }
+        } else if trimmed.starts_with("#EXTINF") {
+            in_segment = true;
+            kind = LineKind::Extinf;
+        } else if in_segment && !trimmed.is_empty() && !trimmed.starts_with('#') {
+            in_segment = false;
+            kind = LineKind::Segment;
+        }
+        visit(line, kind, sequence, key.as_ref());
+        if kind == LineKind::Segment {
+            sequence += 1;
+        }
+    }
+}
+
+/// 列出媒体播放列表中加密的分片及其密钥地址与 IV
+pub fn segment_encryptions(content: &str, base_url: &str) -> Vec<SegmentEncryption> {
+    let base = Url::parse(base_url).ok();
+    let mut segments = Vec::new();
+    walk_playlist(content, base_url, |line, kind, sequence, key| {
+        let Some(key) = key.filter(|_| kind == LineKind::Segment) else {
+            return;
+        };
+        if let Some(url) = resolve(base.as_ref(), line) {
+            segments.push(SegmentEncryption {
+                url,
+                method: key.method,
+                key_uri: key.uri.clone(),
+                iv: key.iv.unwrap_or_else(|| sequence_iv(sequence)),
+            });
+        }
+    });
+    segments
+}
+
+/// 分片是否为 fMP4（带 `#EXT-X-MAP` 初始化分片）
+pub(crate) fn is_fmp4_playlist(content: &str) -> bool {
+    content.contains("#EXT-X-MAP")
+}
+
+/// 列出可以在服务端解密的加密分片
+pub fn decryptable_segments(content: &str, base_url: &str) -> Vec<SegmentEncryption> {
+    let fmp4 = is_fmp4_playlist(content);
+    let mut segments = segment_encryptions(content, base_url);
+    segments.retain(|segment| segment.method.is_decryptable(fmp4));
+    segments
+}
+
+/// 为未写明 IV 的 CBC 加密分片补上显式 IV
+///
+/// 默认 IV 来自分片的媒体序号，删除广告分片后后续分片的序号会前移，解密随之失败；
+/// 在每个分片前重复一次带 IV 的密钥标签即可不受影响
+pub fn pin_segment_ivs(content: &str) -> String {
+    if !content.contains("#EXT-X-KEY") {
+        return content.to_string();
+    }
+    let mut result = String::with_capacity(content.len() * 2);
+    walk_playlist(content, "", |line, kind, sequence, key| {
+        let unpinned = key.filter(|key| {
+            matches!(
+                key.method,
+                EncryptionMethod::Aes128 | EncryptionMethod::SampleAes
+            ) && key.iv.is_none()
+        });
+        if let (LineKind::Extinf, Some(key)) = (kind, unpinned) {
+            result.push_str(key.line.trim_end());
+            result.push_str(",IV=");
+            result.push_str(&format_iv(&sequence_iv(sequence)));
+            result.push('\n');
+        }
+        result.push_str(line);
+        result.push('\n');
+    });
+    result
+}
+
+/// 删除可以在服务端解密的密钥标签，分片解密后播放器不必再解密
+pub fn strip_decryptable_keys(content: &str) -> String {
+    let fmp4 = is_fmp4_playlist(content);
+    let mut result = String::with_capacity(content.len());
+    for line in content.lines() {
+        if let KeyTag::Key(key) = parse_key_tag(line, None) {
+            if key.method.is_decryptable(fmp4) {
+                continue;
+            }
+        }
+        result.push_str(line);
+        result.push('\n');
+    }
+    result
+}
+
+/// AES-128-CBC（PKCS7 填充）解密整个分片
+pub fn decrypt_aes128(
+    data: &[u8],
+    key: &[u8; AES_KEY_LEN],
+    iv: &[u8; AES_KEY_LEN],
+) -> Result<Vec<u8>, String> {
+    if !data.len().is_multiple_of(AES_KEY_LEN) {
+        return Err(format!("加密分片长度 {} 不是 16 的整数倍", data.len()));
+    }
+    cbc::Decryptor::<Aes128>::new(key.into(), iv.into())
+        .decrypt_padded_vec_mut::<Pkcs7>(data)
+        .map_err(|_| "分片解密失败：密钥错误或填充无效".to_string())
+}
+
+/// 按加密方式解密一个分片，SAMPLE-AES 只支持 TS 分片
+pub fn decrypt_hls_segment(
+    method: EncryptionMethod,
+    data: &[u8],
+    key: &[u8; AES_KEY_LEN],
+    iv: &[u8; AES_KEY_LEN],
+) -> Result<Vec<u8>, String> {
+    match method {
+        EncryptionMethod::Aes128 => decrypt_aes128(data, key, iv),
+        EncryptionMethod::SampleAes => crate::sample_aes::decrypt_sample_aes_ts(data, key, iv),
+        EncryptionMethod::SampleAesCtr => Err("不支持解密 SAMPLE-AES-CTR 加密的分片".to_string()),
+    }
+}
+
+/// 按密钥地址缓存 AES-128 密钥，同一播放列表的分片只下载一次密钥；条目 10 分钟后过期
+pub struct HlsKeyCache {
+    keys: Cache<String, [u8; AES_KEY_LEN]>,
+}
+
+impl Default for HlsKeyCache {
+    fn default() -> Self {
+        Self {
+            keys: Cache::builder()
+                .max_capacity(MAX_CACHED_KEYS)
+                .time_to_live(KEY_TTL)
+                .build(),
+        }
+    }
+}
+
+impl HlsKeyCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 返回缓存的密钥，没有时用 `fetch` 下载（并发请求同一密钥只下载一次）；密钥必须正好 16 字节
+    pub async fn get_or_fetch<F, Fut>(
+        &self,
+        key_uri: &str,
+        fetch: F,
+    ) -> Result<[u8; AES_KEY_LEN], String>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Vec<u8>, String>>,
+    {
+        self.keys
+            .try_get_with(key_uri.to_string(), async {
+                let data = fetch().await?;
+                data.as_slice()
+                    .try_into()
+                    .map_err(|_| format!("密钥长度应为 16 字节，实际 {} 字节", data.len()))
+            })
+            .await
+            .map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cbc::cipher::BlockEncryptMut;
+
+    const KEY: [u8; AES_KEY_LEN] = *b"0123456789abcdef";
+
+    fn encrypt(data: &[u8], iv: &[u8; AES_KEY_LEN]) -> Vec<u8> {
+        cbc::Encryptor::<Aes128>::new(&KEY.into(), iv.into()).encrypt_padded_vec_mut::<Pkcs7>(data)
+    }
+
+    const PLAYLIST: &str = "#EXTM3U\n#EXT-X-MEDIA-SEQUENCE:7\n\
+        #EXT-X-KEY:METHOD=AES-128,URI=\"key.bin\"\n\
+        #EXTINF:4,\nseg7.ts\n\
+        #EXTINF:4,\nhttps://ad.example.com/ad/1.ts\n\
+        #EXTINF:4,\nseg9.ts\n\
+        #EXT-X-KEY:METHOD=AES-128,URI=\"/k2\",IV=0x1F\n\
+        #EXTINF:4,\nseg10.ts\n\
+        #EXT-X-KEY:METHOD=NONE\n\
+        #EXTINF:4,\nseg11.ts\n";
+
+    #[test]
+    fn ivs_come_from_tag_or_media_sequence() {
+        let segments = segment_encryptions(PLAYLIST, "https://cdn.example.com/v/index.m3u8");
+        assert_eq!(segments.len(), 4);
+        assert_eq!(segments[0].url, "https://cdn.example.com/v/seg7.ts");
+        assert_eq!(segments[0].key_uri, "https://cdn.example.com/v/key.bin");
+        assert_eq!(segments[0].iv, sequence_iv(7));
+        assert_eq!(segments[2].iv, sequence_iv(9));
+        assert_eq!(segments[3].key_uri, "https://cdn.example.com/k2");
+        assert_eq!(format_iv(&segments[3].iv), format!("0x{:032x}", 0x1f));
+    }
+
+    #[test]
+    fn ad_filtering_keeps_original_sequence_ivs() {
+        let filtered = crate::playback::filter_ads_from_m3_u8(PLAYLIST);
+        assert!(!filtered.contains("ad.example.com"));
+        let segments = segment_encryptions(&filtered, "https://cdn.example.com/v/index.m3u8");
+        assert_eq!(segments.len(), 3);
+        assert_eq!(segments[1].url, "https://cdn.example.com/v/seg9.ts");
+        assert_eq!(segments[1].iv, sequence_iv(9));
+        assert_eq!(parse_iv("0x1F"), Some(segments[2].iv));
+        assert_eq!(pin_segment_ivs(&filtered).trim_end(), filtered.trim_end());
+
+        let stripped = strip_decryptable_keys(&filtered);
+        assert!(!stripped.contains("AES-128"));
+        assert!(stripped.contains("#EXT-X-KEY:METHOD=NONE"));
+    }
+
+    #[test]
+    fn sample_aes_is_decryptable_only_in_ts_playlists() {
+        let ts = "#EXTM3U\n#EXT-X-MEDIA-SEQUENCE:3\n\
+            #EXT-X-KEY:METHOD=SAMPLE-AES,URI=\"key.bin\"\n\
+            #EXTINF:4,\nseg3.ts\n";
+        let segments = decryptable_segments(ts, "https://cdn.example.com/v/index.m3u8");
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].method, EncryptionMethod::SampleAes);
+        assert!(pin_segment_ivs(ts).contains(",IV=0x00000000000000000000000000000003"));
+        assert!(!strip_decryptable_keys(ts).contains("#EXT-X-KEY"));
+
+        let fmp4 = format!("#EXT-X-MAP:URI=\"init.mp4\"\n{ts}");
+        assert!(decryptable_segments(&fmp4, "https://cdn.example.com/v/index.m3u8").is_empty());
+        assert!(strip_decryptable_keys(&fmp4).contains("METHOD=SAMPLE-AES"));
+
+        let ctr = ts.replace("SAMPLE-AES", "SAMPLE-AES-CTR");
+        assert_eq!(
+            segment_encryptions(&ctr, "https://cdn.example.com/v/index.m3u8")[0].method,
+            EncryptionMethod::SampleAesCtr
+        );
+        assert!(decryptable_segments(&ctr, "https://cdn.example.com/v/index.m3u8").is_empty());
+        assert!(decrypt_hls_segment(EncryptionMethod::SampleAesCtr, &[0; 16], &KEY, &KEY).is_err());
+    }
+
+    #[tokio::test]
+    async fn segments_are_decrypted_and_keys_cached() {
+        let iv = sequence_iv(3);
+        let encrypted = encrypt(b"segment payload", &iv);
+        assert_eq!(
+            decrypt_aes128(&encrypted, &KEY, &iv).unwrap(),
+            b"segment payload"
+        );
+        assert!(decrypt_aes128(&encrypted[..5], &KEY, &iv).is_err());
+
+        let cache = HlsKeyCache::new();
+        let key = cache
+            .get_or_fetch("k", || async { Ok(KEY.to_vec()) })
+            .await
+            .unwrap();
+        assert_eq!(key, KEY);
+        let cached = cache
+            .get_or_fetch("k", || async { Err("不应再次下载".to_string()) })
+            .await;
+        assert_eq!(cached, Ok(KEY));
+        assert!(cache
+            .get_or_fetch("short", || async { Ok(vec![0; 8]) })
+            .await
+            .is_err());
+    }
+}
@@ -1,21 +1,79 @@
 pub mod admin_config;
 pub mod adult;
+pub mod byte_range;
+pub mod dns;
+pub mod download;
+pub mod epg;
+pub mod hls_crypto;
+pub mod live;
+pub mod mirror;
+pub mod network_proxy;
+pub mod outbound;
+pub mod pinyin;
 pub mod playback;
+pub mod playlist;
+pub mod prefetch;
+pub mod request_profile;
+pub mod sample_aes;
 pub mod search_aggregation;
+pub mod segment_sniff;
 pub mod source_selection;
+pub mod spell_correction;
+pub mod subscription;
 pub mod types;
+pub mod url_safety;
 
 pub use admin_config::default_admin_config_value;
 pub use admin_config::merge_admin_config_with_defaults;
 pub use admin_config::normalize_source_config;
 pub use admin_config::parse_admin_config;
 pub use adult::{filter_adult_sources, is_adult_source};
+pub use byte_range::{parse_range, ByteRange};
+pub use dns::{is_local_or_private_ip, DnsResolver, DnsSettings, HostOverrides};
+pub use download::{
+    best_variant, BandwidthLimiter, DownloadOutput, DownloadPlan, DownloadSettings, PlannedSegment,
+};
+pub use epg::{
+    local_day_bounds, parse_xmltv, parse_xmltv_time, tvbox_live_epg_urls, EpgChannel, EpgGuide,
+    EpgProgramme,
+};
+pub use hls_crypto::{
+    decrypt_aes128, decrypt_hls_segment, decryptable_segments, pin_segment_ivs,
+    segment_encryptions, strip_decryptable_keys, EncryptionMethod, HlsKeyCache, SegmentEncryption,
+};
+pub use live::{
+    detect_live_format, normalize_channel_name, parse_live_list, parse_m3u, parse_txt, Catchup,
+    LiveChannel, LiveGroup, LiveListFormat, LivePlaylist, DEFAULT_LIVE_GROUP,
+};
+pub use mirror::{
+    merge_mirror_sources, source_endpoints, source_has_key, MirrorStatus, MirrorStrategy,
+    MirrorTracker,
+};
+pub use network_proxy::{ProxyCategory, ProxySettings};
+pub use outbound::{OutboundPermit, OutboundScheduler, RequestPolicy};
 pub use playback::{filter_ads_from_m3_u8, SkipAction, SkipDetection};
+pub use playlist::{media_segments, rewrite_playlist_uris, MediaSegment, PlaylistUriKind};
+pub use prefetch::{BufferMode, SegmentPrefetcher};
+pub use request_profile::{
+    apply_request_defaults, MediaProfileRegistry, RequestProfile, DEFAULT_USER_AGENT,
+    MOBILE_USER_AGENT,
+};
 pub use search_aggregation::{
-    aggregate_search_results, apply_filter, compute_group_stats, sort_by_year, AggregatedGroup,
-    SearchFilter, YearOrder,
+    aggregate_search_results, apply_filter, compute_group_stats, diff_search_results, sort_by_year,
+    AggregatedGroup, SearchFilter, SearchResultDiff, YearOrder,
+};
+pub use segment_sniff::{
+    find_disguised_segment, image_content_type, sniff_segment_format, unwrap_disguised_segment,
+    DisguisedSegment, SegmentFormat, SegmentUnwrapStats,
 };
 pub use source_selection::{
     calculate_source_score, prefer_best_source, test_video_source, SourceTestResult,
 };
+pub use spell_correction::{SpellCorrector, SpellSuggestion};
+pub use subscription::{
+    apply_subscription_sources, merge_subscription_sources, normalize_api_url,
+    remove_subscription_sources, source_subscription, stable_hash, subscriptions_from_config,
+    Subscription, LEGACY_SUBSCRIPTION_KEY, SUBSCRIPTION_FIELD,
+};
 pub use types::SearchResult;
+pub use url_safety::{check_remote_url, validate_remote_url};
@@ -0,0 +1,614 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// 没有分组信息的频道归入的分组
+pub const DEFAULT_LIVE_GROUP: &str = "未分组";
+
+/// 回看（时移）配置，来自 M3U 的 catchup 系列属性
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Catchup {
+    /// 回看方式，如 `append`、`default`、`shift`、`flussonic`
+    pub mode: String,
+    /// 回看地址模板，`append` 方式下拼接在直播地址后
+    pub source: Option<String>,
+    /// 可回看的天数
+    pub days: Option<u32>,
+}
+
+/// 直播频道，同名频道的多个地址合并为一个频道，按顺序作为备用线路
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LiveChannel {
+    pub name: String,
+    pub tvg_id: Option<String>,
+    pub tvg_name: Option<String>,
+    pub logo: Option<String>,
+    pub urls: Vec<String>,
+    pub catchup: Option<Catchup>,
+}
+
+impl LiveChannel {
+    /// 匹配 EPG 使用的名称：优先 tvg-name，其次频道名
+    pub fn epg_name(&self) -> &str {
+        self.tvg_name.as_deref().unwrap_or(&self.name)
+    }
+
+    /// 合并另一条同名频道记录：追加新地址，缺失的元数据用对方补全
+    fn absorb(&mut self, other: LiveChannel) {
+        for url in other.urls {
+            if !self.urls.contains(&url) {
+                self.urls.push(url);
+            }
+        }
+        if self.tvg_id.is_none() {
+            self.tvg_id = other.tvg_id;
+        }
+        if self.tvg_name.is_none() {
+            self.tvg_name = other.tvg_name;
+        }
+        if self.logo.is_none() {
+            self.logo = other.logo;
+        }
+        if self.catchup.is_none() {
+            self.catchup = other.catchup;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LiveGroup {
+    pub name: String,
+    pub channels: Vec<LiveChannel>,
+}
+
+/// 解析后的频道列表，保持分组与频道在原文件中的顺序
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LivePlaylist {
+    /// 列表头声明的 EPG 地址（`x-tvg-url` / `url-tvg`）
+    pub epg_urls: Vec<String>,
+    pub groups: Vec<LiveGroup>,
+}
+
+/// （分组名，归一化频道名）到频道位置的索引，解析与合并时在本地建立
+type ChannelPositions = HashMap<(String, String), (usize, usize)>;
+
+impl LivePlaylist {
+    fn channel_positions(&self) -> ChannelPositions {
+        let mut positions = HashMap::new();
+        for (group_index, group) in self.groups.iter().enumerate() {
+            for (channel_index, channel) in group.channels.iter().enumerate() {
+                positions
+                    .entry((group.name.clone(), normalize_channel_name(&channel.name)))
+                    .or_insert((group_index, channel_index));
+            }
+        }
+        positions
+    }
+
+    /// 把频道加入分组，分组内同名频道合并地址
+    pub fn push_channel(&mut self, group: &str, channel: LiveChannel) {
+        let mut positions = self.channel_positions();
+        self.push_indexed(&mut positions, group, channel);
+    }
+
+    /// 按索引查找同组同名频道，`positions` 须与当前列表一致
+    fn push_indexed(
+        &mut self,
+        positions: &mut ChannelPositions,
+        group: &str,
+        channel: LiveChannel,
+    ) {
+        if channel.urls.is_empty() {
+            return;
+        }
+        let group_name = match group.trim() {
+            "" => DEFAULT_LIVE_GROUP,
+            name => name,
+        };
+        let key = (
+            group_name.to_string(),
+            normalize_channel_name(&channel.name),
+        );
+        if let Some(&(group_index, channel_index)) = positions.get(&key) {
+            self.groups[group_index].channels[channel_index].absorb(channel);
+            return;
+        }
+
+        let group_index = match self.groups.iter().position(|g| g.name == group_name) {
+            Some(index) => index,
+            None => {
+                self.groups.push(LiveGroup {
+                    name: group_name.to_string(),
+                    channels: Vec::new(),
+                });
+                self.groups.len() - 1
+            }
+        };
+        let channels = &mut self.groups[group_index].channels;
+        positions.insert(key, (group_index, channels.len()));
+        channels.push(channel);
+    }
+
+    /// 合并另一份列表（多个订阅），同组同名频道的地址合并去重
+    pub fn merge(&mut self, other: LivePlaylist) {
+        for url in other.epg_urls {
+            if !self.epg_urls.contains(&url) {
+                self.epg_urls.push(url);
+            }
+        }
+        let mut positions = self.channel_positions();
+        for group in other.groups {
+            for channel in group.channels {
+                self.push_indexed(&mut positions, &group.name, channel);
+            }
+        }
+    }
+
+    pub fn channel_count(&self) -> usize {
+        self.groups.iter().map(|group| group.channels.len()).sum()
+    }
+
+    /// 只保留满足条件的地址，没有地址的频道与没有频道的分组一并移除
+    pub fn retain_urls<F>(&mut self, mut keep: F)
+    where
+        F: FnMut(&str) -> bool,
+    {
+        for group in &mut self.groups {
+            for channel in &mut group.channels {
+                channel.urls.retain(|url| keep(url));
+            }
+            group.channels.retain(|channel| !channel.urls.is_empty());
+        }
+        self.groups.retain(|group| !group.channels.is_empty());
+    }
+
+    /// 导出为 M3U，每个地址一条 `#EXTINF`，播放器按同名条目切换线路
+    pub fn to_m3u(&self) -> String {
+        let mut output = String::from("#EXTM3U");
+        if !self.epg_urls.is_empty() {
+            output.push_str(&format!(
+                " x-tvg-url=\"{}\"",
+                m3u_attribute_value(&self.epg_urls.join(","))
+            ));
+        }
+        output.push('\n');
+        for group in &self.groups {
+            for channel in &group.channels {
+                let mut attrs = Vec::new();
+                let mut push = |key: &str, value: Option<&str>| {
+                    if let Some(value) = value.filter(|v| !v.is_empty()) {
+                        attrs.push(format!("{}=\"{}\"", key, m3u_attribute_value(value)));
+                    }
+                };
+                push("tvg-id", channel.tvg_id.as_deref());
+                push("tvg-name", channel.tvg_name.as_deref());
+                push("tvg-logo", channel.logo.as_deref());
+                push("group-title", Some(&group.name));
+                if let Some(catchup) = &channel.catchup {
+                    push("catchup", Some(&catchup.mode));
+                    push("catchup-source", catchup.source.as_deref());
+                    push(
+                        "catchup-days",
+                        catchup.days.map(|d| d.to_string()).as_deref(),
+                    );
+                }
+                for url in &channel.urls {
+                    output.push_str(&format!(
+                        "#EXTINF:-1 {},{}\n{}\n",
+                        attrs.join(" "),
+                        channel.name,
+                        url
+                    ));
+                }
+            }
+        }
+        output
+    }
+
+    /// 导出为 TVBox TXT，同一频道的多个地址以 `#` 连接
+    pub fn to_txt(&self) -> String {
+        let mut output = String::new();
+        for group in &self.groups {
+            output.push_str(&format!("{},#genre#\n", group.name.replace(',', "，")));
+            for channel in &group.channels {
+                output.push_str(&format!(
+                    "{},{}\n",
+                    channel.name.replace(',', "，"),
+                    channel.urls.join("#")
+                ));
+            }
+        }
+        output
+    }
+}
+
+/// M3U 属性值中不能出现双引号
+fn m3u_attribute_value(value: &str) -> String {
+    value.replace('"', "'")
+}
+
+/// 频道名归一化：忽略大小写、空白与连字符，`CCTV-1` 与 `cctv 1` 视为同一频道
+pub fn normalize_channel_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| !c.is_whitespace() && !matches!(c, '-' | '_'))
+        .flat_map(char::to_uppercase)
+        .collect()
+}
+
+/// 频道列表格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LiveListFormat {
+    M3u,
+    Txt,
+}
+
+/// 按内容判断格式：以 `#EXTM3U` 开头或含 `#EXTINF` 的为 M3U，其余按 TVBox TXT 处理
+pub fn detect_live_format(content: &str) -> LiveListFormat {
+    let head = content.trim_start_matches('\u{feff}').trim_start();
+    if head.starts_with("#EXTM3U") || content.contains("#EXTINF") {
+        LiveListFormat::M3u
+    } else {
+        LiveListFormat::Txt
+    }
+}
+
+/// 自动识别格式并解析频道列表
+pub fn parse_live_list(content: &str) -> LivePlaylist {
+    match detect_live_format(content) {
+        LiveListFormat::M3u => parse_m3u(content),
+        LiveListFormat::Txt => parse_txt(content),
+    }
+}
+
+/// 解析 M3U 频道列表
+///
+/// 支持 `#EXTINF` 的 tvg-id、tvg-name、tvg-logo、group-title 与 catchup 系列属性，
+/// `#EXTGRP` 分组，以及列表头的 EPG 地址和默认回看配置
+pub fn parse_m3u(content: &str) -> LivePlaylist {
+    let mut playlist = LivePlaylist::default();
+    let mut positions = ChannelPositions::new();
+    let mut default_catchup: Option<Catchup> = None;
+    let mut pending: Option<(String, LiveChannel)> = None;
+    let mut extgrp: Option<String> = None;
+
+    for line in content.lines() {
+        let line = line.trim().trim_start_matches('\u{feff}');
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(rest) = line.strip_prefix("#EXTM3U") {
+            let (attrs, _) = split_extinf(rest);
+            for key in ["x-tvg-url", "url-tvg"] {
+                if let Some(urls) = attribute(&attrs, key) {
+                    for url in urls.split(',').map(str::trim).filter(|u| !u.is_empty()) {
+                        if !playlist.epg_urls.iter().any(|existing| existing == url) {
+                            playlist.epg_urls.push(url.to_string());
+                        }
+                    }
+                }
+            }
+            default_catchup = parse_catchup(&attrs);
+        } else if let Some(rest) = line.strip_prefix("#EXTINF:") {
+            let (attrs, title) = split_extinf(rest);
+            let tvg_name = attribute(&attrs, "tvg-name");
+            let name = match title.trim() {
+                "" => tvg_name.clone().unwrap_or_default(),
+                title => title.to_string(),
+            };
+            let group = attribute(&attrs, "group-title")
+                .or_else(|| extgrp.clone())
+                .unwrap_or_default();
+            let catchup = parse_catchup(&attrs).or_else(|| default_catchup.clone());
+            pending = Some((
+                group,
+                LiveChannel {
+                    name,
+                    tvg_id: attribute(&attrs, "tvg-id"),
+                    tvg_name,
+                    logo: attribute(&attrs, "tvg-logo"),
+                    urls: Vec::new(),
+                    catchup,
+                },
+            ));
+        } else if let Some(group) = line.strip_prefix("#EXTGRP:") {
+            let group = group.trim();
+            extgrp = (!group.is_empty()).then(|| group.to_string());
+            if let Some((pending_group, _)) = pending.as_mut() {
+                if pending_group.is_empty() {
+                    *pending_group = group.to_string();
+                }
+            }
+        } else if line.starts_with('#') {
+            continue;
+        } else if let Some((group, mut channel)) = pending.take() {
+            if !channel.name.is_empty() {
+                channel.urls.push(line.to_string());
+                playlist.push_indexed(&mut positions, &group, channel);
+            }
+        }
+    }
+    playlist
+}
+
+/// 解析 TVBox TXT 频道列表：`分组,#genre#` 开始一个分组，`频道名,地址1#地址2` 为频道
+pub fn parse_txt(content: &str) -> LivePlaylist {
+    let mut playlist = LivePlaylist::default();
+    let mut positions = ChannelPositions::new();
+    let mut group = String::new();
+
+    for line in content.lines() {
+        let line = line.trim().trim_start_matches('\u{feff}');
+        let Some((name, value)) = line.split_once(',') else {
+            continue;
+        };
+        let (name, value) = (name.trim(), value.trim());
+        if value.eq_ignore_ascii_case("#genre#") {
+            group = name.to_string();
+            continue;
+        }
+        if name.is_empty() || value.is_empty() {
+            continue;
+        }
+        playlist.push_indexed(
+            &mut positions,
+            &group,
+            LiveChannel {
+                name: name.to_string(),
+                urls: split_txt_urls(value),
+                ..Default::default()
+            },
+        );
+    }
+    playlist
+}
+
+/// TXT 中多个地址以 `#` 分隔；不含 `://` 的片段属于前一个地址（如 URL 片段），`$` 之后是线路名
+fn split_txt_urls(value: &str) -> Vec<String> {
+    let mut urls: Vec<String> = Vec::new();
+    for part in value.split('#') {
+        match urls.last_mut() {
+            Some(last) if !part.contains("://") => {
+                last.push('#');
+                last.push_str(part);
+            }
+            _ => urls.push(part.to_string()),
+        }
+    }
+    let mut result: Vec<String> = Vec::new();
+    for url in urls {
+        let url = url.split('$').next().unwrap_or_default().trim().to_string();
+        if !url.is_empty() && !result.contains(&url) {
+            result.push(url);
+        }
+    }
+    result
+}
+
+/// 把 `#EXTINF` 的内容拆成属性列表与标题（第一个不在引号内的逗号之后）
+fn split_extinf(rest: &str) -> (Vec<(String, String)>, &str) {
+    let mut in_quotes = false;
+    let mut split_at = None;
+    for (index, c) in rest.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => {
+                split_at = Some(index);
+                break;
+            }
+            _ => {}
+        }
+    }
+    let (attrs, title) = match split_at {
+        Some(index) => (&rest[..index], &rest[index + 1..]),
+        None => (rest, ""),
+    };
+    (parse_attributes(attrs), title)
+}
+
+/// 解析 `key="value"` 或 `key=value` 形式的属性，键统一为小写
+pub(crate) fn parse_attributes(input: &str) -> Vec<(String, String)> {
+    let mut attrs = Vec::new();
+    let mut rest = input;
+    while let Some(eq) = rest.find('=') {
+        let key = rest[..eq]
+            .rsplit(char::is_whitespace)
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+        let after = &rest[eq + 1..];
+        let (value, remaining) = match after.strip_prefix('"') {
+            Some(quoted) => match quoted.find('"') {
+                Some(end) => (&quoted[..end], &quoted[end + 1..]),
+                None => (quoted, ""),
+            },
+            None => {
+                let end = after.find(char::is_whitespace).unwrap_or(after.len());
+                (&after[..end], &after[end..])
+            }
+        };
+        if !key.is_empty() {
+            attrs.push((key, value.trim().to_string()));
+        }
+        rest = remaining;
+    }
+    attrs
+}
+
+fn attribute(attrs: &[(String, String)], key: &str) -> Option<String> {
+    attrs
+        .iter()
+        .find(|(name, value)| name == key && !value.is_empty())
+        .map(|(_, value)| value.clone())
+}
+
+fn parse_catchup(attrs: &[(String, String)]) -> Option<Catchup> {
+    let mode = attribute(attrs, "catchup");
+    let source = attribute(attrs, "catchup-source");
+    if mode.is_none() && source.is_none() {
+        return None;
+    }
+    Some(Catchup {
+        mode: mode.unwrap_or_else(|| "default".to_string()),
+        source,
+        days: attribute(attrs, "catchup-days").and_then(|days| days.parse().ok()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn m3u_attributes_groups_and_catchup() {
+        let content = "\u{feff}#EXTM3U x-tvg-url=\"https://epg.example.com/e.xml.gz,https://epg2.example.com/e.xml\" catchup=\"append\" catchup-source=\"?playseek=${(b)yyyyMMddHHmmss}\"\n\
+#EXTINF:-1 tvg-id=\"cctv1\" tvg-name=\"CCTV1\" tvg-logo=\"https://logo.example.com/cctv1.png\" group-title=\"央视\",CCTV-1 综合\n\
+http://a.example.com/cctv1.m3u8\n\
+#EXTINF:-1 tvg-id=\"cctv1\" group-title=\"央视\" catchup-days=\"7\",CCTV-1 综合\n\
+http://b.example.com/cctv1.m3u8\n\
+#EXTINF:-1 tvg-name=\"HN\" catchup=\"shift\" catchup-days=\"3\",湖南卫视\n\
+#EXTGRP:卫视\n\
+#EXTVLCOPT:http-user-agent=Mozilla\n\
+http://a.example.com/hunan.m3u8\n\
+#EXTINF:-1,无地址频道\n";
+        let playlist = parse_m3u(content);
+
+        assert_eq!(
+            playlist.epg_urls,
+            vec![
+                "https://epg.example.com/e.xml.gz",
+                "https://epg2.example.com/e.xml"
+            ]
+        );
+        assert_eq!(playlist.groups.len(), 2);
+        let cctv = &playlist.groups[0];
+        assert_eq!(cctv.name, "央视");
+        assert_eq!(cctv.channels.len(), 1);
+        let channel = &cctv.channels[0];
+        assert_eq!(channel.name, "CCTV-1 综合");
+        assert_eq!(channel.tvg_id.as_deref(), Some("cctv1"));
+        assert_eq!(channel.epg_name(), "CCTV1");
+        assert_eq!(
+            channel.urls,
+            vec![
+                "http://a.example.com/cctv1.m3u8",
+                "http://b.example.com/cctv1.m3u8"
+            ]
+        );
+        let catchup = channel.catchup.as_ref().unwrap();
+        assert_eq!(catchup.mode, "append");
+        assert_eq!(
+            catchup.source.as_deref(),
+            Some("?playseek=${(b)yyyyMMddHHmmss}")
+        );
+
+        let satellite = &playlist.groups[1];
+        assert_eq!(satellite.name, "卫视");
+        let hunan = &satellite.channels[0];
+        assert_eq!(hunan.catchup.as_ref().unwrap().mode, "shift");
+        assert_eq!(hunan.catchup.as_ref().unwrap().days, Some(3));
+        assert_eq!(playlist.channel_count(), 2);
+    }
+
+    #[test]
+    fn m3u_titles_may_contain_commas_inside_quoted_attributes() {
+        let content = "#EXTM3U\n#EXTINF:-1 group-title=\"新闻,资讯\" tvg-logo=\"a.png\",凤凰, 资讯台\nhttp://a.example.com/p.m3u8\n";
+        let playlist = parse_m3u(content);
+        assert_eq!(playlist.groups[0].name, "新闻,资讯");
+        assert_eq!(playlist.groups[0].channels[0].name, "凤凰, 资讯台");
+    }
+
+    #[test]
+    fn txt_groups_and_multiple_urls() {
+        let content = "央视频道,#genre#\n\
+CCTV1,http://a.example.com/1.m3u8#http://b.example.com/1.m3u8$线路2\n\
+cctv 1,http://c.example.com/1.m3u8\n\
+CCTV2,http://a.example.com/2.m3u8#token\n\
+\n\
+卫视频道,#genre#\n\
+湖南卫视,http://a.example.com/hunan.m3u8\n\
+无效行\n";
+        let playlist = parse_txt(content);
+        assert_eq!(playlist.groups.len(), 2);
+        let cctv = &playlist.groups[0];
+        assert_eq!(cctv.channels.len(), 2);
+        assert_eq!(
+            cctv.channels[0].urls,
+            vec![
+                "http://a.example.com/1.m3u8",
+                "http://b.example.com/1.m3u8",
+                "http://c.example.com/1.m3u8"
+            ]
+        );
+        assert_eq!(
+            cctv.channels[1].urls,
+            vec!["http://a.example.com/2.m3u8#token"]
+        );
+        assert_eq!(playlist.groups[1].name, "卫视频道");
+    }
+
+    #[test]
+    fn format_detection_and_merge() {
+        assert_eq!(detect_live_format("#EXTM3U\n"), LiveListFormat::M3u);
+        assert_eq!(
+            detect_live_format("分组,#genre#\nA,http://a\n"),
+            LiveListFormat::Txt
+        );
+
+        let mut merged = parse_live_list("A,http://a.example.com/a.m3u8\n");
+        assert_eq!(merged.groups[0].name, DEFAULT_LIVE_GROUP);
+        merged.merge(parse_live_list(
+            "#EXTM3U url-tvg=\"https://epg.example.com/e.xml\"\n#EXTINF:-1 tvg-logo=\"a.png\",A\nhttp://b.example.com/a.m3u8\n#EXTINF:-1,A\nhttp://a.example.com/a.m3u8\n",
+        ));
+        assert_eq!(merged.epg_urls, vec!["https://epg.example.com/e.xml"]);
+        assert_eq!(merged.channel_count(), 1);
+        let channel = &merged.groups[0].channels[0];
+        assert_eq!(
+            channel.urls,
+            vec!["http://a.example.com/a.m3u8", "http://b.example.com/a.m3u8"]
+        );
+        assert_eq!(channel.logo.as_deref(), Some("a.png"));
+    }
+
+    #[test]
+    fn exports_round_trip_and_drop_dead_urls() {
+        let mut playlist = parse_m3u(
+            "#EXTM3U x-tvg-url=\"https://epg.example.com/e.xml\"\n\
+#EXTINF:-1 tvg-id=\"cctv1\" tvg-logo=\"a.png\" group-title=\"央视\" catchup=\"append\" catchup-days=\"7\",CCTV-1\n\
+http://a.example.com/1.m3u8\n\
+#EXTINF:-1 group-title=\"央视\",CCTV-1\n\
+http://dead.example.com/1.m3u8\n\
+#EXTINF:-1 group-title=\"地方\",失效频道\n\
+http://dead.example.com/2.m3u8\n",
+        );
+        playlist.retain_urls(|url| !url.contains("dead"));
+        assert_eq!(playlist.groups.len(), 1);
+        assert_eq!(playlist.groups[0].channels[0].urls.len(), 1);
+
+        let m3u = playlist.to_m3u();
+        assert!(m3u.starts_with("#EXTM3U x-tvg-url=\"https://epg.example.com/e.xml\"\n"));
+        assert_eq!(parse_m3u(&m3u), playlist);
+
+        let txt = playlist.to_txt();
+        assert_eq!(txt, "央视,#genre#\nCCTV-1,http://a.example.com/1.m3u8\n");
+        assert_eq!(
+            parse_txt(&txt).groups[0].channels[0].urls,
+            playlist.groups[0].channels[0].urls
+        );
+
+        // 移除线路后继续合并，同名频道仍能找到
+        playlist.push_channel(
+            "央视",
+            LiveChannel {
+                name: "cctv 1".to_string(),
+                urls: vec!["http://b.example.com/1.m3u8".to_string()],
+                ..Default::default()
+            },
+        );
+        assert_eq!(playlist.channel_count(), 1);
+        assert_eq!(playlist.groups[0].channels[0].urls.len(), 2);
+    }
+}
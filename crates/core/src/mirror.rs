@@ -0,0 +1,383 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// 镜像首次失败后的冷却时间（秒），连续失败时翻倍
+const MIRROR_BASE_COOLDOWN_SECS: u64 = 30;
+/// 镜像冷却时间上限（秒）
+const MIRROR_MAX_COOLDOWN_SECS: u64 = 600;
+/// 没有延迟样本时的对冲等待时间
+const DEFAULT_HEDGE_DELAY_MS: u64 = 1_500;
+const MIN_HEDGE_DELAY_MS: u64 = 500;
+const MAX_HEDGE_DELAY_MS: u64 = 4_000;
+
+/// 多镜像源的请求方式，可在源配置的 `mirror_strategy` 字段中指定
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MirrorStrategy {
+    /// 依次尝试，当前镜像失败后才切换到下一个
+    #[default]
+    Failover,
+    /// 当前镜像超过对冲延迟仍未返回时，同时请求下一个镜像，取先成功者
+    Hedged,
+}
+
+impl MirrorStrategy {
+    pub fn from_source(source: &Value) -> Self {
+        source
+            .get("mirror_strategy")
+            .and_then(|v| serde_json::from_value(v.clone()).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// 源的全部接口地址：`api` 在前，其后为 `mirrors` 中的镜像（去重、去空）
+pub fn source_endpoints(source: &Value) -> Vec<String> {
+    let mut endpoints: Vec<String> = Vec::new();
+    let primary = source.get("api").and_then(|v| v.as_str());
+    let mirrors = source
+        .get("mirrors")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|v| v.as_str());
+
+    for endpoint in primary.into_iter().chain(mirrors) {
+        let endpoint = endpoint.trim();
+        if !endpoint.is_empty() && !endpoints.iter().any(|e| e == endpoint) {
+            endpoints.push(endpoint.to_string());
+        }
+    }
+    endpoints
+}
+
+fn source_key(source: &Value) -> Option<&str> {
+    source
+        .get("key")
+        .and_then(|v| v.as_str())
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+}
+
+/// 源的 key 或合并镜像时保留下来的别名（`aliases`）是否为 `key`
+pub fn source_has_key(source: &Value, key: &str) -> bool {
+    source_key(source) == Some(key)
+        || source
+            .get("aliases")
+            .and_then(|v| v.as_array())
+            .is_some_and(|aliases| aliases.iter().any(|alias| alias.as_str() == Some(key)))
+}
+
+/// 合并订阅中同一站点的多个条目：key 相同，或接口地址出现在另一条目的 `mirrors` 中。
+/// 后出现的地址并入首个条目的 `mirrors`，其 key 保留为首个条目的别名
+pub fn merge_mirror_sources(sources: Vec<Value>) -> Vec<Value> {
+    let mut merged: Vec<Value> = Vec::with_capacity(sources.len());
+    let mut index_by_key: HashMap<String, usize> = HashMap::new();
+    let mut index_by_endpoint: HashMap<String, usize> = HashMap::new();
+
+    for source in sources {
+        let endpoints = source_endpoints(&source);
+        let key = source_key(&source).map(str::to_string);
+        let existing = key
+            .as_ref()
+            .and_then(|key| index_by_key.get(key))
+            .or_else(|| {
+                endpoints
+                    .iter()
+                    .find_map(|endpoint| index_by_endpoint.get(endpoint))
+            })
+            .copied();
+
+        let index = match existing {
+            Some(index) => {
+                let target = &mut merged[index];
+                let mut merged_endpoints = source_endpoints(target);
+                for endpoint in &endpoints {
+                    if !merged_endpoints.contains(endpoint) {
+                        merged_endpoints.push(endpoint.clone());
+                    }
+                }
+                let alias = key
+                    .as_ref()
+                    .filter(|key| !source_has_key(target, key))
+                    .cloned();
+                if let Some(obj) = target.as_object_mut() {
+                    set_source_mirrors(obj, merged_endpoints.into_iter().skip(1).collect());
+                    if let Some(alias) = alias {
+                        add_source_alias(obj, alias);
+                    }
+                }
+                index
+            }
+            None => {
+                merged.push(source);
+                merged.len() - 1
+            }
+        };
+        if let Some(key) = key {
+            index_by_key.entry(key).or_insert(index);
+        }
+        for endpoint in endpoints {
+            index_by_endpoint.entry(endpoint).or_insert(index);
+        }
+    }
+
+    merged
+}
+
+/// 镜像对外展示的健康状态
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct MirrorStatus {
+    pub endpoint: String,
+    pub consecutive_failures: u32,
+    pub avg_latency_ms: Option<u64>,
+    /// 剩余冷却时间（毫秒），为 0 表示可用
+    pub cooldown_remaining_ms: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+struct MirrorHealth {
+    consecutive_failures: u32,
+    blocked_until: Option<Instant>,
+    latency_ms: Option<f64>,
+}
+
+impl MirrorHealth {
+    fn is_blocked_at(&self, now: Instant) -> bool {
+        self.blocked_until.is_some_and(|until| until > now)
+    }
+}
+
+/// 按接口地址记录镜像健康度，决定请求顺序与对冲时机
+#[derive(Default)]
+pub struct MirrorTracker {
+    mirrors: Mutex<HashMap<String, MirrorHealth>>,
+}
+
+impl MirrorTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 请求顺序：可用镜像保持配置顺序在前，冷却中的镜像按解封先后排在最后
+    pub fn order(&self, endpoints: &[String]) -> Vec<String> {
+        self.order_at(endpoints, Instant::now())
+    }
+
+    fn order_at(&self, endpoints: &[String], now: Instant) -> Vec<String> {
+        let mirrors = self.mirrors.lock().unwrap();
+        let mut ranked: Vec<(Option<Instant>, usize, &String)> = endpoints
+            .iter()
+            .enumerate()
+            .map(|(index, endpoint)| {
+                let blocked_until = mirrors
+                    .get(endpoint)
+                    .filter(|health| health.is_blocked_at(now))
+                    .and_then(|health| health.blocked_until);
+                (blocked_until, index, endpoint)
+            })
+            .collect();
+        ranked.sort_by_key(|(blocked_until, index, _)| (*blocked_until, *index));
+        ranked
+            .into_iter()
+            .map(|(_, _, endpoint)| endpoint.clone())
+            .collect()
+    }
+
+    pub fn record_success(&self, endpoint: &str, latency: Duration) {
+        let mut mirrors = self.mirrors.lock().unwrap();
+        let health = mirrors.entry(endpoint.to_string()).or_default();
+        let latency_ms = latency.as_millis() as f64;
+        health.latency_ms = Some(match health.latency_ms {
+            Some(avg) => avg * 0.8 + latency_ms * 0.2,
+            None => latency_ms,
+        });
+        health.consecutive_failures = 0;
+        health.blocked_until = None;
+    }
+
+    pub fn record_failure(&self, endpoint: &str) {
+        self.record_failure_at(endpoint, Instant::now());
+    }
+
+    fn record_failure_at(&self, endpoint: &str, now: Instant) {
+        let mut mirrors = self.mirrors.lock().unwrap();
+        let health = mirrors.entry(endpoint.to_string()).or_default();
+        health.consecutive_failures = health.consecutive_failures.saturating_add(1);
+        let shift = (health.consecutive_failures - 1).min(16);
+        let cooldown = MIRROR_BASE_COOLDOWN_SECS
+            .saturating_mul(1 << shift)
+            .min(MIRROR_MAX_COOLDOWN_SECS);
+        health.blocked_until = Some(now + Duration::from_secs(cooldown));
+    }
+
+    /// 对冲等待时间：该镜像平均延迟的两倍，无样本时使用默认值
+    pub fn hedge_delay(&self, endpoint: &str) -> Duration {
+        let mirrors = self.mirrors.lock().unwrap();
+        let delay_ms = mirrors
+            .get(endpoint)
+            .and_then(|health| health.latency_ms)
+            .map(|avg| ((avg * 2.0) as u64).clamp(MIN_HEDGE_DELAY_MS, MAX_HEDGE_DELAY_MS))
+            .unwrap_or(DEFAULT_HEDGE_DELAY_MS);
+        Duration::from_millis(delay_ms)
+    }
+
+    pub fn status(&self, endpoints: &[String]) -> Vec<MirrorStatus> {
+        let now = Instant::now();
+        let mirrors = self.mirrors.lock().unwrap();
+        endpoints
+            .iter()
+            .map(|endpoint| {
+                let health = mirrors.get(endpoint).cloned().unwrap_or_default();
+                MirrorStatus {
+                    endpoint: endpoint.clone(),
+                    consecutive_failures: health.consecutive_failures,
+                    avg_latency_ms: health.latency_ms.map(|avg| avg as u64),
+                    cooldown_remaining_ms: health
+                        .blocked_until
+                        .map(|until| until.saturating_duration_since(now).as_millis() as u64)
+                        .unwrap_or(0),
+                }
+            })
+            .collect()
+    }
+}
+
+/// 把镜像列表写回源配置，空列表时移除该字段
+fn set_source_mirrors(source: &mut Map<String, Value>, mirrors: Vec<String>) {
+    if mirrors.is_empty() {
+        source.remove("mirrors");
+    } else {
+        let mirrors = mirrors.into_iter().map(Value::String).collect();
+        source.insert("mirrors".to_string(), Value::Array(mirrors));
+    }
+}
+
+fn add_source_alias(source: &mut Map<String, Value>, alias: String) {
+    let aliases = source
+        .entry("aliases")
+        .or_insert_with(|| Value::Array(Vec::new()));
+    if let Some(aliases) = aliases.as_array_mut() {
+        aliases.push(Value::String(alias));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn endpoints_put_api_first_and_dedupe() {
+        let source = json!({
+            "api": "https://a.example.com/api.php/provide/vod",
+            "mirrors": [
+                "https://b.example.com/api.php/provide/vod",
+                " https://a.example.com/api.php/provide/vod ",
+                ""
+            ]
+        });
+        assert_eq!(
+            source_endpoints(&source),
+            vec![
+                "https://a.example.com/api.php/provide/vod".to_string(),
+                "https://b.example.com/api.php/provide/vod".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn merge_follows_declared_mirrors_and_keeps_aliases() {
+        let sources = vec![
+            json!({
+                "key": "a",
+                "name": "资源站",
+                "api": "https://a.example.com/api.php/provide/vod/",
+                "mirrors": ["https://b.example.com/api.php/provide/vod"]
+            }),
+            json!({"key": "b", "name": "其他站", "api": "https://c.example.com/api.php/provide/vod"}),
+            json!({"key": "a2", "name": "资源站", "api": "https://b.example.com/api.php/provide/vod"}),
+            json!({"key": "a", "name": "资源站", "api": "https://d.example.com/api.php/provide/vod"}),
+        ];
+
+        let merged = merge_mirror_sources(sources);
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0]["key"], "a");
+        assert_eq!(
+            merged[0]["mirrors"],
+            json!([
+                "https://b.example.com/api.php/provide/vod",
+                "https://d.example.com/api.php/provide/vod"
+            ])
+        );
+        assert_eq!(merged[0]["aliases"], json!(["a2"]));
+        assert!(source_has_key(&merged[0], "a2"));
+        assert!(!source_has_key(&merged[1], "a2"));
+        assert!(merged[1].get("mirrors").is_none());
+    }
+
+    #[test]
+    fn same_name_on_different_hosts_is_not_merged() {
+        let sources = vec![
+            json!({"key": "a", "name": "资源站", "api": "https://a.example.com/api.php/provide/vod"}),
+            json!({"key": "a2", "name": "资源站", "api": "https://b.example.com/api.php/provide/vod"}),
+        ];
+        let merged = merge_mirror_sources(sources);
+        assert_eq!(merged.len(), 2);
+        assert!(merged.iter().all(|source| source.get("mirrors").is_none()));
+    }
+
+    #[test]
+    fn failed_mirror_moves_to_the_back_until_cooldown_ends() {
+        let tracker = MirrorTracker::new();
+        let endpoints = vec!["https://a".to_string(), "https://b".to_string()];
+        let now = Instant::now();
+
+        tracker.record_failure_at("https://a", now);
+        assert_eq!(
+            tracker.order_at(&endpoints, now),
+            vec!["https://b", "https://a"]
+        );
+
+        let later = now + Duration::from_secs(MIRROR_BASE_COOLDOWN_SECS + 1);
+        assert_eq!(
+            tracker.order_at(&endpoints, later),
+            vec!["https://a", "https://b"]
+        );
+    }
+
+    #[test]
+    fn hedge_delay_follows_observed_latency() {
+        let tracker = MirrorTracker::new();
+        assert_eq!(
+            tracker.hedge_delay("https://a"),
+            Duration::from_millis(DEFAULT_HEDGE_DELAY_MS)
+        );
+
+        tracker.record_success("https://a", Duration::from_millis(600));
+        assert_eq!(
+            tracker.hedge_delay("https://a"),
+            Duration::from_millis(1_200)
+        );
+
+        tracker.record_success("https://b", Duration::from_millis(10));
+        assert_eq!(
+            tracker.hedge_delay("https://b"),
+            Duration::from_millis(MIN_HEDGE_DELAY_MS)
+        );
+    }
+
+    #[test]
+    fn strategy_defaults_to_failover() {
+        assert_eq!(
+            MirrorStrategy::from_source(&json!({})),
+            MirrorStrategy::Failover
+        );
+        assert_eq!(
+            MirrorStrategy::from_source(&json!({"mirror_strategy": "hedged"})),
+            MirrorStrategy::Hedged
+        );
+    }
+}
@@ -0,0 +1,501 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// 默认全局并发上限
+pub const DEFAULT_GLOBAL_CONCURRENCY: usize = 32;
+/// 记录的主机数上限，超出时淘汰空闲的主机
+const MAX_TRACKED_HOSTS: usize = 1024;
+/// 超过该时长未使用且没有进行中请求的主机可以淘汰
+const HOST_IDLE_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// 单个主机的出站请求策略，可在源配置的 `request_policy` 字段中覆盖
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct RequestPolicy {
+    /// 每秒允许发出的请求数
+    pub rate_per_sec: f64,
+    /// 令牌桶容量（允许的突发请求数）
+    pub burst: u32,
+    /// 同一主机的最大并发请求数
+    pub max_concurrency: usize,
+    /// 自适应超时的下限（毫秒）
+    pub min_timeout_ms: u64,
+    /// 自适应超时的上限（毫秒）
+    pub max_timeout_ms: u64,
+}
+
+impl Default for RequestPolicy {
+    fn default() -> Self {
+        Self {
+            rate_per_sec: 8.0,
+            burst: 8,
+            max_concurrency: 6,
+            min_timeout_ms: 3_000,
+            max_timeout_ms: 15_000,
+        }
+    }
+}
+
+impl RequestPolicy {
+    /// 从源配置读取 `request_policy`，未配置的字段使用默认值
+    pub fn from_source(source: &Value) -> Option<Self> {
+        let policy = source.get("request_policy")?;
+        serde_json::from_value::<RequestPolicy>(policy.clone())
+            .ok()
+            .map(|policy| policy.sanitized())
+    }
+
+    fn sanitized(mut self) -> Self {
+        let defaults = RequestPolicy::default();
+        if !self.rate_per_sec.is_finite() || self.rate_per_sec <= 0.0 {
+            self.rate_per_sec = defaults.rate_per_sec;
+        }
+        self.burst = self.burst.max(1);
+        self.max_concurrency = self.max_concurrency.max(1);
+        self.min_timeout_ms = self.min_timeout_ms.max(500);
+        self.max_timeout_ms = self.max_timeout_ms.max(self.min_timeout_ms);
+        self
+    }
+}
+
+/// 令牌桶：允许余额为负，以排队的方式计算需要等待的时间
+#[derive(Debug, Clone)]
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(policy: &RequestPolicy, now: Instant) -> Self {
+        Self {
+            tokens: f64::from(policy.burst),
+            last_refill: now,
+        }
+    }
+
+    /// 预订一个令牌，返回发出请求前需要等待的时长
+    fn reserve(&mut self, policy: &RequestPolicy, now: Instant) -> Duration {
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed * policy.rate_per_sec).min(f64::from(policy.burst));
+        self.last_refill = now;
+        self.tokens -= 1.0;
+
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / policy.rate_per_sec)
+        }
+    }
+}
+
+/// 基于平滑往返时间的延迟估计（与 TCP RTO 的计算方式一致）
+#[derive(Debug, Clone, Default)]
+struct LatencyEstimator {
+    smoothed_ms: Option<f64>,
+    variance_ms: f64,
+}
+
+impl LatencyEstimator {
+    fn observe(&mut self, latency_ms: f64) {
+        match self.smoothed_ms {
+            None => {
+                self.smoothed_ms = Some(latency_ms);
+                self.variance_ms = latency_ms / 2.0;
+            }
+            Some(smoothed) => {
+                self.variance_ms = 0.75 * self.variance_ms + 0.25 * (smoothed - latency_ms).abs();
+                self.smoothed_ms = Some(0.875 * smoothed + 0.125 * latency_ms);
+            }
+        }
+    }
+
+    fn has_samples(&self) -> bool {
+        self.smoothed_ms.is_some()
+    }
+
+    fn timeout(&self, policy: &RequestPolicy, fallback: Duration) -> Duration {
+        let estimate_ms = match self.smoothed_ms {
+            Some(smoothed) => smoothed + 4.0 * self.variance_ms,
+            None => fallback.as_millis() as f64,
+        };
+        let clamped = estimate_ms.clamp(policy.min_timeout_ms as f64, policy.max_timeout_ms as f64);
+        Duration::from_millis(clamped as u64)
+    }
+}
+
+struct HostState {
+    policy: RequestPolicy,
+    bucket: TokenBucket,
+    concurrency: Arc<Semaphore>,
+    latency: LatencyEstimator,
+    last_used: Instant,
+}
+
+impl HostState {
+    fn new(policy: RequestPolicy, now: Instant) -> Self {
+        Self {
+            bucket: TokenBucket::new(&policy, now),
+            concurrency: Arc::new(Semaphore::new(policy.max_concurrency)),
+            latency: LatencyEstimator::default(),
+            last_used: now,
+            policy,
+        }
+    }
+
+    /// 没有进行中的请求（许可持有信号量的引用）
+    fn is_idle(&self) -> bool {
+        Arc::strong_count(&self.concurrency) == 1
+    }
+
+    fn apply_policy(&mut self, policy: &RequestPolicy) {
+        if &self.policy == policy {
+            return;
+        }
+        if self.policy.max_concurrency != policy.max_concurrency {
+            // 旧信号量上的许可在请求结束时自然释放
+            self.concurrency = Arc::new(Semaphore::new(policy.max_concurrency));
+        }
+        self.policy = policy.clone();
+    }
+}
+
+/// 出站请求许可：持有期间占用主机并发与全局并发名额
+pub struct OutboundPermit {
+    _host: OwnedSemaphorePermit,
+    _global: OwnedSemaphorePermit,
+    host: String,
+    timeout: Duration,
+}
+
+impl OutboundPermit {
+    /// 按该主机历史延迟计算出的请求超时
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+}
+
+/// 共享出站请求调度器
+///
+/// 每个主机独立的令牌桶限速与并发上限，叠加全局并发上限；超时根据观测到的延迟自适应调整。
+pub struct OutboundScheduler {
+    global: Arc<Semaphore>,
+    default_policy: RequestPolicy,
+    hosts: Mutex<HashMap<String, HostState>>,
+}
+
+impl Default for OutboundScheduler {
+    fn default() -> Self {
+        Self::new(DEFAULT_GLOBAL_CONCURRENCY, RequestPolicy::default())
+    }
+}
+
+impl OutboundScheduler {
+    pub fn new(global_concurrency: usize, default_policy: RequestPolicy) -> Self {
+        Self {
+            global: Arc::new(Semaphore::new(global_concurrency.max(1))),
+            default_policy: default_policy.sanitized(),
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 调度使用的主机标识（小写主机名加端口），无法解析时使用原字符串
+    pub fn host_key(url: &str) -> String {
+        match url::Url::parse(url) {
+            Ok(parsed) => match (parsed.host_str(), parsed.port()) {
+                (Some(host), Some(port)) => format!("{}:{}", host.to_lowercase(), port),
+                (Some(host), None) => host.to_lowercase(),
+                _ => url.to_string(),
+            },
+            Err(_) => url.to_string(),
+        }
+    }
+
+    /// 申请一次请求许可：先按令牌桶排队，再占用主机与全局并发名额
+    ///
+    /// `policy` 为源级别的策略覆盖；`fallback_timeout` 在该主机尚无延迟样本时使用
+    pub async fn acquire(
+        &self,
+        url: &str,
+        policy: Option<&RequestPolicy>,
+        fallback_timeout: Duration,
+    ) -> OutboundPermit {
+        let host = Self::host_key(url);
+        let (wait, concurrency) = {
+            let now = Instant::now();
+            let mut hosts = self.hosts.lock().unwrap();
+            let state = self.host_state(&mut hosts, &host, policy, now);
+            if let Some(policy) = policy {
+                state.apply_policy(&policy.clone().sanitized());
+            }
+            let policy = state.policy.clone();
+            (
+                state.bucket.reserve(&policy, now),
+                state.concurrency.clone(),
+            )
+        };
+
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+
+        let host_permit = concurrency
+            .acquire_owned()
+            .await
+            .expect("host semaphore closed");
+        let global_permit = self
+            .global
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("global semaphore closed");
+
+        let timeout = self.timeout_for(&host, fallback_timeout);
+        OutboundPermit {
+            _host: host_permit,
+            _global: global_permit,
+            host,
+            timeout,
+        }
+    }
+
+    /// 取得主机状态，新登记主机前先淘汰空闲的主机
+    fn host_state<'a>(
+        &self,
+        hosts: &'a mut HashMap<String, HostState>,
+        host: &str,
+        policy: Option<&RequestPolicy>,
+        now: Instant,
+    ) -> &'a mut HostState {
+        if !hosts.contains_key(host) && hosts.len() >= MAX_TRACKED_HOSTS {
+            hosts.retain(|_, state| {
+                !state.is_idle() || now.saturating_duration_since(state.last_used) < HOST_IDLE_TTL
+            });
+            if hosts.len() >= MAX_TRACKED_HOSTS {
+                let oldest = hosts
+                    .iter()
+                    .filter(|(_, state)| state.is_idle())
+                    .min_by_key(|(_, state)| state.last_used)
+                    .map(|(host, _)| host.clone());
+                if let Some(oldest) = oldest {
+                    hosts.remove(&oldest);
+                }
+            }
+        }
+        let state = hosts.entry(host.to_string()).or_insert_with(|| {
+            HostState::new(
+                policy
+                    .cloned()
+                    .unwrap_or_else(|| self.default_policy.clone()),
+                now,
+            )
+        });
+        state.last_used = now;
+        state
+    }
+
+    fn timeout_for(&self, host: &str, fallback: Duration) -> Duration {
+        let hosts = self.hosts.lock().unwrap();
+        match hosts.get(host) {
+            Some(state) => state.latency.timeout(&state.policy, fallback),
+            None => LatencyEstimator::default().timeout(&self.default_policy, fallback),
+        }
+    }
+
+    /// 记录一次成功请求的延迟
+    pub fn record_latency(&self, url: &str, latency: Duration) {
+        let host = Self::host_key(url);
+        let mut hosts = self.hosts.lock().unwrap();
+        let state = self.host_state(&mut hosts, &host, None, Instant::now());
+        state.latency.observe(latency.as_millis() as f64);
+    }
+
+    /// 记录一次超时：按当时的超时时长计入延迟样本，慢主机的超时随之放宽
+    pub fn record_timeout(&self, url: &str, timeout: Duration) {
+        self.record_latency(url, timeout);
+    }
+
+    /// 用源健康统计中的平均响应时间预热延迟估计，已有样本时忽略
+    pub fn seed_latency(&self, url: &str, avg_latency_ms: u64) {
+        if avg_latency_ms == 0 {
+            return;
+        }
+        let host = Self::host_key(url);
+        let mut hosts = self.hosts.lock().unwrap();
+        let state = self.host_state(&mut hosts, &host, None, Instant::now());
+        if !state.latency.has_samples() {
+            state.latency.observe(avg_latency_ms as f64);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(rate_per_sec: f64, burst: u32) -> RequestPolicy {
+        RequestPolicy {
+            rate_per_sec,
+            burst,
+            ..RequestPolicy::default()
+        }
+    }
+
+    #[test]
+    fn token_bucket_allows_burst_then_spaces_requests() {
+        let policy = policy(2.0, 2);
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(&policy, start);
+
+        assert_eq!(bucket.reserve(&policy, start), Duration::ZERO);
+        assert_eq!(bucket.reserve(&policy, start), Duration::ZERO);
+        assert_eq!(bucket.reserve(&policy, start), Duration::from_millis(500));
+        assert_eq!(bucket.reserve(&policy, start), Duration::from_millis(1000));
+
+        // 令牌随时间恢复
+        let later = start + Duration::from_secs(5);
+        assert_eq!(bucket.reserve(&policy, later), Duration::ZERO);
+    }
+
+    #[test]
+    fn latency_estimator_adapts_and_clamps() {
+        let policy = RequestPolicy::default();
+        let fallback = Duration::from_secs(6);
+        let mut estimator = LatencyEstimator::default();
+        assert_eq!(estimator.timeout(&policy, fallback), fallback);
+
+        for _ in 0..20 {
+            estimator.observe(200.0);
+        }
+        assert_eq!(
+            estimator.timeout(&policy, fallback),
+            Duration::from_millis(policy.min_timeout_ms)
+        );
+
+        for _ in 0..20 {
+            estimator.observe(20_000.0);
+        }
+        assert_eq!(
+            estimator.timeout(&policy, fallback),
+            Duration::from_millis(policy.max_timeout_ms)
+        );
+    }
+
+    #[test]
+    fn policy_from_source_fills_defaults_and_sanitizes() {
+        let source = serde_json::json!({
+            "key": "a",
+            "request_policy": { "rate_per_sec": 1.5, "max_concurrency": 0 }
+        });
+        let policy = RequestPolicy::from_source(&source).unwrap();
+        assert_eq!(policy.rate_per_sec, 1.5);
+        assert_eq!(policy.max_concurrency, 1);
+        assert_eq!(policy.burst, RequestPolicy::default().burst);
+
+        assert!(RequestPolicy::from_source(&serde_json::json!({ "key": "a" })).is_none());
+    }
+
+    #[test]
+    fn host_key_normalizes_case_and_port() {
+        assert_eq!(
+            OutboundScheduler::host_key("https://API.Example.com/a?b=1"),
+            "api.example.com"
+        );
+        assert_eq!(
+            OutboundScheduler::host_key("http://example.com:8080/x"),
+            "example.com:8080"
+        );
+    }
+
+    #[tokio::test]
+    async fn scheduler_limits_per_host_concurrency() {
+        let scheduler = OutboundScheduler::new(
+            8,
+            RequestPolicy {
+                rate_per_sec: 1000.0,
+                burst: 100,
+                max_concurrency: 1,
+                ..RequestPolicy::default()
+            },
+        );
+
+        let first = scheduler
+            .acquire("https://a.example.com/1", None, Duration::from_secs(6))
+            .await;
+        let blocked = tokio::time::timeout(
+            Duration::from_millis(50),
+            scheduler.acquire("https://a.example.com/2", None, Duration::from_secs(6)),
+        )
+        .await;
+        assert!(blocked.is_err());
+
+        // 其他主机不受影响
+        let other = tokio::time::timeout(
+            Duration::from_millis(50),
+            scheduler.acquire("https://b.example.com/1", None, Duration::from_secs(6)),
+        )
+        .await;
+        assert!(other.is_ok());
+
+        drop(first);
+        let next = tokio::time::timeout(
+            Duration::from_millis(50),
+            scheduler.acquire("https://a.example.com/2", None, Duration::from_secs(6)),
+        )
+        .await;
+        assert!(next.is_ok());
+    }
+
+    #[tokio::test]
+    async fn scheduler_timeout_follows_recorded_latency() {
+        let scheduler = OutboundScheduler::default();
+        let url = "https://slow.example.com/api";
+        scheduler.seed_latency(url, 2_000);
+        let permit = scheduler.acquire(url, None, Duration::from_secs(6)).await;
+        // 2000 + 4 * 1000
+        assert_eq!(permit.timeout(), Duration::from_millis(6_000));
+        assert_eq!(permit.host(), "slow.example.com");
+    }
+
+    #[tokio::test]
+    async fn timeouts_widen_the_adaptive_timeout() {
+        let scheduler = OutboundScheduler::default();
+        let url = "https://slow.example.com/api";
+        scheduler.seed_latency(url, 1_000);
+        let first = scheduler.acquire(url, None, Duration::from_secs(6)).await;
+        assert_eq!(first.timeout(), Duration::from_millis(3_000));
+        scheduler.record_timeout(url, first.timeout());
+        drop(first);
+
+        let second = scheduler.acquire(url, None, Duration::from_secs(6)).await;
+        assert!(second.timeout() > Duration::from_millis(3_000));
+    }
+
+    #[tokio::test]
+    async fn idle_hosts_are_evicted_beyond_the_cap() {
+        let scheduler = OutboundScheduler::default();
+        let busy = scheduler
+            .acquire("https://busy.example.com/", None, Duration::from_secs(6))
+            .await;
+        for index in 0..MAX_TRACKED_HOSTS * 2 {
+            scheduler.record_latency(
+                &format!("https://h{}.example.com/", index),
+                Duration::from_millis(100),
+            );
+        }
+
+        let hosts = scheduler.hosts.lock().unwrap();
+        assert!(hosts.len() <= MAX_TRACKED_HOSTS);
+        // 有进行中请求的主机不会被淘汰
+        assert!(hosts.contains_key(busy.host()));
+    }
+}
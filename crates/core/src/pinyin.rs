@@ -0,0 +1,461 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// 常用汉字的无声调拼音分组
+///
+/// 只收录影视标题与搜索输入中的常用字，用于同音字纠错，不追求覆盖全部汉字。
+/// 多音字按最常见读音归组。
+const PINYIN_GROUPS: &[(&str, &str)] = &[
+    ("a", "阿啊"),
+    ("ai", "爱哀挨埃矮艾碍癌唉"),
+    ("an", "安按暗岸案俺鞍庵"),
+    ("ang", "昂"),
+    ("ao", "奥傲澳熬敖袄"),
+    ("ba", "八把吧爸巴拔霸罢靶芭"),
+    ("bai", "白百败拜摆柏佰"),
+    ("ban", "半办班般板版伴搬扮斑颁"),
+    ("bang", "帮邦棒榜膀傍绑"),
+    ("bao", "包报保宝抱爆暴薄饱堡豹"),
+    ("bei", "被北背杯悲备贝倍辈碑卑"),
+    ("ben", "本奔笨"),
+    ("beng", "崩蹦"),
+    ("bi", "比笔必毕闭币彼壁避鼻逼碧毙"),
+    ("bian", "边变便遍编辩鞭扁"),
+    ("biao", "表标彪镖"),
+    ("bie", "别憋"),
+    ("bin", "宾滨彬斌"),
+    ("bing", "并兵病冰饼丙秉"),
+    ("bo", "波博伯播剥薄泊勃搏"),
+    ("bu", "不部布步补捕卜"),
+    ("ca", "擦"),
+    ("cai", "才菜财材彩采猜裁"),
+    ("can", "参残惨餐灿蚕"),
+    ("cang", "藏苍仓舱"),
+    ("cao", "草操曹槽"),
+    ("ce", "策测侧册厕"),
+    ("cen", "岑"),
+    ("ceng", "层曾蹭"),
+    ("cha", "查茶差插察叉"),
+    ("chai", "柴拆差"),
+    ("chan", "产缠蝉禅颤铲"),
+    ("chang", "长常场唱厂尝肠畅昌倡"),
+    ("chao", "超朝潮吵抄巢"),
+    ("che", "车彻撤扯"),
+    ("chen", "陈沉晨尘臣趁辰琛"),
+    ("cheng", "成城程称承诚乘呈橙惩澄"),
+    ("chi", "吃池迟尺赤持齿驰痴"),
+    ("chong", "重冲虫充崇宠"),
+    ("chou", "抽愁仇丑臭筹"),
+    ("chu", "出处初除楚触储础厨"),
+    ("chuan", "传船穿川串"),
+    ("chuang", "创窗床闯疮"),
+    ("chui", "吹垂锤"),
+    ("chun", "春纯唇醇"),
+    ("chuo", "戳绰"),
+    ("ci", "此次词刺辞慈磁雌"),
+    ("cong", "从匆聪丛葱"),
+    ("cu", "粗促醋簇"),
+    ("cui", "催脆翠崔"),
+    ("cun", "村存寸"),
+    ("cuo", "错措挫"),
+    ("da", "大打达答搭"),
+    ("dai", "带代待戴袋贷呆黛"),
+    ("dan", "但单担蛋弹淡胆丹旦"),
+    ("dang", "当党荡档挡"),
+    ("dao", "到道刀倒导岛盗稻蹈"),
+    ("de", "的得德"),
+    ("deng", "等灯登邓瞪"),
+    ("di", "地第底帝敌低弟递滴迪笛"),
+    ("dian", "点电店典殿颠垫"),
+    ("diao", "调掉雕钓吊"),
+    ("die", "爹跌叠蝶谍"),
+    ("ding", "定顶丁订钉鼎"),
+    ("diu", "丢"),
+    ("dong", "东动冬懂洞冻栋"),
+    ("dou", "都斗豆抖逗兜"),
+    ("du", "度读独毒渡杜堵赌都督"),
+    ("duan", "段断短端锻"),
+    ("dui", "对队堆兑"),
+    ("dun", "顿蹲盾敦"),
+    ("duo", "多夺朵躲堕舵"),
+    ("e", "饿恶额俄鹅娥厄"),
+    ("en", "恩"),
+    ("er", "而二儿耳尔"),
+    ("fa", "发法罚乏伐阀"),
+    ("fan", "反饭犯翻凡烦范帆繁番"),
+    ("fang", "方放房防访芳仿"),
+    ("fei", "非飞费肥废菲肺妃"),
+    ("fen", "分份纷奋粉坟愤芬"),
+    ("feng", "风丰封峰疯锋蜂逢凤奉"),
+    ("fo", "佛"),
+    ("fou", "否"),
+    ("fu", "夫服福父富府负复附副付扶浮符腐妇伏"),
+    ("gai", "该改盖概钙"),
+    ("gan", "干感敢赶甘肝杆"),
+    ("gang", "刚钢港岗纲"),
+    ("gao", "高告搞稿糕"),
+    ("ge", "个各歌哥格隔割革戈阁"),
+    ("gei", "给"),
+    ("gen", "根跟"),
+    ("geng", "更耕庚"),
+    ("gong", "工公功共宫攻供恭弓"),
+    ("gou", "够狗沟购构勾"),
+    ("gu", "古故顾骨鼓谷孤姑固雇"),
+    ("gua", "挂瓜刮寡"),
+    ("guai", "怪乖拐"),
+    ("guan", "关管官观馆惯冠罐"),
+    ("guang", "光广逛"),
+    ("gui", "贵鬼归规桂跪柜轨龟"),
+    ("gun", "滚棍"),
+    ("guo", "国过果锅郭"),
+    ("ha", "哈"),
+    ("hai", "还海害孩骇"),
+    ("han", "汉寒含喊汗韩罕憾涵"),
+    ("hang", "行航杭"),
+    ("hao", "好号豪浩毫耗"),
+    ("he", "和河合何喝盒贺荷核赫鹤"),
+    ("hei", "黑嘿"),
+    ("hen", "很恨狠痕"),
+    ("heng", "横恒衡哼"),
+    ("hong", "红洪宏虹轰鸿"),
+    ("hou", "后候厚侯猴吼"),
+    ("hu", "湖虎护呼户胡乎忽狐壶糊互"),
+    ("hua", "话花化华画划滑"),
+    ("huai", "坏怀淮"),
+    ("huan", "还换欢环缓幻患唤焕"),
+    ("huang", "黄皇荒慌谎煌晃凰"),
+    ("hui", "会回灰挥辉慧毁绘惠汇徽"),
+    ("hun", "婚魂混昏浑"),
+    ("huo", "火活或获货伙祸惑霍"),
+    ("ji", "机记几及级急即集计技极击既积基纪季寂迹激吉继忌姬鸡"),
+    ("jia", "家加假价架佳甲嘉夹驾"),
+    ("jian", "见间件建剑坚简减监鉴渐尖健箭检舰"),
+    ("jiang", "将江讲强降酱奖疆姜蒋"),
+    ("jiao", "叫教交脚角觉较焦骄娇郊胶"),
+    ("jie", "接结姐界节解街杰借阶届洁戒劫捷"),
+    ("jin", "进金今近尽紧仅锦劲禁津巾"),
+    ("jing", "经京精静景警井境惊镜竞晶敬径"),
+    ("jiong", "窘"),
+    ("jiu", "就九酒旧久救究纠"),
+    ("ju", "局据举句居剧巨具聚菊拒距"),
+    ("juan", "卷倦捐娟眷"),
+    ("jue", "觉决绝角掘爵诀"),
+    ("jun", "军君均俊峻"),
+    ("ka", "卡咖"),
+    ("kai", "开凯慨"),
+    ("kan", "看砍刊堪"),
+    ("kang", "康抗扛慷"),
+    ("kao", "考靠烤"),
+    ("ke", "可客科克课刻渴颗棵"),
+    ("ken", "肯恳"),
+    ("kong", "空孔控恐"),
+    ("kou", "口扣寇"),
+    ("ku", "苦哭库酷裤枯"),
+    ("kua", "跨夸垮"),
+    ("kuai", "快块筷"),
+    ("kuan", "宽款"),
+    ("kuang", "狂况矿框"),
+    ("kui", "亏愧魁葵"),
+    ("kun", "困昆坤"),
+    ("kuo", "扩阔"),
+    ("la", "拉啦辣蜡"),
+    ("lai", "来赖莱"),
+    ("lan", "蓝兰烂懒篮拦栏澜岚"),
+    ("lang", "浪狼郎朗廊"),
+    ("lao", "老劳牢"),
+    ("le", "了乐勒"),
+    ("lei", "类泪累雷蕾"),
+    ("leng", "冷楞"),
+    ("li", "里理力利立李离历礼丽粒厉励黎莉璃梨"),
+    ("lian", "连脸练恋联莲怜廉炼"),
+    ("liang", "两亮良量凉梁粮谅"),
+    ("liao", "了聊料疗辽"),
+    ("lie", "列烈猎裂劣"),
+    ("lin", "林临邻琳淋霖麟"),
+    ("ling", "令灵零领另岭铃玲凌龄陵"),
+    ("liu", "六流留刘柳溜"),
+    ("long", "龙隆笼聋"),
+    ("lou", "楼漏露"),
+    ("lu", "路陆录鹿露炉卢鲁"),
+    ("lv", "绿旅律率吕虑"),
+    ("luan", "乱卵"),
+    ("lue", "略掠"),
+    ("lun", "论轮伦"),
+    ("luo", "落罗洛骆逻螺"),
+    ("ma", "妈马吗麻骂码"),
+    ("mai", "买卖麦埋迈"),
+    ("man", "满慢漫蛮曼"),
+    ("mang", "忙盲茫芒"),
+    ("mao", "毛猫冒帽贸茂矛"),
+    ("mei", "没美每妹梅媒煤眉魅玫"),
+    ("men", "们门闷"),
+    ("meng", "梦猛蒙盟萌孟"),
+    ("mi", "米密秘迷蜜谜弥"),
+    ("mian", "面免棉眠绵"),
+    ("miao", "秒妙苗庙描喵"),
+    ("mie", "灭"),
+    ("min", "民敏"),
+    ("ming", "名明命鸣铭冥"),
+    ("miu", "谬"),
+    ("mo", "么没末莫魔默摸磨墨模陌漠"),
+    ("mou", "某谋"),
+    ("mu", "目母木幕牧墓慕暮穆"),
+    ("na", "那拿哪纳娜"),
+    ("nai", "奶耐乃"),
+    ("nan", "南难男楠"),
+    ("nang", "囊"),
+    ("nao", "闹脑恼"),
+    ("ne", "呢"),
+    ("nei", "内"),
+    ("neng", "能"),
+    ("ni", "你尼泥逆拟妮"),
+    ("nian", "年念粘"),
+    ("niang", "娘酿"),
+    ("niao", "鸟尿"),
+    ("nie", "捏聂孽"),
+    ("nin", "您"),
+    ("ning", "宁凝拧柠"),
+    ("niu", "牛扭纽"),
+    ("nong", "农弄浓"),
+    ("nu", "怒努奴"),
+    ("nv", "女"),
+    ("nuan", "暖"),
+    ("nuo", "诺挪"),
+    ("o", "哦"),
+    ("ou", "欧偶鸥"),
+    ("pa", "怕爬帕"),
+    ("pai", "派排拍牌"),
+    ("pan", "盘判盼攀潘"),
+    ("pang", "旁胖庞"),
+    ("pao", "跑炮泡抛袍"),
+    ("pei", "配陪培佩"),
+    ("pen", "盆喷"),
+    ("peng", "朋碰鹏蓬彭捧"),
+    ("pi", "皮批匹披疲脾劈"),
+    ("pian", "片篇偏骗"),
+    ("piao", "漂票飘"),
+    ("pin", "品拼贫频"),
+    ("ping", "平评瓶凭屏萍"),
+    ("po", "破婆迫坡泼"),
+    ("pu", "普铺朴扑谱葡"),
+    ("qi", "起其气七期奇齐妻骑旗企启器弃泣祈琪棋"),
+    ("qia", "恰洽"),
+    ("qian", "前千钱浅签欠迁潜牵谦倩"),
+    ("qiang", "强枪墙抢腔"),
+    ("qiao", "桥巧悄敲乔俏"),
+    ("qie", "且切窃"),
+    ("qin", "亲琴秦勤侵禽"),
+    ("qing", "情青清请轻庆晴倾卿擎"),
+    ("qiong", "穷琼"),
+    ("qiu", "求秋球丘囚"),
+    ("qu", "去取区趣曲屈驱渠"),
+    ("quan", "全权劝拳泉圈犬"),
+    ("que", "却确缺雀鹊"),
+    ("qun", "群裙"),
+    ("ran", "然燃染冉"),
+    ("rang", "让嚷"),
+    ("rao", "绕扰饶"),
+    ("re", "热惹"),
+    ("ren", "人认任仁忍刃"),
+    ("reng", "仍扔"),
+    ("ri", "日"),
+    ("rong", "容荣融绒蓉溶"),
+    ("rou", "肉柔揉"),
+    ("ru", "如入乳儒辱"),
+    ("ruan", "软阮"),
+    ("rui", "瑞锐睿蕊"),
+    ("run", "润闰"),
+    ("ruo", "若弱"),
+    ("sa", "撒洒萨"),
+    ("sai", "赛塞"),
+    ("san", "三散伞"),
+    ("sang", "桑丧"),
+    ("sao", "扫嫂骚"),
+    ("se", "色涩瑟"),
+    ("sen", "森"),
+    ("sha", "杀沙傻纱刹"),
+    ("shai", "晒"),
+    ("shan", "山善闪衫扇珊杉"),
+    ("shang", "上商伤尚赏"),
+    ("shao", "少烧绍稍哨"),
+    ("she", "社设舍射蛇涉摄"),
+    ("shei", "谁"),
+    ("shen", "深身神什伸审沈甚慎绅"),
+    ("sheng", "生声胜升省圣盛剩绳"),
+    (
+        "shi",
+        "是时十事世市师使式始实石史识食诗失试士室视施湿狮尸拾",
+    ),
+    ("shou", "手收受首守寿售瘦兽"),
+    ("shu", "书数树术熟属输叔鼠舒殊述束蜀"),
+    ("shua", "刷耍"),
+    ("shuai", "帅摔衰"),
+    ("shuan", "拴"),
+    ("shuang", "双霜爽"),
+    ("shui", "水谁睡税"),
+    ("shun", "顺瞬"),
+    ("shuo", "说硕朔"),
+    ("si", "四死思私司丝似寺斯撕"),
+    ("song", "送松宋颂诵"),
+    ("sou", "搜艘"),
+    ("su", "素速苏诉宿俗肃塑"),
+    ("suan", "算酸蒜"),
+    ("sui", "岁随虽碎遂隋"),
+    ("sun", "孙损笋"),
+    ("suo", "所锁索缩"),
+    ("ta", "他她它塔踏"),
+    ("tai", "太台态泰抬胎"),
+    ("tan", "谈探弹坦叹潭贪滩"),
+    ("tang", "堂唐糖躺汤塘"),
+    ("tao", "逃桃讨套涛陶"),
+    ("te", "特"),
+    ("teng", "疼腾藤"),
+    ("ti", "体题提替梯踢"),
+    ("tian", "天田甜添填"),
+    ("tiao", "条跳挑调"),
+    ("tie", "铁贴"),
+    ("ting", "听停庭挺亭婷"),
+    ("tong", "同通痛童统桶铜瞳"),
+    ("tou", "头投偷透"),
+    ("tu", "图突土途徒兔屠"),
+    ("tuan", "团"),
+    ("tui", "推退腿"),
+    ("tun", "吞屯"),
+    ("tuo", "拖脱托妥驼"),
+    ("wa", "挖娃瓦哇"),
+    ("wai", "外歪"),
+    ("wan", "万完晚玩湾碗弯挽婉宛"),
+    ("wang", "王望往网忘旺亡"),
+    ("wei", "为位未危味卫围微威委伟唯维尾魏"),
+    ("wen", "问文闻稳温纹吻"),
+    ("weng", "翁"),
+    ("wo", "我握卧窝沃"),
+    ("wu", "无五物务误舞屋午武悟雾吴乌巫伍"),
+    ("xi", "西系戏喜洗希息席细夕吸惜溪析熙袭"),
+    ("xia", "下夏吓侠峡霞虾狭"),
+    ("xian", "先现线限鲜险显仙闲县献弦贤嫌"),
+    ("xiang", "想向相香像乡箱响详项湘祥翔"),
+    ("xiao", "小笑校消效晓肖萧销孝"),
+    ("xie", "些写谢协鞋斜血邪胁携"),
+    ("xin", "心新信欣辛薪馨"),
+    ("xing", "行星兴性姓醒形幸型刑杏"),
+    ("xiong", "雄兄凶胸熊"),
+    ("xiu", "修秀休袖绣"),
+    ("xu", "需许续须序徐绪虚旭叙"),
+    ("xuan", "选宣旋悬玄轩"),
+    ("xue", "学雪血穴薛"),
+    ("xun", "寻训讯迅循巡勋"),
+    ("ya", "呀压牙亚雅鸭崖"),
+    ("yan", "眼言严演颜烟验燕延沿岩炎艳宴焰研"),
+    ("yang", "样阳洋养扬杨羊仰央"),
+    ("yao", "要药摇腰遥妖耀邀姚"),
+    ("ye", "也夜业叶爷野页液耶"),
+    ("yi", "一以已意义亿衣医依易移疑艺忆宜姨异益伊仪逸"),
+    ("yin", "因音引银印隐阴饮殷吟"),
+    ("ying", "应影英营迎硬映赢婴鹰樱莹盈"),
+    ("yong", "用永勇拥涌庸咏"),
+    ("you", "有又由友游右油优犹幽悠忧佑"),
+    ("yu", "于与语雨鱼玉遇余育预域欲宇羽愈御狱渔誉"),
+    ("yuan", "元原远院员园愿圆源缘怨援袁渊"),
+    ("yue", "月越约乐阅跃悦岳"),
+    ("yun", "云运允韵孕晕"),
+    ("za", "杂砸"),
+    ("zai", "在再载灾宰"),
+    ("zan", "咱赞暂"),
+    ("zang", "藏脏葬"),
+    ("zao", "早造遭糟澡灶"),
+    ("ze", "则责择泽"),
+    ("zei", "贼"),
+    ("zen", "怎"),
+    ("zeng", "增曾赠"),
+    ("zha", "炸扎眨诈渣"),
+    ("zhai", "摘宅窄债斋"),
+    ("zhan", "战站展占斩盏湛"),
+    ("zhang", "长张章掌丈涨障帐"),
+    ("zhao", "找照招朝赵召兆昭"),
+    ("zhe", "这者着折哲浙遮"),
+    ("zhen", "真针阵镇珍振震侦贞甄"),
+    ("zheng", "正证争整政征郑挣蒸"),
+    ("zhi", "之只知直指制至志纸治致止值织职植枝智质执"),
+    ("zhong", "中种重众钟终忠肿仲"),
+    ("zhou", "周州洲舟粥宙昼皱"),
+    ("zhu", "主住注助著竹珠朱猪祝逐诸驻筑"),
+    ("zhua", "抓爪"),
+    ("zhuan", "转专传砖赚"),
+    ("zhuang", "装状庄壮撞妆"),
+    ("zhui", "追坠缀"),
+    ("zhun", "准"),
+    ("zhuo", "桌捉卓浊灼"),
+    ("zi", "子自字资紫姿滋"),
+    ("zong", "总宗纵踪综"),
+    ("zou", "走奏邹"),
+    ("zu", "组族足祖阻租"),
+    ("zuan", "钻"),
+    ("zui", "最罪醉嘴"),
+    ("zun", "尊遵"),
+    ("zuo", "做作坐左座昨佐"),
+];
+
+fn pinyin_index() -> &'static HashMap<char, &'static str> {
+    static INDEX: OnceLock<HashMap<char, &'static str>> = OnceLock::new();
+    INDEX.get_or_init(|| {
+        let mut index = HashMap::new();
+        for (syllable, chars) in PINYIN_GROUPS {
+            for ch in chars.chars() {
+                // 多音字保留首次出现的读音
+                index.entry(ch).or_insert(*syllable);
+            }
+        }
+        index
+    })
+}
+
+/// 获取单个汉字的无声调拼音，未收录时返回 None
+pub fn char_pinyin(ch: char) -> Option<&'static str> {
+    pinyin_index().get(&ch).copied()
+}
+
+/// 判断两个字符是否同音（完全相同的字符也视为同音）
+pub fn is_homophone(a: char, b: char) -> bool {
+    if a == b {
+        return true;
+    }
+    match (char_pinyin(a), char_pinyin(b)) {
+        (Some(pa), Some(pb)) => pa == pb,
+        _ => false,
+    }
+}
+
+/// 把文本转换为拼音串，未收录的字符原样保留
+pub fn to_pinyin(text: &str) -> String {
+    text.chars()
+        .map(|ch| match char_pinyin(ch) {
+            Some(py) => py.to_string(),
+            None => ch.to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn char_pinyin_looks_up_common_chars() {
+        assert_eq!(char_pinyin('情'), Some("qing"));
+        assert_eq!(char_pinyin('陈'), Some("chen"));
+        assert_eq!(char_pinyin('a'), None);
+    }
+
+    #[test]
+    fn is_homophone_matches_same_syllable() {
+        assert!(is_homophone('情', '晴'));
+        assert!(is_homophone('x', 'x'));
+        assert!(!is_homophone('情', '令'));
+    }
+
+    #[test]
+    fn to_pinyin_keeps_unknown_chars() {
+        assert_eq!(to_pinyin("陈情令2"), "chenqingling2");
+    }
+}
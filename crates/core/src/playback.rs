@@ -1,3 +1,4 @@
+use crate::hls_crypto::pin_segment_ivs;
 use serde::{Deserialize, Serialize};
 
 /// 跳过片头片尾检测器
@@ -66,6 +67,9 @@ pub enum SkipAction {
 
 // 去广告相关
 pub fn filter_ads_from_m3_u8(content: &str) -> String {
+    // 删除广告分片会打乱按媒体序号推算的 IV，过滤前先写成显式 IV
+    let pinned = pin_segment_ivs(content);
+    let content = pinned.as_str();
     let mut result = String::with_capacity(content.len());
 
     // ===== 状态 =====
@@ -136,10 +140,8 @@ pub fn filter_ads_from_m3_u8(content: &str) -> String {
         // =========================================================
         // 2️⃣ DATERANGE —— 只过滤标签本身
         // =========================================================
-        if trimmed.starts_with("#EXT-X-DATERANGE") {
-            if is_ad_daterange(trimmed) {
-                continue;
-            }
+        if trimmed.starts_with("#EXT-X-DATERANGE") && is_ad_daterange(trimmed) {
+            continue;
         }
 
         // =========================================================
@@ -0,0 +1,229 @@
+use url::Url;
+
+/// 播放列表中 URI 指向的资源类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaylistUriKind {
+    /// 嵌套的播放列表：变体流、备选音轨/字幕、I 帧流
+    Playlist,
+    /// 分片、初始化分片（MAP）与密钥
+    Media,
+}
+
+/// 带 `URI` 属性的标签及其指向的资源类型
+fn tag_uri_kind(tag: &str) -> Option<PlaylistUriKind> {
+    match tag {
+        "#EXT-X-MEDIA" | "#EXT-X-I-FRAME-STREAM-INF" | "#EXT-X-RENDITION-REPORT" => {
+            Some(PlaylistUriKind::Playlist)
+        }
+        "#EXT-X-KEY"
+        | "#EXT-X-SESSION-KEY"
+        | "#EXT-X-MAP"
+        | "#EXT-X-PART"
+        | "#EXT-X-PRELOAD-HINT" => Some(PlaylistUriKind::Media),
+        _ => None,
+    }
+}
+
+/// 重写 HLS 播放列表（主列表或媒体列表）中的全部 URI
+///
+/// 包括分片行以及 KEY / MAP / MEDIA 等标签中的 `URI` 属性。相对地址先按 `base_url` 解析为绝对地址，
+/// 再交给 `rewrite`；`data:`、`skd:` 等非 http(s) 地址保持原样
+pub fn rewrite_playlist_uris<F>(content: &str, base_url: &str, mut rewrite: F) -> String
+where
+    F: FnMut(&str, PlaylistUriKind) -> String,
+{
+    let base = Url::parse(base_url).ok();
+    let mut result = String::with_capacity(content.len() * 2);
+    let mut after_stream_inf = false;
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+
+        if trimmed.is_empty() {
+            result.push_str(line);
+        } else if trimmed.starts_with('#') {
+            let tag = trimmed.split(':').next().unwrap_or(trimmed);
+            if tag == "#EXT-X-STREAM-INF" {
+                after_stream_inf = true;
+            }
+            let rewritten = tag_uri_kind(tag).and_then(|kind| {
+                let (start, end) = find_uri_attribute(line)?;
+                let absolute = resolve_uri(base.as_ref(), &line[start..end])?;
+                Some(format!(
+                    "{}{}{}",
+                    &line[..start],
+                    rewrite(absolute.as_str(), kind),
+                    &line[end..]
+                ))
+            });
+            match rewritten {
+                Some(rewritten) => result.push_str(&rewritten),
+                None => result.push_str(line),
+            }
+        } else {
+            match resolve_uri(base.as_ref(), trimmed) {
+                Some(absolute) => {
+                    let kind = if after_stream_inf || is_playlist_path(&absolute) {
+                        PlaylistUriKind::Playlist
+                    } else {
+                        PlaylistUriKind::Media
+                    };
+                    result.push_str(&rewrite(absolute.as_str(), kind));
+                }
+                None => result.push_str(line),
+            }
+            after_stream_inf = false;
+        }
+        result.push('\n');
+    }
+
+    result
+}
+
+/// 媒体播放列表中的一个分片
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaSegment {
+    /// 绝对地址
+    pub url: String,
+    /// 时长（秒），来自 `#EXTINF`
+    pub duration: f64,
+}
+
+/// 按顺序列出媒体播放列表中的分片；主播放列表返回空列表
+pub fn media_segments(content: &str, base_url: &str) -> Vec<MediaSegment> {
+    let base = Url::parse(base_url).ok();
+    let mut segments = Vec::new();
+    let mut duration = None;
+
+    for line in content.lines().map(str::trim) {
+        if let Some(info) = line.strip_prefix("#EXTINF:") {
+            duration = info
+                .split(',')
+                .next()
+                .and_then(|value| value.trim().parse::<f64>().ok());
+        } else if !line.is_empty() && !line.starts_with('#') {
+            // 只有 #EXTINF 之后的地址才是分片，主播放列表中的变体流地址不计入
+            if let Some(duration) = duration.take() {
+                if let Some(url) = resolve_uri(base.as_ref(), line) {
+                    segments.push(MediaSegment {
+                        url: url.to_string(),
+                        duration,
+                    });
+                }
+            }
+        }
+    }
+    segments
+}
+
+/// `URI="..."` 属性值在行内的位置（不含引号）
+pub(crate) fn find_uri_attribute(line: &str) -> Option<(usize, usize)> {
+    const PREFIX: &str = "URI=\"";
+    let mut from = 0;
+    while let Some(offset) = line[from..].find(PREFIX) {
+        let start = from + offset;
+        let value_start = start + PREFIX.len();
+        let value_end = value_start + line[value_start..].find('"')?;
+        // 排除 `X-FOO-URI=` 之类的其他属性
+        if line[..start].ends_with([':', ',']) {
+            return Some((value_start, value_end));
+        }
+        from = value_end;
+    }
+    None
+}
+
+fn resolve_uri(base: Option<&Url>, uri: &str) -> Option<Url> {
+    let uri = uri.trim();
+    if uri.is_empty() {
+        return None;
+    }
+    let resolved = match base {
+        Some(base) => base.join(uri).ok()?,
+        None => Url::parse(uri).ok()?,
+    };
+    matches!(resolved.scheme(), "http" | "https").then_some(resolved)
+}
+
+fn is_playlist_path(url: &Url) -> bool {
+    let path = url.path().to_ascii_lowercase();
+    path.ends_with(".m3u8") || path.ends_with(".m3u")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tag(url: &str, kind: PlaylistUriKind) -> String {
+        match kind {
+            PlaylistUriKind::Playlist => format!("P[{}]", url),
+            PlaylistUriKind::Media => format!("M[{}]", url),
+        }
+    }
+
+    #[test]
+    fn master_playlist_variants_and_renditions_are_playlists() {
+        let master = "#EXTM3U\n\
+            #EXT-X-MEDIA:TYPE=AUDIO,GROUP-ID=\"aud\",NAME=\"zh\",URI=\"audio/zh.m3u8\"\n\
+            #EXT-X-I-FRAME-STREAM-INF:BANDWIDTH=1,URI=\"iframe/index\"\n\
+            #EXT-X-STREAM-INF:BANDWIDTH=1280000\n\
+            hd/index?sign=1\n";
+        let rewritten = rewrite_playlist_uris(master, "https://cdn.example.com/v/master.m3u8", tag);
+        assert!(rewritten.contains("URI=\"P[https://cdn.example.com/v/audio/zh.m3u8]\""));
+        assert!(rewritten.contains("URI=\"P[https://cdn.example.com/v/iframe/index]\""));
+        assert!(rewritten.contains("\nP[https://cdn.example.com/v/hd/index?sign=1]\n"));
+    }
+
+    #[test]
+    fn media_playlist_keys_maps_and_segments_are_media() {
+        let media = "#EXTM3U\n\
+            #EXT-X-KEY:METHOD=AES-128,URI=\"/keys/k1\",IV=0x1\n\
+            #EXT-X-MAP:URI=\"init.mp4\"\n\
+            #EXTINF:4.0,\n\
+            seg-0.jpg\n\
+            #EXTINF:4.0,\n\
+            https://img.example.net/seg-1\n\
+            #EXT-X-SESSION-KEY:METHOD=SAMPLE-AES,URI=\"skd://key\"\n";
+        let rewritten =
+            rewrite_playlist_uris(media, "https://cdn.example.com/v/hd/index.m3u8", tag);
+        assert!(rewritten.contains("URI=\"M[https://cdn.example.com/keys/k1]\",IV=0x1"));
+        assert!(rewritten.contains("#EXT-X-MAP:URI=\"M[https://cdn.example.com/v/hd/init.mp4]\""));
+        assert!(rewritten.contains("\nM[https://cdn.example.com/v/hd/seg-0.jpg]\n"));
+        assert!(rewritten.contains("\nM[https://img.example.net/seg-1]\n"));
+        assert!(rewritten.contains("URI=\"skd://key\""));
+    }
+
+    #[test]
+    fn media_segments_follow_extinf_entries() {
+        let media = "#EXTM3U\n#EXT-X-TARGETDURATION:6\n\
+            #EXTINF:5.5,\nseg0.ts\n\
+            #EXT-X-DISCONTINUITY\n#EXTINF:6,title\nhttps://b.example.com/seg1.ts\n";
+        let segments = media_segments(media, "https://a.example.com/v/index.m3u8");
+        assert_eq!(
+            segments,
+            vec![
+                MediaSegment {
+                    url: "https://a.example.com/v/seg0.ts".to_string(),
+                    duration: 5.5
+                },
+                MediaSegment {
+                    url: "https://b.example.com/seg1.ts".to_string(),
+                    duration: 6.0
+                },
+            ]
+        );
+
+        let master = "#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=1\nhd.m3u8\n";
+        assert!(media_segments(master, "https://a.example.com/").is_empty());
+    }
+
+    #[test]
+    fn only_exact_uri_attribute_is_rewritten() {
+        let line = "#EXT-X-KEY:METHOD=AES-128,X-OTHER-URI=\"a\",URI=\"b\"\n";
+        let rewritten = rewrite_playlist_uris(line, "https://cdn.example.com/", tag);
+        assert_eq!(
+            rewritten,
+            "#EXT-X-KEY:METHOD=AES-128,X-OTHER-URI=\"a\",URI=\"M[https://cdn.example.com/b]\"\n"
+        );
+    }
+}
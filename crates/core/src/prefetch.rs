@@ -0,0 +1,458 @@
+use crate::playlist::{media_segments, MediaSegment};
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+use tokio::task::AbortHandle;
+
+/// 预取窗口的上限（分片数）
+const MAX_LOOKAHEAD: usize = 30;
+/// 没有 `#EXTINF` 时长时假定的分片时长
+const DEFAULT_SEGMENT_SECS: f64 = 6.0;
+/// 吞吐量的指数平滑系数
+const THROUGHPUT_SMOOTHING: f64 = 0.3;
+/// 同时跟踪的播放会话上限，超出时丢弃最久未使用的
+const MAX_SESSIONS: usize = 32;
+
+/// 播放缓冲模式，对应 UserPreferences 的 `player_buffer_mode`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BufferMode {
+    Standard,
+    Enhanced,
+    Max,
+}
+
+impl BufferMode {
+    pub fn from_preference(value: &str) -> Self {
+        match value.trim() {
+            "enhanced" => BufferMode::Enhanced,
+            "max" => BufferMode::Max,
+            _ => BufferMode::Standard,
+        }
+    }
+
+    /// 希望提前缓存的播放时长（秒）
+    fn target_secs(&self) -> f64 {
+        match self {
+            BufferMode::Standard => 30.0,
+            BufferMode::Enhanced => 60.0,
+            BufferMode::Max => 120.0,
+        }
+    }
+}
+
+/// 下载一个分片并写入缓存，返回下载的字节数；已在缓存中时返回 0
+pub type PrefetchFuture = Pin<Box<dyn Future<Output = Result<u64, String>> + Send>>;
+type FetchFn = dyn Fn(String) -> PrefetchFuture + Send + Sync;
+
+struct Session {
+    id: u64,
+    playlist_url: String,
+    segments: Vec<MediaSegment>,
+    positions: HashMap<String, usize>,
+    /// 按分片地址记录，直播刷新后分片序号会整体前移
+    tasks: HashMap<String, AbortHandle>,
+    done: HashSet<String>,
+    last_used: Instant,
+}
+
+#[derive(Default)]
+struct Throughput {
+    /// 下载速度（字节/秒）
+    bandwidth: Option<f64>,
+    /// 媒体码率（字节/秒）
+    bitrate: Option<f64>,
+}
+
+impl Throughput {
+    fn record(&mut self, bytes: u64, elapsed: Duration, media_secs: f64) {
+        let smooth = |old: Option<f64>, sample: f64| {
+            Some(match old {
+                Some(old) => old + THROUGHPUT_SMOOTHING * (sample - old),
+                None => sample,
+            })
+        };
+        if bytes == 0 {
+            return;
+        }
+        let secs = elapsed.as_secs_f64().max(0.001);
+        self.bandwidth = smooth(self.bandwidth, bytes as f64 / secs);
+        if media_secs > 0.0 {
+            self.bitrate = smooth(self.bitrate, bytes as f64 / media_secs);
+        }
+    }
+
+    /// 预取窗口相对目标时长的比例：带宽富余时预取完整窗口，接近码率时收缩，避免挤占正在播放的分片
+    fn window_factor(&self) -> f64 {
+        match (self.bandwidth, self.bitrate) {
+            (Some(bandwidth), Some(bitrate)) if bitrate > 0.0 => {
+                (bandwidth / bitrate / 2.0).clamp(0.25, 1.0)
+            }
+            _ => 0.5,
+        }
+    }
+}
+
+struct State {
+    mode: BufferMode,
+    sessions: HashMap<String, Session>,
+    throughput: Throughput,
+    next_id: u64,
+}
+
+/// 按媒体播放列表预取分片
+///
+/// 播放器每请求一个分片就以它为当前位置，预取其后的分片；窗口按缓冲模式与实测带宽调整。
+/// 跳转时取消窗口外的预取，切换播放列表（换源、换清晰度）时取消旧列表的全部预取。
+/// 会话按客户端区分，桌面端只有一个客户端
+pub struct SegmentPrefetcher {
+    state: Arc<Mutex<State>>,
+    fetch: Arc<FetchFn>,
+    permits: Arc<Semaphore>,
+}
+
+impl SegmentPrefetcher {
+    /// `concurrency` 为同时进行的预取下载数
+    pub fn new<F, Fut>(concurrency: usize, fetch: F) -> Self
+    where
+        F: Fn(String) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<u64, String>> + Send + 'static,
+    {
+        Self {
+            state: Arc::new(Mutex::new(State {
+                mode: BufferMode::Standard,
+                sessions: HashMap::new(),
+                throughput: Throughput::default(),
+                next_id: 0,
+            })),
+            fetch: Arc::new(move |url| Box::pin(fetch(url)) as PrefetchFuture),
+            permits: Arc::new(Semaphore::new(concurrency.max(1))),
+        }
+    }
+
+    pub fn set_buffer_mode(&self, mode: BufferMode) {
+        self.state.lock().unwrap().mode = mode;
+    }
+
+    /// 登记客户端正在播放的媒体播放列表
+    ///
+    /// 与该客户端上次的播放列表相同时（直播刷新）只更新分片列表；不同时取消旧列表的预取。
+    /// 主播放列表不含分片，忽略
+    pub fn register_playlist(&self, client: &str, playlist_url: &str, content: &str) {
+        let segments = media_segments(content, playlist_url);
+        if segments.is_empty() {
+            return;
+        }
+        let positions: HashMap<String, usize> = segments
+            .iter()
+            .enumerate()
+            .map(|(index, segment)| (segment.url.clone(), index))
+            .collect();
+
+        let mut state = self.state.lock().unwrap();
+        if let Some(session) = state.sessions.get_mut(client) {
+            if session.playlist_url == playlist_url {
+                // 已滑出播放列表的分片不会再被请求
+                session.done.retain(|url| positions.contains_key(url));
+                session.segments = segments;
+                session.positions = positions;
+                session.last_used = Instant::now();
+                return;
+            }
+        }
+
+        state.next_id += 1;
+        let session = Session {
+            id: state.next_id,
+            playlist_url: playlist_url.to_string(),
+            segments,
+            positions,
+            tasks: HashMap::new(),
+            done: HashSet::new(),
+            last_used: Instant::now(),
+        };
+        if let Some(old) = state.sessions.insert(client.to_string(), session) {
+            abort_all(&old);
+        }
+        if state.sessions.len() > MAX_SESSIONS {
+            if let Some(oldest) = state
+                .sessions
+                .iter()
+                .min_by_key(|(_, session)| session.last_used)
+                .map(|(client, _)| client.clone())
+            {
+                if let Some(old) = state.sessions.remove(&oldest) {
+                    abort_all(&old);
+                }
+            }
+        }
+    }
+
+    /// 取消客户端的全部预取并忘记其播放列表
+    pub fn cancel(&self, client: &str) {
+        if let Some(old) = self.state.lock().unwrap().sessions.remove(client) {
+            abort_all(&old);
+        }
+    }
+
+    /// 播放器请求了一个分片：以它为当前位置，取消窗口外的预取并补齐窗口内的预取
+    ///
+    /// 不在客户端当前播放列表中的地址忽略；需在 tokio 运行时中调用
+    pub fn on_segment_request(&self, client: &str, url: &str) {
+        let mut state = self.state.lock().unwrap();
+        let mode = state.mode;
+        let factor = state.throughput.window_factor();
+        let Some(session) = state.sessions.get_mut(client) else {
+            return;
+        };
+        let Some(&position) = session.positions.get(url) else {
+            return;
+        };
+        session.last_used = Instant::now();
+
+        let lookahead = lookahead(&session.segments, position, mode, factor);
+        let end = (position + lookahead).min(session.segments.len() - 1);
+        let window = &session.segments[(position + 1).min(end + 1)..=end];
+
+        // 跳转后窗口外的预取不再需要
+        session.tasks.retain(|url, task| {
+            let keep = window.iter().any(|segment| &segment.url == url);
+            if !keep {
+                task.abort();
+            }
+            keep
+        });
+
+        for segment in window {
+            if session.tasks.contains_key(&segment.url) || session.done.contains(&segment.url) {
+                continue;
+            }
+            let task = tokio::spawn(prefetch_segment(
+                self.state.clone(),
+                self.fetch.clone(),
+                self.permits.clone(),
+                client.to_string(),
+                session.id,
+                segment.clone(),
+            ));
+            session
+                .tasks
+                .insert(segment.url.clone(), task.abort_handle());
+        }
+    }
+
+    /// 客户端当前位置之后预取的分片数
+    pub fn lookahead(&self, client: &str) -> usize {
+        let state = self.state.lock().unwrap();
+        let factor = state.throughput.window_factor();
+        state
+            .sessions
+            .get(client)
+            .map(|session| lookahead(&session.segments, 0, state.mode, factor))
+            .unwrap_or(0)
+    }
+}
+
+fn abort_all(session: &Session) {
+    for task in session.tasks.values() {
+        task.abort();
+    }
+}
+
+/// 从 `position` 之后按平均分片时长折算出的预取分片数
+fn lookahead(segments: &[MediaSegment], position: usize, mode: BufferMode, factor: f64) -> usize {
+    let upcoming = &segments[(position + 1).min(segments.len())..];
+    let durations: Vec<f64> = upcoming
+        .iter()
+        .take(MAX_LOOKAHEAD)
+        .map(|segment| segment.duration)
+        .filter(|duration| *duration > 0.0)
+        .collect();
+    let average = if durations.is_empty() {
+        DEFAULT_SEGMENT_SECS
+    } else {
+        durations.iter().sum::<f64>() / durations.len() as f64
+    };
+    let count = (mode.target_secs() * factor / average.max(1.0)).ceil() as usize;
+    count.clamp(1, MAX_LOOKAHEAD)
+}
+
+async fn prefetch_segment(
+    state: Arc<Mutex<State>>,
+    fetch: Arc<FetchFn>,
+    permits: Arc<Semaphore>,
+    client: String,
+    session_id: u64,
+    segment: MediaSegment,
+) {
+    let Ok(_permit) = permits.acquire().await else {
+        return;
+    };
+    let started = Instant::now();
+    let result = fetch(segment.url.clone()).await;
+
+    let mut state = state.lock().unwrap();
+    if let Ok(bytes) = result {
+        state
+            .throughput
+            .record(bytes, started.elapsed(), segment.duration);
+    }
+    let Some(session) = state.sessions.get_mut(&client) else {
+        return;
+    };
+    if session.id != session_id {
+        return;
+    }
+    session.tasks.remove(&segment.url);
+    // 失败的分片不再重试，播放器请求时会自行下载
+    session.done.insert(segment.url);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn playlist(count: usize, name: &str) -> String {
+        let mut content = "#EXTM3U\n#EXT-X-TARGETDURATION:6\n".to_string();
+        for index in 0..count {
+            content.push_str(&format!("#EXTINF:6.0,\n{}-{}.ts\n", name, index));
+        }
+        content
+    }
+
+    /// 从媒体序号 `start` 开始的直播播放列表
+    fn live_playlist(start: usize, count: usize) -> String {
+        let mut content =
+            format!("#EXTM3U\n#EXT-X-TARGETDURATION:6\n#EXT-X-MEDIA-SEQUENCE:{start}\n");
+        for index in start..start + count {
+            content.push_str(&format!("#EXTINF:6.0,\nlive-{}.ts\n", index));
+        }
+        content
+    }
+
+    fn recording_prefetcher(delay: Duration) -> (SegmentPrefetcher, Arc<Mutex<Vec<String>>>) {
+        let fetched = Arc::new(Mutex::new(Vec::new()));
+        let log = fetched.clone();
+        let prefetcher = SegmentPrefetcher::new(8, move |url: String| {
+            let log = log.clone();
+            async move {
+                tokio::time::sleep(delay).await;
+                log.lock().unwrap().push(url);
+                Ok(1024)
+            }
+        });
+        (prefetcher, fetched)
+    }
+
+    /// 等到其他任务都在等待：时钟暂停时，运行时空闲后才会自动推进到下一个计时器
+    async fn run_until_idle() {
+        tokio::time::sleep(Duration::from_millis(1)).await;
+    }
+
+    /// 让已派生的预取开始下载后推进暂停的时钟，并等到期的预取运行完
+    async fn advance(duration: Duration) {
+        run_until_idle().await;
+        tokio::time::advance(duration).await;
+        run_until_idle().await;
+    }
+
+    #[test]
+    fn lookahead_grows_with_buffer_mode_and_bandwidth() {
+        let segments = media_segments(&playlist(100, "s"), "https://cdn.example.com/");
+        assert_eq!(lookahead(&segments, 0, BufferMode::Standard, 0.5), 3);
+        assert_eq!(lookahead(&segments, 0, BufferMode::Max, 1.0), 20);
+        assert_eq!(lookahead(&segments, 0, BufferMode::Standard, 0.25), 2);
+
+        let mut throughput = Throughput::default();
+        throughput.record(6_000_000, Duration::from_secs(1), 6.0);
+        assert_eq!(throughput.window_factor(), 1.0);
+        assert_eq!(
+            BufferMode::from_preference("enhanced"),
+            BufferMode::Enhanced
+        );
+    }
+
+    #[tokio::test]
+    async fn prefetches_ahead_of_the_current_segment() {
+        tokio::time::pause();
+        let (prefetcher, fetched) = recording_prefetcher(Duration::ZERO);
+        let base = "https://cdn.example.com/v/index.m3u8";
+        prefetcher.register_playlist("tv", base, &playlist(10, "a"));
+        prefetcher.on_segment_request("tv", "https://cdn.example.com/v/a-0.ts");
+        advance(Duration::from_millis(50)).await;
+
+        let mut urls = fetched.lock().unwrap().clone();
+        urls.sort();
+        assert_eq!(
+            urls,
+            vec![
+                "https://cdn.example.com/v/a-1.ts",
+                "https://cdn.example.com/v/a-2.ts",
+                "https://cdn.example.com/v/a-3.ts",
+            ]
+        );
+
+        // 未知客户端或播放列表之外的地址不触发预取
+        prefetcher.on_segment_request("other", "https://cdn.example.com/v/a-0.ts");
+        prefetcher.on_segment_request("tv", "https://cdn.example.com/v/zzz.ts");
+        advance(Duration::from_millis(20)).await;
+        assert_eq!(fetched.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn seeking_and_switching_cancel_pending_prefetches() {
+        tokio::time::pause();
+        let (prefetcher, fetched) = recording_prefetcher(Duration::from_millis(100));
+        let base = "https://cdn.example.com/v/index.m3u8";
+        prefetcher.register_playlist("tv", base, &playlist(30, "a"));
+        prefetcher.on_segment_request("tv", "https://cdn.example.com/v/a-0.ts");
+        // 跳转到后面，旧窗口中未完成的预取被取消
+        prefetcher.on_segment_request("tv", "https://cdn.example.com/v/a-20.ts");
+        advance(Duration::from_millis(200)).await;
+
+        let mut urls = fetched.lock().unwrap().clone();
+        urls.sort();
+        assert_eq!(
+            urls,
+            vec![
+                "https://cdn.example.com/v/a-21.ts",
+                "https://cdn.example.com/v/a-22.ts",
+                "https://cdn.example.com/v/a-23.ts",
+            ]
+        );
+
+        // 换源后旧播放列表的预取全部取消
+        fetched.lock().unwrap().clear();
+        prefetcher.on_segment_request("tv", "https://cdn.example.com/v/a-25.ts");
+        prefetcher.register_playlist(
+            "tv",
+            "https://other.example.com/index.m3u8",
+            &playlist(5, "b"),
+        );
+        advance(Duration::from_millis(200)).await;
+        assert!(fetched.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn live_refresh_keeps_prefetch_state_per_segment() {
+        tokio::time::pause();
+        let (prefetcher, fetched) = recording_prefetcher(Duration::ZERO);
+        let base = "https://cdn.example.com/live/index.m3u8";
+        let segment = |index: usize| format!("https://cdn.example.com/live/live-{}.ts", index);
+        prefetcher.register_playlist("tv", base, &live_playlist(0, 10));
+        prefetcher.on_segment_request("tv", &segment(0));
+        advance(Duration::from_millis(50)).await;
+
+        // 刷新后分片序号前移了 3 个，已预取的 1-3 号不会挡住其后的分片，也不会重复下载；
+        // 首批预取测得带宽充足，窗口扩大到 5 个分片
+        prefetcher.register_playlist("tv", base, &live_playlist(3, 10));
+        prefetcher.on_segment_request("tv", &segment(3));
+        advance(Duration::from_millis(50)).await;
+
+        let mut urls = fetched.lock().unwrap().clone();
+        urls.sort();
+        assert_eq!(urls, (1..=8).map(segment).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,391 @@
+use crate::outbound::OutboundScheduler;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, COOKIE, ORIGIN, REFERER, USER_AGENT};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+/// 默认桌面端 User-Agent，所有出站请求共用
+pub const DEFAULT_USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/122.0.0.0 Safari/537.36";
+/// 移动端 User-Agent，部分 TVBox 资源只对移动端放行
+pub const MOBILE_USER_AGENT: &str = "Mozilla/5.0 (Linux; Android 11; SM-G973F) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/91.0.4472.124 Mobile Safari/537.36";
+
+/// 媒体主机登记上限，超出后整体清空重新登记
+const MAX_MEDIA_HOSTS: usize = 4096;
+
+/// 源级别的请求配置，保存在源配置的 `request_profile` 字段中
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RequestProfile {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub referer: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub origin: Option<String>,
+    /// 原样发送的 Cookie，如 `a=1; b=2`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cookie: Option<String>,
+    /// 其他自定义请求头
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, String>,
+    /// 源站之外也发送 Cookie 与 User-Agent 的主机（含子域名），如需要登录态的分片 CDN
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub cookie_hosts: Vec<String>,
+}
+
+impl RequestProfile {
+    /// 读取源配置的 `request_profile`，没有时回退到 TVBox 的 `header` 字段
+    pub fn from_source(source: &Value) -> Option<Self> {
+        let profile = match source.get("request_profile") {
+            Some(profile) => serde_json::from_value::<RequestProfile>(profile.clone()).ok()?,
+            None => Self::from_tvbox_header(source.get("header")?)?,
+        };
+        (!profile.is_empty()).then_some(profile)
+    }
+
+    /// 解析 TVBox 站点的 `header` 字段（对象或 JSON 字符串）
+    pub fn from_tvbox_header(header: &Value) -> Option<Self> {
+        let parsed;
+        let header = match header {
+            Value::String(raw) => {
+                parsed = serde_json::from_str::<Value>(raw).ok()?;
+                &parsed
+            }
+            other => other,
+        };
+
+        let mut profile = RequestProfile::default();
+        for (name, value) in header.as_object()? {
+            let Some(value) = value.as_str().map(str::trim).filter(|v| !v.is_empty()) else {
+                continue;
+            };
+            let value = value.to_string();
+            match name.trim().to_ascii_lowercase().as_str() {
+                "user-agent" => profile.user_agent = Some(value),
+                "referer" => profile.referer = Some(value),
+                "origin" => profile.origin = Some(value),
+                "cookie" => profile.cookie = Some(value),
+                _ => {
+                    profile.headers.insert(name.trim().to_string(), value);
+                }
+            }
+        }
+        (!profile.is_empty()).then_some(profile)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.user_agent.is_none()
+            && self.referer.is_none()
+            && self.origin.is_none()
+            && self.cookie.is_none()
+            && self.headers.is_empty()
+    }
+
+    /// 转换为 TVBox 站点的 `header` 字段
+    pub fn to_tvbox_header(&self) -> BTreeMap<String, String> {
+        let mut header = self.headers.clone();
+        let named = [
+            ("User-Agent", &self.user_agent),
+            ("Referer", &self.referer),
+            ("Origin", &self.origin),
+            ("Cookie", &self.cookie),
+        ];
+        for (name, value) in named {
+            if let Some(value) = value {
+                header.insert(name.to_string(), value.clone());
+            }
+        }
+        header
+    }
+
+    /// 用于 `url` 的配置：`url` 不在源站 `source_url` 的主机或 `cookie_hosts` 中时去掉 Cookie 与 User-Agent
+    pub fn scoped_to(&self, url: &str, source_url: &str) -> RequestProfile {
+        let host = OutboundScheduler::host_key(url);
+        let trusted = host == OutboundScheduler::host_key(source_url)
+            || self.cookie_hosts.iter().any(|allowed| {
+                let allowed = allowed.trim().trim_start_matches('.').to_ascii_lowercase();
+                !allowed.is_empty() && (host == allowed || host.ends_with(&format!(".{allowed}")))
+            });
+        let mut profile = self.clone();
+        if !trusted {
+            profile.cookie = None;
+            profile.user_agent = None;
+        }
+        profile
+    }
+
+    /// 写入请求头，已存在的请求头（调用方显式指定的）不会被覆盖，非法的名称或值会被跳过
+    pub fn apply_to(&self, headers: &mut HeaderMap) {
+        let named = [
+            (USER_AGENT, &self.user_agent),
+            (REFERER, &self.referer),
+            (ORIGIN, &self.origin),
+            (COOKIE, &self.cookie),
+        ];
+        for (name, value) in named {
+            if let Some(value) = value {
+                insert_if_missing(headers, name, value);
+            }
+        }
+        for (name, value) in &self.headers {
+            if let Ok(name) = HeaderName::from_bytes(name.as_bytes()) {
+                insert_if_missing(headers, name, value);
+            }
+        }
+    }
+}
+
+fn insert_if_missing(headers: &mut HeaderMap, name: HeaderName, value: &str) {
+    if headers.contains_key(&name) {
+        return;
+    }
+    if let Ok(value) = HeaderValue::from_str(value) {
+        headers.insert(name, value);
+    }
+}
+
+/// 内置的 Referer 规则：豆瓣图片与接口需要豆瓣 Referer
+pub fn builtin_referer(url: &str) -> Option<&'static str> {
+    (url.contains("doubanio.com") || url.contains("douban.com"))
+        .then_some("https://www.douban.com/")
+}
+
+/// 补齐默认请求头：内置 Referer → 源配置 → 默认 User-Agent
+pub fn apply_request_defaults(
+    headers: &mut HeaderMap,
+    url: &str,
+    profile: Option<&RequestProfile>,
+) {
+    if let Some(referer) = builtin_referer(url) {
+        insert_if_missing(headers, REFERER, referer);
+    }
+    if let Some(profile) = profile {
+        profile.apply_to(headers);
+    }
+    insert_if_missing(headers, USER_AGENT, DEFAULT_USER_AGENT);
+}
+
+/// 按媒体主机登记请求配置与所属源
+///
+/// 播放地址、海报和分片通常位于源站之外的 CDN，请求时只有 URL，
+/// 因此在拿到源返回的地址时按主机登记该源的配置，后续媒体请求按主机查找
+#[derive(Default)]
+pub struct MediaProfileRegistry {
+    hosts: Mutex<HashMap<String, RequestProfile>>,
+    sources: Mutex<HashMap<String, String>>,
+}
+
+fn insert_media_host<T>(map: &Mutex<HashMap<String, T>>, url: &str, value: T) {
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return;
+    }
+    let host = OutboundScheduler::host_key(url);
+    let mut map = map.lock().unwrap();
+    if map.len() >= MAX_MEDIA_HOSTS && !map.contains_key(&host) {
+        map.clear();
+    }
+    map.insert(host, value);
+}
+
+impl MediaProfileRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 登记主机的请求配置，有内置规则的主机（豆瓣）不登记
+    pub fn register_url(&self, url: &str, profile: &RequestProfile) {
+        if builtin_referer(url).is_none() {
+            insert_media_host(&self.hosts, url, profile.clone());
+        }
+    }
+
+    /// 登记媒体主机所属的源，用于把分片统计计入该源
+    pub fn register_source(&self, url: &str, source_key: &str) {
+        insert_media_host(&self.sources, url, source_key.to_string());
+    }
+
+    /// 播放列表中引用的其他主机（分片 CDN）沿用播放列表所属主机的配置与源，
+    /// Cookie 与 User-Agent 只沿用到 [`RequestProfile::scoped_to`] 允许的主机
+    pub fn register_playlist(&self, playlist_url: &str, content: &str) {
+        let profile = self.profile_for(playlist_url);
+        let source_key = self.source_for(playlist_url);
+        if profile.is_none() && source_key.is_none() {
+            return;
+        }
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(profile) = &profile {
+                self.register_url(line, &profile.scoped_to(line, playlist_url));
+            }
+            if let Some(source_key) = &source_key {
+                self.register_source(line, source_key);
+            }
+        }
+    }
+
+    pub fn profile_for(&self, url: &str) -> Option<RequestProfile> {
+        let host = OutboundScheduler::host_key(url);
+        self.hosts.lock().unwrap().get(&host).cloned()
+    }
+
+    pub fn source_for(&self, url: &str) -> Option<String> {
+        let host = OutboundScheduler::host_key(url);
+        self.sources.lock().unwrap().get(&host).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn tvbox_header_maps_known_fields() {
+        let source = json!({
+            "api": "https://a.example.com/api.php/provide/vod",
+            "header": "{\"User-Agent\":\"okhttp/3.12\",\"referer\":\"https://a.example.com/\",\"X-Token\":\"t\"}"
+        });
+        let profile = RequestProfile::from_source(&source).unwrap();
+        assert_eq!(profile.user_agent.as_deref(), Some("okhttp/3.12"));
+        assert_eq!(profile.referer.as_deref(), Some("https://a.example.com/"));
+        assert_eq!(
+            profile.headers.get("X-Token").map(String::as_str),
+            Some("t")
+        );
+        assert_eq!(
+            profile
+                .to_tvbox_header()
+                .get("User-Agent")
+                .map(String::as_str),
+            Some("okhttp/3.12")
+        );
+    }
+
+    #[test]
+    fn explicit_headers_win_over_profile_and_defaults() {
+        let profile = RequestProfile {
+            user_agent: Some("okhttp/3.12".to_string()),
+            cookie: Some("sid=1".to_string()),
+            ..Default::default()
+        };
+        let mut headers = HeaderMap::new();
+        headers.insert(REFERER, HeaderValue::from_static("https://explicit/"));
+
+        apply_request_defaults(
+            &mut headers,
+            "https://img.doubanio.com/a.jpg",
+            Some(&profile),
+        );
+        assert_eq!(headers.get(REFERER).unwrap(), "https://explicit/");
+        assert_eq!(headers.get(USER_AGENT).unwrap(), "okhttp/3.12");
+        assert_eq!(headers.get(COOKIE).unwrap(), "sid=1");
+
+        let mut headers = HeaderMap::new();
+        apply_request_defaults(&mut headers, "https://img.doubanio.com/a.jpg", None);
+        assert_eq!(headers.get(REFERER).unwrap(), "https://www.douban.com/");
+        assert_eq!(headers.get(USER_AGENT).unwrap(), DEFAULT_USER_AGENT);
+    }
+
+    #[test]
+    fn builtin_referer_wins_over_profile() {
+        let profile = RequestProfile {
+            referer: Some("https://a.example.com/".to_string()),
+            ..Default::default()
+        };
+        let mut headers = HeaderMap::new();
+        apply_request_defaults(
+            &mut headers,
+            "https://img.doubanio.com/a.jpg",
+            Some(&profile),
+        );
+        assert_eq!(headers.get(REFERER).unwrap(), "https://www.douban.com/");
+
+        let registry = MediaProfileRegistry::new();
+        registry.register_url("https://img.doubanio.com/a.jpg", &profile);
+        assert_eq!(registry.profile_for("https://img.doubanio.com/b.jpg"), None);
+    }
+
+    #[test]
+    fn cookies_stay_on_source_and_listed_hosts() {
+        let profile = RequestProfile {
+            user_agent: Some("okhttp/3.12".to_string()),
+            referer: Some("https://a.example.com/".to_string()),
+            cookie: Some("sid=1".to_string()),
+            cookie_hosts: vec!["cdn.example.net".to_string()],
+            ..Default::default()
+        };
+        let source = "https://a.example.com/api.php/provide/vod";
+
+        assert_eq!(
+            profile.scoped_to("https://a.example.com/play.m3u8", source),
+            profile
+        );
+        assert_eq!(
+            profile.scoped_to("https://v1.cdn.example.net/seg.ts", source),
+            profile
+        );
+        let other = profile.scoped_to("https://img.other.org/a.jpg", source);
+        assert_eq!(other.cookie, None);
+        assert_eq!(other.user_agent, None);
+        assert_eq!(other.referer, profile.referer);
+
+        let registry = MediaProfileRegistry::new();
+        registry.register_url("https://a.example.com/index.m3u8", &profile);
+        registry.register_playlist(
+            "https://a.example.com/index.m3u8",
+            "#EXTM3U\n#EXTINF:10,\nhttps://cdn.example.net/seg0.ts\nhttps://ads.other.org/ad.ts\n",
+        );
+        assert_eq!(
+            registry.profile_for("https://cdn.example.net/seg1.ts"),
+            Some(profile.clone())
+        );
+        let ads = registry.profile_for("https://ads.other.org/ad.ts").unwrap();
+        assert_eq!(ads.cookie, None);
+        assert_eq!(ads.user_agent, None);
+    }
+
+    #[test]
+    fn playlist_hosts_inherit_profile() {
+        let registry = MediaProfileRegistry::new();
+        let profile = RequestProfile {
+            referer: Some("https://a.example.com/".to_string()),
+            ..Default::default()
+        };
+        registry.register_url("https://play.example.com/index.m3u8", &profile);
+        registry.register_playlist(
+            "https://play.example.com/index.m3u8",
+            "#EXTM3U\n#EXTINF:10,\nhttps://cdn.example.net/seg0.ts\nseg1.ts\n",
+        );
+
+        assert_eq!(
+            registry.profile_for("https://cdn.example.net/seg9.ts"),
+            Some(profile)
+        );
+        assert_eq!(registry.profile_for("https://other.example.org/a.ts"), None);
+    }
+
+    #[test]
+    fn playlist_hosts_inherit_source() {
+        let registry = MediaProfileRegistry::new();
+        registry.register_source("https://play.example.com/index.m3u8", "source_a");
+        registry.register_playlist(
+            "https://play.example.com/index.m3u8",
+            "#EXTM3U\n#EXTINF:10,\nhttps://cdn.example.net/seg0.ts\n",
+        );
+
+        assert_eq!(
+            registry
+                .source_for("https://CDN.example.net/seg1.ts")
+                .as_deref(),
+            Some("source_a")
+        );
+        assert_eq!(
+            registry.profile_for("https://cdn.example.net/seg1.ts"),
+            None
+        );
+        assert_eq!(registry.source_for("https://other.example.org/a.ts"), None);
+    }
+}
@@ -0,0 +1,732 @@
+//! SAMPLE-AES 加密的 MPEG-TS 分片解密
+//!
+//! 按 Apple 的 MPEG-2 Stream Encryption Format for HTTP Live Streaming：
+//! H.264 只加密类型 1、5 且长于 48 字节的 NAL，前 32 字节为明文，之后每 160 字节加密开头的 16 字节；
+//! AAC 在 ADTS 帧头之后、AC-3 / E-AC-3 在帧开头之后留 16 字节明文，再加密其余完整的块。
+//! 每个 NAL 或音频帧都从标签中的 IV 重新开始 CBC
+use crate::hls_crypto::AES_KEY_LEN;
+use aes::cipher::{BlockDecrypt, KeyInit};
+use aes::{Aes128, Block};
+use std::collections::{HashMap, HashSet};
+
+const TS_PACKET_LEN: usize = 188;
+const TS_SYNC_BYTE: u8 = 0x47;
+const PAT_PID: u16 = 0;
+
+/// NAL 开头不加密的字节数
+const NAL_CLEAR_LEADER: usize = 32;
+/// 不超过该长度的 NAL 不加密
+const NAL_MIN_ENCRYPTED_LEN: usize = 48;
+/// 每个加密块之后的明文字节数（1:9 模式）
+const NAL_CLEAR_STRIDE: usize = 144;
+/// 音频帧不加密的开头字节数（AAC 从 ADTS 帧头之后算起）
+const AUDIO_CLEAR_LEADER: usize = 16;
+
+/// AC-3 各码率（kbps），按 frmsizecod / 2 索引
+const AC3_BITRATES: [usize; 19] = [
+    32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384, 448, 512, 576, 640,
+];
+
+/// SAMPLE-AES 加密的基本流
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EncryptedStream {
+    H264,
+    Aac,
+    Ac3,
+}
+
+impl EncryptedStream {
+    /// 由加密流的 stream_type 得到流类型与解密后的明文 stream_type
+    fn from_stream_type(stream_type: u8) -> Option<(Self, u8)> {
+        match stream_type {
+            0xDB => Some((EncryptedStream::H264, 0x1B)),
+            0xCF => Some((EncryptedStream::Aac, 0x0F)),
+            0xC1 => Some((EncryptedStream::Ac3, 0x81)),
+            0xC2 => Some((EncryptedStream::Ac3, 0x87)),
+            _ => None,
+        }
+    }
+}
+
+/// 解密 SAMPLE-AES 加密的 TS 分片，节目映射表中的加密流类型改回明文类型
+///
+/// 没有加密流时原样返回
+pub fn decrypt_sample_aes_ts(
+    data: &[u8],
+    key: &[u8; AES_KEY_LEN],
+    iv: &[u8; AES_KEY_LEN],
+) -> Result<Vec<u8>, String> {
+    if data.is_empty() || !data.len().is_multiple_of(TS_PACKET_LEN) {
+        return Err(format!("TS 分片长度 {} 不是 188 的整数倍", data.len()));
+    }
+    let mut packets: Vec<Vec<u8>> = data.chunks(TS_PACKET_LEN).map(<[u8]>::to_vec).collect();
+    if packets.iter().any(|packet| packet[0] != TS_SYNC_BYTE) {
+        return Err("SAMPLE-AES 只支持 MPEG-TS 分片".to_string());
+    }
+
+    let streams = rewrite_program_maps(&mut packets);
+    if streams.is_empty() {
+        return Ok(data.to_vec());
+    }
+
+    let cipher = Aes128::new(key.into());
+    let mut unused = HashSet::new();
+    for (&pid, &stream) in &streams {
+        for unit in pes_units(&packets, pid) {
+            let pes: Vec<u8> = unit
+                .iter()
+                .flat_map(|&(index, offset)| packets[index][offset..].iter().copied())
+                .collect();
+            let Some(pes) = decrypt_pes(&pes, stream, &cipher, iv) else {
+                continue;
+            };
+            unused.extend(repacketize(&mut packets, &unit, &pes)?);
+        }
+    }
+
+    let mut packets: Vec<Vec<u8>> = packets
+        .into_iter()
+        .enumerate()
+        .filter(|(index, _)| !unused.contains(index))
+        .map(|(_, packet)| packet)
+        .collect();
+    renumber_continuity(&mut packets, &streams);
+    Ok(packets.concat())
+}
+
+fn pid(packet: &[u8]) -> u16 {
+    u16::from(packet[1] & 0x1F) << 8 | u16::from(packet[2])
+}
+
+fn starts_unit(packet: &[u8]) -> bool {
+    packet[1] & 0x40 != 0
+}
+
+/// 负载在包内的起始位置，没有负载时为 `None`
+fn payload_offset(packet: &[u8]) -> Option<usize> {
+    let control = (packet[3] >> 4) & 0x03;
+    if control & 0x01 == 0 {
+        return None;
+    }
+    let offset = if control & 0x02 != 0 {
+        5 + usize::from(packet[4])
+    } else {
+        4
+    };
+    (offset < TS_PACKET_LEN).then_some(offset)
+}
+
+/// 单个包内完整的 PSI 段（不支持跨包的段）
+fn psi_section(packet: &[u8]) -> Option<(usize, usize)> {
+    if !starts_unit(packet) {
+        return None;
+    }
+    let payload = payload_offset(packet)?;
+    let start = payload + 1 + usize::from(*packet.get(payload)?);
+    let header = packet.get(start..start + 3)?;
+    let end = start + 3 + (usize::from(header[1] & 0x0F) << 8 | usize::from(header[2]));
+    (end <= TS_PACKET_LEN && end >= start + 12).then_some((start, end))
+}
+
+/// 把 PMT 中的加密流类型改回明文类型并重算 CRC，返回加密流的 PID
+fn rewrite_program_maps(packets: &mut [Vec<u8>]) -> HashMap<u16, EncryptedStream> {
+    let mut program_maps = HashSet::new();
+    for packet in packets.iter().filter(|packet| pid(packet) == PAT_PID) {
+        let Some((start, end)) = psi_section(packet) else {
+            continue;
+        };
+        // 节目号 0 指向网络信息表
+        for entry in packet[start + 8..end - 4].chunks_exact(4) {
+            if entry[0] != 0 || entry[1] != 0 {
+                program_maps.insert(u16::from(entry[2] & 0x1F) << 8 | u16::from(entry[3]));
+            }
+        }
+    }
+
+    let mut streams = HashMap::new();
+    for packet in packets
+        .iter_mut()
+        .filter(|packet| program_maps.contains(&pid(packet)))
+    {
+        let Some((start, end)) = psi_section(packet) else {
+            continue;
+        };
+        if packet[start] != 0x02 {
+            continue;
+        }
+        let program_info =
+            usize::from(packet[start + 10] & 0x0F) << 8 | usize::from(packet[start + 11]);
+        let mut entry = start + 12 + program_info;
+        let mut changed = false;
+        while entry + 5 <= end - 4 {
+            if let Some((stream, clear_type)) = EncryptedStream::from_stream_type(packet[entry]) {
+                let pid = u16::from(packet[entry + 1] & 0x1F) << 8 | u16::from(packet[entry + 2]);
+                streams.insert(pid, stream);
+                packet[entry] = clear_type;
+                changed = true;
+            }
+            let es_info =
+                usize::from(packet[entry + 3] & 0x0F) << 8 | usize::from(packet[entry + 4]);
+            entry += 5 + es_info;
+        }
+        if changed {
+            let crc = crc32_mpeg2(&packet[start..end - 4]);
+            packet[end - 4..end].copy_from_slice(&crc.to_be_bytes());
+        }
+    }
+    streams
+}
+
+/// MPEG-2 PSI 使用的 CRC-32
+fn crc32_mpeg2(data: &[u8]) -> u32 {
+    let mut crc = u32::MAX;
+    for &byte in data {
+        crc ^= u32::from(byte) << 24;
+        for _ in 0..8 {
+            crc = if crc & 0x8000_0000 != 0 {
+                (crc << 1) ^ 0x04C1_1DB7
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// 按 PES 分组的包序号与负载位置；分片开头不完整的 PES 跳过
+fn pes_units(packets: &[Vec<u8>], target: u16) -> Vec<Vec<(usize, usize)>> {
+    let mut units: Vec<Vec<(usize, usize)>> = Vec::new();
+    for (index, packet) in packets.iter().enumerate() {
+        if pid(packet) != target {
+            continue;
+        }
+        let Some(offset) = payload_offset(packet) else {
+            continue;
+        };
+        if starts_unit(packet) {
+            units.push(vec![(index, offset)]);
+        } else if let Some(unit) = units.last_mut() {
+            unit.push((index, offset));
+        }
+    }
+    units
+}
+
+/// 解密 PES 中的基本流数据，PES 头不完整时返回 `None`
+fn decrypt_pes(
+    pes: &[u8],
+    stream: EncryptedStream,
+    cipher: &Aes128,
+    iv: &[u8; AES_KEY_LEN],
+) -> Option<Vec<u8>> {
+    if pes.len() < 9 || pes[..3] != [0, 0, 1] {
+        return None;
+    }
+    let es_start = 9 + usize::from(pes[8]);
+    if es_start > pes.len() {
+        return None;
+    }
+    let mut result = pes[..es_start].to_vec();
+    match stream {
+        EncryptedStream::H264 => result.extend(decrypt_h264(&pes[es_start..], cipher, iv)),
+        EncryptedStream::Aac | EncryptedStream::Ac3 => {
+            let mut es = pes[es_start..].to_vec();
+            decrypt_audio_frames(&mut es, stream, cipher, iv);
+            result.extend(es);
+        }
+    }
+    // 去掉防竞争字节后 PES 变短，声明了长度时随之更新
+    if pes[4] != 0 || pes[5] != 0 {
+        let length = u16::try_from(result.len() - 6).unwrap_or(0);
+        result[4..6].copy_from_slice(&length.to_be_bytes());
+    }
+    Some(result)
+}
+
+/// 解密 Annex B 格式的 H.264 数据
+fn decrypt_h264(es: &[u8], cipher: &Aes128, iv: &[u8; AES_KEY_LEN]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(es.len());
+    let mut cursor = 0;
+    for (start, end) in nal_units(es) {
+        result.extend_from_slice(&es[cursor..start]);
+        let nal = &es[start..end];
+        let mut clear = Vec::new();
+        if matches!(nal[0] & 0x1F, 1 | 5) {
+            clear = remove_emulation_prevention(nal);
+        }
+        if clear.len() > NAL_MIN_ENCRYPTED_LEN {
+            decrypt_nal(&mut clear, cipher, iv);
+            result.extend(clear);
+        } else {
+            result.extend_from_slice(nal);
+        }
+        cursor = end;
+    }
+    result.extend_from_slice(&es[cursor..]);
+    result
+}
+
+/// 各 NAL 的起止位置（不含起始码与其前的补零）
+fn nal_units(es: &[u8]) -> Vec<(usize, usize)> {
+    let mut starts = Vec::new();
+    let mut index = 0;
+    while index + 3 <= es.len() {
+        if es[index..index + 3] == [0, 0, 1] {
+            starts.push(index + 3);
+            index += 3;
+        } else {
+            index += 1;
+        }
+    }
+    let mut units = Vec::new();
+    for (position, &start) in starts.iter().enumerate() {
+        let mut end = starts.get(position + 1).map_or(es.len(), |next| next - 3);
+        while end > start && es[end - 1] == 0 {
+            end -= 1;
+        }
+        if end > start {
+            units.push((start, end));
+        }
+    }
+    units
+}
+
+/// 去掉 `00 00 03` 中的防竞争字节
+fn remove_emulation_prevention(nal: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(nal.len());
+    let mut zeros = 0;
+    for &byte in nal {
+        if zeros >= 2 && byte == 0x03 {
+            zeros = 0;
+            continue;
+        }
+        zeros = if byte == 0 { zeros + 1 } else { 0 };
+        result.push(byte);
+    }
+    result
+}
+
+fn decrypt_nal(nal: &mut [u8], cipher: &Aes128, iv: &[u8; AES_KEY_LEN]) {
+    let mut chain = Block::from(*iv);
+    let mut offset = NAL_CLEAR_LEADER;
+    while offset < nal.len() {
+        if nal.len() - offset > AES_KEY_LEN {
+            decrypt_block(cipher, &mut chain, &mut nal[offset..offset + AES_KEY_LEN]);
+            offset += AES_KEY_LEN;
+        }
+        offset += NAL_CLEAR_STRIDE.min(nal.len() - offset);
+    }
+}
+
+/// 解密 ADTS 或 AC-3 / E-AC-3 帧序列，遇到无法识别的数据时停止
+fn decrypt_audio_frames(
+    es: &mut [u8],
+    stream: EncryptedStream,
+    cipher: &Aes128,
+    iv: &[u8; AES_KEY_LEN],
+) {
+    let mut offset = 0;
+    while offset < es.len() {
+        let frame = &es[offset..];
+        let parsed = match stream {
+            EncryptedStream::Aac => adts_frame(frame),
+            _ => ac3_frame(frame).map(|length| (0, length)),
+        };
+        let Some((header_len, frame_len)) = parsed else {
+            break;
+        };
+        if frame_len == 0 || offset + frame_len > es.len() {
+            break;
+        }
+        let start = offset + header_len + AUDIO_CLEAR_LEADER;
+        let end = offset + frame_len;
+        if start < end {
+            let blocks_end = start + (end - start) / AES_KEY_LEN * AES_KEY_LEN;
+            let mut chain = Block::from(*iv);
+            for block in es[start..blocks_end].chunks_exact_mut(AES_KEY_LEN) {
+                decrypt_block(cipher, &mut chain, block);
+            }
+        }
+        offset = end;
+    }
+}
+
+/// ADTS 帧头长度与帧长度
+fn adts_frame(frame: &[u8]) -> Option<(usize, usize)> {
+    let header = frame.get(..7)?;
+    if header[0] != 0xFF || header[1] & 0xF0 != 0xF0 {
+        return None;
+    }
+    let header_len = if header[1] & 0x01 != 0 { 7 } else { 9 };
+    let frame_len = usize::from(header[3] & 0x03) << 11
+        | usize::from(header[4]) << 3
+        | usize::from(header[5] >> 5);
+    (frame_len >= header_len).then_some((header_len, frame_len))
+}
+
+/// AC-3 / E-AC-3 同步帧的长度
+fn ac3_frame(frame: &[u8]) -> Option<usize> {
+    let header = frame.get(..6)?;
+    if header[..2] != [0x0B, 0x77] {
+        return None;
+    }
+    let bsid = header[5] >> 3;
+    if bsid > 10 {
+        // E-AC-3：frmsiz 为 16 位字数减一
+        let words = (usize::from(header[2] & 0x07) << 8 | usize::from(header[3])) + 1;
+        return Some(words * 2);
+    }
+    let code = usize::from(header[4] & 0x3F);
+    let bitrate = *AC3_BITRATES.get(code / 2)?;
+    let words = match header[4] >> 6 {
+        0 => bitrate * 2,
+        1 => bitrate * 320 / 147 + code % 2,
+        2 => bitrate * 3,
+        _ => return None,
+    };
+    Some(words * 2)
+}
+
+/// 解密一个 CBC 块，`chain` 为上一个密文块
+fn decrypt_block(cipher: &Aes128, chain: &mut Block, data: &mut [u8]) {
+    let encrypted = Block::clone_from_slice(data);
+    let mut block = encrypted;
+    cipher.decrypt_block(&mut block);
+    for ((byte, plain), previous) in data.iter_mut().zip(block.iter()).zip(chain.iter()) {
+        *byte = plain ^ previous;
+    }
+    *chain = encrypted;
+}
+
+/// 把 PES 写回原来的包，末尾不足一包时用调整字段填充；返回用不到的包
+fn repacketize(
+    packets: &mut [Vec<u8>],
+    unit: &[(usize, usize)],
+    pes: &[u8],
+) -> Result<Vec<usize>, String> {
+    let mut rest = pes;
+    let mut unused = Vec::new();
+    for &(index, offset) in unit {
+        if rest.is_empty() {
+            unused.push(index);
+            continue;
+        }
+        let capacity = TS_PACKET_LEN - offset;
+        if rest.len() >= capacity {
+            packets[index][offset..].copy_from_slice(&rest[..capacity]);
+            rest = &rest[capacity..];
+        } else {
+            packets[index] = stuffed_packet(&packets[index], rest);
+            rest = &[];
+        }
+    }
+    if !rest.is_empty() {
+        return Err("解密后的 PES 超出了原有的 TS 包".to_string());
+    }
+    Ok(unused)
+}
+
+fn stuffed_packet(packet: &[u8], payload: &[u8]) -> Vec<u8> {
+    let adaptation: &[u8] = if packet[3] & 0x20 != 0 {
+        &packet[5..5 + usize::from(packet[4])]
+    } else {
+        &[]
+    };
+    let field_len = TS_PACKET_LEN - 5 - payload.len();
+    let mut result = packet[..4].to_vec();
+    result[3] |= 0x30;
+    result.push(field_len as u8);
+    if field_len > 0 {
+        if adaptation.is_empty() {
+            result.push(0x00);
+        } else {
+            result.extend_from_slice(adaptation);
+        }
+    }
+    result.resize(TS_PACKET_LEN - payload.len(), 0xFF);
+    result.extend_from_slice(payload);
+    result
+}
+
+/// 删除包之后重新编号连续计数器，只有带负载的包递增
+fn renumber_continuity(packets: &mut [Vec<u8>], streams: &HashMap<u16, EncryptedStream>) {
+    let mut counters: HashMap<u16, u8> = HashMap::new();
+    for packet in packets.iter_mut() {
+        let pid = pid(packet);
+        if !streams.contains_key(&pid) {
+            continue;
+        }
+        let counter = match counters.get(&pid) {
+            Some(&previous) if payload_offset(packet).is_some() => (previous + 1) & 0x0F,
+            Some(&previous) => previous,
+            None => packet[3] & 0x0F,
+        };
+        packet[3] = (packet[3] & 0xF0) | counter;
+        counters.insert(pid, counter);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aes::cipher::BlockEncrypt;
+
+    const KEY: [u8; AES_KEY_LEN] = *b"0123456789abcdef";
+    const IV: [u8; AES_KEY_LEN] = *b"fedcba9876543210";
+    const PMT_PID: u16 = 0x1000;
+    const VIDEO_PID: u16 = 0x100;
+    const AUDIO_PID: u16 = 0x101;
+
+    fn encrypt_block(cipher: &Aes128, chain: &mut Block, data: &mut [u8]) {
+        let mut block = Block::clone_from_slice(data);
+        for (byte, previous) in block.iter_mut().zip(chain.iter()) {
+            *byte ^= previous;
+        }
+        cipher.encrypt_block(&mut block);
+        data.copy_from_slice(&block);
+        *chain = block;
+    }
+
+    fn add_emulation_prevention(data: &[u8]) -> Vec<u8> {
+        let mut result = Vec::new();
+        let mut zeros = 0;
+        for &byte in data {
+            if zeros >= 2 && byte <= 0x03 {
+                result.push(0x03);
+                zeros = 0;
+            }
+            zeros = if byte == 0 { zeros + 1 } else { 0 };
+            result.push(byte);
+        }
+        result
+    }
+
+    fn encrypt_nal(nal: &[u8]) -> Vec<u8> {
+        let cipher = Aes128::new(&KEY.into());
+        let mut data = nal.to_vec();
+        let mut chain = Block::from(IV);
+        let mut offset = NAL_CLEAR_LEADER;
+        while offset < data.len() {
+            if data.len() - offset > AES_KEY_LEN {
+                encrypt_block(&cipher, &mut chain, &mut data[offset..offset + AES_KEY_LEN]);
+                offset += AES_KEY_LEN;
+            }
+            offset += NAL_CLEAR_STRIDE.min(data.len() - offset);
+        }
+        add_emulation_prevention(&data)
+    }
+
+    fn encrypt_frame(frame: &[u8], clear_len: usize) -> Vec<u8> {
+        let cipher = Aes128::new(&KEY.into());
+        let mut data = frame.to_vec();
+        let mut chain = Block::from(IV);
+        let blocks = (data.len() - clear_len) / AES_KEY_LEN;
+        for block in data[clear_len..clear_len + blocks * AES_KEY_LEN].chunks_exact_mut(16) {
+            encrypt_block(&cipher, &mut chain, block);
+        }
+        data
+    }
+
+    /// 已含防竞争字节的 IDR 片，明文区域中有 `00 00 03 01`
+    fn idr_slice(len: usize) -> Vec<u8> {
+        let mut nal = vec![0x65];
+        while nal.len() < len {
+            nal.extend_from_slice(&[0x00, 0x00, 0x03, 0x01, 0x5A, 0x21, 0x7E, 0x42]);
+        }
+        nal.truncate(len);
+        nal.push(0x80);
+        nal
+    }
+
+    fn adts_frame_bytes(payload_len: usize, seed: u8) -> Vec<u8> {
+        let frame_len = 7 + payload_len;
+        let mut frame = vec![
+            0xFF,
+            0xF1,
+            0x50,
+            0x80 | (frame_len >> 11) as u8,
+            (frame_len >> 3) as u8,
+            ((frame_len & 0x07) << 5) as u8 | 0x1F,
+            0xFC,
+        ];
+        frame.extend((0..payload_len).map(|i| seed.wrapping_add(i as u8)));
+        frame
+    }
+
+    fn section_packet(pid: u16, mut section: Vec<u8>) -> Vec<u8> {
+        let crc = crc32_mpeg2(&section);
+        section.extend_from_slice(&crc.to_be_bytes());
+        let mut packet = vec![0x47, 0x40 | (pid >> 8) as u8, pid as u8, 0x10, 0x00];
+        packet.extend(section);
+        packet.resize(TS_PACKET_LEN, 0xFF);
+        packet
+    }
+
+    fn pat() -> Vec<u8> {
+        let pmt = [0xE0 | (PMT_PID >> 8) as u8, PMT_PID as u8];
+        section_packet(
+            PAT_PID,
+            vec![
+                0x00, 0xB0, 13, 0x00, 0x01, 0xC1, 0x00, 0x00, 0x00, 0x01, pmt[0], pmt[1],
+            ],
+        )
+    }
+
+    fn pmt(streams: &[(u8, u16)]) -> Vec<u8> {
+        let mut section = vec![
+            0x02,
+            0xB0,
+            (13 + 5 * streams.len()) as u8,
+            0x00,
+            0x01,
+            0xC1,
+            0x00,
+            0x00,
+            0xE0 | (VIDEO_PID >> 8) as u8,
+            VIDEO_PID as u8,
+            0xF0,
+            0x00,
+        ];
+        for &(stream_type, pid) in streams {
+            section.extend_from_slice(&[
+                stream_type,
+                0xE0 | (pid >> 8) as u8,
+                pid as u8,
+                0xF0,
+                0x00,
+            ]);
+        }
+        section_packet(PMT_PID, section)
+    }
+
+    fn pes_packets(pid: u16, stream_id: u8, es: &[u8], bounded: bool) -> Vec<Vec<u8>> {
+        let mut pes = vec![
+            0, 0, 1, stream_id, 0, 0, 0x80, 0x80, 0x05, 0x21, 0x00, 0x01, 0x00, 0x01,
+        ];
+        pes.extend_from_slice(es);
+        if bounded {
+            let length = (pes.len() - 6) as u16;
+            pes[4..6].copy_from_slice(&length.to_be_bytes());
+        }
+        let mut packets = Vec::new();
+        let mut rest = pes.as_slice();
+        let mut counter = 0u8;
+        while !rest.is_empty() {
+            let first = packets.is_empty();
+            let header = vec![
+                0x47,
+                if first { 0x40 } else { 0x00 } | (pid >> 8) as u8,
+                pid as u8,
+                0x10 | counter,
+            ];
+            let packet = if rest.len() >= 184 {
+                let mut packet = header;
+                packet.extend_from_slice(&rest[..184]);
+                rest = &rest[184..];
+                packet
+            } else {
+                let packet = stuffed_packet(&header, rest);
+                rest = &[];
+                packet
+            };
+            packets.push(packet);
+            counter = (counter + 1) & 0x0F;
+        }
+        packets
+    }
+
+    fn elementary_stream(ts: &[u8], target: u16) -> Vec<u8> {
+        let packets: Vec<Vec<u8>> = ts.chunks(TS_PACKET_LEN).map(<[u8]>::to_vec).collect();
+        let pes: Vec<u8> = pes_units(&packets, target)
+            .concat()
+            .into_iter()
+            .flat_map(|(index, offset)| packets[index][offset..].to_vec())
+            .collect();
+        if pes[4] != 0 || pes[5] != 0 {
+            assert_eq!(
+                usize::from(u16::from_be_bytes([pes[4], pes[5]])),
+                pes.len() - 6
+            );
+        }
+        pes[9 + usize::from(pes[8])..].to_vec()
+    }
+
+    fn video_es(slice: &[u8]) -> Vec<u8> {
+        let mut es = vec![0, 0, 0, 1, 0x09, 0xF0, 0, 0, 0, 1];
+        es.extend_from_slice(slice);
+        // 不超过 48 字节的片不加密
+        es.extend_from_slice(&[0, 0, 1, 0x41, 0x9A, 0x02, 0x03, 0x04, 0x80]);
+        es
+    }
+
+    fn segment(streams: &[(u8, u16)], video: &[u8], audio: &[u8]) -> Vec<u8> {
+        let mut packets = vec![pat(), pmt(streams)];
+        packets.extend(pes_packets(VIDEO_PID, 0xE0, video, false));
+        packets.extend(pes_packets(AUDIO_PID, 0xC0, audio, true));
+        packets.concat()
+    }
+
+    #[test]
+    fn h264_and_aac_samples_are_decrypted() {
+        let slice = idr_slice(3000);
+        let clear_video = video_es(&slice);
+        let encrypted_video = video_es(&encrypt_nal(&slice));
+        assert!(encrypted_video.len() > clear_video.len() + 184);
+
+        let frames = [adts_frame_bytes(300, 1), adts_frame_bytes(25, 2)];
+        let clear_audio = frames.concat();
+        let encrypted_audio: Vec<u8> = frames
+            .iter()
+            .flat_map(|frame| encrypt_frame(frame, 7 + AUDIO_CLEAR_LEADER))
+            .collect();
+        assert_ne!(clear_audio, encrypted_audio);
+
+        let encrypted = segment(
+            &[(0xDB, VIDEO_PID), (0xCF, AUDIO_PID)],
+            &encrypted_video,
+            &encrypted_audio,
+        );
+        let decrypted = decrypt_sample_aes_ts(&encrypted, &KEY, &IV).unwrap();
+        let expected = segment(
+            &[(0x1B, VIDEO_PID), (0x0F, AUDIO_PID)],
+            &clear_video,
+            &clear_audio,
+        );
+
+        assert_eq!(decrypted.len(), expected.len());
+        assert_eq!(elementary_stream(&decrypted, VIDEO_PID), clear_video);
+        assert_eq!(elementary_stream(&decrypted, AUDIO_PID), clear_audio);
+        assert_eq!(decrypted, expected);
+    }
+
+    #[test]
+    fn ac3_frames_are_decrypted_from_the_frame_start() {
+        // 48 kHz、frmsizecod 8（64 kbps）：128 个 16 位字
+        let mut frame = vec![0x0B, 0x77, 0x00, 0x00, 0x08, 0x40];
+        frame.extend((0..250).map(|i| i as u8 | 1));
+        assert_eq!(ac3_frame(&frame), Some(256));
+
+        let encrypted_audio = encrypt_frame(&frame, AUDIO_CLEAR_LEADER);
+        let video = video_es(&idr_slice(40));
+        let encrypted = segment(
+            &[(0x1B, VIDEO_PID), (0xC1, AUDIO_PID)],
+            &video,
+            &encrypted_audio,
+        );
+        let decrypted = decrypt_sample_aes_ts(&encrypted, &KEY, &IV).unwrap();
+        assert_eq!(elementary_stream(&decrypted, AUDIO_PID), frame);
+        assert_eq!(
+            decrypted,
+            segment(&[(0x1B, VIDEO_PID), (0x81, AUDIO_PID)], &video, &frame)
+        );
+    }
+
+    #[test]
+    fn clear_and_invalid_segments() {
+        let video = video_es(&idr_slice(500));
+        let clear = segment(&[(0x1B, VIDEO_PID), (0x0F, AUDIO_PID)], &video, &[]);
+        assert_eq!(decrypt_sample_aes_ts(&clear, &KEY, &IV).unwrap(), clear);
+        assert!(decrypt_sample_aes_ts(&clear[..100], &KEY, &IV).is_err());
+        assert!(decrypt_sample_aes_ts(&[0u8; 188], &KEY, &IV).is_err());
+    }
+}
@@ -83,7 +83,7 @@ pub fn aggregate_search_results(
             }
 
             // 顺序包含关键词的所有字符 (转换后的词)
-            if norm_query != &query_lower
+            if *norm_query != query_lower
                 && subsequence_match(&title_no_space, &norm_query_no_space)
             {
                 return true;
@@ -107,7 +107,7 @@ pub fn aggregate_search_results(
         let key = format!("{}-{}-{}", item.title.replace(" ", ""), year_str, item_type);
 
         let is_new_key = !map.contains_key(&key);
-        map.entry(key.clone()).or_insert_with(Vec::new).push(item);
+        map.entry(key.clone()).or_default().push(item);
 
         if is_new_key {
             key_order.push(key);
@@ -282,6 +282,48 @@ fn compare_year(a_year: &str, b_year: &str, order: &YearOrder) -> std::cmp::Orde
     }
 }
 
+/// 两次搜索结果之间的差异（按 source + id 比较）
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SearchResultDiff {
+    /// 新出现的结果
+    pub added: Vec<SearchResult>,
+    /// 已消失结果的 "source|id"
+    pub removed: Vec<String>,
+    /// 剧集列表发生变化的结果
+    pub updated: Vec<SearchResult>,
+}
+
+impl SearchResultDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.updated.is_empty()
+    }
+}
+
+/// 比较旧结果与新结果，得出新增、移除与更新的条目
+pub fn diff_search_results(old: &[SearchResult], new: &[SearchResult]) -> SearchResultDiff {
+    let result_key = |item: &SearchResult| format!("{}|{}", item.source, item.id);
+    let old_map: HashMap<String, &SearchResult> =
+        old.iter().map(|item| (result_key(item), item)).collect();
+    let new_keys: std::collections::HashSet<String> = new.iter().map(result_key).collect();
+
+    let mut diff = SearchResultDiff::default();
+    for item in new {
+        match old_map.get(&result_key(item)) {
+            None => diff.added.push(item.clone()),
+            Some(previous) if previous.episodes != item.episodes => diff.updated.push(item.clone()),
+            Some(_) => {}
+        }
+    }
+    for item in old {
+        let key = result_key(item);
+        if !new_keys.contains(&key) && !diff.removed.contains(&key) {
+            diff.removed.push(key);
+        }
+    }
+
+    diff
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -585,4 +627,25 @@ mod tests {
         assert_eq!(aggregated.len(), 1);
         assert_eq!(aggregated[0].1[0].year.as_deref(), Some("2023"));
     }
+
+    #[test]
+    fn test_diff_search_results() {
+        let make = |source: &str, id: &str, episodes: usize| SearchResult {
+            source: source.to_string(),
+            id: id.to_string(),
+            episodes: (0..episodes).map(|i| i.to_string()).collect(),
+            ..Default::default()
+        };
+        let old = vec![make("a", "1", 10), make("a", "2", 1), make("b", "1", 3)];
+        let new = vec![make("a", "1", 12), make("b", "1", 3), make("c", "9", 1)];
+
+        let diff = diff_search_results(&old, &new);
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.added[0].source, "c");
+        assert_eq!(diff.removed, vec!["a|2".to_string()]);
+        assert_eq!(diff.updated.len(), 1);
+        assert_eq!(diff.updated[0].episodes.len(), 12);
+
+        assert!(diff_search_results(&new, &new).is_empty());
+    }
 }
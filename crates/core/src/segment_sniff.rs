@@ -0,0 +1,268 @@
+use crate::outbound::OutboundScheduler;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+/// 查找真实分片起点时最多检查的字节数（伪装前缀有时是一整张图片）
+pub const SNIFF_LIMIT: usize = 256 * 1024;
+
+/// 判断是否为伪装分片所需的最少字节数
+pub const SNIFF_HEADER_LEN: usize = 8;
+
+const TS_PACKET_LEN: usize = 188;
+const TS_SYNC_BYTE: u8 = 0x47;
+/// 除起点外还需连续命中的同步字节数
+const TS_SYNC_CONFIRMATIONS: usize = 3;
+
+const MP4_BOX_TYPES: [&[u8; 4]; 6] = [b"ftyp", b"styp", b"moof", b"sidx", b"moov", b"emsg"];
+
+/// 分片的真实格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SegmentFormat {
+    MpegTs,
+    Mp4,
+}
+
+impl SegmentFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            SegmentFormat::MpegTs => "video/mp2t",
+            SegmentFormat::Mp4 => "video/mp4",
+        }
+    }
+}
+
+/// 伪装分片：去掉 `offset` 字节的图片前缀后即为真实内容
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DisguisedSegment {
+    pub offset: usize,
+    pub format: SegmentFormat,
+}
+
+/// 是否以 PNG / JPEG / GIF / BMP 文件头开始
+pub fn has_image_header(data: &[u8]) -> bool {
+    data.starts_with(b"\x89PNG\r\n\x1a\n")
+        || data.starts_with(&[0xFF, 0xD8, 0xFF])
+        || data.starts_with(b"GIF87a")
+        || data.starts_with(b"GIF89a")
+        || data.starts_with(b"BM")
+}
+
+/// 按文件头识别图片的 MIME 类型，无法识别时返回 `None`
+pub fn image_content_type(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Some("image/jpeg")
+    } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if data.starts_with(b"RIFF") && data.get(8..12) == Some(b"WEBP") {
+        Some("image/webp")
+    } else if data.get(4..12) == Some(b"ftypavif") || data.get(4..12) == Some(b"ftypavis") {
+        Some("image/avif")
+    } else if data.starts_with(b"BM") {
+        Some("image/bmp")
+    } else {
+        None
+    }
+}
+
+/// 按文件头识别分片的真实格式（已去掉伪装前缀），无法识别时返回 `None`
+pub fn sniff_segment_format(data: &[u8]) -> Option<SegmentFormat> {
+    segment_format_at(data, 0)
+}
+
+/// 查找伪装在图片文件头之后的 TS 或 MP4 分片
+///
+/// 只处理以图片文件头开始的数据；数据不足以确认时返回 `None`，可读取更多后重试
+pub fn find_disguised_segment(data: &[u8]) -> Option<DisguisedSegment> {
+    if !has_image_header(data) {
+        return None;
+    }
+    let limit = data.len().min(SNIFF_LIMIT);
+    // 完整图片之后紧跟分片时，从图片结束标记之后开始找，避免误判图片数据
+    let start = image_end(data).filter(|end| *end < limit).unwrap_or(1);
+    (start..limit).chain(1..start).find_map(|offset| {
+        segment_format_at(data, offset).map(|format| DisguisedSegment { offset, format })
+    })
+}
+
+/// 去掉伪装前缀，返回真实内容与格式；不是伪装分片时原样返回
+pub fn unwrap_disguised_segment(data: Vec<u8>) -> (Vec<u8>, Option<DisguisedSegment>) {
+    match find_disguised_segment(&data) {
+        Some(found) => (data[found.offset..].to_vec(), Some(found)),
+        None => (data, None),
+    }
+}
+
+fn segment_format_at(data: &[u8], offset: usize) -> Option<SegmentFormat> {
+    if is_ts_sync_at(data, offset) {
+        return Some(SegmentFormat::MpegTs);
+    }
+    if is_mp4_box_at(data, offset) {
+        return Some(SegmentFormat::Mp4);
+    }
+    None
+}
+
+fn is_ts_sync_at(data: &[u8], offset: usize) -> bool {
+    (0..=TS_SYNC_CONFIRMATIONS)
+        .all(|packet| data.get(offset + packet * TS_PACKET_LEN) == Some(&TS_SYNC_BYTE))
+}
+
+fn is_mp4_box_at(data: &[u8], offset: usize) -> bool {
+    let Some(header) = data.get(offset..offset + 8) else {
+        return false;
+    };
+    let size = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+    size >= 8
+        && MP4_BOX_TYPES
+            .iter()
+            .any(|box_type| &header[4..8] == *box_type)
+}
+
+/// PNG 的 IEND 块或 JPEG 的 EOI 标记之后的位置
+fn image_end(data: &[u8]) -> Option<usize> {
+    if data.starts_with(b"\x89PNG") {
+        // IEND 类型之后还有 4 字节 CRC
+        return find(data, b"IEND").map(|pos| pos + 8);
+    }
+    if data.starts_with(&[0xFF, 0xD8]) {
+        return find(data, &[0xFF, 0xD9]).map(|pos| pos + 2);
+    }
+    None
+}
+
+fn find(data: &[u8], needle: &[u8]) -> Option<usize> {
+    data.windows(needle.len())
+        .take(SNIFF_LIMIT)
+        .position(|window| window == needle)
+}
+
+/// 单个分片主机的去伪装次数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct UnwrapCount {
+    pub segments: u64,
+    pub stripped_bytes: u64,
+}
+
+/// 按分片主机统计去伪装次数，供拿不到分片所属源的代理使用
+#[derive(Default)]
+pub struct SegmentUnwrapStats {
+    hosts: Mutex<HashMap<String, UnwrapCount>>,
+}
+
+impl SegmentUnwrapStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, url: &str, stripped_bytes: usize) {
+        let host = OutboundScheduler::host_key(url);
+        let mut hosts = self.hosts.lock().unwrap();
+        let count = hosts.entry(host).or_default();
+        count.segments += 1;
+        count.stripped_bytes += stripped_bytes as u64;
+    }
+
+    pub fn snapshot(&self) -> BTreeMap<String, UnwrapCount> {
+        self.hosts
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(host, count)| (host.clone(), *count))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ts_packets(count: usize) -> Vec<u8> {
+        let mut data = vec![0u8; TS_PACKET_LEN * count];
+        for packet in 0..count {
+            data[packet * TS_PACKET_LEN] = TS_SYNC_BYTE;
+        }
+        data
+    }
+
+    #[test]
+    fn fake_png_prefix_is_stripped_from_ts() {
+        let mut data = b"\x89PNG\r\n\x1a\n".to_vec();
+        data.extend_from_slice(&[0x47, 0x00, 0x47]);
+        data.extend_from_slice(b"\0\0\0\0IEND\xAE\x42\x60\x82");
+        let offset = data.len();
+        data.extend(ts_packets(5));
+
+        let (unwrapped, found) = unwrap_disguised_segment(data);
+        assert_eq!(
+            found,
+            Some(DisguisedSegment {
+                offset,
+                format: SegmentFormat::MpegTs
+            })
+        );
+        assert_eq!(unwrapped, ts_packets(5));
+    }
+
+    #[test]
+    fn fake_gif_prefix_is_stripped_from_fmp4() {
+        let mut data = b"GIF89a\x01\x00\x01\x00".to_vec();
+        let offset = data.len();
+        data.extend_from_slice(&[0, 0, 0, 16]);
+        data.extend_from_slice(b"moof");
+        data.extend_from_slice(&[0; 8]);
+
+        let found = find_disguised_segment(&data).unwrap();
+        assert_eq!(found.offset, offset);
+        assert_eq!(found.format.content_type(), "video/mp4");
+    }
+
+    #[test]
+    fn plain_segments_and_real_images_are_left_alone() {
+        assert_eq!(find_disguised_segment(&ts_packets(4)), None);
+
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE0];
+        jpeg.extend_from_slice(&[0x12; 1024]);
+        jpeg.extend_from_slice(&[0xFF, 0xD9]);
+        let (unchanged, found) = unwrap_disguised_segment(jpeg.clone());
+        assert_eq!(found, None);
+        assert_eq!(unchanged, jpeg);
+    }
+
+    #[test]
+    fn content_types_follow_the_bytes() {
+        assert_eq!(
+            sniff_segment_format(&ts_packets(4)),
+            Some(SegmentFormat::MpegTs)
+        );
+        let mut fmp4 = vec![0, 0, 0, 16];
+        fmp4.extend_from_slice(b"styp");
+        fmp4.extend_from_slice(&[0; 8]);
+        assert_eq!(sniff_segment_format(&fmp4), Some(SegmentFormat::Mp4));
+        assert_eq!(sniff_segment_format(b"not a segment"), None);
+
+        assert_eq!(
+            image_content_type(b"RIFF\0\0\0\0WEBPVP8 "),
+            Some("image/webp")
+        );
+        assert_eq!(image_content_type(b"\x89PNG\r\n\x1a\n"), Some("image/png"));
+        assert_eq!(image_content_type(b"<html>"), None);
+    }
+
+    #[test]
+    fn unwrap_stats_are_counted_per_host() {
+        let stats = SegmentUnwrapStats::new();
+        stats.record("https://img.example.com/a/1.png", 100);
+        stats.record("https://IMG.example.com/a/2.png", 50);
+        let snapshot = stats.snapshot();
+        assert_eq!(
+            snapshot["img.example.com"],
+            UnwrapCount {
+                segments: 2,
+                stripped_bytes: 150
+            }
+        );
+    }
+}
@@ -247,7 +247,7 @@ pub async fn prefer_best_source(
     }
 
     // 分批测速，避免一次性过多请求
-    let batch_size = (sources.len() + 1) / 2; // 分成两批
+    let batch_size = sources.len().div_ceil(2); // 分成两批
     let mut all_results = Vec::new();
 
     for start in (0..sources.len()).step_by(batch_size) {
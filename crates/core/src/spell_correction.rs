@@ -0,0 +1,297 @@
+use crate::pinyin::{is_homophone, to_pinyin};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// 同音字替换的代价（普通替换为 1.0）
+const HOMOPHONE_COST: f64 = 0.3;
+/// 语料权重带来的最大加分
+const MAX_WEIGHT_BONUS: f64 = 0.1;
+
+/// 纠错建议
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SpellSuggestion {
+    /// 建议的搜索词
+    pub text: String,
+    /// 排序分数（越大越相关）
+    pub score: f64,
+    /// 加权编辑距离（同音替换按 0.3 计）
+    pub distance: f64,
+    /// 是否仅由同音字差异导致
+    pub homophone: bool,
+}
+
+struct CorpusEntry {
+    text: String,
+    chars: Vec<char>,
+    pinyin: String,
+    weight: f64,
+}
+
+/// 基于本地标题语料的搜索纠错器
+///
+/// 语料来自内容池标题、搜索历史、豆瓣榜单等，结合编辑距离与拼音同音相似度给出建议。
+#[derive(Default)]
+pub struct SpellCorrector {
+    entries: Vec<CorpusEntry>,
+    index: HashMap<String, usize>,
+}
+
+impl SpellCorrector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 添加语料，重复的词条取较大权重
+    pub fn add_term(&mut self, text: &str, weight: f64) {
+        let display = text.trim();
+        let chars = normalize_chars(display);
+        if chars.is_empty() {
+            return;
+        }
+
+        let key: String = chars.iter().collect();
+        if let Some(&pos) = self.index.get(&key) {
+            let entry = &mut self.entries[pos];
+            entry.weight = entry.weight.max(weight);
+            return;
+        }
+
+        self.index.insert(key.clone(), self.entries.len());
+        self.entries.push(CorpusEntry {
+            text: display.to_string(),
+            pinyin: to_pinyin(&key),
+            chars,
+            weight,
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// 查询词是否与语料中的某个词条完全一致
+    pub fn contains(&self, query: &str) -> bool {
+        let key: String = normalize_chars(query).into_iter().collect();
+        self.index.contains_key(&key)
+    }
+
+    /// 给出按分数降序排列的纠错建议，查询词本身不会出现在结果中
+    pub fn suggest(&self, query: &str, limit: usize) -> Vec<SpellSuggestion> {
+        let query_chars = normalize_chars(query);
+        if query_chars.len() < 2 || limit == 0 {
+            return Vec::new();
+        }
+        let query_key: String = query_chars.iter().collect();
+        let is_latin = query_chars.iter().all(|c| c.is_ascii_alphabetic());
+        let max_distance = max_allowed_distance(query_chars.len());
+
+        let mut best: HashMap<String, SpellSuggestion> = HashMap::new();
+        for entry in &self.entries {
+            let pinyin_input =
+                is_latin && query_chars.len() >= 4 && !entry.chars.iter().all(|c| c.is_ascii());
+            let candidate = if pinyin_input {
+                // 拼音输入：与中文标题的拼音整体比较
+                let pinyin_chars: Vec<char> = entry.pinyin.chars().collect();
+                let distance = weighted_distance(&query_chars, &pinyin_chars);
+                (distance <= max_distance)
+                    .then(|| (entry.text.clone(), distance, pinyin_chars.len(), false))
+            } else {
+                best_window_match(&query_chars, &entry.chars, max_distance).map(
+                    |(start, end, distance)| {
+                        let text = if start == 0 && end == entry.chars.len() {
+                            entry.text.clone()
+                        } else {
+                            entry.chars[start..end].iter().collect()
+                        };
+                        let homophone = end - start == query_chars.len()
+                            && query_chars
+                                .iter()
+                                .zip(&entry.chars[start..end])
+                                .all(|(a, b)| is_homophone(*a, *b));
+                        (text, distance, end - start, homophone)
+                    },
+                )
+            };
+
+            let Some((text, distance, candidate_len, homophone)) = candidate else {
+                continue;
+            };
+            if normalize_chars(&text).into_iter().collect::<String>() == query_key {
+                continue;
+            }
+
+            let longest = query_chars.len().max(candidate_len) as f64;
+            let similarity = 1.0 - distance / longest;
+            let bonus = ((1.0 + entry.weight.max(0.0)).ln() * 0.02).min(MAX_WEIGHT_BONUS);
+            let suggestion = SpellSuggestion {
+                text,
+                score: similarity + bonus,
+                distance,
+                homophone,
+            };
+
+            match best.get(&suggestion.text) {
+                Some(existing) if existing.score >= suggestion.score => {}
+                _ => {
+                    best.insert(suggestion.text.clone(), suggestion);
+                }
+            }
+        }
+
+        let mut suggestions: Vec<SpellSuggestion> = best.into_values().collect();
+        suggestions.sort_by(|a, b| {
+            b.score
+                .partial_cmp(&a.score)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| a.text.cmp(&b.text))
+        });
+        suggestions.truncate(limit);
+        suggestions
+    }
+}
+
+/// 按查询长度决定可接受的最大加权编辑距离
+fn max_allowed_distance(len: usize) -> f64 {
+    match len {
+        0..=2 => HOMOPHONE_COST * 2.0,
+        3..=4 => 1.0,
+        5..=8 => 2.0,
+        _ => 3.0,
+    }
+}
+
+/// 小写化并去掉空白与标点
+fn normalize_chars(text: &str) -> Vec<char> {
+    text.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+/// 在候选标题中寻找与查询最接近的连续片段，返回 (起点, 终点, 距离)
+fn best_window_match(
+    query: &[char],
+    title: &[char],
+    max_distance: f64,
+) -> Option<(usize, usize, f64)> {
+    let mut best: Option<(usize, usize, f64)> = None;
+    let min_len = query.len().saturating_sub(1).max(1);
+    let max_len = (query.len() + 1).min(title.len());
+
+    for len in min_len..=max_len {
+        for start in 0..=(title.len() - len) {
+            let distance = weighted_distance(query, &title[start..start + len]);
+            if distance > max_distance {
+                continue;
+            }
+            let better = match best {
+                None => true,
+                Some((_, _, current)) => distance < current,
+            };
+            if better {
+                best = Some((start, start + len, distance));
+            }
+        }
+    }
+
+    best
+}
+
+/// 加权编辑距离：同音字替换代价较低
+fn weighted_distance(a: &[char], b: &[char]) -> f64 {
+    let mut prev: Vec<f64> = (0..=b.len()).map(|j| j as f64).collect();
+    let mut curr = vec![0.0; b.len() + 1];
+
+    for (i, ca) in a.iter().enumerate() {
+        curr[0] = (i + 1) as f64;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = if ca == cb {
+                0.0
+            } else if is_homophone(*ca, *cb) {
+                HOMOPHONE_COST
+            } else {
+                1.0
+            };
+            curr[j + 1] = (prev[j] + substitution)
+                .min(prev[j + 1] + 1.0)
+                .min(curr[j] + 1.0);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn corrector(terms: &[&str]) -> SpellCorrector {
+        let mut corrector = SpellCorrector::new();
+        for term in terms {
+            corrector.add_term(term, 1.0);
+        }
+        corrector
+    }
+
+    #[test]
+    fn weighted_distance_discounts_homophones() {
+        let a: Vec<char> = "陈晴令".chars().collect();
+        let b: Vec<char> = "陈情令".chars().collect();
+        let c: Vec<char> = "陈某令".chars().collect();
+        assert!((weighted_distance(&a, &b) - HOMOPHONE_COST).abs() < 1e-9);
+        assert!((weighted_distance(&a, &c) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn suggest_corrects_homophone_typo() {
+        let corrector = corrector(&["陈情令", "庆余年", "狂飙"]);
+        let suggestions = corrector.suggest("陈晴令", 5);
+        assert_eq!(suggestions[0].text, "陈情令");
+        assert!(suggestions[0].homophone);
+    }
+
+    #[test]
+    fn suggest_matches_inside_longer_titles() {
+        let corrector = corrector(&["陈情令 第二季"]);
+        let suggestions = corrector.suggest("陈晴令", 5);
+        assert_eq!(suggestions[0].text, "陈情令");
+    }
+
+    #[test]
+    fn suggest_handles_pinyin_input() {
+        let corrector = corrector(&["狂飙", "庆余年"]);
+        let suggestions = corrector.suggest("qingyunian", 3);
+        assert_eq!(suggestions[0].text, "庆余年");
+    }
+
+    #[test]
+    fn suggest_skips_exact_and_distant_terms() {
+        let corrector = corrector(&["陈情令", "三体"]);
+        assert!(corrector.suggest("陈情令", 5).is_empty());
+        assert!(corrector.suggest("流浪地球", 5).is_empty());
+    }
+
+    #[test]
+    fn suggest_prefers_higher_weight_on_tie() {
+        let mut corrector = SpellCorrector::new();
+        corrector.add_term("天龙八部", 1.0);
+        corrector.add_term("天龙八步", 500.0);
+        let suggestions = corrector.suggest("天龙八布", 2);
+        assert_eq!(suggestions[0].text, "天龙八步");
+    }
+
+    #[test]
+    fn add_term_dedupes_normalized_text() {
+        let mut corrector = SpellCorrector::new();
+        corrector.add_term("Test Title", 1.0);
+        corrector.add_term("test title", 3.0);
+        assert_eq!(corrector.len(), 1);
+        assert!(corrector.contains("TESTTITLE"));
+    }
+}
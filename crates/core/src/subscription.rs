@@ -0,0 +1,468 @@
+//! 多订阅：按优先级合并各订阅的视频源，并记录每个源来自哪个订阅
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+
+/// 源配置中记录所属订阅标识的字段
+pub const SUBSCRIPTION_FIELD: &str = "subscription";
+/// 由旧版单订阅配置 `ConfigSubscribtion` 迁移而来的订阅标识
+pub const LEGACY_SUBSCRIPTION_KEY: &str = "default";
+
+const MS_PER_HOUR: i64 = 3_600_000;
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// 一个视频源订阅
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Subscription {
+    pub key: String,
+    pub name: String,
+    pub url: String,
+    /// 数值越大越优先，同一接口在多个订阅中出现时以优先级高的为准
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// 自动更新间隔（小时），0 表示不自动更新
+    #[serde(default)]
+    pub update_interval_hours: u32,
+    /// 上次成功更新时间（毫秒时间戳）
+    #[serde(default)]
+    pub last_update: i64,
+}
+
+impl Subscription {
+    /// 已启用自动更新且距上次更新超过间隔
+    pub fn is_due(&self, now_ms: i64) -> bool {
+        self.enabled
+            && self.update_interval_hours > 0
+            && now_ms - self.last_update >= i64::from(self.update_interval_hours) * MS_PER_HOUR
+    }
+}
+
+/// 读取配置中的订阅列表；从未保存过 `Subscriptions` 时由旧版 `ConfigSubscribtion` 转换
+pub fn subscriptions_from_config(config: &Value) -> Vec<Subscription> {
+    if let Some(items) = config.get("Subscriptions").and_then(|v| v.as_array()) {
+        return items
+            .iter()
+            .filter_map(|item| serde_json::from_value(item.clone()).ok())
+            .collect();
+    }
+
+    let legacy = config.get("ConfigSubscribtion").unwrap_or(&Value::Null);
+    let url = legacy
+        .get("URL")
+        .and_then(|v| v.as_str())
+        .unwrap_or("")
+        .trim();
+    if url.is_empty() {
+        return Vec::new();
+    }
+    let auto_update = legacy
+        .get("AutoUpdate")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    vec![Subscription {
+        key: LEGACY_SUBSCRIPTION_KEY.to_string(),
+        name: "默认订阅".to_string(),
+        url: url.to_string(),
+        priority: 0,
+        enabled: true,
+        update_interval_hours: if auto_update { 24 } else { 0 },
+        last_update: 0,
+    }]
+}
+
+/// 源所属的订阅标识，手动添加的源没有
+pub fn source_subscription(source: &Value) -> Option<&str> {
+    source
+        .get(SUBSCRIPTION_FIELD)
+        .and_then(|v| v.as_str())
+        .filter(|key| !key.is_empty())
+}
+
+/// 接口地址去重用的归一化形式：协议与主机小写、去掉默认端口、片段与末尾斜杠；
+/// 非 URL（如 spider 类名）区分大小写，只去掉首尾空白与末尾斜杠
+pub fn normalize_api_url(api: &str) -> String {
+    let trimmed = api.trim();
+    match url::Url::parse(trimmed) {
+        Ok(mut url) => {
+            url.set_fragment(None);
+            let path = url.path().trim_end_matches('/').to_string();
+            url.set_path(&path);
+            url.as_str().trim_end_matches('/').to_string()
+        }
+        Err(_) => trimmed.trim_end_matches('/').to_string(),
+    }
+}
+
+/// 与顺序、进程无关的短哈希（FNV-1a），用于生成稳定的标识
+pub fn stable_hash(text: &str) -> String {
+    let hash = text.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    });
+    format!("{:08x}", (hash >> 32) ^ (hash & 0xffff_ffff))
+}
+
+/// 去重用的源标识：归一化的接口地址；非 http 接口由 jar 中的 spider 实现，还要区分 jar
+fn source_api_identity(source: &Value) -> Option<String> {
+    let api = source
+        .get("api")
+        .and_then(|v| v.as_str())
+        .map(normalize_api_url)
+        .filter(|api| !api.is_empty())?;
+    if api.starts_with("http://") || api.starts_with("https://") {
+        return Some(api);
+    }
+    match source
+        .get("jar")
+        .and_then(|v| v.as_str())
+        .map(str::trim)
+        .filter(|jar| !jar.is_empty())
+    {
+        Some(jar) => Some(format!("{}@{}", api, jar)),
+        None => Some(api),
+    }
+}
+
+fn source_key(source: &Value) -> String {
+    source
+        .get("key")
+        .and_then(|v| v.as_str())
+        .unwrap_or("")
+        .to_string()
+}
+
+fn set_source_key(source: &mut Value, key: String) {
+    if let Some(obj) = source.as_object_mut() {
+        obj.insert("key".to_string(), Value::String(key));
+    }
+}
+
+/// 源标识冲突时按接口标识追加哈希，同一接口总是得到同一个标识
+fn keyed_by_identity(key: &str, identity: &str, used: &HashSet<String>) -> String {
+    let base = match key {
+        "" => stable_hash(identity),
+        key => format!("{}_{}", key, stable_hash(identity)),
+    };
+    let mut candidate = base.clone();
+    let mut index = 2;
+    while used.contains(&candidate) {
+        candidate = format!("{}_{}", base, index);
+        index += 1;
+    }
+    candidate
+}
+
+/// 合并多个订阅的源
+///
+/// 跳过未启用的订阅；按接口地址去重，名称、禁用等冲突字段取优先级高的订阅，
+/// 其缺少的字段由优先级低的补齐。结果按优先级排列，每个源写入所属订阅标识。
+/// 源标识只在被不同接口共用时才追加接口哈希，因此不随订阅顺序变化
+pub fn merge_subscription_sources(sets: Vec<(&Subscription, Vec<Value>)>) -> Vec<Value> {
+    let mut sets: Vec<_> = sets.into_iter().filter(|(sub, _)| sub.enabled).collect();
+    sets.sort_by_key(|(sub, _)| Reverse(sub.priority));
+
+    let mut merged: Vec<(String, Map<String, Value>)> = Vec::new();
+    let mut by_api: HashMap<String, usize> = HashMap::new();
+
+    for (subscription, sources) in sets {
+        for source in sources {
+            let (Some(api), Some(mut obj)) =
+                (source_api_identity(&source), source.as_object().cloned())
+            else {
+                continue;
+            };
+            if let Some(&index) = by_api.get(&api) {
+                let winner = &mut merged[index].1;
+                for (field, value) in obj {
+                    winner.entry(field).or_insert(value);
+                }
+                continue;
+            }
+
+            obj.insert(
+                SUBSCRIPTION_FIELD.to_string(),
+                Value::String(subscription.key.clone()),
+            );
+            by_api.insert(api.clone(), merged.len());
+            merged.push((api, obj));
+        }
+    }
+
+    let mut identities_by_key: HashMap<String, usize> = HashMap::new();
+    for (_, obj) in &merged {
+        let key = obj.get("key").and_then(|v| v.as_str()).unwrap_or("");
+        *identities_by_key.entry(key.to_string()).or_default() += 1;
+    }
+    let mut used_keys: HashSet<String> = HashSet::new();
+    merged
+        .into_iter()
+        .map(|(api, obj)| {
+            let mut source = Value::Object(obj);
+            let key = source_key(&source);
+            let key = if !key.is_empty() && identities_by_key[&key] == 1 {
+                key
+            } else {
+                keyed_by_identity(&key, &api, &used_keys)
+            };
+            used_keys.insert(key.clone());
+            set_source_key(&mut source, key);
+            source
+        })
+        .collect()
+}
+
+/// 用新的订阅合并结果替换现有源中来自订阅的部分
+///
+/// 手动添加的源（`from` 为 `custom` 且不属于任何订阅）保留在前，
+/// 订阅中与其接口相同的源被忽略；已有的订阅源沿用原来的标识
+pub fn apply_subscription_sources(existing: &[Value], merged: Vec<Value>) -> Vec<Value> {
+    let is_local = |source: &Value| {
+        source_subscription(source).is_none()
+            && source.get("from").and_then(|v| v.as_str()) == Some("custom")
+    };
+    let mut result: Vec<Value> = existing
+        .iter()
+        .filter(|source| is_local(source))
+        .cloned()
+        .collect();
+    let local_apis: HashSet<String> = result.iter().filter_map(source_api_identity).collect();
+    let previous_keys: HashMap<String, String> = existing
+        .iter()
+        .filter(|source| !is_local(source))
+        .filter_map(|source| Some((source_api_identity(source)?, source_key(source))))
+        .filter(|(_, key)| !key.is_empty())
+        .collect();
+    let mut used_keys: HashSet<String> = result.iter().map(source_key).collect();
+
+    // 先为沿用原标识的源占位，其余的源再避开这些标识
+    let mut pending = Vec::new();
+    for source in merged {
+        let api = source_api_identity(&source).unwrap_or_default();
+        if local_apis.contains(&api) {
+            continue;
+        }
+        let previous = previous_keys
+            .get(&api)
+            .filter(|key| used_keys.insert((*key).clone()))
+            .cloned();
+        pending.push((source, api, previous));
+    }
+
+    for (mut source, api, previous) in pending {
+        let key = previous.unwrap_or_else(|| {
+            let key = source_key(&source);
+            if key.is_empty() || used_keys.contains(&key) {
+                keyed_by_identity(&key, &api, &used_keys)
+            } else {
+                key
+            }
+        });
+        used_keys.insert(key.clone());
+        set_source_key(&mut source, key);
+        result.push(source);
+    }
+    result
+}
+
+/// 移除来自某个订阅的全部源
+pub fn remove_subscription_sources(sources: Vec<Value>, subscription: &str) -> Vec<Value> {
+    sources
+        .into_iter()
+        .filter(|source| source_subscription(source) != Some(subscription))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn subscription(key: &str, priority: i32) -> Subscription {
+        Subscription {
+            key: key.to_string(),
+            name: key.to_string(),
+            url: format!("https://{}.example.com/config.json", key),
+            priority,
+            enabled: true,
+            update_interval_hours: 0,
+            last_update: 0,
+        }
+    }
+
+    #[test]
+    fn api_urls_normalize_host_port_and_trailing_slash() {
+        assert_eq!(
+            normalize_api_url(" HTTPS://A.Example.com:443/api.php/provide/vod/ "),
+            "https://a.example.com/api.php/provide/vod"
+        );
+        assert_eq!(
+            normalize_api_url("http://a.example.com/api?ac=list#x"),
+            "http://a.example.com/api?ac=list"
+        );
+        assert_eq!(
+            normalize_api_url("https://a.example.com/"),
+            "https://a.example.com"
+        );
+    }
+
+    #[test]
+    fn higher_priority_wins_and_sources_are_attributed() {
+        let low = subscription("low", 1);
+        let high = subscription("high", 10);
+        let merged = merge_subscription_sources(vec![
+            (
+                &low,
+                vec![
+                    json!({ "key": "a", "name": "低优先级名", "api": "https://a.example.com/api/", "disabled": true, "detail": "https://a.example.com" }),
+                    json!({ "key": "b", "name": "B", "api": "https://b.example.com/api" }),
+                ],
+            ),
+            (
+                &high,
+                vec![
+                    json!({ "key": "a2", "name": "高优先级名", "api": "https://A.example.com/api", "disabled": false }),
+                    json!({ "key": "b", "name": "另一个 B", "api": "https://b2.example.com/api" }),
+                ],
+            ),
+        ]);
+
+        let b_high = format!("b_{}", stable_hash("https://b2.example.com/api"));
+        let b_low = format!("b_{}", stable_hash("https://b.example.com/api"));
+        assert_eq!(merged.len(), 3);
+        assert_eq!(merged[0]["key"], "a2");
+        assert_eq!(merged[0]["name"], "高优先级名");
+        assert_eq!(merged[0]["disabled"], false);
+        assert_eq!(merged[0]["detail"], "https://a.example.com");
+        assert_eq!(merged[0][SUBSCRIPTION_FIELD], "high");
+        assert_eq!(merged[1]["key"], b_high.as_str());
+        assert_eq!(merged[1][SUBSCRIPTION_FIELD], "high");
+        assert_eq!(merged[2]["key"], b_low.as_str());
+        assert_eq!(merged[2][SUBSCRIPTION_FIELD], "low");
+
+        let remaining = remove_subscription_sources(merged, "high");
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0]["key"], b_low.as_str());
+    }
+
+    #[test]
+    fn keys_do_not_depend_on_subscription_order() {
+        let sources = |name: &str| {
+            vec![
+                json!({ "key": "b", "name": name, "api": format!("https://{}.example.com/api", name) }),
+                json!({ "key": "csp", "name": name, "api": "csp_Demo", "jar": format!("https://{}.example.com/a.jar", name) }),
+            ]
+        };
+        let keys = |first: i32, second: i32| {
+            let one = subscription("one", first);
+            let two = subscription("two", second);
+            let mut keys: Vec<(String, String)> =
+                merge_subscription_sources(vec![(&one, sources("one")), (&two, sources("two"))])
+                    .iter()
+                    .map(|s| (s["name"].to_string(), s["key"].to_string()))
+                    .collect();
+            keys.sort();
+            keys
+        };
+        let keys_a = keys(1, 2);
+        assert_eq!(keys_a.len(), 4);
+        assert_eq!(keys_a, keys(2, 1));
+        assert_eq!(normalize_api_url(" csp_Demo/ "), "csp_Demo");
+    }
+
+    #[test]
+    fn existing_subscription_sources_keep_their_keys() {
+        let sub = subscription("s", 0);
+        let existing = vec![
+            json!({ "key": "mine", "api": "https://m.example.com", "from": "custom" }),
+            json!({ "key": "old_b", "api": "https://b.example.com/api", "from": "config", "subscription": "s" }),
+        ];
+        let merged = merge_subscription_sources(vec![(
+            &sub,
+            vec![
+                json!({ "key": "old_b", "api": "https://new.example.com/api" }),
+                json!({ "key": "b", "api": "https://b.example.com/api/" }),
+                json!({ "key": "mine", "api": "https://x.example.com/api" }),
+            ],
+        )]);
+        let applied = apply_subscription_sources(&existing, merged);
+        let keys: Vec<_> = applied.iter().map(|s| s["key"].as_str().unwrap()).collect();
+        assert_eq!(
+            keys,
+            vec![
+                "mine".to_string(),
+                format!("old_b_{}", stable_hash("https://new.example.com/api")),
+                "old_b".to_string(),
+                format!("mine_{}", stable_hash("https://x.example.com/api")),
+            ]
+        );
+    }
+
+    #[test]
+    fn disabled_subscriptions_are_skipped_and_custom_sources_kept() {
+        let mut off = subscription("off", 5);
+        off.enabled = false;
+        let on = subscription("on", 0);
+        let merged = merge_subscription_sources(vec![
+            (
+                &off,
+                vec![json!({ "key": "x", "name": "X", "api": "https://x.example.com" })],
+            ),
+            (
+                &on,
+                vec![
+                    json!({ "key": "c", "name": "C", "api": "https://c.example.com/api" }),
+                    json!({ "key": "d", "name": "D", "api": "https://d.example.com/api" }),
+                ],
+            ),
+        ]);
+        assert_eq!(merged.len(), 2);
+
+        let existing = vec![
+            json!({ "key": "c", "name": "我的源", "api": "https://c.example.com/api/", "from": "custom" }),
+            json!({ "key": "old", "name": "旧订阅源", "api": "https://old.example.com", "from": "config" }),
+            json!({ "key": "d", "name": "D", "api": "https://d.example.com/api", "from": "config", "subscription": "on" }),
+        ];
+        let applied = apply_subscription_sources(&existing, merged);
+        let keys: Vec<_> = applied.iter().map(|s| s["key"].as_str().unwrap()).collect();
+        assert_eq!(keys, vec!["c", "d"]);
+        assert_eq!(applied[0]["name"], "我的源");
+        assert_eq!(applied[1][SUBSCRIPTION_FIELD], "on");
+    }
+
+    #[test]
+    fn legacy_single_subscription_is_converted() {
+        let config = json!({
+            "ConfigSubscribtion": { "URL": "https://a.example.com/c.json", "AutoUpdate": true }
+        });
+        let subs = subscriptions_from_config(&config);
+        assert_eq!(subs.len(), 1);
+        assert_eq!(subs[0].key, LEGACY_SUBSCRIPTION_KEY);
+        assert_eq!(subs[0].update_interval_hours, 24);
+        assert!(subs[0].is_due(24 * MS_PER_HOUR));
+        assert!(!subs[0].is_due(24 * MS_PER_HOUR - 1));
+
+        let config = json!({
+            "ConfigSubscribtion": { "URL": "https://a.example.com/c.json" },
+            "Subscriptions": [{ "key": "k", "name": "K", "url": "https://k.example.com" }]
+        });
+        let subs = subscriptions_from_config(&config);
+        assert_eq!(subs.len(), 1);
+        assert_eq!(subs[0].key, "k");
+        assert!(subs[0].enabled);
+        assert!(!subs[0].is_due(i64::MAX));
+
+        // 已删除全部订阅时不再回退到旧版配置
+        let config = json!({
+            "ConfigSubscribtion": { "URL": "https://a.example.com/c.json" },
+            "Subscriptions": []
+        });
+        assert!(subscriptions_from_config(&config).is_empty());
+    }
+}
@@ -0,0 +1,181 @@
+use crate::dns::{is_local_or_private_ip, DnsResolver};
+use reqwest::redirect::Policy;
+use std::net::IpAddr;
+use std::sync::Arc;
+use url::{Host, Url};
+
+/// 跟随重定向的默认上限（部分 CDN 会多次跳转）
+pub const DEFAULT_MAX_REDIRECTS: usize = 10;
+
+const LAN_DISABLED: &str = "LAN and localhost URLs are disabled";
+
+/// 本机或局域网常用的主机名（无点的单标签名同样视为局域网）
+pub fn is_local_hostname(host: &str) -> bool {
+    let normalized = host.trim().trim_end_matches('.').to_ascii_lowercase();
+    normalized == "localhost"
+        || normalized.ends_with(".localhost")
+        || normalized.ends_with(".local")
+        || normalized.ends_with(".internal")
+        || normalized.ends_with(".home.arpa")
+        || !normalized.contains('.')
+}
+
+/// 校验 URL 字面量：仅允许 http(s)、不允许内嵌凭据，未开启局域网时拒绝局域网主机
+///
+/// 只检查字面量，域名实际解析到的地址由 [`check_remote_url`] 或客户端的解析器检查
+pub fn validate_remote_url(url: &str, allow_lan: bool) -> Result<Url, String> {
+    let parsed = Url::parse(url).map_err(|e| format!("Invalid URL: {}", e))?;
+
+    match parsed.scheme() {
+        "http" | "https" => {}
+        scheme => return Err(format!("Unsupported URL scheme: {}", scheme)),
+    }
+
+    if !parsed.username().is_empty() || parsed.password().is_some() {
+        return Err("URLs with embedded credentials are not allowed".to_string());
+    }
+
+    let host = parsed
+        .host()
+        .ok_or_else(|| "URL must include a host".to_string())?;
+
+    if !allow_lan {
+        let is_lan = match host {
+            Host::Ipv4(ip) => is_local_or_private_ip(IpAddr::V4(ip)),
+            Host::Ipv6(ip) => is_local_or_private_ip(IpAddr::V6(ip)),
+            Host::Domain(domain) => is_local_hostname(domain),
+        };
+        if is_lan {
+            return Err(LAN_DISABLED.to_string());
+        }
+    }
+
+    Ok(parsed)
+}
+
+/// 校验 URL 并解析域名，拒绝解析到局域网地址的域名
+///
+/// 请求发出时客户端的解析器会再次检查，预检只用于尽早拒绝并覆盖经代理（由代理端解析域名）的请求
+pub async fn check_remote_url(
+    url: &str,
+    allow_lan: bool,
+    resolver: &DnsResolver,
+) -> Result<Url, String> {
+    let parsed = validate_remote_url(url, allow_lan)?;
+    if allow_lan {
+        return Ok(parsed);
+    }
+    if let Some(Host::Domain(domain)) = parsed.host() {
+        let addrs = resolver.lookup(domain).await?;
+        // 经代理请求时由代理再次解析，只要有一个地址在局域网内就拒绝
+        if addrs.iter().any(|ip| is_local_or_private_ip(*ip)) {
+            return Err(LAN_DISABLED.to_string());
+        }
+    }
+    Ok(parsed)
+}
+
+/// 逐跳校验重定向目标，局域网策略取自解析器的当前设置
+///
+/// 经代理请求时客户端的解析器不参与，因此跟随前同样解析目标域名
+pub fn redirect_policy(resolver: Arc<DnsResolver>, max_redirects: usize) -> Policy {
+    Policy::custom(move |attempt| {
+        if attempt.previous().len() >= max_redirects {
+            return attempt.error(format!("too many redirects (max {})", max_redirects));
+        }
+        match check_redirect_target(attempt.url().as_str(), &resolver) {
+            Ok(_) => attempt.follow(),
+            Err(error) => attempt.error(format!("redirect blocked: {}", error)),
+        }
+    })
+}
+
+/// [`check_remote_url`] 的同步版本，重定向策略不能等待异步解析
+fn check_redirect_target(url: &str, resolver: &DnsResolver) -> Result<Url, String> {
+    let allow_lan = resolver.allows_private();
+    let parsed = validate_remote_url(url, allow_lan)?;
+    if allow_lan {
+        return Ok(parsed);
+    }
+    if let Some(Host::Domain(domain)) = parsed.host() {
+        let addrs = resolver.lookup_blocking(domain)?;
+        if addrs.iter().any(|ip| is_local_or_private_ip(*ip)) {
+            return Err(LAN_DISABLED.to_string());
+        }
+    }
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dns::HostOverrides;
+    use serde_json::json;
+
+    #[test]
+    fn literal_lan_hosts_are_rejected_unless_allowed() {
+        for url in [
+            "http://127.0.0.1:8080/a.m3u8",
+            "http://[::1]/a.ts",
+            "http://[::ffff:192.168.1.2]/a.ts",
+            "http://169.254.169.254/latest/meta-data/",
+            "http://router/a.ts",
+            "http://nas.local/a.ts",
+        ] {
+            assert!(validate_remote_url(url, false).is_err(), "{}", url);
+        }
+        assert!(validate_remote_url("http://192.168.1.2/a.ts", true).is_ok());
+        assert!(validate_remote_url("https://user:pw@example.com/", true).is_err());
+        assert!(validate_remote_url("https://cdn.example.com/a.ts", false).is_ok());
+    }
+
+    #[test]
+    fn redirect_targets_resolving_to_lan_are_rejected() {
+        let resolver = DnsResolver::new();
+        resolver.set_source_hosts(
+            "a",
+            &HostOverrides::from_value(&json!({
+                "rebind.example.com": "10.0.0.5",
+                "cdn.example.com": "93.184.216.34"
+            })),
+        );
+
+        assert!(check_redirect_target("http://rebind.example.com/a.ts", &resolver).is_err());
+        assert!(check_redirect_target("http://cdn.example.com/a.ts", &resolver).is_ok());
+        assert!(check_redirect_target("http://127.0.0.1/a.ts", &resolver).is_err());
+
+        resolver.set_allow_private(true);
+        assert!(check_redirect_target("http://rebind.example.com/a.ts", &resolver).is_ok());
+    }
+
+    #[tokio::test]
+    async fn domains_resolving_to_lan_are_rejected() {
+        let resolver = DnsResolver::new();
+        resolver.set_source_hosts(
+            "a",
+            &HostOverrides::from_value(&json!({
+                "rebind.example.com": "10.0.0.5",
+                "mixed.example.com": ["93.184.216.34", "100.100.100.200"],
+                "cdn.example.com": "93.184.216.34"
+            })),
+        );
+
+        let blocked = check_remote_url("http://rebind.example.com/a.ts", false, &resolver).await;
+        assert!(blocked.is_err());
+        assert!(
+            check_remote_url("http://rebind.example.com/a.ts", true, &resolver)
+                .await
+                .is_ok()
+        );
+        assert!(
+            check_remote_url("http://mixed.example.com/a.ts", false, &resolver)
+                .await
+                .is_err()
+        );
+        assert!(
+            check_remote_url("http://cdn.example.com/a.ts", false, &resolver)
+                .await
+                .is_ok()
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use quantumtv_core::ProxyCategory;
 use serde::{Deserialize, Serialize};
 use std::sync::Mutex;
 
@@ -121,9 +122,12 @@ fn get_bangumi_urls() -> Vec<String> {
 async fn bangumi_calendar_data() -> Result<Vec<BangumiCalendarData>, String> {
     let urls = get_bangumi_urls();
     let mut errors = Vec::new();
+    let client = crate::network::client_builder(ProxyCategory::Metadata)
+        .build()
+        .map_err(|e| e.to_string())?;
 
     for url in urls {
-        match reqwest::get(&url).await {
+        match client.get(&url).send().await {
             Ok(response) => {
                 if !response.status().is_success() {
                     errors.push(format!("{}: HTTP status {}", url, response.status()));
@@ -1,12 +1,15 @@
 use crate::commands::source_intelligence::SourceIntelligenceManager;
 use crate::commands::bangumi::set_bangumi_proxy_url;
+use crate::commands::subscription::{has_subscription_list, sync_legacy_subscription};
 use crate::db::db_client::Db;
+use crate::network::{set_allow_lan_sources, set_dns_settings, set_proxy_settings};
 use crate::storage::StorageManager;
 use quantumtv_core::adult;
 use quantumtv_core::default_admin_config_value;
 use quantumtv_core::merge_admin_config_with_defaults;
 use quantumtv_core::normalize_source_config as normalize_source_config_core;
 use quantumtv_core::parse_admin_config as parse_admin_config_core;
+use quantumtv_core::{DnsSettings, DownloadSettings, ProxyCategory, ProxySettings, DEFAULT_USER_AGENT};
 use rusqlite::params;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -172,7 +175,7 @@ pub(crate) fn strip_source_config(config: &Value) -> Value {
     stripped
 }
 
-fn merge_config_with_sources(config: &Value, sources: Vec<Value>) -> Value {
+pub(crate) fn merge_config_with_sources(config: &Value, sources: Vec<Value>) -> Value {
     let mut merged = merge_admin_config_with_defaults(config);
     if let Some(obj) = merged.as_object_mut() {
         obj.insert("SourceConfig".to_string(), Value::Array(sources));
@@ -180,17 +183,35 @@ fn merge_config_with_sources(config: &Value, sources: Vec<Value>) -> Value {
     merged
 }
 
+/// video_sources 表中有独立列的源字段，其余字段（如 request_policy）存入 options_json
+const SOURCE_COLUMN_FIELDS: [&str; 7] = [
+    "key", "name", "api", "detail", "from", "disabled", "is_adult",
+];
+
+fn source_options_json(source: &Value) -> String {
+    let options = source
+        .as_object()
+        .map(|obj| {
+            obj.iter()
+                .filter(|(key, _)| !SOURCE_COLUMN_FIELDS.contains(&key.as_str()))
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect::<serde_json::Map<String, Value>>()
+        })
+        .unwrap_or_default();
+    Value::Object(options).to_string()
+}
+
 pub(crate) fn load_source_config_values(db: &Db) -> Result<Vec<Value>, String> {
     db.with_conn(|conn| {
         let mut stmt = conn.prepare(
-            "SELECT source_key, name, api, detail, from_type, disabled, is_adult
+            "SELECT source_key, name, api, detail, from_type, disabled, is_adult, options_json
              FROM video_sources
              ORDER BY sort_order ASC, updated_at DESC, source_key ASC",
         )?;
 
         let rows = stmt
             .query_map([], |row| {
-                Ok(serde_json::json!({
+                let mut source = serde_json::json!({
                     "key": row.get::<_, String>(0)?,
                     "name": row.get::<_, String>(1)?,
                     "api": row.get::<_, String>(2)?,
@@ -198,7 +219,16 @@ pub(crate) fn load_source_config_values(db: &Db) -> Result<Vec<Value>, String> {
                     "from": row.get::<_, String>(4)?,
                     "disabled": row.get::<_, i32>(5)? != 0,
                     "is_adult": row.get::<_, i32>(6)? != 0,
-                }))
+                });
+                let options =
+                    serde_json::from_str::<Value>(&row.get::<_, String>(7)?).unwrap_or(Value::Null);
+                if let (Some(target), Some(options)) = (source.as_object_mut(), options.as_object())
+                {
+                    for (key, value) in options {
+                        target.entry(key.clone()).or_insert_with(|| value.clone());
+                    }
+                }
+                Ok(source)
             })?
             .collect::<Result<Vec<_>, _>>()?;
 
@@ -220,6 +250,7 @@ pub(crate) fn persist_source_config_values(
         disabled: bool,
         is_adult: bool,
         sort_order: i64,
+        options_json: String,
     }
 
     let rows = sources
@@ -258,6 +289,7 @@ pub(crate) fn persist_source_config_values(
                     .and_then(|value| value.as_bool())
                     .unwrap_or(false),
                 sort_order: index as i64,
+                options_json: source_options_json(&normalized),
             })
         })
         .collect::<Result<Vec<_>, String>>()?;
@@ -287,9 +319,10 @@ pub(crate) fn persist_source_config_values(
                     disabled,
                     is_adult,
                     sort_order,
+                    options_json,
                     created_at,
                     updated_at
-                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
                 ON CONFLICT(source_key) DO UPDATE SET
                     name = excluded.name,
                     api = excluded.api,
@@ -298,6 +331,7 @@ pub(crate) fn persist_source_config_values(
                     disabled = excluded.disabled,
                     is_adult = excluded.is_adult,
                     sort_order = excluded.sort_order,
+                    options_json = excluded.options_json,
                     updated_at = excluded.updated_at",
                 params![
                     &row.key,
@@ -308,6 +342,7 @@ pub(crate) fn persist_source_config_values(
                     if row.disabled { 1 } else { 0 },
                     if row.is_adult { 1 } else { 0 },
                     row.sort_order,
+                    &row.options_json,
                     created_at,
                     now,
                 ],
@@ -385,6 +420,9 @@ fn rebuild_source_stats_with_fk(db: &Db) -> Result<(), String> {
                 consecutive_failures INTEGER NOT NULL DEFAULT 0,
                 auto_degraded INTEGER NOT NULL DEFAULT 0,
                 recent_results_json TEXT NOT NULL DEFAULT '[]',
+                circuit_state TEXT NOT NULL DEFAULT 'closed',
+                circuit_trips INTEGER NOT NULL DEFAULT 0,
+                next_retry_time INTEGER,
                 updated_at INTEGER NOT NULL
             );
 
@@ -399,6 +437,9 @@ fn rebuild_source_stats_with_fk(db: &Db) -> Result<(), String> {
                 consecutive_failures,
                 auto_degraded,
                 recent_results_json,
+                circuit_state,
+                circuit_trips,
+                next_retry_time,
                 updated_at
             )
             SELECT
@@ -412,6 +453,9 @@ fn rebuild_source_stats_with_fk(db: &Db) -> Result<(), String> {
                 consecutive_failures,
                 auto_degraded,
                 recent_results_json,
+                circuit_state,
+                circuit_trips,
+                next_retry_time,
                 updated_at
             FROM source_intelligence_stats
             WHERE source_key IN (SELECT source_key FROM video_sources);
@@ -525,9 +569,9 @@ fn civil_from_days(days: i64) -> (i32, u32, u32) {
 }
 
 pub(crate) async fn fetch_subscription_text(url: &str) -> Result<String, String> {
-    let client = reqwest::Client::builder()
+    let client = crate::network::client_builder(ProxyCategory::Sources)
         .timeout(Duration::from_secs(30))
-        .user_agent("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36")
+        .user_agent(DEFAULT_USER_AGENT)
         .build()
         .map_err(|e| e.to_string())?;
 
@@ -540,6 +584,37 @@ pub(crate) async fn fetch_subscription_text(url: &str) -> Result<String, String>
     response.text().await.map_err(|e| e.to_string())
 }
 
+/// 写入旧版单订阅设置 ConfigSubscribtion
+fn set_legacy_subscription(
+    config: &mut Value,
+    url: Option<&str>,
+    auto_update: Option<bool>,
+    checked: bool,
+) {
+    let Some(obj) = config.as_object_mut() else {
+        return;
+    };
+    let sub = obj
+        .entry("ConfigSubscribtion".to_string())
+        .or_insert_with(|| serde_json::json!({}));
+    if !sub.is_object() {
+        *sub = serde_json::json!({});
+    }
+    let sub_obj = sub.as_object_mut().unwrap();
+    if let Some(url) = url {
+        sub_obj.insert("URL".to_string(), Value::String(url.to_string()));
+    }
+    if let Some(auto_update) = auto_update {
+        sub_obj.insert("AutoUpdate".to_string(), Value::Bool(auto_update));
+    }
+    if checked {
+        sub_obj.insert(
+            "LastCheck".to_string(),
+            Value::String(format_rfc3339_utc_now()),
+        );
+    }
+}
+
 async fn resolve_subscription_json(
     subscription_url: Option<&str>,
     raw_json: Option<&str>,
@@ -609,6 +684,7 @@ pub async fn pull_subscription_config(
     subscription_url: String,
     state: State<'_, StorageManager>,
     db: State<'_, Db>,
+    source_manager: State<'_, SourceIntelligenceManager>,
 ) -> Result<SubscriptionPullResponse, String> {
     let url = subscription_url.trim();
     if url.is_empty() {
@@ -620,21 +696,20 @@ pub async fn pull_subscription_config(
 
     let data = state.get_data()?;
     let mut config = merge_admin_config_with_defaults(&data.config);
+    set_legacy_subscription(&mut config, Some(url), None, true);
+
+    // 已使用订阅列表时更新默认订阅并重新合并，ConfigFile 由合并结果决定
+    if has_subscription_list(&config) {
+        state.update_config(strip_source_config(&config))?;
+        let config = sync_legacy_subscription(&state, &db, &source_manager, Some(&text))?;
+        return Ok(SubscriptionPullResponse {
+            raw_json: text,
+            config,
+        });
+    }
 
     if let Some(obj) = config.as_object_mut() {
         obj.insert("ConfigFile".to_string(), Value::String(text.clone()));
-        let sub = obj
-            .entry("ConfigSubscribtion".to_string())
-            .or_insert_with(|| serde_json::json!({}));
-        if !sub.is_object() {
-            *sub = serde_json::json!({});
-        }
-        let sub_obj = sub.as_object_mut().unwrap();
-        sub_obj.insert("URL".to_string(), Value::String(url.to_string()));
-        sub_obj.insert(
-            "LastCheck".to_string(),
-            Value::String(format_rfc3339_utc_now()),
-        );
     }
 
     let stripped = strip_source_config(&config);
@@ -673,24 +748,17 @@ pub async fn save_subscription_config(
 
     let data = state.get_data()?;
     let mut config = merge_admin_config_with_defaults(&data.config);
+    set_legacy_subscription(&mut config, subscription_url.as_deref(), auto_update, false);
+
+    // 已使用订阅列表时只更新默认订阅，其他订阅的源保持不变
+    if has_subscription_list(&config) {
+        state.update_config(strip_source_config(&config))?;
+        return sync_legacy_subscription(&state, &db, &source_manager, Some(&raw_json));
+    }
 
     if let Some(obj) = config.as_object_mut() {
         obj.insert("ConfigFile".to_string(), Value::String(raw_json));
         obj.insert("CustomCategories".to_string(), Value::Array(categories));
-
-        let sub = obj
-            .entry("ConfigSubscribtion".to_string())
-            .or_insert_with(|| serde_json::json!({}));
-        if !sub.is_object() {
-            *sub = serde_json::json!({});
-        }
-        let sub_obj = sub.as_object_mut().unwrap();
-        if let Some(url) = subscription_url {
-            sub_obj.insert("URL".to_string(), Value::String(url));
-        }
-        if let Some(auto_update) = auto_update {
-            sub_obj.insert("AutoUpdate".to_string(), Value::Bool(auto_update));
-        }
     }
 
     persist_source_config_values(&db, &sources)?;
@@ -775,26 +843,22 @@ pub async fn update_subscription_settings(
     auto_update: bool,
     state: State<'_, StorageManager>,
     db: State<'_, Db>,
+    source_manager: State<'_, SourceIntelligenceManager>,
 ) -> Result<Value, String> {
     let data = state.get_data()?;
     let mut config = merge_admin_config_with_defaults(&data.config);
-
-    if let Some(obj) = config.as_object_mut() {
-        let sub = obj
-            .entry("ConfigSubscribtion".to_string())
-            .or_insert_with(|| serde_json::json!({}));
-        if !sub.is_object() {
-            *sub = serde_json::json!({});
-        }
-        let sub_obj = sub.as_object_mut().unwrap();
-        if let Some(url) = subscription_url {
-            sub_obj.insert("URL".to_string(), Value::String(url));
-        }
-        sub_obj.insert("AutoUpdate".to_string(), Value::Bool(auto_update));
-    }
+    set_legacy_subscription(
+        &mut config,
+        subscription_url.as_deref(),
+        Some(auto_update),
+        false,
+    );
 
     let stripped = strip_source_config(&config);
     state.update_config(stripped.clone())?;
+    if has_subscription_list(&stripped) {
+        return sync_legacy_subscription(&state, &db, &source_manager, None);
+    }
     Ok(merge_config_with_sources(
         &stripped,
         load_source_config_values(&db)?,
@@ -1155,13 +1219,16 @@ pub async fn set_player_config(
 
     let config_obj = data.config.as_object_mut().unwrap();
 
+    let allow_lan_sources = config.allow_lan_sources;
     // 保存播放器配置
     config_obj.insert(
         "PlayerConfig".to_string(),
         serde_json::to_value(config).map_err(|e| e.to_string())?,
     );
 
-    state.update_config(data.config)
+    state.update_config(data.config)?;
+    set_allow_lan_sources(allow_lan_sources);
+    Ok(())
 }
 
 /// 播放器配置结构
@@ -1227,6 +1294,7 @@ pub async fn update_player_config(
     );
 
     state.update_config(data.config)?;
+    set_allow_lan_sources(updated.allow_lan_sources);
     Ok(updated)
 }
 
@@ -1269,6 +1337,16 @@ pub struct UserPreferences {
     pub bangumi_proxy_type: String,
     /// 番剧代理URL
     pub bangumi_proxy_url: String,
+
+    // 网络设置
+    /// 出站代理（按源接口、元数据、媒体分别配置）
+    pub proxy: ProxySettings,
+    /// 域名解析（DoH、静态解析、缓存）
+    pub dns: DnsSettings,
+
+    // 下载设置
+    /// 离线下载的并发数、带宽上限与保存形式
+    pub download: DownloadSettings,
 }
 
 impl Default for UserPreferences {
@@ -1294,6 +1372,9 @@ impl Default for UserPreferences {
             has_seen_announcement: String::new(),
             bangumi_proxy_type: String::new(),
             bangumi_proxy_url: String::new(),
+            proxy: ProxySettings::default(),
+            dns: DnsSettings::default(),
+            download: DownloadSettings::default(),
         }
     }
 }
@@ -1314,6 +1395,9 @@ pub struct UserPreferencesPatch {
     pub has_seen_announcement: Option<String>,
     pub bangumi_proxy_type: Option<String>,
     pub bangumi_proxy_url: Option<String>,
+    pub proxy: Option<ProxySettings>,
+    pub dns: Option<DnsSettings>,
+    pub download: Option<DownloadSettings>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
@@ -1474,10 +1558,19 @@ fn apply_user_preferences_patch(
     if let Some(value) = patch.bangumi_proxy_url {
         preferences.bangumi_proxy_url = value;
     }
+    if let Some(value) = patch.proxy {
+        preferences.proxy = value;
+    }
+    if let Some(value) = patch.dns {
+        preferences.dns = value;
+    }
+    if let Some(value) = patch.download {
+        preferences.download = value;
+    }
     preferences
 }
 
-fn user_preferences_from_config(config: &Value) -> UserPreferences {
+pub(crate) fn user_preferences_from_config(config: &Value) -> UserPreferences {
     if let Some(user_prefs) = config.get("UserPreferences") {
         if let Ok(prefs) = serde_json::from_value::<UserPreferences>(user_prefs.clone()) {
             return prefs;
@@ -1534,6 +1627,15 @@ fn user_preferences_from_config(config: &Value) -> UserPreferences {
     prefs
 }
 
+/// 启动时按已保存的配置应用网络设置（代理、域名解析、局域网策略）
+pub(crate) fn apply_saved_network_settings(storage: &StorageManager) -> Result<(), String> {
+    let data = storage.get_data()?;
+    let preferences = user_preferences_from_config(&data.config);
+    set_dns_settings(preferences.dns);
+    set_allow_lan_sources(player_config_from_config(&data.config).allow_lan_sources);
+    set_proxy_settings(preferences.proxy)
+}
+
 /// 获取用户偏好配置（统一配置，自动从 SiteConfig 迁移）
 #[tauri::command]
 pub async fn get_user_preferences(
@@ -1557,6 +1659,8 @@ pub async fn set_user_preferences(
     preferences: UserPreferences,
     state: State<'_, StorageManager>,
 ) -> Result<(), String> {
+    preferences.proxy.validate()?;
+    preferences.dns.validate()?;
     let mut data = state.get_data()?;
 
     // 确保配置结构存在
@@ -1572,6 +1676,8 @@ pub async fn set_user_preferences(
     } else {
         set_bangumi_proxy_url("");
     }
+    set_proxy_settings(preferences.proxy.clone())?;
+    set_dns_settings(preferences.dns.clone());
 
     // 保存用户偏好配置
     config_obj.insert(
@@ -1591,6 +1697,8 @@ pub async fn update_user_preferences(
     let mut data = state.get_data()?;
     let current = user_preferences_from_config(&data.config);
     let updated = apply_user_preferences_patch(current, preferences);
+    updated.proxy.validate()?;
+    updated.dns.validate()?;
 
     if !data.config.is_object() {
         data.config = serde_json::json!({});
@@ -1608,6 +1716,8 @@ pub async fn update_user_preferences(
     } else {
         set_bangumi_proxy_url("");
     }
+    set_proxy_settings(updated.proxy.clone())?;
+    set_dns_settings(updated.dns.clone());
 
     state.update_config(data.config)?;
     Ok(updated)
@@ -1630,6 +1740,7 @@ mod tests {
         user_preferences_from_config, validate_subscription_json, CustomCategoryAction,
         PlayerConfig, PlayerConfigPatch, SourceConfigAction, UserPreferences, UserPreferencesPatch,
     };
+    use quantumtv_core::ProxySettings;
     use rusqlite::params;
     use serde_json::json;
 
@@ -1767,6 +1878,24 @@ mod tests {
         assert_eq!(updated.site_name, base.site_name);
     }
 
+    #[test]
+    fn user_preferences_without_proxy_default_to_direct() {
+        let prefs = user_preferences_from_config(&json!({
+            "UserPreferences": { "site_name": "QuantumTV" }
+        }));
+        assert_eq!(prefs.proxy, ProxySettings::default());
+
+        let patch = UserPreferencesPatch {
+            proxy: Some(ProxySettings {
+                media: "socks5://127.0.0.1:1080".to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let updated = apply_user_preferences_patch(prefs, patch);
+        assert_eq!(updated.proxy.media, "socks5://127.0.0.1:1080");
+    }
+
     #[test]
     fn should_use_local_source_config_only_for_localstorage() {
         assert!(should_use_local_source_config("localstorage"));
@@ -1947,6 +2076,7 @@ mod tests {
                 disabled INTEGER NOT NULL DEFAULT 0,
                 is_adult INTEGER NOT NULL DEFAULT 0,
                 sort_order INTEGER NOT NULL DEFAULT 0,
+                options_json TEXT NOT NULL DEFAULT '{}',
                 created_at INTEGER NOT NULL,
                 updated_at INTEGER NOT NULL
             );
@@ -1962,6 +2092,9 @@ mod tests {
                 consecutive_failures INTEGER NOT NULL DEFAULT 0,
                 auto_degraded INTEGER NOT NULL DEFAULT 0,
                 recent_results_json TEXT NOT NULL DEFAULT '[]',
+                circuit_state TEXT NOT NULL DEFAULT 'closed',
+                circuit_trips INTEGER NOT NULL DEFAULT 0,
+                next_retry_time INTEGER,
                 updated_at INTEGER NOT NULL
             );
             "#,
@@ -1987,6 +2120,29 @@ mod tests {
         assert_eq!(loaded[1].get("key").and_then(|v| v.as_str()), Some("a"));
     }
 
+    #[test]
+    fn persist_source_config_values_keeps_extra_fields() {
+        let db = setup_source_db();
+        let sources = vec![serde_json::json!({
+            "key": "a",
+            "name": "A",
+            "api": "https://a.example.com",
+            "request_policy": { "rate_per_sec": 2.0, "max_concurrency": 2 }
+        })];
+
+        persist_source_config_values(&db, &sources).unwrap();
+
+        let loaded = load_source_config_values(&db).unwrap();
+        assert_eq!(
+            loaded[0].get("request_policy"),
+            Some(&serde_json::json!({ "rate_per_sec": 2.0, "max_concurrency": 2 }))
+        );
+        assert_eq!(
+            loaded[0].get("api").and_then(|v| v.as_str()),
+            Some("https://a.example.com")
+        );
+    }
+
     #[test]
     fn rebuild_source_stats_with_fk_enables_cascade_delete() {
         let db = setup_source_db();
@@ -4,6 +4,7 @@ use crate::commands::video::{parse_source_categories, SourceCategoryItem};
 use crate::db::db_client::Db;
 use crate::db::page_cache::PageCacheManager;
 use crate::storage::StorageManager;
+use quantumtv_core::{ProxyCategory, DEFAULT_USER_AGENT};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -542,7 +543,7 @@ async fn fetch_douban_categories(
         params.category,
         params.type_
     );
-    let client_builder = reqwest::Client::builder()
+    let client_builder = crate::network::client_builder(ProxyCategory::Metadata)
         .timeout(std::time::Duration::from_secs(30)) // 30秒超时        .connect_timeout(std::time::Duration::from_secs(10)) // 10绉掕繛鎺ヨ秴鏃?        .pool_max_idle_per_host(10) // 杩炴帴姹犱紭鍖?        .pool_idle_timeout(std::time::Duration::from_secs(90))
         .tcp_keepalive(std::time::Duration::from_secs(60))
         .http1_only()
@@ -552,8 +553,10 @@ async fn fetch_douban_categories(
         .no_zstd(); // TCP keepalive
 
     let client_builder = if !proxy_url.is_empty() && !use_tencent_cdn && !use_ali_cdn {
-        // 只有非 CDN 情况下用代理
-        client_builder.proxy(reqwest::Proxy::all(&proxy_url)?)
+        // 只有非 CDN 情况下用豆瓣代理，并优先于全局代理设置
+        client_builder
+            .no_proxy()
+            .proxy(reqwest::Proxy::all(&proxy_url)?)
     } else {
         client_builder
     };
@@ -567,7 +570,7 @@ async fn fetch_douban_categories(
     loop {
         match client
             .get(&target)
-            .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/122.0.0.0 Safari/537.36")
+            .header("User-Agent", DEFAULT_USER_AGENT)
             .header("Referer", "https://movie.douban.com/")
             .header("Accept", "application/json, text/plain, */*")
             .header("Accept-Language", "zh-CN,zh;q=0.9,en;q=0.8")
@@ -646,7 +649,7 @@ async fn fetch_douban_recommends(
     let query = params.build_query_string()?;
     let target_url = format!("{}?{}", base_url, query);
 
-    let client_builder = reqwest::Client::builder()
+    let client_builder = crate::network::client_builder(ProxyCategory::Metadata)
         .timeout(std::time::Duration::from_secs(30))
         .connect_timeout(std::time::Duration::from_secs(10))
         .http1_only()
@@ -656,8 +659,10 @@ async fn fetch_douban_recommends(
         .no_zstd();
 
     let client_builder = if !proxy_url.is_empty() && !use_tencent_cdn && !use_ali_cdn {
-        // 只有非 CDN 情况下用代理
-        client_builder.proxy(reqwest::Proxy::all(&proxy_url)?)
+        // 只有非 CDN 情况下用豆瓣代理，并优先于全局代理设置
+        client_builder
+            .no_proxy()
+            .proxy(reqwest::Proxy::all(&proxy_url)?)
     } else {
         client_builder
     };
@@ -666,7 +671,7 @@ async fn fetch_douban_recommends(
 
     let response = client
         .get(&target_url)
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/122.0.0.0 Safari/537.36")
+        .header("User-Agent", DEFAULT_USER_AGENT)
         .header("Referer", "https://movie.douban.com/")
         .header("Accept", "application/json, text/plain, */*")
         .header("Accept-Language", "zh-CN,zh;q=0.9,en;q=0.8")
@@ -776,7 +781,9 @@ pub async fn get_douban_list(params: DoubanListParams) -> Result<DoubanResult, S
                 params.page_start.unwrap_or(0)
             );
 
-            let client = reqwest::Client::new();
+            let client = crate::network::client_builder(ProxyCategory::Metadata)
+                .build()
+                .map_err(|e| e.to_string())?;
             let resp = client
                 .get(&url)
                 .send()
@@ -856,7 +863,7 @@ pub async fn fetch_douban_list(
         "{}?type={}&tag={}&sort=recommend&page_limit={}&page_start={}",
         base_url, params.type_, params.tag, page_limit, page_start
     );
-    let client_builder = reqwest::Client::builder()
+    let client_builder = crate::network::client_builder(ProxyCategory::Metadata)
         .timeout(std::time::Duration::from_secs(30))
         .connect_timeout(std::time::Duration::from_secs(10))
         .http1_only()
@@ -866,8 +873,10 @@ pub async fn fetch_douban_list(
         .no_zstd();
 
     let client_builder = if !proxy_url.is_empty() && !use_tencent_cdn && !use_ali_cdn {
-        // 只有非 CDN 情况下用代理
-        client_builder.proxy(reqwest::Proxy::all(&proxy_url).map_err(|e| e.to_string())?)
+        // 只有非 CDN 情况下用豆瓣代理，并优先于全局代理设置
+        client_builder
+            .no_proxy()
+            .proxy(reqwest::Proxy::all(&proxy_url).map_err(|e| e.to_string())?)
     } else {
         client_builder
     };
@@ -876,7 +885,7 @@ pub async fn fetch_douban_list(
 
     let response = client
         .get(&target)
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/122.0.0.0 Safari/537.36")
+        .header("User-Agent", DEFAULT_USER_AGENT)
         .header("Referer", "https://movie.douban.com/")
         .header("Accept", "application/json, text/plain, */*")
         .header("Accept-Language", "zh-CN,zh;q=0.9,en;q=0.8")
@@ -991,7 +1000,9 @@ pub async fn get_douban_recommends(params: DoubanRecommendsParams) -> Result<Dou
                 params.label
             );
 
-            let client = reqwest::Client::new();
+            let client = crate::network::client_builder(ProxyCategory::Metadata)
+                .build()
+                .map_err(|e| e.to_string())?;
             let resp = client
                 .get(&url)
                 .send()
@@ -1844,7 +1855,7 @@ pub async fn get_douban_page_state(
             .ok_or_else(|| format!("Source not found or disabled: {}", resolved.source_key))?;
 
         let url = crate::commands::video::source_url(&source.api, "?ac=list");
-        let body = crate::commands::video::get_video_client()
+        let body = crate::commands::video::get_video_client(ProxyCategory::Sources)
             .get(url)
             .send()
             .await
@@ -1982,7 +1993,7 @@ pub async fn get_filtered_source_categories(
         .ok_or_else(|| format!("Source not found or disabled: {}", source_key))?;
 
     let url = crate::commands::video::source_url(&source.api, "?ac=list");
-    let body = crate::commands::video::get_video_client()
+    let body = crate::commands::video::get_video_client(ProxyCategory::Sources)
         .get(url)
         .send()
         .await
@@ -0,0 +1,528 @@
+// 离线下载：按集下载 HLS 视频，支持断点续传、解密与限速
+use crate::commands::config::user_preferences_from_config;
+use crate::commands::source_intelligence::SourceIntelligenceManager;
+use crate::commands::video::{
+    apply_media_headers, check_remote_url_against_config, get_video_client, get_video_detail,
+    media_profiles, outbound_scheduler, unwrap_segment,
+};
+use crate::db::db_client::Db;
+use crate::db::download::{
+    self as store, DownloadItem, NewDownload, STATUS_COMPLETED, STATUS_DOWNLOADING, STATUS_FAILED,
+    STATUS_PAUSED, STATUS_QUEUED,
+};
+use crate::media_server::MediaServer;
+use crate::storage::StorageManager;
+use quantumtv_core::{
+    best_variant, decrypt_hls_segment, BandwidthLimiter, DownloadOutput, DownloadPlan,
+    DownloadSettings, HlsKeyCache, PlannedSegment, ProxyCategory,
+};
+use reqwest::header::HeaderMap;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tauri::async_runtime::JoinHandle;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tokio::time::{timeout, Duration};
+
+const DOWNLOAD_PROGRESS_EVENT: &str = "download-progress";
+/// 单个请求的总时长上限；限速时分片可能下载很久，只对读取停顿单独计时
+const DOWNLOAD_REQUEST_TIMEOUT: Duration = Duration::from_secs(600);
+const DOWNLOAD_READ_TIMEOUT: Duration = Duration::from_secs(30);
+const DOWNLOAD_ATTEMPTS: u32 = 3;
+/// 主播放列表最多嵌套的层数
+const MAX_PLAYLIST_DEPTH: usize = 3;
+const INIT_FILE_NAME: &str = "init.mp4";
+
+/// 正在运行的下载任务，以及所有任务共享的限速器和密钥缓存
+pub struct DownloadManager {
+    running: Mutex<HashMap<String, JoinHandle<()>>>,
+    limiter: Arc<BandwidthLimiter>,
+    keys: Arc<HlsKeyCache>,
+}
+
+impl DownloadManager {
+    pub fn new() -> Self {
+        Self {
+            running: Mutex::new(HashMap::new()),
+            limiter: Arc::new(BandwidthLimiter::new()),
+            keys: Arc::new(HlsKeyCache::new()),
+        }
+    }
+
+    /// 停止任务，返回是否确实在运行
+    fn abort(&self, id: &str) -> bool {
+        match self.running.lock().unwrap().remove(id) {
+            Some(handle) => {
+                handle.abort();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Default for DownloadManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn download_settings(app: &AppHandle) -> DownloadSettings {
+    app.state::<StorageManager>()
+        .get_data()
+        .map(|data| user_preferences_from_config(&data.config).download)
+        .unwrap_or_default()
+}
+
+pub(crate) fn downloads_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map(|dir| dir.join("downloads"))
+        .map_err(|e| format!("无法获取下载目录: {}", e))
+}
+
+fn emit_progress(app: &AppHandle, id: &str) {
+    if let Ok(Some(item)) = store::get_download(&app.state::<Db>(), id) {
+        let _ = app.emit(DOWNLOAD_PROGRESS_EVENT, item);
+    }
+}
+
+/// 启动时把上次中断的下载重新排队并继续
+pub(crate) fn restore_downloads(app: &AppHandle) {
+    match store::requeue_interrupted_downloads(&app.state::<Db>()) {
+        Ok(count) if count > 0 => log::info!("恢复 {} 个未完成的下载", count),
+        Ok(_) => {}
+        Err(error) => log::warn!("恢复下载任务失败: {}", error),
+    }
+    schedule_downloads(app);
+}
+
+/// 按设置的同时任务数启动排队中的下载
+fn schedule_downloads(app: &AppHandle) {
+    let manager = app.state::<DownloadManager>();
+    let settings = download_settings(app);
+    manager
+        .limiter
+        .set_limit_kbps(settings.bandwidth_limit_kbps);
+
+    let mut running = manager.running.lock().unwrap();
+    let slots = settings.max_tasks.max(1).saturating_sub(running.len());
+    if slots == 0 {
+        return;
+    }
+    let queued = match store::queued_downloads(&app.state::<Db>(), slots + running.len()) {
+        Ok(ids) => ids,
+        Err(error) => {
+            log::warn!("读取下载队列失败: {}", error);
+            return;
+        }
+    };
+    let to_start: Vec<String> = queued
+        .into_iter()
+        .filter(|id| !running.contains_key(id))
+        .take(slots)
+        .collect();
+    for id in to_start {
+        let handle =
+            tauri::async_runtime::spawn(download_task(app.clone(), id.clone(), settings.clone()));
+        running.insert(id, handle);
+    }
+}
+
+async fn download_task(app: AppHandle, id: String, settings: DownloadSettings) {
+    if let Err(error) = run_download(&app, &id, &settings).await {
+        log::warn!("下载失败 {}: {}", id, error);
+        let _ = store::set_download_status(&app.state::<Db>(), &id, STATUS_FAILED, Some(&error));
+    }
+    emit_progress(&app, &id);
+    app.state::<DownloadManager>()
+        .running
+        .lock()
+        .unwrap()
+        .remove(&id);
+    schedule_downloads(&app);
+}
+
+async fn run_download(
+    app: &AppHandle,
+    id: &str,
+    settings: &DownloadSettings,
+) -> Result<(), String> {
+    let db = app.state::<Db>();
+    let manager = app.state::<DownloadManager>();
+    let item = store::get_download(&db, id)?.ok_or_else(|| "下载任务不存在".to_string())?;
+    store::set_download_status(&db, id, STATUS_DOWNLOADING, None)?;
+    emit_progress(app, id);
+
+    let config = app.state::<StorageManager>().get_data()?.config;
+    check_remote_url_against_config(&item.episode_url, &config).await?;
+    let (playlist_url, content) = fetch_media_playlist(&item.episode_url, &manager.limiter).await?;
+    let plan = DownloadPlan::from_playlist(&content, &playlist_url)?;
+
+    let dir = downloads_dir(app)?.join(id);
+    tokio::fs::create_dir_all(&dir)
+        .await
+        .map_err(|e| format!("创建下载目录失败: {}", e))?;
+
+    let init_file = match &plan.init_url {
+        Some(url) => {
+            let path = dir.join(INIT_FILE_NAME);
+            if tokio::fs::metadata(&path).await.is_err() {
+                let data = fetch_bytes(url, &manager.limiter).await?;
+                write_file(&path, &data).await?;
+            }
+            Some(INIT_FILE_NAME)
+        }
+        None => None,
+    };
+
+    // 已存在的分片文件视为下载完成（断点续传）
+    let total = plan.segments.len();
+    let mut completed = 0;
+    let mut downloaded_bytes = 0;
+    let mut pending = Vec::new();
+    for (index, segment) in plan.segments.iter().enumerate() {
+        match tokio::fs::metadata(dir.join(plan.segment_file_name(index))).await {
+            Ok(meta) => {
+                completed += 1;
+                downloaded_bytes += meta.len();
+            }
+            Err(_) => pending.push((index, segment.clone())),
+        }
+    }
+    store::update_download_progress(&db, id, total, completed, downloaded_bytes)?;
+    emit_progress(app, id);
+
+    // 任务被暂停（abort）时 JoinSet 随之丢弃，未完成的分片一并取消
+    let semaphore = Arc::new(Semaphore::new(settings.segment_concurrency.max(1)));
+    let source_manager = app.state::<SourceIntelligenceManager>().inner().clone();
+    let mut tasks = JoinSet::new();
+    for (index, segment) in pending {
+        let path = dir.join(plan.segment_file_name(index));
+        let semaphore = semaphore.clone();
+        let limiter = manager.limiter.clone();
+        let keys = manager.keys.clone();
+        let source_manager = source_manager.clone();
+        let source_key = item.source.clone();
+        tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await.map_err(|e| e.to_string())?;
+            download_segment(
+                &segment,
+                &path,
+                &limiter,
+                &keys,
+                &source_manager,
+                &source_key,
+            )
+            .await
+        });
+    }
+    while let Some(result) = tasks.join_next().await {
+        let size = result.map_err(|e| format!("下载分片任务异常: {}", e))??;
+        completed += 1;
+        downloaded_bytes += size;
+        store::update_download_progress(&db, id, total, completed, downloaded_bytes)?;
+        emit_progress(app, id);
+    }
+
+    let file_path = match settings.output {
+        DownloadOutput::Hls => {
+            let path = dir.join("index.m3u8");
+            write_file(&path, plan.local_playlist(init_file).as_bytes()).await?;
+            path
+        }
+        DownloadOutput::Ts => concat_segments(&plan, &dir, init_file).await?,
+    };
+    store::complete_download(&db, id, &file_path.to_string_lossy())?;
+    log::info!("下载完成: {} {}", item.title, item.episode_title);
+    Ok(())
+}
+
+/// 下载播放列表；主播放列表选择带宽最高的变体
+async fn fetch_media_playlist(
+    url: &str,
+    limiter: &BandwidthLimiter,
+) -> Result<(String, String), String> {
+    let mut url = url.to_string();
+    for _ in 0..MAX_PLAYLIST_DEPTH {
+        let body = fetch_bytes(&url, limiter).await?;
+        let content =
+            String::from_utf8(body).map_err(|e| format!("无法将 M3U8 内容解码为 UTF-8: {}", e))?;
+        if !content.trim_start().starts_with("#EXTM3U") {
+            return Err("只支持下载 M3U8 视频".to_string());
+        }
+        media_profiles().register_playlist(&url, &content);
+        match best_variant(&content, &url) {
+            Some(variant) => url = variant,
+            None => return Ok((url, content)),
+        }
+    }
+    Err("播放列表嵌套层数过多".to_string())
+}
+
+async fn download_segment(
+    segment: &PlannedSegment,
+    path: &Path,
+    limiter: &BandwidthLimiter,
+    keys: &HlsKeyCache,
+    source_manager: &SourceIntelligenceManager,
+    source_key: &str,
+) -> Result<u64, String> {
+    let mut data = fetch_bytes(&segment.url, limiter).await?;
+    if let Some(encryption) = &segment.encryption {
+        let key = keys
+            .get_or_fetch(&encryption.key_uri, || {
+                fetch_bytes(&encryption.key_uri, limiter)
+            })
+            .await?;
+        data = decrypt_hls_segment(encryption.method, &data, &key, &encryption.iv)?;
+    }
+    let data = unwrap_segment(source_manager, Some(source_key), &segment.url, data);
+    write_file(path, &data).await?;
+    Ok(data.len() as u64)
+}
+
+/// 带重试的下载，数据经过共享的限速器
+async fn fetch_bytes(url: &str, limiter: &BandwidthLimiter) -> Result<Vec<u8>, String> {
+    let mut delay_ms = 500;
+    let mut attempt = 1;
+    loop {
+        match fetch_once(url, limiter).await {
+            Ok(data) => return Ok(data),
+            Err(error) if attempt >= DOWNLOAD_ATTEMPTS => return Err(error),
+            Err(error) => log::debug!(
+                "下载重试 {}/{} {}: {}",
+                attempt,
+                DOWNLOAD_ATTEMPTS,
+                url,
+                error
+            ),
+        }
+        tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+        delay_ms *= 2;
+        attempt += 1;
+    }
+}
+
+async fn fetch_once(url: &str, limiter: &BandwidthLimiter) -> Result<Vec<u8>, String> {
+    let mut headers = HeaderMap::new();
+    apply_media_headers(&mut headers, url);
+    let permit = outbound_scheduler()
+        .acquire(url, None, DOWNLOAD_READ_TIMEOUT)
+        .await;
+    let response = get_video_client(ProxyCategory::Media)
+        .get(url)
+        .headers(headers)
+        .timeout(DOWNLOAD_REQUEST_TIMEOUT)
+        .send()
+        .await;
+    // 调度器只限制发起请求，响应头到达后释放名额，长时间的正文读取不占用源站接口的并发
+    drop(permit);
+    let mut response = response.map_err(|e| format!("Network error: {} - {}", e, url))?;
+    if !response.status().is_success() {
+        return Err(format!("HTTP {}: {}", response.status(), url));
+    }
+
+    let mut data = Vec::new();
+    while let Some(chunk) = timeout(DOWNLOAD_READ_TIMEOUT, response.chunk())
+        .await
+        .map_err(|_| format!("读取超时: {}", url))?
+        .map_err(|e| format!("读取失败: {} - {}", e, url))?
+    {
+        limiter.consume(chunk.len()).await;
+        data.extend_from_slice(&chunk);
+    }
+    Ok(data)
+}
+
+/// 先写临时文件再改名，中断时不会留下不完整的分片
+async fn write_file(path: &Path, data: &[u8]) -> Result<(), String> {
+    let tmp = path.with_extension("part");
+    tokio::fs::write(&tmp, data)
+        .await
+        .map_err(|e| format!("写入文件失败: {}", e))?;
+    tokio::fs::rename(&tmp, path)
+        .await
+        .map_err(|e| format!("写入文件失败: {}", e))
+}
+
+/// 按顺序拼接初始化分片与所有分片，成功后删除分片文件
+async fn concat_segments(
+    plan: &DownloadPlan,
+    dir: &Path,
+    init_file: Option<&str>,
+) -> Result<PathBuf, String> {
+    let output = dir.join(format!("video.{}", plan.extension()));
+    let tmp = output.with_extension("part");
+    let mut parts: Vec<PathBuf> = init_file.map(|name| dir.join(name)).into_iter().collect();
+    parts.extend((0..plan.segments.len()).map(|index| dir.join(plan.segment_file_name(index))));
+
+    let mut file = tokio::fs::File::create(&tmp)
+        .await
+        .map_err(|e| format!("创建视频文件失败: {}", e))?;
+    for part in &parts {
+        let data = tokio::fs::read(part)
+            .await
+            .map_err(|e| format!("读取分片失败: {}", e))?;
+        file.write_all(&data)
+            .await
+            .map_err(|e| format!("写入视频文件失败: {}", e))?;
+    }
+    file.flush()
+        .await
+        .map_err(|e| format!("写入视频文件失败: {}", e))?;
+    drop(file);
+    tokio::fs::rename(&tmp, &output)
+        .await
+        .map_err(|e| format!("写入视频文件失败: {}", e))?;
+
+    for part in &parts {
+        let _ = tokio::fs::remove_file(part).await;
+    }
+    Ok(output)
+}
+
+/// 把某一集加入下载队列
+#[tauri::command]
+pub async fn enqueue_download(
+    source: String,
+    id: String,
+    episode_index: usize,
+    app: AppHandle,
+) -> Result<DownloadItem, String> {
+    let detail = get_video_detail(
+        source.clone(),
+        id.clone(),
+        app.state(),
+        app.state(),
+        app.state(),
+    )
+    .await?;
+    let episode_url = detail
+        .episodes
+        .get(episode_index)
+        .cloned()
+        .ok_or_else(|| format!("集数超出范围: {}", episode_index + 1))?;
+    let episode_title = detail
+        .episodes_titles
+        .get(episode_index)
+        .cloned()
+        .unwrap_or_else(|| format!("第{}集", episode_index + 1));
+
+    let item = store::enqueue_download(
+        &app.state::<Db>(),
+        &NewDownload {
+            source,
+            video_id: id,
+            episode_index: episode_index as i64,
+            title: detail.title,
+            episode_title,
+            cover: detail.poster,
+            episode_url,
+        },
+    )?;
+    schedule_downloads(&app);
+    let _ = app.emit(DOWNLOAD_PROGRESS_EVENT, &item);
+    Ok(item)
+}
+
+/// 列出下载任务，可按播放记录的键（`source+id`）过滤
+#[tauri::command]
+pub fn get_downloads(
+    record_key: Option<String>,
+    db: State<'_, Db>,
+) -> Result<Vec<DownloadItem>, String> {
+    store::list_downloads(&db, record_key.as_deref())
+}
+
+#[tauri::command]
+pub fn pause_download(id: String, app: AppHandle) -> Result<(), String> {
+    let item = store::get_download(&app.state::<Db>(), &id)?
+        .ok_or_else(|| "下载任务不存在".to_string())?;
+    if item.status == STATUS_COMPLETED {
+        return Ok(());
+    }
+    let was_running = app.state::<DownloadManager>().abort(&id);
+    store::set_download_status(&app.state::<Db>(), &id, STATUS_PAUSED, None)?;
+    emit_progress(&app, &id);
+    if was_running {
+        schedule_downloads(&app);
+    }
+    Ok(())
+}
+
+/// 继续已暂停或失败的下载，已下载的分片不会重复下载
+#[tauri::command]
+pub fn resume_download(id: String, app: AppHandle) -> Result<(), String> {
+    let item = store::get_download(&app.state::<Db>(), &id)?
+        .ok_or_else(|| "下载任务不存在".to_string())?;
+    if item.status == STATUS_PAUSED || item.status == STATUS_FAILED {
+        store::set_download_status(&app.state::<Db>(), &id, STATUS_QUEUED, None)?;
+        emit_progress(&app, &id);
+        schedule_downloads(&app);
+    }
+    Ok(())
+}
+
+/// 删除下载任务及其文件
+#[tauri::command]
+pub async fn delete_download(id: String, app: AppHandle) -> Result<(), String> {
+    if store::get_download(&app.state::<Db>(), &id)?.is_none() {
+        return Ok(());
+    }
+    let was_running = app.state::<DownloadManager>().abort(&id);
+    let dir = downloads_dir(&app)?.join(&id);
+    if tokio::fs::metadata(&dir).await.is_ok() {
+        tokio::fs::remove_dir_all(&dir)
+            .await
+            .map_err(|e| format!("删除下载文件失败: {}", e))?;
+    }
+    store::delete_download(&app.state::<Db>(), &id)?;
+    if was_running {
+        schedule_downloads(&app);
+    }
+    Ok(())
+}
+
+/// 已下载到本地的某一集，文件已被删除时返回 None
+#[tauri::command]
+pub async fn get_offline_episode(
+    source: String,
+    id: String,
+    episode_index: usize,
+    db: State<'_, Db>,
+) -> Result<Option<DownloadItem>, String> {
+    let Some(item) = store::find_completed_download(&db, &source, &id, episode_index as i64)?
+    else {
+        return Ok(None);
+    };
+    match &item.file_path {
+        Some(path) if tokio::fs::metadata(path).await.is_ok() => Ok(Some(item)),
+        _ => Ok(None),
+    }
+}
+
+/// 已下载的某一集在本地媒体服务上的播放地址
+#[tauri::command]
+pub async fn get_offline_play_url(
+    source: String,
+    id: String,
+    episode_index: usize,
+    app: AppHandle,
+) -> Result<Option<String>, String> {
+    let server = app
+        .try_state::<MediaServer>()
+        .ok_or_else(|| "本地媒体服务未启动".to_string())?;
+    let Some(item) = get_offline_episode(source, id, episode_index, app.state()).await? else {
+        return Ok(None);
+    };
+    let file_name = item
+        .file_path
+        .as_deref()
+        .and_then(|path| Path::new(path).file_name())
+        .map(|name| name.to_string_lossy().into_owned());
+    Ok(file_name.map(|name| server.download_url(&item.id, &name)))
+}
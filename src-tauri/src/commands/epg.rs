@@ -0,0 +1,223 @@
+// 直播节目单：拉取 XMLTV、写入数据库并按频道查询
+use crate::commands::live::{load_live_playlist, LiveManager};
+use crate::commands::video::check_remote_url_against_config;
+use crate::db::db_client::Db;
+use crate::db::epg as store;
+use crate::db::live::list_live_sources;
+use crate::storage::StorageManager;
+use quantumtv_core::{
+    local_day_bounds, parse_xmltv, tvbox_live_epg_urls, EpgProgramme, ProxyCategory,
+    DEFAULT_USER_AGENT,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Manager, State};
+
+const EPG_FETCH_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// 定时刷新节目单的间隔
+pub(crate) const EPG_REFRESH_INTERVAL: Duration = Duration::from_secs(6 * 3600);
+
+/// 在该时长内刷新过的节目单，非强制刷新时跳过（略短于定时刷新间隔）
+const EPG_FRESH_SECS: i64 = 5 * 3600;
+
+/// 待执行的刷新：0 无，1 跳过仍新鲜的节目单，2 强制全部刷新
+const EPG_NO_REFRESH: u8 = 0;
+const EPG_REFRESH_STALE: u8 = 1;
+const EPG_REFRESH_ALL: u8 = 2;
+
+static EPG_REFRESHING: AtomicBool = AtomicBool::new(false);
+static EPG_PENDING: AtomicU8 = AtomicU8::new(EPG_NO_REFRESH);
+
+/// 查询节目单的频道：优先按 tvg-id 匹配，其次按频道名
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EpgChannelQuery {
+    #[serde(default)]
+    pub tvg_id: Option<String>,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EpgNowNext {
+    pub name: String,
+    pub now: Option<EpgProgramme>,
+    pub next: Option<EpgProgramme>,
+}
+
+fn current_timestamp() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
+
+/// 需要拉取的节目单地址：各直播订阅列表头与手动指定的地址，以及订阅配置中 TVBox `lives` 声明的地址
+async fn collect_epg_urls(app: &AppHandle, config: &Value) -> Result<Vec<String>, String> {
+    let db = app.state::<Db>();
+    let manager = app.state::<LiveManager>();
+    let mut urls: Vec<String> = Vec::new();
+
+    for source in list_live_sources(&db)?.into_iter().filter(|s| !s.disabled) {
+        match load_live_playlist(&source, false, config, &manager).await {
+            Ok(playlist) => urls.extend(playlist.epg_urls.iter().cloned()),
+            Err(e) => log::warn!("[节目单] 直播订阅 {} 读取失败: {}", source.name, e),
+        }
+    }
+    let lives = config
+        .get("ConfigFile")
+        .and_then(|v| v.as_str())
+        .and_then(|text| serde_json::from_str::<Value>(text).ok())
+        .and_then(|file| file.get("lives").cloned())
+        .unwrap_or(Value::Null);
+    urls.extend(tvbox_live_epg_urls(&lives));
+
+    let mut unique: Vec<String> = Vec::new();
+    for url in urls {
+        if !unique.contains(&url) {
+            unique.push(url);
+        }
+    }
+    Ok(unique)
+}
+
+async fn fetch_guide(url: &str, config: &Value) -> Result<Vec<u8>, String> {
+    check_remote_url_against_config(url, config).await?;
+    let client = crate::network::client_builder(ProxyCategory::Sources)
+        .timeout(EPG_FETCH_TIMEOUT)
+        .user_agent(DEFAULT_USER_AGENT)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| format!("Network error: {} - {}", e, url))?;
+    if !response.status().is_success() {
+        return Err(format!("HTTP {}: {}", response.status().as_u16(), url));
+    }
+    let bytes = response.bytes().await.map_err(|e| e.to_string())?;
+    Ok(bytes.to_vec())
+}
+
+/// 拉取节目单并写入数据库，返回写入的节目数；`force` 为 false 时跳过仍新鲜的节目单
+///
+/// 已有刷新在进行时把本次请求排队并返回 0，进行中的刷新结束后会再执行一次
+pub(crate) async fn refresh_epg(app: &AppHandle, force: bool) -> Result<usize, String> {
+    let request = if force {
+        EPG_REFRESH_ALL
+    } else {
+        EPG_REFRESH_STALE
+    };
+    EPG_PENDING.fetch_max(request, Ordering::SeqCst);
+
+    let mut total = 0;
+    let mut error = None;
+    while !EPG_REFRESHING.swap(true, Ordering::SeqCst) {
+        loop {
+            let pending = EPG_PENDING.swap(EPG_NO_REFRESH, Ordering::SeqCst);
+            if pending == EPG_NO_REFRESH {
+                break;
+            }
+            match refresh_epg_inner(app, pending == EPG_REFRESH_ALL).await {
+                Ok(written) => total += written,
+                Err(e) => error = Some(e),
+            }
+        }
+        EPG_REFRESHING.store(false, Ordering::SeqCst);
+        // 释放前后可能有新的请求排队，而请求方看到刷新仍在进行已直接返回
+        if EPG_PENDING.load(Ordering::SeqCst) == EPG_NO_REFRESH {
+            break;
+        }
+    }
+    match error {
+        Some(e) => Err(e),
+        None => Ok(total),
+    }
+}
+
+async fn refresh_epg_inner(app: &AppHandle, force: bool) -> Result<usize, String> {
+    let config = app.state::<StorageManager>().get_data()?.config;
+    let urls = collect_epg_urls(app, &config).await?;
+    let mut total = 0;
+
+    for url in urls {
+        if !force {
+            let refreshed_at = store::epg_guide_refreshed_at(&app.state::<Db>(), &url)?;
+            if refreshed_at.is_some_and(|at| current_timestamp() - at < EPG_FRESH_SECS) {
+                continue;
+            }
+        }
+        let data = match fetch_guide(&url, &config).await {
+            Ok(data) => data,
+            Err(e) => {
+                log::warn!("[节目单] 拉取失败 {}: {}", url, e);
+                continue;
+            }
+        };
+        let guide = match tauri::async_runtime::spawn_blocking(move || parse_xmltv(&data)).await {
+            Ok(Ok(guide)) => guide,
+            Ok(Err(e)) => {
+                log::warn!("[节目单] 解析失败 {}: {}", url, e);
+                continue;
+            }
+            Err(e) => return Err(e.to_string()),
+        };
+        let written = store::replace_epg_guide(&app.state::<Db>(), &url, &guide)?;
+        log::info!("[节目单] {} 写入 {} 个节目", url, written);
+        total += written;
+    }
+    Ok(total)
+}
+
+/// 批量获取频道的当前与下一个节目，用于频道列表
+#[tauri::command]
+pub async fn get_live_epg_now_next(
+    channels: Vec<EpgChannelQuery>,
+    db: State<'_, Db>,
+) -> Result<Vec<EpgNowNext>, String> {
+    let now = current_timestamp();
+    let mut result = Vec::with_capacity(channels.len());
+    for channel in channels {
+        let (now_playing, next) =
+            match store::resolve_epg_channel(&db, channel.tvg_id.as_deref(), &channel.name)? {
+                Some((epg_url, channel_id)) => {
+                    store::epg_now_next(&db, &epg_url, &channel_id, now)?
+                }
+                None => (None, None),
+            };
+        result.push(EpgNowNext {
+            name: channel.name,
+            now: now_playing,
+            next,
+        });
+    }
+    Ok(result)
+}
+
+/// 频道某一天的节目表，`date` 为本地日期 `YYYY-MM-DD`，`utc_offset_minutes` 为本地时区偏移
+#[tauri::command]
+pub async fn get_live_epg_day(
+    channel: EpgChannelQuery,
+    date: String,
+    utc_offset_minutes: i32,
+    db: State<'_, Db>,
+) -> Result<Vec<EpgProgramme>, String> {
+    let (from, to) = local_day_bounds(&date, utc_offset_minutes)
+        .ok_or_else(|| format!("无效的日期: {}", date))?;
+    match store::resolve_epg_channel(&db, channel.tvg_id.as_deref(), &channel.name)? {
+        Some((epg_url, channel_id)) => {
+            store::epg_programmes_between(&db, &epg_url, &channel_id, from, to)
+        }
+        None => Ok(Vec::new()),
+    }
+}
+
+/// 立即刷新全部节目单
+#[tauri::command]
+pub async fn refresh_live_epg(app: AppHandle) -> Result<usize, String> {
+    refresh_epg(&app, true).await
+}
@@ -0,0 +1,255 @@
+// 直播：订阅管理、频道列表解析与线路切换
+use crate::commands::epg::refresh_epg;
+use crate::commands::video::check_remote_url_against_config;
+use crate::db::db_client::Db;
+use crate::db::live::{self as store, LiveFavorite, LiveSource};
+use crate::db::play_favorite::TogglePlayFavoriteResponse;
+use crate::storage::StorageManager;
+use moka::future::Cache;
+use quantumtv_core::{
+    parse_live_list, LivePlaylist, MirrorStatus, MirrorTracker, ProxyCategory, DEFAULT_USER_AGENT,
+};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, State};
+
+const LIVE_LIST_TTL: Duration = Duration::from_secs(10 * 60);
+const LIVE_LIST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 解析后的频道列表缓存，以及各直播地址的健康度
+pub struct LiveManager {
+    playlists: Cache<String, Arc<LivePlaylist>>,
+    tracker: MirrorTracker,
+}
+
+impl LiveManager {
+    pub fn new() -> Self {
+        Self {
+            playlists: Cache::builder()
+                .max_capacity(32)
+                .time_to_live(LIVE_LIST_TTL)
+                .build(),
+            tracker: MirrorTracker::new(),
+        }
+    }
+
+    /// 频道地址按健康度排序：失败冷却中的线路排到最后，其余保持列表顺序
+    fn ordered(&self, playlist: &LivePlaylist) -> LivePlaylist {
+        let mut playlist = playlist.clone();
+        for channel in playlist
+            .groups
+            .iter_mut()
+            .flat_map(|group| group.channels.iter_mut())
+        {
+            if channel.urls.len() > 1 {
+                channel.urls = self.tracker.order(&channel.urls);
+            }
+        }
+        playlist
+    }
+}
+
+impl Default for LiveManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+async fn fetch_live_list(
+    source: &LiveSource,
+    config: &serde_json::Value,
+) -> Result<String, String> {
+    check_remote_url_against_config(&source.url, config).await?;
+    let user_agent = source
+        .ua
+        .as_deref()
+        .filter(|ua| !ua.trim().is_empty())
+        .unwrap_or(DEFAULT_USER_AGENT);
+    let client = crate::network::client_builder(ProxyCategory::Sources)
+        .timeout(LIVE_LIST_TIMEOUT)
+        .user_agent(user_agent)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let response = client
+        .get(&source.url)
+        .send()
+        .await
+        .map_err(|e| format!("Network error: {} - {}", e, source.url))?;
+    if !response.status().is_success() {
+        return Err(format!(
+            "HTTP {}: {}",
+            response.status().as_u16(),
+            source.url
+        ));
+    }
+    response.text().await.map_err(|e| e.to_string())
+}
+
+/// 读取（必要时拉取并解析）某个订阅的频道列表，手动指定的 EPG 地址排在最前
+pub(crate) async fn load_live_playlist(
+    source: &LiveSource,
+    refresh: bool,
+    config: &serde_json::Value,
+    manager: &LiveManager,
+) -> Result<Arc<LivePlaylist>, String> {
+    if refresh {
+        manager.playlists.invalidate(&source.key).await;
+    }
+    manager
+        .playlists
+        .try_get_with(source.key.clone(), async {
+            let content = fetch_live_list(source, config).await?;
+            let mut playlist = parse_live_list(&content);
+            if playlist.channel_count() == 0 {
+                return Err(format!("频道列表为空: {}", source.url));
+            }
+            if let Some(epg) = source
+                .epg
+                .as_deref()
+                .map(str::trim)
+                .filter(|e| !e.is_empty())
+            {
+                playlist.epg_urls.retain(|url| url != epg);
+                playlist.epg_urls.insert(0, epg.to_string());
+            }
+            log::info!(
+                "📺 直播订阅 {} 解析完成: {} 个频道",
+                source.name,
+                playlist.channel_count()
+            );
+            Ok(Arc::new(playlist))
+        })
+        .await
+        .map_err(|e: Arc<String>| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_live_sources(db: State<'_, Db>) -> Result<Vec<LiveSource>, String> {
+    store::list_live_sources(&db)
+}
+
+/// 新增或编辑直播订阅，未填写标识时自动生成；保存后在后台刷新节目单
+#[tauri::command]
+pub async fn save_live_source(
+    source: LiveSource,
+    app: AppHandle,
+    db: State<'_, Db>,
+    state: State<'_, StorageManager>,
+    manager: State<'_, LiveManager>,
+) -> Result<LiveSource, String> {
+    let name = source.name.trim();
+    let url = source.url.trim();
+    if name.is_empty() || url.is_empty() {
+        return Err("请填写完整信息".to_string());
+    }
+    check_remote_url_against_config(url, &state.get_data()?.config).await?;
+    let key = match source.key.trim() {
+        "" => uuid::Uuid::new_v4().simple().to_string(),
+        key => key.to_string(),
+    };
+    let trimmed = |value: Option<String>| {
+        value
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+    let source = LiveSource {
+        key,
+        name: name.to_string(),
+        url: url.to_string(),
+        ua: trimmed(source.ua),
+        epg: trimmed(source.epg),
+        disabled: source.disabled,
+    };
+    store::upsert_live_source(&db, &source)?;
+    manager.playlists.invalidate(&source.key).await;
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = refresh_epg(&app, false).await {
+            log::warn!("[节目单] 刷新失败: {}", e);
+        }
+    });
+    Ok(source)
+}
+
+#[tauri::command]
+pub async fn toggle_live_source(key: String, db: State<'_, Db>) -> Result<LiveSource, String> {
+    let mut source = store::get_live_source(&db, &key)?.ok_or("直播订阅不存在")?;
+    source.disabled = !source.disabled;
+    store::upsert_live_source(&db, &source)?;
+    Ok(source)
+}
+
+#[tauri::command]
+pub async fn delete_live_source(
+    key: String,
+    db: State<'_, Db>,
+    manager: State<'_, LiveManager>,
+) -> Result<(), String> {
+    store::delete_live_source(&db, &key)?;
+    manager.playlists.invalidate(&key).await;
+    Ok(())
+}
+
+/// 获取某个订阅的频道列表，每个频道的地址已按线路健康度排序
+#[tauri::command]
+pub async fn get_live_channels(
+    source_key: String,
+    refresh: Option<bool>,
+    db: State<'_, Db>,
+    state: State<'_, StorageManager>,
+    manager: State<'_, LiveManager>,
+) -> Result<LivePlaylist, String> {
+    let source = store::get_live_source(&db, &source_key)?.ok_or("直播订阅不存在")?;
+    let config = state.get_data()?.config;
+    let playlist = load_live_playlist(&source, refresh.unwrap_or(false), &config, &manager).await?;
+    Ok(manager.ordered(&playlist))
+}
+
+/// 播放器上报直播地址的可用性，失败的线路进入冷却，下次排在备用线路之后
+#[tauri::command]
+pub async fn report_live_url(
+    url: String,
+    success: bool,
+    latency_ms: Option<u64>,
+    manager: State<'_, LiveManager>,
+) -> Result<(), String> {
+    if success {
+        manager
+            .tracker
+            .record_success(&url, Duration::from_millis(latency_ms.unwrap_or(0)));
+    } else {
+        manager.tracker.record_failure(&url);
+    }
+    Ok(())
+}
+
+/// 频道各线路的健康状态
+#[tauri::command]
+pub async fn get_live_url_status(
+    urls: Vec<String>,
+    manager: State<'_, LiveManager>,
+) -> Result<Vec<MirrorStatus>, String> {
+    Ok(manager.tracker.status(&urls))
+}
+
+#[tauri::command]
+pub async fn get_live_favorites(db: State<'_, Db>) -> Result<Vec<LiveFavorite>, String> {
+    store::list_live_favorites(&db)
+}
+
+#[tauri::command]
+pub async fn toggle_live_favorite(
+    source_key: String,
+    channel_name: String,
+    group_name: Option<String>,
+    logo: Option<String>,
+    db: State<'_, Db>,
+) -> Result<TogglePlayFavoriteResponse, String> {
+    let favorited = store::toggle_live_favorite(
+        &db,
+        &source_key,
+        &channel_name,
+        group_name.as_deref().unwrap_or_default(),
+        logo.as_deref().unwrap_or_default(),
+    )?;
+    Ok(TogglePlayFavoriteResponse { favorited })
+}
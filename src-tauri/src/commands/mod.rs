@@ -4,13 +4,17 @@ pub mod config;
 pub mod content_analyzer;
 pub mod data_fusion;
 pub mod douban_client;
+pub mod download;
+pub mod epg;
 pub mod home;
+pub mod live;
 pub mod preload;
 pub mod recommendation;
 pub mod search;
 pub mod settings;
 pub mod skip;
 pub mod source_intelligence;
+pub mod subscription;
 pub mod version;
 pub mod version_check;
 pub mod video;
@@ -7,14 +7,20 @@ use quantumtv_core::search_aggregation::{
     aggregate_search_results_with_filter, apply_filter, compute_group_stats, sort_by_year,
     AggregatedGroup, SearchFilter,
 };
+use quantumtv_core::spell_correction::{SpellCorrector, SpellSuggestion};
 use quantumtv_core::types::SearchResult;
 use rusqlite::params;
 use serde::Serialize;
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, OnceLock};
 use std::time::{Duration, Instant};
 use tauri::State;
 
+/// 结果少于该数量时附带纠错建议
+const SPARSE_RESULT_THRESHOLD: usize = 3;
+/// 纠错建议的最大条数
+const MAX_SPELL_SUGGESTIONS: usize = 5;
+
 /// 搜索结果缓存条目
 struct CacheEntry {
     results: Vec<SearchResult>,
@@ -254,6 +260,13 @@ impl FilterResultCache {
         None
     }
 
+    /// 清除某个查询的全部过滤结果（原始结果被后台刷新时调用）
+    pub fn invalidate_query(&self, query: &str) {
+        let prefix = format!("{}|", query.trim().to_lowercase());
+        let mut cache = self.cache.lock().unwrap();
+        cache.retain(|key, _| !key.starts_with(&prefix));
+    }
+
     /// 清理过期条目
     fn cleanup_expired(&self, cache: &mut HashMap<String, FilterCacheEntry>) {
         let now = Instant::now();
@@ -290,6 +303,152 @@ pub fn get_search_suggestions(db: State<'_, Db>, query: String) -> Result<Vec<St
     })
 }
 
+/// 从内容池标题、搜索历史与豆瓣榜单缓存构建纠错语料
+///
+/// 各数据源读取失败时跳过，不影响搜索本身
+fn build_spell_corrector(db: &Db) -> SpellCorrector {
+    let mut corrector = SpellCorrector::new();
+
+    let pool_titles: Vec<(String, f64)> = db
+        .with_conn(|conn| {
+            let mut stmt = conn.prepare(
+                "SELECT title, COALESCE(popularity_score, 0) FROM content_pool
+                 ORDER BY popularity_score DESC LIMIT 5000",
+            )?;
+            let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            rows.collect::<Result<Vec<_>, _>>()
+        })
+        .unwrap_or_default();
+    for (title, popularity) in pool_titles {
+        corrector.add_term(&title, popularity);
+    }
+
+    let history: Vec<String> = db
+        .with_conn(|conn| {
+            let mut stmt = conn
+                .prepare("SELECT keyword FROM search_history ORDER BY save_time DESC LIMIT 500")?;
+            let rows = stmt.query_map([], |row| row.get(0))?;
+            rows.collect::<Result<Vec<_>, _>>()
+        })
+        .unwrap_or_default();
+    for keyword in history {
+        corrector.add_term(&keyword, 1.0);
+    }
+
+    // 豆瓣榜单以 JSON 形式缓存在 page_cache 中，提取其中的 title 字段
+    let cached_pages: Vec<String> = db
+        .with_conn(|conn| {
+            let mut stmt = conn.prepare("SELECT data FROM page_cache LIMIT 200")?;
+            let rows = stmt.query_map([], |row| row.get(0))?;
+            rows.collect::<Result<Vec<_>, _>>()
+        })
+        .unwrap_or_default();
+    for data in cached_pages {
+        if let Ok(value) = serde_json::from_str::<serde_json::Value>(&data) {
+            let mut titles = Vec::new();
+            collect_json_titles(&value, &mut titles);
+            for title in titles {
+                corrector.add_term(&title, 1.0);
+            }
+        }
+    }
+
+    corrector
+}
+
+/// 纠错语料的版本：各来源表的行数与最近更新时间，任一变化即重建纠错器
+type CorpusVersion = [i64; 6];
+
+fn corpus_version(db: &Db) -> Option<CorpusVersion> {
+    db.with_conn(|conn| {
+        conn.query_row(
+            "SELECT
+                (SELECT COUNT(*) FROM content_pool),
+                (SELECT COALESCE(MAX(last_updated), 0) FROM content_pool),
+                (SELECT COUNT(*) FROM search_history),
+                (SELECT COALESCE(MAX(save_time), 0) FROM search_history),
+                (SELECT COUNT(*) FROM page_cache),
+                (SELECT COALESCE(MAX(cached_at), 0) FROM page_cache)",
+            [],
+            |row| {
+                Ok([
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                    row.get(5)?,
+                ])
+            },
+        )
+    })
+    .ok()
+}
+
+/// 纠错器缓存：语料未变化时复用，避免每次搜索都重新读取语料并建索引
+#[derive(Default)]
+struct SpellCorrectorCache {
+    entry: Mutex<Option<(CorpusVersion, Arc<SpellCorrector>)>>,
+}
+
+impl SpellCorrectorCache {
+    fn get(&self, db: &Db) -> Arc<SpellCorrector> {
+        let Some(version) = corpus_version(db) else {
+            return Arc::new(build_spell_corrector(db));
+        };
+        let mut entry = self.entry.lock().unwrap();
+        if let Some((cached_version, corrector)) = entry.as_ref() {
+            if *cached_version == version {
+                return corrector.clone();
+            }
+        }
+        let corrector = Arc::new(build_spell_corrector(db));
+        *entry = Some((version, corrector.clone()));
+        corrector
+    }
+}
+
+fn spell_corrector(db: &Db) -> Arc<SpellCorrector> {
+    static CACHE: OnceLock<SpellCorrectorCache> = OnceLock::new();
+    CACHE.get_or_init(SpellCorrectorCache::default).get(db)
+}
+
+fn collect_json_titles(value: &serde_json::Value, titles: &mut Vec<String>) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, item) in map {
+                match item {
+                    serde_json::Value::String(text) if key == "title" => titles.push(text.clone()),
+                    _ => collect_json_titles(item, titles),
+                }
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for item in items {
+                collect_json_titles(item, titles);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// 结果为空或过少时给出纠错建议
+fn spell_suggestions_for(db: &Db, query: &str, result_count: usize) -> Vec<SpellSuggestion> {
+    if query.trim().is_empty() || result_count >= SPARSE_RESULT_THRESHOLD {
+        return Vec::new();
+    }
+    spell_corrector(db).suggest(query, MAX_SPELL_SUGGESTIONS)
+}
+
+/// 获取搜索词的纠错建议（"你是不是要找"）
+#[tauri::command]
+pub fn get_spell_suggestions(
+    db: State<'_, Db>,
+    query: String,
+) -> Result<Vec<SpellSuggestion>, String> {
+    Ok(spell_corrector(&db).suggest(&query, MAX_SPELL_SUGGESTIONS))
+}
+
 #[derive(Debug, Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct SearchFilterOption {
@@ -312,6 +471,7 @@ pub struct SearchPageStateResponse {
     pub filtered_results: Vec<SearchResult>,
     pub filter_categories_all: Vec<SearchFilterCategory>,
     pub filter_categories_agg: Vec<SearchFilterCategory>,
+    pub suggestions: Vec<SpellSuggestion>,
 }
 
 #[derive(Debug, Serialize)]
@@ -327,6 +487,9 @@ pub struct SearchPageQueryResponse {
     pub cache_hit: bool,
     pub filter_categories_all: Vec<SearchFilterCategory>,
     pub filter_categories_agg: Vec<SearchFilterCategory>,
+    pub suggestions: Vec<SpellSuggestion>,
+    /// 自动纠错后实际使用的搜索词
+    pub corrected_query: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -338,6 +501,9 @@ pub struct SearchPageOpenResponse {
     pub cache_hit: bool,
     pub filter_categories_all: Vec<SearchFilterCategory>,
     pub filter_categories_agg: Vec<SearchFilterCategory>,
+    pub suggestions: Vec<SpellSuggestion>,
+    /// 自动纠错后实际使用的搜索词
+    pub corrected_query: Option<String>,
 }
 
 fn build_filter_categories(results: &[SearchResult]) -> Vec<SearchFilterCategory> {
@@ -448,6 +614,7 @@ pub async fn build_search_page_state(
     normalized_query: Option<String>,
     filter_agg: SearchFilter,
     filter_all: SearchFilter,
+    db: State<'_, Db>,
 ) -> Result<SearchPageStateResponse, String> {
     let filter_categories = build_filter_categories(&results);
 
@@ -464,12 +631,14 @@ pub async fn build_search_page_state(
 
     let filtered = apply_filter(results, &filter_all);
     let filtered_results = sort_by_year(filtered, filter_all.year_order.clone());
+    let suggestions = spell_suggestions_for(&db, &query, aggregated_entries.len());
 
     Ok(SearchPageStateResponse {
         aggregated_entries,
         filtered_results,
         filter_categories_all: filter_categories.clone(),
         filter_categories_agg: filter_categories,
+        suggestions,
     })
 }
 
@@ -542,20 +711,64 @@ pub async fn get_search_page_bootstrap(
     Ok(build_search_bootstrap(search_history, preferences))
 }
 
+/// 结果为空时用排名第一的纠错建议重试一次
+///
+/// 返回 (结果, 是否命中缓存, 实际使用的纠错词)
+async fn retry_with_top_suggestion(
+    suggestions: &[SpellSuggestion],
+    app_handle: tauri::AppHandle,
+    storage: State<'_, StorageManager>,
+    cache: State<'_, SearchCacheManager>,
+    db: &Db,
+) -> Result<Option<(Vec<SearchResult>, bool, String)>, String> {
+    let Some(top) = suggestions.first() else {
+        return Ok(None);
+    };
+
+    let (results, cache_hit) =
+        search_with_cache_hit(top.text.clone(), app_handle, storage, cache, db).await?;
+    if results.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some((results, cache_hit, top.text.clone())))
+}
+
 #[tauri::command]
 pub async fn search_page_query(
     query: String,
+    auto_correct: Option<bool>,
     app_handle: tauri::AppHandle,
     storage: State<'_, StorageManager>,
     cache: State<'_, SearchCacheManager>,
     db: State<'_, Db>,
     result_cache: State<'_, SearchResultCache>,
 ) -> Result<SearchPageQueryResponse, String> {
-    let (results, cache_hit) =
-        search_with_cache_hit(query.clone(), app_handle, storage, cache, &db).await?;
+    let (mut results, mut cache_hit) = search_with_cache_hit(
+        query.clone(),
+        app_handle.clone(),
+        storage.clone(),
+        cache.clone(),
+        &db,
+    )
+    .await?;
+
+    let suggestions = spell_suggestions_for(&db, &query, results.len());
+    let mut corrected_query = None;
+    if results.is_empty() && auto_correct.unwrap_or(false) {
+        if let Some((retried, retried_hit, corrected)) =
+            retry_with_top_suggestion(&suggestions, app_handle, storage, cache, &db).await?
+        {
+            results = retried;
+            cache_hit = retried_hit;
+            corrected_query = Some(corrected);
+        }
+    }
 
-    // 保存搜索结果到缓存
-    result_cache.save(&query, results.clone());
+    // 保存搜索结果到缓存（纠错后以实际搜索词为键）
+    result_cache.save(
+        corrected_query.as_deref().unwrap_or(&query),
+        results.clone(),
+    );
 
     let filter_categories = build_filter_categories(&results);
 
@@ -564,12 +777,15 @@ pub async fn search_page_query(
         cache_hit,
         filter_categories_all: filter_categories.clone(),
         filter_categories_agg: filter_categories,
+        suggestions,
+        corrected_query,
     })
 }
 
 #[tauri::command]
 pub async fn search_page_open(
     query: Option<String>,
+    auto_correct: Option<bool>,
     db: State<'_, Db>,
     storage: State<'_, StorageManager>,
     app_handle: tauri::AppHandle,
@@ -580,15 +796,37 @@ pub async fn search_page_open(
     let preferences = get_user_preferences(storage.clone()).await?;
 
     let trimmed_query = query.unwrap_or_default().trim().to_string();
-    let (results, cache_hit) = if trimmed_query.is_empty() {
+    let (mut results, mut cache_hit) = if trimmed_query.is_empty() {
         (Vec::new(), false)
     } else {
-        search_with_cache_hit(trimmed_query.clone(), app_handle, storage, cache, &db).await?
+        search_with_cache_hit(
+            trimmed_query.clone(),
+            app_handle.clone(),
+            storage.clone(),
+            cache.clone(),
+            &db,
+        )
+        .await?
     };
 
-    // 保存搜索结果到缓存
+    let suggestions = spell_suggestions_for(&db, &trimmed_query, results.len());
+    let mut corrected_query = None;
+    if !trimmed_query.is_empty() && results.is_empty() && auto_correct.unwrap_or(false) {
+        if let Some((retried, retried_hit, corrected)) =
+            retry_with_top_suggestion(&suggestions, app_handle, storage, cache, &db).await?
+        {
+            results = retried;
+            cache_hit = retried_hit;
+            corrected_query = Some(corrected);
+        }
+    }
+
+    // 保存搜索结果到缓存（纠错后以实际搜索词为键）
     if !trimmed_query.is_empty() {
-        result_cache.save(&trimmed_query, results.clone());
+        result_cache.save(
+            corrected_query.as_deref().unwrap_or(&trimmed_query),
+            results.clone(),
+        );
     }
 
     let filter_categories = build_filter_categories(&results);
@@ -600,6 +838,8 @@ pub async fn search_page_open(
         cache_hit,
         filter_categories_all: filter_categories.clone(),
         filter_categories_agg: filter_categories,
+        suggestions,
+        corrected_query,
     })
 }
 
@@ -733,6 +973,28 @@ mod tests {
         assert!(retrieved.is_some());
     }
 
+    #[test]
+    fn filter_result_cache_invalidates_single_query() {
+        let cache = FilterResultCache::new();
+        let filter = SearchFilter {
+            source: "all".to_string(),
+            title: "all".to_string(),
+            year: "all".to_string(),
+            year_order: quantumtv_core::search_aggregation::YearOrder::None,
+        };
+        let response = ApplySearchFilterResponse {
+            aggregated_entries: vec![],
+            filtered_results: vec![],
+        };
+
+        cache.save("query", &filter, &filter, response.clone());
+        cache.save("query2", &filter, &filter, response);
+        cache.invalidate_query(" QUERY ");
+
+        assert!(cache.get("query", &filter, &filter).is_none());
+        assert!(cache.get("query2", &filter, &filter).is_some());
+    }
+
     #[test]
     fn filter_result_cache_different_filters_different_keys() {
         let cache = FilterResultCache::new();
@@ -1026,4 +1288,68 @@ mod tests {
         assert_eq!(stats.ttl_seconds, 30 * 60);
         assert_eq!(stats.expired_count, 0);
     }
+
+    fn setup_spell_db() -> Db {
+        let conn = rusqlite::Connection::open_in_memory().expect("open in-memory db");
+        conn.execute_batch(
+            r#"
+            CREATE TABLE content_pool (
+                title TEXT NOT NULL,
+                popularity_score REAL DEFAULT 0.0,
+                last_updated INTEGER NOT NULL DEFAULT 0
+            );
+            CREATE TABLE search_history (keyword TEXT PRIMARY KEY, save_time INTEGER NOT NULL);
+            CREATE TABLE page_cache (
+                page_key TEXT PRIMARY KEY,
+                data TEXT NOT NULL,
+                cached_at INTEGER NOT NULL DEFAULT 0
+            );
+            INSERT INTO content_pool (title, popularity_score) VALUES ('陈情令', 10.0);
+            INSERT INTO search_history (keyword, save_time) VALUES ('庆余年', 1);
+            INSERT INTO page_cache (page_key, data)
+                VALUES ('home:Mon', '{"hotMovies":[{"id":"1","title":"流浪地球"}]}');
+            "#,
+        )
+        .expect("init spell corpus");
+        Db::new(conn)
+    }
+
+    #[test]
+    fn spell_corrector_reads_all_corpus_sources() {
+        let db = setup_spell_db();
+        let corrector = build_spell_corrector(&db);
+
+        assert_eq!(corrector.len(), 3);
+        assert!(corrector.contains("流浪地球"));
+        assert_eq!(corrector.suggest("陈晴令", 3)[0].text, "陈情令");
+    }
+
+    #[test]
+    fn spell_suggestions_only_for_sparse_results() {
+        let db = setup_spell_db();
+
+        assert!(!spell_suggestions_for(&db, "陈晴令", 0).is_empty());
+        assert!(spell_suggestions_for(&db, "陈晴令", SPARSE_RESULT_THRESHOLD).is_empty());
+        assert!(spell_suggestions_for(&db, "  ", 0).is_empty());
+    }
+
+    #[test]
+    fn spell_corrector_is_cached_until_corpus_changes() {
+        let db = setup_spell_db();
+        let cache = SpellCorrectorCache::default();
+
+        let first = cache.get(&db);
+        assert!(Arc::ptr_eq(&first, &cache.get(&db)));
+
+        db.with_conn(|conn| {
+            conn.execute(
+                "INSERT INTO search_history (keyword, save_time) VALUES ('三体', 2)",
+                [],
+            )
+        })
+        .unwrap();
+        let rebuilt = cache.get(&db);
+        assert!(!Arc::ptr_eq(&first, &rebuilt));
+        assert!(rebuilt.contains("三体"));
+    }
 }
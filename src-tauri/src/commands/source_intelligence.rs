@@ -6,6 +6,45 @@ use std::sync::{Arc, Mutex};
 use tauri::State;
 
 const MAX_RECENT_RESULTS: usize = 20;
+/// 首次熔断的冷却时间，之后每次熔断翻倍
+const BASE_COOLDOWN_SECS: u64 = 30;
+/// 冷却时间上限
+const MAX_COOLDOWN_SECS: u64 = 30 * 60;
+/// 半开状态下允许同时进行的试探请求数
+const HALF_OPEN_MAX_TRIALS: u32 = 2;
+/// 半开试探迟迟没有结果时，超过该时长重新放行
+const HALF_OPEN_TRIAL_WINDOW_SECS: u64 = 60;
+
+/// 源熔断器状态
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    /// 正常放行
+    #[default]
+    Closed,
+    /// 熔断中，冷却结束前拒绝请求
+    Open,
+    /// 冷却结束，仅放行少量试探请求
+    HalfOpen,
+}
+
+impl CircuitState {
+    fn as_str(&self) -> &'static str {
+        match self {
+            CircuitState::Closed => "closed",
+            CircuitState::Open => "open",
+            CircuitState::HalfOpen => "half_open",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "open" => CircuitState::Open,
+            "half_open" => CircuitState::HalfOpen,
+            _ => CircuitState::Closed,
+        }
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct SourceTestResult {
@@ -29,9 +68,117 @@ pub struct SourceStats {
     pub last_available_time: Option<u64>,
     pub consecutive_failures: u32,
     pub auto_degraded: bool,
+    pub circuit_state: CircuitState,
+    /// 连续熔断次数，决定冷却时长
+    pub circuit_trips: u32,
+    /// 熔断冷却结束、允许试探的时间
+    pub next_retry_time: Option<u64>,
+    /// 本次运行中去除伪装前缀的分片数
+    pub unwrapped_segments: u64,
+    /// 本次运行中去除的伪装前缀字节数
+    pub unwrapped_bytes: u64,
     pub recent_results: Vec<SourceTestResult>,
 }
 
+/// 真实流量中的请求类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestKind {
+    Search,
+    Detail,
+    Category,
+}
+
+impl RequestKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RequestKind::Search => "search",
+            RequestKind::Detail => "detail",
+            RequestKind::Category => "category",
+        }
+    }
+}
+
+/// 请求失败分类
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RequestFailure {
+    Timeout,
+    HttpStatus(u16),
+    Network,
+    InvalidJson,
+    Empty,
+}
+
+impl RequestFailure {
+    pub fn from_reqwest(error: &reqwest::Error) -> Self {
+        if error.is_timeout() {
+            RequestFailure::Timeout
+        } else if let Some(status) = error.status() {
+            RequestFailure::HttpStatus(status.as_u16())
+        } else {
+            RequestFailure::Network
+        }
+    }
+
+    pub fn as_reason(&self) -> String {
+        match self {
+            RequestFailure::Timeout => "timeout".to_string(),
+            RequestFailure::HttpStatus(status) => format!("http_{}", status),
+            RequestFailure::Network => "network".to_string(),
+            RequestFailure::InvalidJson => "invalid_json".to_string(),
+            RequestFailure::Empty => "empty".to_string(),
+        }
+    }
+}
+
+/// 一次真实请求的遥测结果（被动测速）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestOutcome {
+    pub source_key: String,
+    pub kind: RequestKind,
+    pub latency_ms: u64,
+    pub failure: Option<RequestFailure>,
+}
+
+impl RequestOutcome {
+    pub fn success(source_key: &str, kind: RequestKind, latency_ms: u64) -> Self {
+        Self {
+            source_key: source_key.to_string(),
+            kind,
+            latency_ms,
+            failure: None,
+        }
+    }
+
+    pub fn failure(
+        source_key: &str,
+        kind: RequestKind,
+        latency_ms: u64,
+        failure: RequestFailure,
+    ) -> Self {
+        Self {
+            source_key: source_key.to_string(),
+            kind,
+            latency_ms,
+            failure: Some(failure),
+        }
+    }
+
+    /// 失败原因形如 "search:timeout"、"detail:http_503"
+    fn into_test_result(self) -> SourceTestResult {
+        let error_reason = self
+            .failure
+            .as_ref()
+            .map(|failure| format!("{}:{}", self.kind.as_str(), failure.as_reason()));
+        SourceTestResult {
+            source_key: self.source_key,
+            success: self.failure.is_none(),
+            response_time_ms: self.latency_ms,
+            error_reason,
+            timestamp: current_timestamp(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 struct SourcePerformance {
     total_tests: u64,
@@ -42,6 +189,17 @@ struct SourcePerformance {
     last_available_time: Option<u64>,
     consecutive_failures: u32,
     auto_degraded: bool,
+    circuit_state: CircuitState,
+    circuit_trips: u32,
+    next_retry_time: Option<u64>,
+    /// 半开状态下已放行、尚未返回结果的试探请求数（不持久化）
+    #[serde(skip)]
+    half_open_trials: u32,
+    /// 去伪装的分片数与前缀字节数，每个分片都会更新，只保存在内存中
+    #[serde(skip)]
+    unwrapped_segments: u64,
+    #[serde(skip)]
+    unwrapped_bytes: u64,
     recent_results: Vec<SourceTestResult>,
 }
 
@@ -50,9 +208,18 @@ impl SourcePerformance {
         Self::default()
     }
 
-    fn record_test(&mut self, mut result: SourceTestResult, max_consecutive_failures: u32) {
+    fn record_test(&mut self, result: SourceTestResult, max_consecutive_failures: u32) {
+        self.record_test_at(result, max_consecutive_failures, current_timestamp());
+    }
+
+    fn record_test_at(
+        &mut self,
+        mut result: SourceTestResult,
+        max_consecutive_failures: u32,
+        now: u64,
+    ) {
         if result.timestamp == 0 {
-            result.timestamp = current_timestamp();
+            result.timestamp = now;
         }
 
         self.total_tests += 1;
@@ -63,11 +230,18 @@ impl SourcePerformance {
             self.last_success_time = Some(result.timestamp);
             self.last_available_time = Some(result.timestamp);
             self.consecutive_failures = 0;
-            self.auto_degraded = false;
+            self.close_circuit();
         } else {
             self.last_failure_time = Some(result.timestamp);
             self.consecutive_failures += 1;
-            self.auto_degraded = self.consecutive_failures >= max_consecutive_failures;
+            match self.circuit_state {
+                // 半开试探失败立即重新熔断，冷却时间翻倍
+                CircuitState::HalfOpen => self.trip_circuit(now),
+                CircuitState::Closed if self.consecutive_failures >= max_consecutive_failures => {
+                    self.trip_circuit(now)
+                }
+                _ => {}
+            }
         }
 
         self.recent_results.push(result);
@@ -77,6 +251,60 @@ impl SourcePerformance {
         }
     }
 
+    fn trip_circuit(&mut self, now: u64) {
+        self.circuit_trips = self.circuit_trips.saturating_add(1);
+        let exponent = self.circuit_trips.saturating_sub(1).min(16);
+        let cooldown = (BASE_COOLDOWN_SECS << exponent).min(MAX_COOLDOWN_SECS);
+        self.circuit_state = CircuitState::Open;
+        self.next_retry_time = Some(now + cooldown);
+        self.half_open_trials = 0;
+        self.auto_degraded = true;
+    }
+
+    fn close_circuit(&mut self) {
+        self.circuit_state = CircuitState::Closed;
+        self.circuit_trips = 0;
+        self.next_retry_time = None;
+        self.half_open_trials = 0;
+        self.auto_degraded = false;
+    }
+
+    /// 熔断器当前是否会拒绝请求（不占用试探名额）
+    fn is_blocked_at(&self, now: u64) -> bool {
+        match self.circuit_state {
+            CircuitState::Closed => false,
+            CircuitState::Open => self.next_retry_time.is_some_and(|retry| now < retry),
+            CircuitState::HalfOpen => {
+                self.half_open_trials >= HALF_OPEN_MAX_TRIALS
+                    && self.next_retry_time.is_some_and(|retry| now < retry)
+            }
+        }
+    }
+
+    /// 申请一次请求名额：冷却结束后转为半开并放行有限的试探请求
+    fn try_acquire_at(&mut self, now: u64) -> bool {
+        if self.is_blocked_at(now) {
+            return false;
+        }
+        match self.circuit_state {
+            CircuitState::Closed => {}
+            CircuitState::Open => {
+                self.circuit_state = CircuitState::HalfOpen;
+                self.half_open_trials = 1;
+                self.next_retry_time = Some(now + HALF_OPEN_TRIAL_WINDOW_SECS);
+            }
+            CircuitState::HalfOpen => {
+                // 试探超时未返回时重新开放名额
+                if self.half_open_trials >= HALF_OPEN_MAX_TRIALS {
+                    self.half_open_trials = 0;
+                    self.next_retry_time = Some(now + HALF_OPEN_TRIAL_WINDOW_SECS);
+                }
+                self.half_open_trials += 1;
+            }
+        }
+        true
+    }
+
     fn success_rate(&self) -> f64 {
         if self.total_tests == 0 {
             return 0.0;
@@ -117,6 +345,11 @@ impl SourcePerformance {
             last_available_time: self.last_available_time,
             consecutive_failures: self.consecutive_failures,
             auto_degraded: self.auto_degraded,
+            circuit_state: self.circuit_state,
+            circuit_trips: self.circuit_trips,
+            next_retry_time: self.next_retry_time,
+            unwrapped_segments: self.unwrapped_segments,
+            unwrapped_bytes: self.unwrapped_bytes,
             recent_results: self.recent_results.clone(),
         }
     }
@@ -145,6 +378,7 @@ fn option_to_u64(value: Option<i64>) -> Result<Option<u64>, String> {
     value.map(to_u64).transpose()
 }
 
+#[derive(Clone)]
 pub struct SourceIntelligenceManager {
     performances: Arc<Mutex<HashMap<String, SourcePerformance>>>,
     max_consecutive_failures: u32,
@@ -170,6 +404,9 @@ impl SourceIntelligenceManager {
             u32,
             i32,
             String,
+            String,
+            u32,
+            Option<i64>,
         )> = db.with_conn(|conn| {
             let mut stmt = conn.prepare(
                 "SELECT
@@ -182,7 +419,10 @@ impl SourceIntelligenceManager {
                     last_available_time,
                     consecutive_failures,
                     auto_degraded,
-                    recent_results_json
+                    recent_results_json,
+                    circuit_state,
+                    circuit_trips,
+                    next_retry_time
                  FROM source_intelligence_stats",
             )?;
 
@@ -199,6 +439,9 @@ impl SourceIntelligenceManager {
                         row.get::<_, u32>(7)?,
                         row.get::<_, i32>(8)?,
                         row.get::<_, String>(9)?,
+                        row.get::<_, String>(10)?,
+                        row.get::<_, u32>(11)?,
+                        row.get::<_, Option<i64>>(12)?,
                     ))
                 })?
                 .collect::<Result<Vec<_>, _>>()?;
@@ -220,6 +463,9 @@ impl SourceIntelligenceManager {
             consecutive_failures,
             auto_degraded,
             recent_results_json,
+            circuit_state,
+            circuit_trips,
+            next_retry_time,
         ) in rows
         {
             let recent_results =
@@ -237,6 +483,12 @@ impl SourceIntelligenceManager {
                     last_available_time: option_to_u64(last_available_time)?,
                     consecutive_failures,
                     auto_degraded: auto_degraded != 0,
+                    circuit_state: CircuitState::parse(&circuit_state),
+                    circuit_trips,
+                    next_retry_time: option_to_u64(next_retry_time)?,
+                    half_open_trials: 0,
+                    unwrapped_segments: 0,
+                    unwrapped_bytes: 0,
                     recent_results,
                 },
             );
@@ -279,6 +531,14 @@ impl SourceIntelligenceManager {
         )
     }
 
+    /// 记录搜索、详情、分类等真实请求的结果，使排序与自动降级反映实际使用情况
+    pub fn record_request_outcome(&self, db: &Db, outcome: RequestOutcome) -> Result<(), String> {
+        if outcome.source_key.trim().is_empty() {
+            return Ok(());
+        }
+        self.record_test_result_persisted(db, outcome.into_test_result())
+    }
+
     pub fn ensure_source_persisted(&self, db: &Db, source_key: String) -> Result<(), String> {
         let snapshot = {
             let mut performances = self.performances.lock().unwrap();
@@ -333,6 +593,7 @@ impl SourceIntelligenceManager {
         let last_success_time = option_to_i64(perf.last_success_time)?;
         let last_failure_time = option_to_i64(perf.last_failure_time)?;
         let last_available_time = option_to_i64(perf.last_available_time)?;
+        let next_retry_time = option_to_i64(perf.next_retry_time)?;
         let updated_at = to_i64(current_timestamp())?;
 
         db.with_conn(|conn| {
@@ -348,8 +609,11 @@ impl SourceIntelligenceManager {
                     consecutive_failures,
                     auto_degraded,
                     recent_results_json,
+                    circuit_state,
+                    circuit_trips,
+                    next_retry_time,
                     updated_at
-                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)
                 ON CONFLICT(source_key) DO UPDATE SET
                     total_tests = excluded.total_tests,
                     successful_tests = excluded.successful_tests,
@@ -360,6 +624,9 @@ impl SourceIntelligenceManager {
                     consecutive_failures = excluded.consecutive_failures,
                     auto_degraded = excluded.auto_degraded,
                     recent_results_json = excluded.recent_results_json,
+                    circuit_state = excluded.circuit_state,
+                    circuit_trips = excluded.circuit_trips,
+                    next_retry_time = excluded.next_retry_time,
                     updated_at = excluded.updated_at",
                 params![
                     source_key,
@@ -372,6 +639,9 @@ impl SourceIntelligenceManager {
                     perf.consecutive_failures,
                     if perf.auto_degraded { 1 } else { 0 },
                     recent_results_json,
+                    perf.circuit_state.as_str(),
+                    perf.circuit_trips,
+                    next_retry_time,
                     updated_at,
                 ],
             )?;
@@ -379,6 +649,14 @@ impl SourceIntelligenceManager {
         })
     }
 
+    /// 记录该源的一个伪装分片及去掉的前缀字节数
+    pub fn record_segment_unwrap(&self, source_key: &str, stripped_bytes: usize) {
+        let mut performances = self.performances.lock().unwrap();
+        let perf = performances.entry(source_key.to_string()).or_default();
+        perf.unwrapped_segments += 1;
+        perf.unwrapped_bytes += stripped_bytes as u64;
+    }
+
     pub fn get_all_stats(&self) -> Vec<SourceStats> {
         let performances = self.performances.lock().unwrap();
         let mut stats: Vec<SourceStats> = performances
@@ -456,14 +734,24 @@ impl SourceIntelligenceManager {
             .collect()
     }
 
+    /// 熔断冷却中或半开试探名额已满时跳过该源
     pub fn should_skip_source(&self, source_key: &str) -> bool {
         let performances = self.performances.lock().unwrap();
         performances
             .get(source_key)
-            .map(|perf| perf.auto_degraded)
+            .map(|perf| perf.is_blocked_at(current_timestamp()))
             .unwrap_or(false)
     }
 
+    /// 发起真实请求前申请熔断器名额，冷却结束后放行有限的试探请求
+    pub fn try_acquire(&self, source_key: &str) -> bool {
+        let mut performances = self.performances.lock().unwrap();
+        performances
+            .get_mut(source_key)
+            .map(|perf| perf.try_acquire_at(current_timestamp()))
+            .unwrap_or(true)
+    }
+
     pub fn has_stats(&self, source_key: &str) -> bool {
         let performances = self.performances.lock().unwrap();
         performances
@@ -508,7 +796,7 @@ impl SourceIntelligenceManager {
         let mut performances = self.performances.lock().unwrap();
         if let Some(perf) = performances.get_mut(source_key) {
             perf.consecutive_failures = 0;
-            perf.auto_degraded = false;
+            perf.close_circuit();
         }
     }
 }
@@ -588,6 +876,9 @@ mod tests {
                 consecutive_failures INTEGER NOT NULL DEFAULT 0,
                 auto_degraded INTEGER NOT NULL DEFAULT 0,
                 recent_results_json TEXT NOT NULL DEFAULT '[]',
+                circuit_state TEXT NOT NULL DEFAULT 'closed',
+                circuit_trips INTEGER NOT NULL DEFAULT 0,
+                next_retry_time INTEGER,
                 updated_at INTEGER NOT NULL
             );
             "#,
@@ -826,6 +1117,19 @@ mod tests {
         assert_eq!(stats.recent_results.len(), 3);
     }
 
+    #[test]
+    fn test_segment_unwraps_are_counted_per_source() {
+        let manager = SourceIntelligenceManager::new();
+        manager.record_test_result(make_result("source1", true, 100, 100));
+        manager.record_segment_unwrap("source1", 120);
+        manager.record_segment_unwrap("source1", 80);
+
+        let stats = manager.get_source_stats("source1").unwrap();
+        assert_eq!(stats.unwrapped_segments, 2);
+        assert_eq!(stats.unwrapped_bytes, 200);
+        assert_eq!(stats.total_tests, 1);
+    }
+
     #[test]
     fn test_persist_and_load_source_stats() {
         let db = setup_test_db();
@@ -890,4 +1194,156 @@ mod tests {
         assert_eq!(stats.success_rate, 0.0);
         assert!(!stats.auto_degraded);
     }
+
+    #[test]
+    fn test_record_request_outcome_classifies_failures() {
+        let db = setup_test_db();
+        seed_source(&db, "source1");
+        let manager = SourceIntelligenceManager::new();
+
+        manager
+            .record_request_outcome(
+                &db,
+                RequestOutcome::success("source1", RequestKind::Search, 300),
+            )
+            .unwrap();
+        manager
+            .record_request_outcome(
+                &db,
+                RequestOutcome::failure(
+                    "source1",
+                    RequestKind::Detail,
+                    8000,
+                    RequestFailure::Timeout,
+                ),
+            )
+            .unwrap();
+        manager
+            .record_request_outcome(
+                &db,
+                RequestOutcome::failure(
+                    "source1",
+                    RequestKind::Category,
+                    120,
+                    RequestFailure::HttpStatus(503),
+                ),
+            )
+            .unwrap();
+
+        let stats = manager.get_source_stats("source1").unwrap();
+        assert_eq!(stats.total_tests, 3);
+        assert_eq!(stats.avg_response_time_ms, 300);
+        assert_eq!(stats.consecutive_failures, 2);
+        let reasons: Vec<Option<String>> = stats
+            .recent_results
+            .iter()
+            .map(|item| item.error_reason.clone())
+            .collect();
+        assert_eq!(
+            reasons,
+            vec![
+                None,
+                Some("detail:timeout".to_string()),
+                Some("category:http_503".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_request_failures_trigger_auto_degrade() {
+        let db = setup_test_db();
+        seed_source(&db, "source1");
+        let manager = SourceIntelligenceManager::new();
+
+        for failure in [
+            RequestFailure::InvalidJson,
+            RequestFailure::Empty,
+            RequestFailure::Network,
+        ] {
+            manager
+                .record_request_outcome(
+                    &db,
+                    RequestOutcome::failure("source1", RequestKind::Search, 50, failure),
+                )
+                .unwrap();
+        }
+
+        assert!(manager.should_skip_source("source1"));
+    }
+
+    #[test]
+    fn test_circuit_opens_with_growing_cooldown() {
+        let mut perf = SourcePerformance::new();
+        for i in 0..3 {
+            perf.record_test_at(make_result("source1", false, 0, 100 + i), 3, 1000);
+        }
+        assert_eq!(perf.circuit_state, CircuitState::Open);
+        assert_eq!(perf.next_retry_time, Some(1000 + BASE_COOLDOWN_SECS));
+        assert!(perf.is_blocked_at(1010));
+        assert!(!perf.try_acquire_at(1010));
+
+        // 冷却结束后进入半开，试探失败立即再次熔断且冷却翻倍
+        assert!(perf.try_acquire_at(1030));
+        assert_eq!(perf.circuit_state, CircuitState::HalfOpen);
+        perf.record_test_at(make_result("source1", false, 0, 1031), 3, 1031);
+        assert_eq!(perf.circuit_state, CircuitState::Open);
+        assert_eq!(perf.circuit_trips, 2);
+        assert_eq!(perf.next_retry_time, Some(1031 + BASE_COOLDOWN_SECS * 2));
+    }
+
+    #[test]
+    fn test_circuit_half_open_limits_trials_and_closes_on_success() {
+        let mut perf = SourcePerformance::new();
+        for i in 0..3 {
+            perf.record_test_at(make_result("source1", false, 0, 100 + i), 3, 1000);
+        }
+
+        let now = 1000 + BASE_COOLDOWN_SECS;
+        for _ in 0..HALF_OPEN_MAX_TRIALS {
+            assert!(perf.try_acquire_at(now));
+        }
+        assert!(!perf.try_acquire_at(now + 1));
+        // 试探迟迟没有结果时重新放行
+        assert!(perf.try_acquire_at(now + HALF_OPEN_TRIAL_WINDOW_SECS));
+
+        perf.record_test_at(make_result("source1", true, 100, now + 70), 3, now + 70);
+        assert_eq!(perf.circuit_state, CircuitState::Closed);
+        assert_eq!(perf.circuit_trips, 0);
+        assert_eq!(perf.next_retry_time, None);
+        assert!(!perf.auto_degraded);
+    }
+
+    #[test]
+    fn test_cooldown_is_capped() {
+        let mut perf = SourcePerformance::new();
+        for trip in 0..20 {
+            perf.trip_circuit(trip);
+        }
+        assert_eq!(perf.next_retry_time, Some(19 + MAX_COOLDOWN_SECS));
+    }
+
+    #[test]
+    fn test_circuit_state_persisted_and_exposed() {
+        let db = setup_test_db();
+        seed_source(&db, "source1");
+        let manager = SourceIntelligenceManager::new();
+        for i in 0..3 {
+            manager
+                .record_test_result_persisted(&db, make_result("source1", false, 0, 100 + i))
+                .unwrap();
+        }
+
+        let reloaded = SourceIntelligenceManager::new();
+        reloaded.load_from_db(&db).unwrap();
+        let stats = reloaded
+            .get_all_stats()
+            .into_iter()
+            .find(|item| item.source_key == "source1")
+            .unwrap();
+        assert_eq!(stats.circuit_state, CircuitState::Open);
+        assert_eq!(stats.circuit_trips, 1);
+        assert!(stats.next_retry_time.is_some());
+        assert!(reloaded.should_skip_source("source1"));
+        assert!(!reloaded.try_acquire("source1"));
+    }
 }
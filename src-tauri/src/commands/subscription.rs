@@ -0,0 +1,352 @@
+// 多订阅：管理订阅列表，拉取各订阅配置后按优先级合并为视频源
+use crate::commands::config::{
+    fetch_subscription_text, load_source_config_values, merge_config_with_sources,
+    persist_source_config_values, strip_source_config, sync_source_intelligence_cache,
+    validate_subscription_json,
+};
+use crate::commands::source_intelligence::SourceIntelligenceManager;
+use crate::db::db_client::Db;
+use crate::db::subscription as cache;
+use crate::storage::StorageManager;
+use quantumtv_core::{
+    apply_subscription_sources, merge_admin_config_with_defaults, merge_subscription_sources,
+    parse_admin_config as parse_admin_config_core, subscriptions_from_config, Subscription,
+    LEGACY_SUBSCRIPTION_KEY,
+};
+use serde_json::Value;
+use std::cmp::Reverse;
+use std::collections::HashSet;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::State;
+
+fn current_time_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as i64
+}
+
+pub(crate) fn has_subscription_list(config: &Value) -> bool {
+    config.get("Subscriptions").is_some_and(Value::is_array)
+}
+
+/// 读取订阅列表；尚未迁移的旧版单订阅沿用 ConfigFile 作为其缓存
+pub(crate) fn load_subscriptions(
+    storage: &StorageManager,
+    db: &Db,
+) -> Result<Vec<Subscription>, String> {
+    let config = storage.get_data()?.config;
+    let subscriptions = subscriptions_from_config(&config);
+    if !has_subscription_list(&config) {
+        let config_file = config
+            .get("ConfigFile")
+            .and_then(|v| v.as_str())
+            .unwrap_or("");
+        if let Some(legacy) = subscriptions.first() {
+            if !config_file.trim().is_empty()
+                && !cache::load_subscription_cache(db)?.contains_key(&legacy.key)
+            {
+                cache::save_subscription_cache(db, &legacy.key, config_file)?;
+            }
+        }
+    }
+    Ok(subscriptions)
+}
+
+/// 拉取订阅配置并写入缓存
+async fn fetch_into_cache(db: &Db, subscription: &Subscription) -> Result<(), String> {
+    let text = fetch_subscription_text(subscription.url.trim()).await?;
+    validate_subscription_json(&text)?;
+    parse_admin_config_core(&text)?;
+    cache::save_subscription_cache(db, &subscription.key, &text)
+}
+
+/// 分类按名称与查询词去重，优先级高的订阅在前
+fn merge_categories(sets: &[(&Subscription, Vec<Value>)]) -> Vec<Value> {
+    let mut seen = HashSet::new();
+    let mut merged = Vec::new();
+    for (_, categories) in sets {
+        for category in categories {
+            let identity = (
+                category.get("name").and_then(|v| v.as_str()).unwrap_or(""),
+                category.get("query").and_then(|v| v.as_str()).unwrap_or(""),
+            );
+            if seen.insert(identity) {
+                merged.push(category.clone());
+            }
+        }
+    }
+    merged
+}
+
+/// 由缓存的各订阅配置重新合并视频源，保存订阅列表并返回完整配置
+///
+/// 手动添加的源保持不变；ConfigFile 取优先级最高的已启用订阅，
+/// 各订阅提供分类时合并替换 CustomCategories
+pub(crate) fn rebuild_subscription_sources(
+    storage: &StorageManager,
+    db: &Db,
+    source_manager: &SourceIntelligenceManager,
+    subscriptions: &[Subscription],
+) -> Result<Value, String> {
+    let cached = cache::load_subscription_cache(db)?;
+    let mut enabled: Vec<&Subscription> = subscriptions.iter().filter(|s| s.enabled).collect();
+    enabled.sort_by_key(|s| Reverse(s.priority));
+
+    let mut config_file = None;
+    let mut source_sets = Vec::new();
+    let mut category_sets = Vec::new();
+    for subscription in enabled {
+        let Some(raw) = cached.get(&subscription.key) else {
+            continue;
+        };
+        let parsed = match parse_admin_config_core(raw) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                log::warn!("[订阅] {} 配置解析失败: {}", subscription.name, e);
+                continue;
+            }
+        };
+        let field = |name: &str| {
+            parsed
+                .get(name)
+                .and_then(|v| v.as_array())
+                .cloned()
+                .unwrap_or_default()
+        };
+        config_file.get_or_insert_with(|| raw.clone());
+        source_sets.push((subscription, field("SourceConfig")));
+        category_sets.push((subscription, field("CustomCategories")));
+    }
+
+    let merged = merge_subscription_sources(source_sets);
+    let sources = apply_subscription_sources(&load_source_config_values(db)?, merged);
+    let categories = merge_categories(&category_sets);
+
+    let data = storage.get_data()?;
+    let mut config = merge_admin_config_with_defaults(&data.config);
+    if let Some(obj) = config.as_object_mut() {
+        obj.insert(
+            "Subscriptions".to_string(),
+            serde_json::to_value(subscriptions).map_err(|e| e.to_string())?,
+        );
+        if let Some(config_file) = config_file {
+            obj.insert("ConfigFile".to_string(), Value::String(config_file));
+        }
+        if !categories.is_empty() {
+            obj.insert("CustomCategories".to_string(), Value::Array(categories));
+        }
+    }
+
+    persist_source_config_values(db, &sources)?;
+    let stripped = strip_source_config(&config);
+    storage.update_config(stripped.clone())?;
+    sync_source_intelligence_cache(source_manager, db)?;
+    Ok(merge_config_with_sources(
+        &stripped,
+        load_source_config_values(db)?,
+    ))
+}
+
+/// 旧版单订阅设置（ConfigSubscribtion）同步到订阅列表中的默认订阅并重新合并
+///
+/// `raw_json` 为新拉取或手动编辑的配置，写入默认订阅的缓存
+pub(crate) fn sync_legacy_subscription(
+    storage: &StorageManager,
+    db: &Db,
+    source_manager: &SourceIntelligenceManager,
+    raw_json: Option<&str>,
+) -> Result<Value, String> {
+    let config = storage.get_data()?.config;
+    let legacy = config.get("ConfigSubscribtion").unwrap_or(&Value::Null);
+    let url = legacy
+        .get("URL")
+        .and_then(|v| v.as_str())
+        .unwrap_or("")
+        .trim();
+    let auto_update = legacy
+        .get("AutoUpdate")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    let mut subscriptions = load_subscriptions(storage, db)?;
+    if !subscriptions
+        .iter()
+        .any(|s| s.key == LEGACY_SUBSCRIPTION_KEY)
+    {
+        if url.is_empty() && raw_json.is_none() {
+            return rebuild_subscription_sources(storage, db, source_manager, &subscriptions);
+        }
+        subscriptions.push(Subscription {
+            key: LEGACY_SUBSCRIPTION_KEY.to_string(),
+            name: "默认订阅".to_string(),
+            url: url.to_string(),
+            priority: 0,
+            enabled: true,
+            update_interval_hours: 0,
+            last_update: 0,
+        });
+    }
+    let subscription = subscriptions
+        .iter_mut()
+        .find(|s| s.key == LEGACY_SUBSCRIPTION_KEY)
+        .ok_or_else(|| "默认订阅不存在".to_string())?;
+    if !url.is_empty() && subscription.url != url {
+        subscription.url = url.to_string();
+        if raw_json.is_none() {
+            cache::delete_subscription_cache(db, &subscription.key)?;
+            subscription.last_update = 0;
+        }
+    }
+    subscription.update_interval_hours = match (auto_update, subscription.update_interval_hours) {
+        (false, _) => 0,
+        (true, 0) => 24,
+        (true, hours) => hours,
+    };
+    if let Some(raw_json) = raw_json {
+        cache::save_subscription_cache(db, &subscription.key, raw_json)?;
+        subscription.last_update = current_time_ms();
+    }
+    rebuild_subscription_sources(storage, db, source_manager, &subscriptions)
+}
+
+/// 拉取指定订阅并重新合并；全部拉取失败时返回错误且不改动配置
+pub(crate) async fn update_subscriptions(
+    storage: &StorageManager,
+    db: &Db,
+    source_manager: &SourceIntelligenceManager,
+    keys: &[String],
+) -> Result<Value, String> {
+    let mut subscriptions = load_subscriptions(storage, db)?;
+    let mut errors = Vec::new();
+    let mut updated = 0;
+    for subscription in subscriptions
+        .iter_mut()
+        .filter(|s| s.enabled && keys.contains(&s.key))
+    {
+        match fetch_into_cache(db, subscription).await {
+            Ok(()) => {
+                subscription.last_update = current_time_ms();
+                updated += 1;
+            }
+            Err(e) => {
+                log::warn!("[订阅] {} 更新失败: {}", subscription.name, e);
+                errors.push(format!("{}: {}", subscription.name, e));
+            }
+        }
+    }
+    if updated == 0 && !errors.is_empty() {
+        return Err(errors.join("; "));
+    }
+    rebuild_subscription_sources(storage, db, source_manager, &subscriptions)
+}
+
+/// 更新已到自动更新时间的订阅，返回是否有订阅被更新；未使用订阅列表时返回 None
+pub(crate) async fn update_due_subscriptions(
+    storage: &StorageManager,
+    db: &Db,
+    source_manager: &SourceIntelligenceManager,
+) -> Result<Option<bool>, String> {
+    if !has_subscription_list(&storage.get_data()?.config) {
+        return Ok(None);
+    }
+    let now = current_time_ms();
+    let due: Vec<String> = load_subscriptions(storage, db)?
+        .into_iter()
+        .filter(|s| s.is_due(now))
+        .map(|s| s.key)
+        .collect();
+    if due.is_empty() {
+        return Ok(Some(false));
+    }
+    update_subscriptions(storage, db, source_manager, &due).await?;
+    Ok(Some(true))
+}
+
+/// 获取订阅列表（旧版单订阅会作为默认订阅返回）
+#[tauri::command]
+pub async fn get_subscriptions(
+    state: State<'_, StorageManager>,
+    db: State<'_, Db>,
+) -> Result<Vec<Subscription>, String> {
+    load_subscriptions(&state, &db)
+}
+
+/// 新增或更新订阅；新订阅、地址变更或尚无缓存时立即拉取
+#[tauri::command]
+pub async fn save_subscription(
+    subscription: Subscription,
+    state: State<'_, StorageManager>,
+    db: State<'_, Db>,
+    source_manager: State<'_, SourceIntelligenceManager>,
+) -> Result<Value, String> {
+    let name = subscription.name.trim();
+    let url = subscription.url.trim();
+    if name.is_empty() || url.is_empty() {
+        return Err("请填写订阅名称和订阅URL".to_string());
+    }
+    let mut subscriptions = load_subscriptions(&state, &db)?;
+    let key = match subscription.key.trim() {
+        "" => uuid::Uuid::new_v4().simple().to_string(),
+        key => key.to_string(),
+    };
+    let existing = subscriptions.iter().position(|s| s.key == key);
+    let mut subscription = Subscription {
+        key,
+        name: name.to_string(),
+        url: url.to_string(),
+        last_update: existing
+            .map(|index| subscriptions[index].last_update)
+            .unwrap_or(0),
+        ..subscription
+    };
+
+    let url_changed = existing.is_none_or(|index| subscriptions[index].url != subscription.url);
+    let needs_fetch =
+        url_changed || !cache::load_subscription_cache(&db)?.contains_key(&subscription.key);
+    if subscription.enabled && needs_fetch {
+        fetch_into_cache(&db, &subscription).await?;
+        subscription.last_update = current_time_ms();
+    } else if url_changed {
+        cache::delete_subscription_cache(&db, &subscription.key)?;
+        subscription.last_update = 0;
+    }
+
+    match existing {
+        Some(index) => subscriptions[index] = subscription,
+        None => subscriptions.push(subscription),
+    }
+    rebuild_subscription_sources(&state, &db, &source_manager, &subscriptions)
+}
+
+/// 删除订阅及其提供的视频源
+#[tauri::command]
+pub async fn delete_subscription(
+    key: String,
+    state: State<'_, StorageManager>,
+    db: State<'_, Db>,
+    source_manager: State<'_, SourceIntelligenceManager>,
+) -> Result<Value, String> {
+    let mut subscriptions = load_subscriptions(&state, &db)?;
+    subscriptions.retain(|s| s.key != key);
+    cache::delete_subscription_cache(&db, &key)?;
+    rebuild_subscription_sources(&state, &db, &source_manager, &subscriptions)
+}
+
+/// 立即更新订阅，`keys` 为空时更新全部已启用的订阅
+#[tauri::command]
+pub async fn refresh_subscriptions(
+    keys: Option<Vec<String>>,
+    state: State<'_, StorageManager>,
+    db: State<'_, Db>,
+    source_manager: State<'_, SourceIntelligenceManager>,
+) -> Result<Value, String> {
+    let keys = match keys.filter(|keys| !keys.is_empty()) {
+        Some(keys) => keys,
+        None => load_subscriptions(&state, &db)?
+            .into_iter()
+            .map(|s| s.key)
+            .collect(),
+    };
+    update_subscriptions(&state, &db, &source_manager, &keys).await
+}
@@ -1,3 +1,4 @@
+use quantumtv_core::{ProxyCategory, DEFAULT_USER_AGENT};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
@@ -40,7 +41,7 @@ async fn fetch_latest_github_release(client: &Client) -> Option<String> {
     for url in urls {
         let resp = client
             .get(url)
-            .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/122.0.0.0 Safari/537.36")
+            .header("User-Agent", DEFAULT_USER_AGENT)
             .header("Accept", "application/json")
             .timeout(Duration::from_secs(3))
             .send()
@@ -127,7 +128,7 @@ pub fn get_current_version() -> String {
 pub async fn version_for_updates() -> Result<Option<RemoteVersionInfo>, ()> {
     let local_ts = get_build_timestamp();
 
-    let client = Client::builder()
+    let client = crate::network::client_builder(ProxyCategory::Metadata)
         .timeout(Duration::from_secs(5))
         .build()
         .map_err(|_| ())?;
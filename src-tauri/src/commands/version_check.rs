@@ -1,3 +1,4 @@
+use quantumtv_core::ProxyCategory;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
@@ -92,7 +93,7 @@ async fn fetch_remote_timestamp(client: &Client) -> Option<String> {
 // 主版本检测函数
 #[tauri::command]
 pub async fn check_for_updates() -> VersionCheckResult {
-    let client = Client::builder()
+    let client = crate::network::client_builder(ProxyCategory::Metadata)
         .user_agent("QuantumTV-VersionCheck")
         .build()
         .unwrap();
@@ -1,13 +1,21 @@
 use crate::commands::config::get_config_with_db_sources;
 use crate::commands::recommendation::{invalidate_recommendation_cache, RecommendationEngine};
-use crate::commands::source_intelligence::SourceIntelligenceManager;
+use crate::commands::source_intelligence::{
+    RequestFailure, RequestKind, RequestOutcome, SourceIntelligenceManager,
+};
+use crate::db::search_result_cache::{load_cached_search, save_cached_search, source_fingerprint};
 use crate::storage::StorageManager;
 use image::{GenericImageView, ImageOutputFormat};
 use moka::future::Cache;
 use quantumtv_core::playback::{SkipAction, SkipDetection};
+use quantumtv_core::search_aggregation::diff_search_results;
 use quantumtv_core::types::SearchResult;
 use quantumtv_core::{
-    prefer_best_source, test_video_source, SourceTestResult as CoreSourceTestResult,
+    apply_request_defaults, check_remote_url, prefer_best_source, source_endpoints, source_has_key,
+    test_video_source, unwrap_disguised_segment, validate_remote_url, BufferMode, HostOverrides,
+    MediaProfileRegistry, MirrorStatus, MirrorStrategy, MirrorTracker, OutboundScheduler,
+    ProxyCategory, RequestPolicy, RequestProfile, SegmentPrefetcher,
+    SourceTestResult as CoreSourceTestResult, DEFAULT_USER_AGENT,
 };
 use regex::Regex;
 use reqwest::header::{
@@ -17,12 +25,10 @@ use rusqlite::params;
 use scraper::{Html, Selector};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::io::Cursor;
-use std::net::IpAddr;
 use std::sync::{Arc, OnceLock};
 use tauri::{Emitter, Manager, State};
-use tokio::sync::Semaphore;
 use tokio::time::{timeout, Duration};
 use url::Url;
 use uuid::Uuid;
@@ -36,7 +42,6 @@ pub struct CacheStats {
 
 pub struct VideoCacheManager {
     pub cache: Cache<String, Vec<u8>>,
-    pub semaphore: Arc<Semaphore>, // 并发控制
 }
 
 impl VideoCacheManager {
@@ -50,10 +55,7 @@ impl VideoCacheManager {
             .time_to_live(std::time::Duration::from_secs(1200))
             .build();
 
-        // 并发限制：最多同时下载30个片段
-        let semaphore = Arc::new(Semaphore::new(30));
-
-        Self { cache, semaphore }
+        Self { cache }
     }
 
     pub async fn get(&self, url: &str) -> Option<Vec<u8>> {
@@ -79,6 +81,10 @@ impl VideoCacheManager {
 
 pub struct SearchCacheManager {
     pub cache: Cache<String, Vec<SearchResult>>,
+    /// 当前启用源集合的指纹，变化时清空内存缓存
+    source_fingerprint: std::sync::Mutex<String>,
+    /// 正在后台刷新的查询，避免同一查询重复刷新
+    revalidating: std::sync::Mutex<HashSet<String>>,
 }
 
 impl SearchCacheManager {
@@ -88,7 +94,11 @@ impl SearchCacheManager {
             .max_capacity(1000)
             .time_to_live(std::time::Duration::from_secs(3600))
             .build();
-        Self { cache }
+        Self {
+            cache,
+            source_fingerprint: std::sync::Mutex::new(String::new()),
+            revalidating: std::sync::Mutex::new(HashSet::new()),
+        }
     }
 
     pub async fn get(&self, query: &str) -> Option<Vec<SearchResult>> {
@@ -106,6 +116,33 @@ impl SearchCacheManager {
         self.cache.insert(key, results).await;
     }
 
+    /// 启用源集合变化时清空内存缓存
+    pub fn sync_source_fingerprint(&self, fingerprint: &str) {
+        let mut current = self.source_fingerprint.lock().unwrap();
+        if *current != fingerprint {
+            if !current.is_empty() {
+                log::debug!("搜索源集合已变化，清空搜索缓存");
+                self.cache.invalidate_all();
+            }
+            *current = fingerprint.to_string();
+        }
+    }
+
+    /// 标记查询开始后台刷新，已在刷新中时返回 false
+    fn begin_revalidate(&self, query: &str) -> bool {
+        self.revalidating
+            .lock()
+            .unwrap()
+            .insert(Self::normalize_key(query))
+    }
+
+    fn end_revalidate(&self, query: &str) {
+        self.revalidating
+            .lock()
+            .unwrap()
+            .remove(&Self::normalize_key(query));
+    }
+
     fn normalize_key(query: &str) -> String {
         query.trim().to_lowercase()
     }
@@ -462,8 +499,8 @@ async fn select_best_source_from_search(
     } else if has_source_intelligence(&candidates, source_manager) {
         candidates[0].clone()
     } else {
-        let client = get_video_client();
-        let (best, tests) = prefer_best_source(client, candidates.clone()).await?;
+        let client = get_video_client(ProxyCategory::Media);
+        let (best, tests) = prefer_best_source(&client, candidates.clone()).await?;
         persist_source_test_results(source_manager, db, &candidates, &tests);
         best
     };
@@ -493,8 +530,8 @@ async fn probe_and_persist_source_health(
         .or_else(|| detail.episodes.get(0));
 
     if let Some(url) = probe_url {
-        let client = get_video_client();
-        match test_video_source(client, url).await {
+        let client = get_video_client(ProxyCategory::Media);
+        match test_video_source(&client, url).await {
             Ok(result) => {
                 let _ = manager.record_runtime_test_result_persisted(
                     db,
@@ -663,6 +700,29 @@ pub struct ApiSite {
     pub name: String,
     pub detail: Option<String>,
     pub is_adult: Option<bool>,
+    /// 源级别的出站请求策略（限速、并发、超时）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_policy: Option<RequestPolicy>,
+    /// 与 `api` 等价的镜像接口地址，按配置顺序排列
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mirrors: Vec<String>,
+    #[serde(default)]
+    pub mirror_strategy: MirrorStrategy,
+    /// 源级别的请求头（User-Agent、Referer、Cookie 等）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_profile: Option<RequestProfile>,
+    /// 源级别的静态解析（域名 → IP），用于绕过被污染的 DNS
+    #[serde(default, skip_serializing_if = "HostOverrides::is_empty")]
+    pub hosts: HostOverrides,
+}
+
+impl ApiSite {
+    /// 全部接口地址，主地址在前
+    pub fn endpoints(&self) -> Vec<String> {
+        std::iter::once(self.api.clone())
+            .chain(self.mirrors.iter().cloned())
+            .collect()
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -745,66 +805,30 @@ fn allow_lan_sources_from_config(config: &Value) -> bool {
         .unwrap_or(false)
 }
 
-fn is_local_hostname(host: &str) -> bool {
-    let normalized = host.trim().trim_end_matches('.').to_ascii_lowercase();
-    normalized == "localhost"
-        || normalized.ends_with(".localhost")
-        || normalized.ends_with(".local")
-        || normalized.ends_with(".internal")
-        || normalized.ends_with(".home.arpa")
-        || !normalized.contains('.')
-}
-
-fn is_local_or_private_ip(ip: IpAddr) -> bool {
-    match ip {
-        IpAddr::V4(addr) => {
-            addr.is_private()
-                || addr.is_loopback()
-                || addr.is_link_local()
-                || addr.is_broadcast()
-                || addr.is_documentation()
-                || addr.is_unspecified()
-        }
-        IpAddr::V6(addr) => {
-            addr.is_loopback()
-                || addr.is_unique_local()
-                || addr.is_unicast_link_local()
-                || addr.is_unspecified()
-        }
-    }
+fn validate_remote_url_against_config(url: &str, config: &Value) -> Result<Url, String> {
+    validate_remote_url(url, allow_lan_sources_from_config(config))
 }
 
-fn validate_remote_url(url: &str, allow_lan_sources: bool) -> Result<Url, String> {
-    let parsed = Url::parse(url).map_err(|e| format!("Invalid URL: {}", e))?;
-
-    match parsed.scheme() {
-        "http" | "https" => {}
-        scheme => return Err(format!("Unsupported URL scheme: {}", scheme)),
-    }
-
-    if !parsed.username().is_empty() || parsed.password().is_some() {
-        return Err("URLs with embedded credentials are not allowed".to_string());
-    }
-
-    let host = parsed
-        .host_str()
-        .ok_or_else(|| "URL must include a host".to_string())?;
-
-    if !allow_lan_sources {
-        if let Ok(ip) = host.parse::<IpAddr>() {
-            if is_local_or_private_ip(ip) {
-                return Err("LAN and localhost URLs are disabled".to_string());
-            }
-        } else if is_local_hostname(host) {
-            return Err("LAN and localhost URLs are disabled".to_string());
-        }
-    }
-
-    Ok(parsed)
+/// 校验用户传入的地址，并确认域名实际解析到的地址符合局域网设置
+pub(crate) async fn check_remote_url_against_config(
+    url: &str,
+    config: &Value,
+) -> Result<Url, String> {
+    check_remote_url(
+        url,
+        allow_lan_sources_from_config(config),
+        crate::network::dns_resolver(),
+    )
+    .await
 }
 
-fn validate_remote_url_against_config(url: &str, config: &Value) -> Result<Url, String> {
-    validate_remote_url(url, allow_lan_sources_from_config(config))
+/// 源配置中通过地址校验的镜像（不含主地址）
+fn source_mirrors(source: &Value, config: &Value) -> Vec<String> {
+    source_endpoints(source)
+        .into_iter()
+        .skip(1)
+        .filter(|endpoint| validate_remote_url_against_config(endpoint, config).is_ok())
+        .collect()
 }
 
 pub(crate) fn resolve_enabled_source(config: &Value, source_key: &str) -> Option<ApiSite> {
@@ -815,7 +839,8 @@ pub(crate) fn resolve_enabled_source(config: &Value, source_key: &str) -> Option
             sources.iter().find_map(|s| {
                 let key = s.get("key")?.as_str()?;
                 let disabled = s.get("disabled").and_then(|d| d.as_bool()).unwrap_or(false);
-                if key != source_key || disabled {
+                // 合并镜像后被并入的源 key 作为别名解析到合并后的源
+                if !source_has_key(s, source_key) || disabled {
                     return None;
                 }
                 let site = ApiSite {
@@ -827,6 +852,11 @@ pub(crate) fn resolve_enabled_source(config: &Value, source_key: &str) -> Option
                         .and_then(|v| v.as_str())
                         .map(|v| v.to_string()),
                     is_adult: s.get("is_adult").and_then(|v| v.as_bool()),
+                    request_policy: RequestPolicy::from_source(s),
+                    mirrors: source_mirrors(s, config),
+                    mirror_strategy: MirrorStrategy::from_source(s),
+                    request_profile: RequestProfile::from_source(s),
+                    hosts: HostOverrides::from_source(s),
                 };
                 validate_remote_url_against_config(&site.api, config).ok()?;
                 Some(site)
@@ -957,35 +987,354 @@ pub struct DoubanCommentsResponse {
     pub total: i32,
     pub comments: Vec<DoubanComment>,
 }
-static VIDEO_CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
-
-pub(crate) fn get_video_client() -> &'static reqwest::Client {
-    VIDEO_CLIENT.get_or_init(|| {
-        reqwest::Client::builder()
-            // 大幅增加连接池大小，允许更高并发
-            .pool_max_idle_per_host(150) // 从100增加到150
-            .pool_idle_timeout(std::time::Duration::from_secs(180)) // 从120增加到180秒
-            // 开启 TCP_NODELAY，减少小包延迟
-            .tcp_nodelay(true)
-            .tcp_keepalive(std::time::Duration::from_secs(60))
-            // 开启自适应窗口，解决跨国高延迟下的吞吐量瓶颈
-            .http2_adaptive_window(true)
-            // 保持 H2 连接活跃，防止中间设备切断
-            .http2_keep_alive_interval(std::time::Duration::from_secs(30))
-            .http2_keep_alive_timeout(std::time::Duration::from_secs(20))
-            // 增加超时时间，适应跨国慢速网络
-            .timeout(std::time::Duration::from_secs(40)) // 从30增加到40秒
-            .connect_timeout(std::time::Duration::from_secs(15)) // 从10增加到15秒
-            // 烂证书 野鸡CDN 连接问题
-            .danger_accept_invalid_certs(true) // 忽略证书无效/过期/自签名
-            .danger_accept_invalid_hostnames(true) // 忽略域名不匹配
-            .no_proxy() // (可选) 避免被系统代理设置干扰，直连
-            // 禁用重定向限制（某些CDN可能有多次重定向）
-            .redirect(reqwest::redirect::Policy::limited(10))
-            .build()
-            .expect("Failed to create global video client")
+static VIDEO_CLIENTS: std::sync::Mutex<(u64, Vec<(ProxyCategory, reqwest::Client)>)> =
+    std::sync::Mutex::new((0, Vec::new()));
+static OUTBOUND_SCHEDULER: OnceLock<OutboundScheduler> = OnceLock::new();
+static MIRROR_TRACKER: OnceLock<MirrorTracker> = OnceLock::new();
+static MEDIA_PROFILES: OnceLock<MediaProfileRegistry> = OnceLock::new();
+static SEGMENT_PREFETCHER: OnceLock<SegmentPrefetcher> = OnceLock::new();
+/// 播放器最近一次请求分片时携带的请求头，预取沿用
+static PREFETCH_HEADERS: std::sync::Mutex<Option<HashMap<String, String>>> =
+    std::sync::Mutex::new(None);
+
+/// 同时预取的分片数，避免挤占正在播放的分片请求
+const PREFETCH_CONCURRENCY: usize = 2;
+/// 桌面端只有一个播放器，预取会话固定使用该标识
+const PREFETCH_CLIENT: &str = "local";
+/// 媒体（分片、播放列表、图片）请求的超时
+const MEDIA_REQUEST_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// 镜像健康度，进程内共享
+pub(crate) fn mirror_tracker() -> &'static MirrorTracker {
+    MIRROR_TRACKER.get_or_init(MirrorTracker::new)
+}
+
+/// 媒体主机对应的源请求配置，进程内共享
+pub(crate) fn media_profiles() -> &'static MediaProfileRegistry {
+    MEDIA_PROFILES.get_or_init(MediaProfileRegistry::new)
+}
+
+/// 去掉分片前伪装的图片文件头，并计入分片所属源的统计
+///
+/// 未指定源时按分片主机查找登记的源，查不到则只去伪装不计数
+pub(crate) fn unwrap_segment(
+    source_manager: &SourceIntelligenceManager,
+    source_key: Option<&str>,
+    url: &str,
+    body: Vec<u8>,
+) -> Vec<u8> {
+    let (body, disguised) = unwrap_disguised_segment(body);
+    if let Some(found) = disguised {
+        log::debug!("去除伪装分片前缀 {} bytes: {}", found.offset, url);
+        let source_key = source_key
+            .map(str::to_string)
+            .or_else(|| media_profiles().source_for(url));
+        if let Some(source_key) = source_key {
+            source_manager.record_segment_unwrap(&source_key, found.offset);
+        }
+    }
+    body
+}
+
+/// 按播放列表预取分片，写入视频缓存；首次使用时创建
+fn segment_prefetcher(
+    cache: &Cache<String, Vec<u8>>,
+    source_manager: &SourceIntelligenceManager,
+) -> &'static SegmentPrefetcher {
+    SEGMENT_PREFETCHER.get_or_init(|| {
+        let cache = cache.clone();
+        let source_manager = source_manager.clone();
+        SegmentPrefetcher::new(PREFETCH_CONCURRENCY, move |url: String| {
+            let cache = cache.clone();
+            let source_manager = source_manager.clone();
+            async move { prefetch_segment(&cache, &source_manager, &url).await }
+        })
     })
 }
+
+/// 下载一个分片写入缓存，返回下载的字节数；已缓存时返回 0
+async fn prefetch_segment(
+    cache: &Cache<String, Vec<u8>>,
+    source_manager: &SourceIntelligenceManager,
+    url: &str,
+) -> Result<u64, String> {
+    if cache.contains_key(url) {
+        return Ok(0);
+    }
+    let mut headers = HeaderMap::new();
+    let forwarded = PREFETCH_HEADERS.lock().unwrap().clone();
+    for (k, v) in forwarded.unwrap_or_default() {
+        if let Ok(name) = reqwest::header::HeaderName::from_bytes(k.as_bytes()) {
+            if let Ok(value) = HeaderValue::from_str(&v) {
+                headers.insert(name, value);
+            }
+        }
+    }
+    apply_media_headers(&mut headers, url);
+
+    let _permit = outbound_scheduler()
+        .acquire(url, None, MEDIA_REQUEST_TIMEOUT)
+        .await;
+    let request = get_video_client(ProxyCategory::Media)
+        .get(url)
+        .headers(headers)
+        .send();
+    let resp = timeout(MEDIA_REQUEST_TIMEOUT, request)
+        .await
+        .map_err(|_| format!("预取超时: {}", url))?
+        .map_err(|e| e.to_string())?;
+    if !resp.status().is_success() {
+        return Err(format!("预取失败 {}: {}", resp.status(), url));
+    }
+    let data = timeout(MEDIA_REQUEST_TIMEOUT, resp.bytes())
+        .await
+        .map_err(|_| format!("预取超时: {}", url))?
+        .map_err(|e| e.to_string())?;
+    let size = data.len() as u64;
+    let data = unwrap_segment(source_manager, None, url, data.to_vec());
+    cache.insert(url.to_string(), data).await;
+    log::debug!("✅ 预取成功: {} ({} bytes)", url, size);
+    Ok(size)
+}
+
+/// 媒体请求（播放列表、分片、图片）补齐请求头：调用方显式指定的优先，
+/// 其次是该主机登记的源配置，最后是内置 Referer 与默认 User-Agent
+pub(crate) fn apply_media_headers(headers: &mut HeaderMap, url: &str) {
+    let profile = media_profiles().profile_for(url);
+    apply_request_defaults(headers, url, profile.as_ref());
+}
+
+/// 所有出站请求共享的调度器：按主机限速、限并发，并控制全局并发
+pub(crate) fn outbound_scheduler() -> &'static OutboundScheduler {
+    OUTBOUND_SCHEDULER.get_or_init(OutboundScheduler::default)
+}
+
+/// 获取该流量分类共享的客户端，代理设置变化后自动重建
+pub(crate) fn get_video_client(category: ProxyCategory) -> reqwest::Client {
+    let generation = crate::network::settings_generation();
+    let mut clients = VIDEO_CLIENTS.lock().unwrap_or_else(|e| e.into_inner());
+    if clients.0 != generation {
+        *clients = (generation, Vec::new());
+    }
+    if let Some((_, client)) = clients.1.iter().find(|(c, _)| *c == category) {
+        return client.clone();
+    }
+    let client = build_video_client(category);
+    clients.1.push((category, client.clone()));
+    client
+}
+
+fn build_video_client(category: ProxyCategory) -> reqwest::Client {
+    crate::network::client_builder(category)
+        // 大幅增加连接池大小，允许更高并发
+        .pool_max_idle_per_host(150) // 从100增加到150
+        .pool_idle_timeout(std::time::Duration::from_secs(180)) // 从120增加到180秒
+        // 开启 TCP_NODELAY，减少小包延迟
+        .tcp_nodelay(true)
+        .tcp_keepalive(std::time::Duration::from_secs(60))
+        // 开启自适应窗口，解决跨国高延迟下的吞吐量瓶颈
+        .http2_adaptive_window(true)
+        // 保持 H2 连接活跃，防止中间设备切断
+        .http2_keep_alive_interval(std::time::Duration::from_secs(30))
+        .http2_keep_alive_timeout(std::time::Duration::from_secs(20))
+        // 增加超时时间，适应跨国慢速网络
+        .timeout(std::time::Duration::from_secs(40)) // 从30增加到40秒
+        .connect_timeout(std::time::Duration::from_secs(15)) // 从10增加到15秒
+        // 烂证书 野鸡CDN 连接问题
+        .danger_accept_invalid_certs(true) // 忽略证书无效/过期/自签名
+        .danger_accept_invalid_hostnames(true) // 忽略域名不匹配
+        // 重定向由 client_builder 逐跳校验（最多 10 次，部分 CDN 会多次跳转）
+        .build()
+        .expect("Failed to create global video client")
+}
+/// 经调度器请求源站接口并读取响应文本，失败时给出分类
+///
+/// 发送超时按该主机的历史延迟自适应，尚无样本时使用 `fallback_timeout`
+async fn fetch_source_text(
+    url: &str,
+    policy: Option<&RequestPolicy>,
+    profile: Option<&RequestProfile>,
+    fallback_timeout: Duration,
+) -> Result<String, RequestFailure> {
+    let mut headers = HeaderMap::new();
+    apply_request_defaults(&mut headers, url, profile);
+
+    let scheduler = outbound_scheduler();
+    let permit = scheduler.acquire(url, policy, fallback_timeout).await;
+    let started = std::time::Instant::now();
+    let request = get_video_client(ProxyCategory::Sources)
+        .get(url)
+        .headers(headers)
+        .send();
+    let resp = match timeout(permit.timeout(), request).await {
+        Ok(Ok(res)) => res,
+        Ok(Err(error)) => return Err(RequestFailure::from_reqwest(&error)),
+        Err(_) => {
+            // 超时按当时的超时时长计入延迟样本，避免慢主机一直按旧超时失败
+            scheduler.record_timeout(url, permit.timeout());
+            return Err(RequestFailure::Timeout);
+        }
+    };
+    scheduler.record_latency(url, started.elapsed());
+
+    if !resp.status().is_success() {
+        return Err(RequestFailure::HttpStatus(resp.status().as_u16()));
+    }
+
+    let body = match timeout(Duration::from_secs(5), resp.text()).await {
+        Ok(Ok(text)) => text,
+        Ok(Err(error)) => return Err(RequestFailure::from_reqwest(&error)),
+        Err(_) => return Err(RequestFailure::Timeout),
+    };
+
+    if body.trim().is_empty() {
+        return Err(RequestFailure::Empty);
+    }
+    Ok(body)
+}
+
+/// 请求源站接口并解析，同时把耗时与失败分类记入源健康统计（被动遥测）
+///
+/// 请求前先向熔断器申请名额，熔断中的源直接返回错误；`build_url` 由接口地址拼出请求 URL，源配置了镜像时按健康度依次或对冲请求；
+/// `is_empty` 判定解析结果是否应记为空响应失败，返回值仍交给调用方处理
+#[allow(clippy::too_many_arguments)]
+async fn fetch_source_api<T>(
+    manager: &SourceIntelligenceManager,
+    db: &crate::db::db_client::Db,
+    site: &ApiSite,
+    kind: RequestKind,
+    build_url: impl Fn(&str) -> String,
+    fallback_timeout: Duration,
+    parse: impl Fn(&str) -> Result<T, String>,
+    is_empty: impl Fn(&T) -> bool,
+) -> Result<T, String> {
+    let source_key = site.key.as_str();
+    if !manager.try_acquire(source_key) {
+        return Err(format!(
+            "Source {} is temporarily disabled by circuit breaker",
+            source_key
+        ));
+    }
+    crate::network::dns_resolver().set_source_hosts(source_key, &site.hosts);
+    // 用源健康统计中的平均响应时间预热自适应超时
+    if let Some(stats) = manager.get_source_stats(source_key) {
+        outbound_scheduler().seed_latency(&build_url(&site.api), stats.avg_response_time_ms);
+    }
+
+    let started = std::time::Instant::now();
+    let result = fetch_from_mirrors(site, kind, &build_url, fallback_timeout, &parse).await;
+
+    let latency_ms = started.elapsed().as_millis() as u64;
+    let outcome = match &result {
+        Ok(value) if is_empty(value) => {
+            RequestOutcome::failure(source_key, kind, latency_ms, RequestFailure::Empty)
+        }
+        Ok(_) => RequestOutcome::success(source_key, kind, latency_ms),
+        Err((failure, _)) => RequestOutcome::failure(source_key, kind, latency_ms, failure.clone()),
+    };
+    if let Err(error) = manager.record_request_outcome(db, outcome) {
+        log::debug!("记录源请求统计失败 {}: {}", source_key, error);
+    }
+
+    result.map_err(|(_, message)| message)
+}
+
+/// 单个镜像请求的结果：接口地址、耗时与响应文本
+type MirrorFetch = (String, Duration, Result<String, RequestFailure>);
+
+fn spawn_mirror_fetch(
+    in_flight: &mut tokio::task::JoinSet<MirrorFetch>,
+    site: &ApiSite,
+    url: String,
+    endpoint: String,
+    fallback_timeout: Duration,
+) {
+    let policy = site.request_policy.clone();
+    let profile = site.request_profile.clone();
+    in_flight.spawn(async move {
+        let started = std::time::Instant::now();
+        let fetched =
+            fetch_source_text(&url, policy.as_ref(), profile.as_ref(), fallback_timeout).await;
+        (endpoint, started.elapsed(), fetched)
+    });
+}
+
+/// 按镜像健康度请求源站：失败时切换到下一个镜像；对冲模式下当前镜像
+/// 超过对冲延迟仍未返回，就同时请求下一个镜像，取先成功的结果
+async fn fetch_from_mirrors<T>(
+    site: &ApiSite,
+    kind: RequestKind,
+    build_url: &impl Fn(&str) -> String,
+    fallback_timeout: Duration,
+    parse: &impl Fn(&str) -> Result<T, String>,
+) -> Result<T, (RequestFailure, String)> {
+    let tracker = mirror_tracker();
+    let hedged = site.mirror_strategy == MirrorStrategy::Hedged;
+    let mut pending = tracker.order(&site.endpoints()).into_iter();
+    let mut in_flight: tokio::task::JoinSet<MirrorFetch> = tokio::task::JoinSet::new();
+    let mut latest = String::new();
+    let mut last_error = (
+        RequestFailure::Network,
+        format!("Failed to fetch {}: no endpoint", kind.as_str()),
+    );
+
+    loop {
+        if in_flight.is_empty() {
+            let Some(endpoint) = pending.next() else {
+                return Err(last_error);
+            };
+            latest = endpoint.clone();
+            spawn_mirror_fetch(
+                &mut in_flight,
+                site,
+                build_url(&endpoint),
+                endpoint,
+                fallback_timeout,
+            );
+        }
+
+        let joined = if hedged && pending.len() > 0 {
+            let delay = tracker.hedge_delay(&latest);
+            tokio::select! {
+                joined = in_flight.join_next() => joined,
+                _ = tokio::time::sleep(delay) => {
+                    if let Some(endpoint) = pending.next() {
+                        latest = endpoint.clone();
+                        let url = build_url(&endpoint);
+                        spawn_mirror_fetch(&mut in_flight, site, url, endpoint, fallback_timeout);
+                    }
+                    continue;
+                }
+            }
+        } else {
+            in_flight.join_next().await
+        };
+        let Some(Ok((endpoint, elapsed, fetched))) = joined else {
+            continue;
+        };
+
+        let parsed = match fetched {
+            Ok(body) => parse(&body).map_err(|error| (RequestFailure::InvalidJson, error)),
+            Err(failure) => {
+                let message = format!("Failed to fetch {}: {}", kind.as_str(), failure.as_reason());
+                Err((failure, message))
+            }
+        };
+        match parsed {
+            // 返回时丢弃 JoinSet，仍在进行的对冲请求随之取消
+            Ok(value) => {
+                tracker.record_success(&endpoint, elapsed);
+                return Ok(value);
+            }
+            Err(error) => {
+                log::debug!("源接口请求失败 {} {}: {}", site.key, endpoint, error.1);
+                tracker.record_failure(&endpoint);
+                last_error = error;
+            }
+        }
+    }
+}
+
 fn is_playable_m3u8(url: &str) -> bool {
     url.to_lowercase().contains(".m3u8")
 }
@@ -1038,6 +1387,20 @@ const YELLOW_WORDS: &[&str] = &[
     "诱惑",
 ];
 
+/// 登记源返回的海报与播放地址所在主机，后续媒体请求沿用该源的请求配置，分片统计计入该源
+fn register_media_hosts(site: &ApiSite, poster: &str, episodes: &[String]) {
+    let registry = media_profiles();
+    for url in episodes {
+        registry.register_source(url, &site.key);
+    }
+    let Some(profile) = &site.request_profile else {
+        return;
+    };
+    for url in std::iter::once(poster).chain(episodes.iter().map(String::as_str)) {
+        registry.register_url(url, &profile.scoped_to(url, &site.api));
+    }
+}
+
 fn parse_episodes(play_url: &str) -> (Vec<String>, Vec<String>) {
     let mut episodes = Vec::new();
     let mut titles = Vec::new();
@@ -1065,20 +1428,30 @@ fn parse_episodes(play_url: &str) -> (Vec<String>, Vec<String>) {
     (episodes, titles)
 }
 
-pub(crate) async fn search_with_cache_hit(
-    query: String,
-    app_handle: tauri::AppHandle,
-    storage: State<'_, StorageManager>,
-    cache: State<'_, SearchCacheManager>,
-    db: &crate::db::db_client::Db,
-) -> Result<(Vec<SearchResult>, bool), String> {
-    // 首先尝试从缓存获取结果
-    if let Some(cached_results) = cache.get(&query).await {
-        return Ok((cached_results, true));
-    }
+/// 持久化缓存在该时长内视为新鲜，超过后先返回旧结果再后台刷新
+const SEARCH_CACHE_FRESH_SECS: i64 = 10 * 60;
 
-    let config = get_config_with_db_sources(&storage, db)?;
+/// 一次聚合搜索所需的源与过滤配置
+#[derive(Clone)]
+struct SearchPlan {
+    sites: Vec<ApiSite>,
+    disable_yellow_filter: bool,
+    use_streaming: bool,
+    /// 启用源集合的指纹，用于缓存失效
+    fingerprint: String,
+}
 
+/// 后台刷新完成后推送给前端的差异事件
+#[derive(Debug, Serialize, Clone)]
+pub struct SearchCacheRefreshEvent {
+    pub query: String,
+    pub added: Vec<SearchResult>,
+    pub removed: Vec<String>,
+    pub updated: Vec<SearchResult>,
+    pub total: usize,
+}
+
+fn build_search_plan(config: &Value) -> SearchPlan {
     // 读取 FluidSearch 配置，判断是否启用流式搜索（从 UserPreferences 读取）
     let fluid_search = config
         .get("UserPreferences")
@@ -1086,9 +1459,6 @@ pub(crate) async fn search_with_cache_hit(
         .and_then(|v| v.as_bool())
         .unwrap_or(true);
 
-    // 仅在启用 FluidSearch 时才使用流式输出
-    let use_streaming = fluid_search;
-
     let mut sites =
         if let Some(source_config) = config.get("SourceConfig").and_then(|v| v.as_array()) {
             source_config
@@ -1098,7 +1468,7 @@ pub(crate) async fn search_with_cache_hit(
                         return None;
                     }
                     let api = s.get("api")?.as_str()?.to_string();
-                    validate_remote_url_against_config(&api, &config).ok()?;
+                    validate_remote_url_against_config(&api, config).ok()?;
                     Some(ApiSite {
                         key: s.get("key")?.as_str()?.to_string(),
                         api,
@@ -1108,6 +1478,11 @@ pub(crate) async fn search_with_cache_hit(
                             .and_then(|v| v.as_str())
                             .map(|v| v.to_string()),
                         is_adult: s.get("is_adult").and_then(|v| v.as_bool()),
+                        request_policy: RequestPolicy::from_source(s),
+                        mirrors: source_mirrors(s, config),
+                        mirror_strategy: MirrorStrategy::from_source(s),
+                        request_profile: RequestProfile::from_source(s),
+                        hosts: HostOverrides::from_source(s),
                     })
                 })
                 .collect::<Vec<ApiSite>>()
@@ -1115,10 +1490,6 @@ pub(crate) async fn search_with_cache_hit(
             vec![]
         };
 
-    if sites.is_empty() {
-        return Ok((vec![], false));
-    }
-
     // 读取过滤配置
     let disable_yellow_filter = config
         .get("UserPreferences")
@@ -1131,132 +1502,142 @@ pub(crate) async fn search_with_cache_hit(
         sites.retain(|site| !site.is_adult.unwrap_or(false));
     }
 
-    // 过滤后如果没有源了，直接返回
-    if sites.is_empty() {
-        return Ok((vec![], false));
+    let fingerprint = source_fingerprint(
+        sites
+            .iter()
+            .map(|site| (site.key.as_str(), site.api.as_str())),
+        !disable_yellow_filter,
+    );
+
+    SearchPlan {
+        sites,
+        disable_yellow_filter,
+        // 仅在启用 FluidSearch 时才使用流式输出
+        use_streaming: fluid_search,
+        fingerprint,
     }
+}
+
+/// 尝试获取窗口 - 兼容桌面端和移动端
+fn main_window(app_handle: &tauri::AppHandle) -> Option<tauri::WebviewWindow> {
+    app_handle
+        .get_webview_window("main")
+        .or_else(|| app_handle.webview_windows().values().next().cloned())
+}
 
+/// 并发请求所有源并返回去重排序后的结果
+///
+/// stream 为 true 时逐源推送 search-stream-result 事件；每个源的请求结果都会记入源健康统计
+async fn fetch_search_results(
+    query: &str,
+    plan: &SearchPlan,
+    app_handle: tauri::AppHandle,
+    stream: bool,
+) -> Vec<SearchResult> {
+    // 熔断中的源直接跳过；试探名额由 fetch_source_api 申请，这里只做不占名额的检查
+    let sites: Vec<ApiSite> = {
+        let manager = app_handle.state::<SourceIntelligenceManager>();
+        plan.sites
+            .iter()
+            .filter(|site| !manager.should_skip_source(&site.key))
+            .cloned()
+            .collect()
+    };
     let total_sources = sites.len() as i32;
+    let disable_yellow_filter = plan.disable_yellow_filter;
 
-    // 限制并发数：最多同时请求 20 个源，充分利用并发
-    let semaphore = Arc::new(Semaphore::new(20));
-    let client = get_video_client();
+    // 并发与限速由共享出站调度器按主机控制
     let completed = Arc::new(tokio::sync::Mutex::new(0i32));
 
     let mut handles = Vec::new();
     for site in &sites {
-        let semaphore = semaphore.clone();
-        let client = client.clone();
-        let query = query.clone();
+        let query = query.to_string();
         let site_clone = site.clone();
-        // 仅在启用流式搜索时才传递 app_handle
-        let app_handle_opt = if use_streaming {
-            Some(app_handle.clone())
-        } else {
-            None
-        };
+        let app_handle = app_handle.clone();
+        let app_handle_opt = stream.then(|| app_handle.clone());
         let completed = completed.clone();
         // 克隆过滤配置到闭包中
         let disable_filter = disable_yellow_filter;
 
         let handle = tokio::spawn(async move {
-            let _permit = semaphore.acquire().await.ok()?;
-            let url = format!(
-                "{}?ac=videolist&wd={}",
-                site_clone.api,
-                urlencoding::encode(&query)
-            );
-
-            // 单个源请求超时 6 秒
-            let resp = match timeout(Duration::from_secs(6), client.get(&url).send()).await {
-                Ok(Ok(res)) if res.status().is_success() => res,
-                _ => {
-                    // 如果启用了流式搜索，即使失败也要发送事件
-                    if let Some(app_handle) = &app_handle_opt {
-                        // 尝试获取窗口 - 兼容桌面端和移动端
-                        let window = app_handle
-                            .get_webview_window("main")
-                            .or_else(|| app_handle.webview_windows().values().next().cloned());
-
-                        if let Some(window) = window {
-                            let mut count = completed.lock().await;
-                            *count += 1;
-                            let _ = window.emit(
-                                "search-stream-result",
-                                SearchStreamEvent {
-                                    results: vec![],
-                                    source: site_clone.key.clone(),
-                                    source_name: site_clone.name.clone(),
-                                    total_sources,
-                                    completed_sources: *count,
-                                },
-                            );
-                        }
-                    }
-                    return Some(vec![]);
+            let encoded_query = urlencoding::encode(&query).into_owned();
+
+            // 失败时也要发送事件，保证前端进度完整
+            let emit_progress = |results: Vec<SearchResult>| {
+                let app_handle_opt = app_handle_opt.clone();
+                let completed = completed.clone();
+                let site = site_clone.clone();
+                async move {
+                    let Some(window) = app_handle_opt.as_ref().and_then(main_window) else {
+                        return;
+                    };
+                    let mut count = completed.lock().await;
+                    *count += 1;
+                    let _ = window.emit(
+                        "search-stream-result",
+                        SearchStreamEvent {
+                            results,
+                            source: site.key.clone(),
+                            source_name: site.name.clone(),
+                            total_sources,
+                            completed_sources: *count,
+                        },
+                    );
                 }
             };
 
-            let body = match timeout(Duration::from_secs(5), resp.text()).await {
-                Ok(Ok(text)) => text,
-                _ => {
-                    if let Some(app_handle) = &app_handle_opt {
-                        // 尝试获取窗口 - 兼容桌面端和移动端
-                        let window = app_handle
-                            .get_webview_window("main")
-                            .or_else(|| app_handle.webview_windows().values().next().cloned());
-
-                        if let Some(window) = window {
-                            let mut count = completed.lock().await;
-                            *count += 1;
-                            let _ = window.emit(
-                                "search-stream-result",
-                                SearchStreamEvent {
-                                    results: vec![],
-                                    source: site_clone.key.clone(),
-                                    source_name: site_clone.name.clone(),
-                                    total_sources,
-                                    completed_sources: *count,
-                                },
-                            );
-                        }
-                    }
+            // 无延迟样本时超时 6 秒；搜不到结果不算源故障，不计为空响应
+            let manager = app_handle.state::<SourceIntelligenceManager>();
+            let db = app_handle.state::<crate::db::db_client::Db>();
+            let search_res = match fetch_source_api(
+                &manager,
+                &db,
+                &site_clone,
+                RequestKind::Search,
+                |api| format!("{}?ac=videolist&wd={}", api, encoded_query),
+                Duration::from_secs(6),
+                |body| serde_json::from_str::<ApiSearchResponse>(body).map_err(|e| e.to_string()),
+                |_| false,
+            )
+            .await
+            {
+                Ok(res) => res,
+                Err(_) => {
+                    emit_progress(vec![]).await;
                     return Some(vec![]);
                 }
             };
 
-            let mut source_results = Vec::new();
-            if let Ok(search_res) = serde_json::from_str::<ApiSearchResponse>(&body) {
-                source_results = search_res
-                    .list
-                    .into_iter()
-                    .map(|item| {
-                        let (episodes, episodes_titles) =
-                            parse_episodes(item.vod_play_url.as_deref().unwrap_or(""));
-                        SearchResult {
-                            id: match item.vod_id {
-                                Value::String(s) => s,
-                                Value::Number(n) => n.to_string(),
-                                _ => "".to_string(),
-                            },
-                            title: item.vod_name.trim().to_string(),
-                            poster: item.vod_pic,
-                            episodes,
-                            episodes_titles,
-                            source: site_clone.key.clone(),
-                            source_name: site_clone.name.clone(),
-                            class: item.vod_class,
-                            year: item.vod_year,
-                            desc: item.vod_content.map(|c| clean_html_tags(&c)),
-                            type_name: item.type_name,
-                            douban_id: item
-                                .vod_douban_id
-                                .and_then(|v| v.as_i64())
-                                .map(|v| v as i32),
-                        }
-                    })
-                    .collect::<Vec<SearchResult>>();
-            }
+            let mut source_results = search_res
+                .list
+                .into_iter()
+                .map(|item| {
+                    let (episodes, episodes_titles) =
+                        parse_episodes(item.vod_play_url.as_deref().unwrap_or(""));
+                    register_media_hosts(&site_clone, &item.vod_pic, &episodes);
+                    SearchResult {
+                        id: match item.vod_id {
+                            Value::String(s) => s,
+                            Value::Number(n) => n.to_string(),
+                            _ => "".to_string(),
+                        },
+                        title: item.vod_name.trim().to_string(),
+                        poster: item.vod_pic,
+                        episodes,
+                        episodes_titles,
+                        source: site_clone.key.clone(),
+                        source_name: site_clone.name.clone(),
+                        class: item.vod_class,
+                        year: item.vod_year,
+                        desc: item.vod_content.map(|c| clean_html_tags(&c)),
+                        type_name: item.type_name,
+                        douban_id: item
+                            .vod_douban_id
+                            .and_then(|v| v.as_i64())
+                            .map(|v| v as i32),
+                    }
+                })
+                .collect::<Vec<SearchResult>>();
 
             // 在流式输出前进行内容关键词过滤（源已经在搜索前过滤了）
             if !disable_filter {
@@ -1268,27 +1649,7 @@ pub(crate) async fn search_with_cache_hit(
             }
 
             // 如果启用了流式搜索，立即发送该源的搜索结果给前端
-            if let Some(app_handle) = &app_handle_opt {
-                // 尝试获取窗口 - 兼容桌面端和移动端
-                let window = app_handle
-                    .get_webview_window("main")
-                    .or_else(|| app_handle.webview_windows().values().next().cloned());
-
-                if let Some(window) = window {
-                    let mut count = completed.lock().await;
-                    *count += 1;
-                    let _ = window.emit(
-                        "search-stream-result",
-                        SearchStreamEvent {
-                            results: source_results.clone(),
-                            source: site_clone.key.clone(),
-                            source_name: site_clone.name.clone(),
-                            total_sources,
-                            completed_sources: *count,
-                        },
-                    );
-                }
-            }
+            emit_progress(source_results.clone()).await;
 
             Some(source_results)
         });
@@ -1323,8 +1684,8 @@ pub(crate) async fn search_with_cache_hit(
 
     // Basic ranking
     unique_results.sort_by(|a, b| {
-        let a_match = a.title.contains(&query);
-        let b_match = b.title.contains(&query);
+        let a_match = a.title.contains(query);
+        let b_match = b.title.contains(query);
         if a_match && !b_match {
             std::cmp::Ordering::Less
         } else if !a_match && b_match {
@@ -1334,14 +1695,112 @@ pub(crate) async fn search_with_cache_hit(
         }
     });
 
-    // 如果启用了流式搜索，发送搜索完成事件
-    if use_streaming {
-        // 尝试获取窗口 - 兼容桌面端和移动端
-        let window = app_handle
-            .get_webview_window("main")
-            .or_else(|| app_handle.webview_windows().values().next().cloned());
+    unique_results
+}
+
+/// 后台刷新过期的持久化缓存，并把差异推送给前端
+fn spawn_search_revalidation(
+    app_handle: tauri::AppHandle,
+    query: String,
+    plan: SearchPlan,
+    stale: Vec<SearchResult>,
+) {
+    if !app_handle
+        .state::<SearchCacheManager>()
+        .begin_revalidate(&query)
+    {
+        return;
+    }
+
+    tauri::async_runtime::spawn(async move {
+        let fresh = fetch_search_results(&query, &plan, app_handle.clone(), false).await;
+        let cache = app_handle.state::<SearchCacheManager>();
+        cache.end_revalidate(&query);
+
+        // 所有源都失败时保留旧结果，等待下次刷新
+        if fresh.is_empty() && !stale.is_empty() {
+            log::debug!("搜索缓存后台刷新无结果，保留旧结果: {}", query);
+            return;
+        }
+
+        cache.set(query.clone(), fresh.clone()).await;
+        let db = app_handle.state::<crate::db::db_client::Db>();
+        if let Err(error) = save_cached_search(&db, &query, &plan.fingerprint, &fresh) {
+            log::warn!("写入搜索持久化缓存失败: {}", error);
+        }
+
+        let diff = diff_search_results(&stale, &fresh);
+        if diff.is_empty() {
+            return;
+        }
+
+        app_handle
+            .state::<crate::commands::search::SearchResultCache>()
+            .save(&query, fresh.clone());
+        app_handle
+            .state::<crate::commands::search::FilterResultCache>()
+            .invalidate_query(&query);
+
+        if let Some(window) = main_window(&app_handle) {
+            let _ = window.emit(
+                "search-cache-refreshed",
+                SearchCacheRefreshEvent {
+                    query: query.clone(),
+                    added: diff.added,
+                    removed: diff.removed,
+                    updated: diff.updated,
+                    total: fresh.len(),
+                },
+            );
+        }
+    });
+}
+
+pub(crate) async fn search_with_cache_hit(
+    query: String,
+    app_handle: tauri::AppHandle,
+    storage: State<'_, StorageManager>,
+    cache: State<'_, SearchCacheManager>,
+    db: &crate::db::db_client::Db,
+) -> Result<(Vec<SearchResult>, bool), String> {
+    let config = get_config_with_db_sources(&storage, db)?;
+    let plan = build_search_plan(&config);
+    cache.sync_source_fingerprint(&plan.fingerprint);
+
+    // 首先尝试从内存缓存获取结果
+    if let Some(cached_results) = cache.get(&query).await {
+        return Ok((cached_results, true));
+    }
+
+    // 过滤后如果没有源了，直接返回
+    if plan.sites.is_empty() {
+        return Ok((vec![], false));
+    }
 
-        if let Some(window) = window {
+    // 其次读取持久化缓存：新鲜直接返回，过期则先返回旧结果再后台刷新
+    match load_cached_search(db, &query, &plan.fingerprint) {
+        Ok(Some(persisted)) => {
+            cache.set(query.clone(), persisted.results.clone()).await;
+            if persisted.age_secs >= SEARCH_CACHE_FRESH_SECS {
+                spawn_search_revalidation(
+                    app_handle,
+                    query.clone(),
+                    plan,
+                    persisted.results.clone(),
+                );
+            }
+            return Ok((persisted.results, true));
+        }
+        Ok(None) => {}
+        Err(error) => log::warn!("读取搜索持久化缓存失败: {}", error),
+    }
+
+    let unique_results =
+        fetch_search_results(&query, &plan, app_handle.clone(), plan.use_streaming).await;
+
+    // 如果启用了流式搜索，发送搜索完成事件
+    if plan.use_streaming {
+        if let Some(window) = main_window(&app_handle) {
             let _ = window.emit(
                 "search-stream-completed",
                 serde_json::json!({
@@ -1352,8 +1811,13 @@ pub(crate) async fn search_with_cache_hit(
         }
     }
 
-    // 缓存搜索结果
-    cache.set(query, unique_results.clone()).await;
+    // 缓存搜索结果（空结果不落盘，下次仍然实时搜索）
+    cache.set(query.clone(), unique_results.clone()).await;
+    if !unique_results.is_empty() {
+        if let Err(error) = save_cached_search(db, &query, &plan.fingerprint, &unique_results) {
+            log::warn!("写入搜索持久化缓存失败: {}", error);
+        }
+    }
 
     Ok((unique_results, false))
 }
@@ -1371,45 +1835,50 @@ pub async fn search(
     Ok(results)
 }
 
+/// 请求单个视频详情，详情列表为空时记为空响应失败
+async fn fetch_detail_item(
+    manager: &SourceIntelligenceManager,
+    db: &crate::db::db_client::Db,
+    site: &ApiSite,
+    id: &str,
+) -> Result<ApiSearchItem, String> {
+    let search_res = fetch_source_api(
+        manager,
+        db,
+        site,
+        RequestKind::Detail,
+        |api| format!("{}?ac=videolist&ids={}", api, id),
+        Duration::from_secs(8),
+        |body| {
+            serde_json::from_str::<ApiSearchResponse>(body)
+                .map_err(|e| format!("Parse error: {}, body: {}", e, body))
+        },
+        |res| res.list.is_empty(),
+    )
+    .await?;
+
+    search_res
+        .list
+        .into_iter()
+        .next()
+        .ok_or_else(|| "Video not found".to_string())
+}
+
 #[tauri::command]
 pub async fn get_video_detail(
     source: String,
     id: String,
     storage: State<'_, StorageManager>,
     db: State<'_, crate::db::db_client::Db>,
+    source_manager: State<'_, SourceIntelligenceManager>,
 ) -> Result<SearchResult, String> {
     let config = get_config_with_db_sources(&storage, &db)?;
     let site = resolve_enabled_source(&config, &source)
         .ok_or_else(|| format!("Source not found or disabled: {}", source))?;
-    let client = get_video_client();
-
-    let url = format!("{}?ac=videolist&ids={}", site.api, id);
-
-    // 添加超时控制：8秒
-    let resp = match timeout(Duration::from_secs(8), client.get(&url).send()).await {
-        Ok(Ok(res)) => res,
-        _ => return Err("Failed to fetch detail: request timeout or network error".to_string()),
-    };
-
-    if !resp.status().is_success() {
-        return Err(format!("Failed to fetch detail: {}", resp.status()));
-    }
-
-    let body = match timeout(Duration::from_secs(5), resp.text()).await {
-        Ok(Ok(text)) => text,
-        _ => return Err("Failed to read response: timeout".to_string()),
-    };
-
-    let search_res = serde_json::from_str::<ApiSearchResponse>(&body)
-        .map_err(|e| format!("Parse error: {}, body: {}", e, body))?;
-
-    let item = search_res
-        .list
-        .into_iter()
-        .next()
-        .ok_or_else(|| "Video not found".to_string())?;
+    let item = fetch_detail_item(&source_manager, &db, &site, &id).await?;
 
     let (episodes, episodes_titles) = parse_episodes(item.vod_play_url.as_deref().unwrap_or(""));
+    register_media_hosts(&site, &item.vod_pic, &episodes);
 
     Ok(SearchResult {
         id: match item.vod_id {
@@ -1441,37 +1910,16 @@ pub async fn get_video_detail_optimized(
     storage: State<'_, StorageManager>,
     cache: State<'_, SearchCacheManager>,
     db: State<'_, crate::db::db_client::Db>,
+    source_manager: State<'_, SourceIntelligenceManager>,
     also_search_similar: Option<bool>,
 ) -> Result<GetVideoDetailOptimizedResponse, String> {
     let config = get_config_with_db_sources(&storage, &db)?;
     let site = resolve_enabled_source(&config, &source)
         .ok_or_else(|| format!("Source not found or disabled: {}", source))?;
-    let client = get_video_client();
-    let url = format!("{}?ac=videolist&ids={}", site.api, id);
-
-    let resp = match timeout(Duration::from_secs(8), client.get(&url).send()).await {
-        Ok(Ok(res)) => res,
-        _ => return Err("Failed to fetch detail: timeout".to_string()),
-    };
-
-    if !resp.status().is_success() {
-        return Err(format!("Failed to fetch detail: {}", resp.status()));
-    }
-
-    let body = match timeout(Duration::from_secs(5), resp.text()).await {
-        Ok(Ok(text)) => text,
-        _ => return Err("Failed to read response: timeout".to_string()),
-    };
-
-    let search_res = serde_json::from_str::<ApiSearchResponse>(&body).map_err(|e| e.to_string())?;
-
-    let item = search_res
-        .list
-        .into_iter()
-        .next()
-        .ok_or_else(|| "Video not found".to_string())?;
+    let item = fetch_detail_item(&source_manager, &db, &site, &id).await?;
 
     let (episodes, episodes_titles) = parse_episodes(item.vod_play_url.as_deref().unwrap_or(""));
+    register_media_hosts(&site, &item.vod_pic, &episodes);
 
     let detail = SearchResult {
         id: match item.vod_id {
@@ -1522,24 +1970,36 @@ pub async fn get_source_categories(
     source_key: String,
     storage: State<'_, StorageManager>,
     db: State<'_, crate::db::db_client::Db>,
+    source_manager: State<'_, SourceIntelligenceManager>,
 ) -> Result<Vec<SourceCategoryItem>, String> {
     let config = get_config_with_db_sources(&storage, &db)?;
     let source = resolve_enabled_source(&config, &source_key)
         .ok_or_else(|| format!("Source not found or disabled: {}", source_key))?;
 
-    let url = source_url(&source.api, "?ac=class");
-    let body = get_video_client()
-        .get(url)
-        .send()
-        .await
-        .map_err(|e| e.to_string())?
-        .error_for_status()
-        .map_err(|e| e.to_string())?
-        .text()
-        .await
-        .map_err(|e| e.to_string())?;
+    fetch_source_api(
+        &source_manager,
+        &db,
+        &source,
+        RequestKind::Category,
+        |api| source_url(api, "?ac=class"),
+        Duration::from_secs(8),
+        parse_source_categories,
+        |categories| categories.is_empty(),
+    )
+    .await
+}
 
-    parse_source_categories(&body)
+/// 查询源各镜像的健康状态
+#[tauri::command]
+pub async fn get_source_mirror_status(
+    source_key: String,
+    storage: State<'_, StorageManager>,
+    db: State<'_, crate::db::db_client::Db>,
+) -> Result<Vec<MirrorStatus>, String> {
+    let config = get_config_with_db_sources(&storage, &db)?;
+    let source = resolve_enabled_source(&config, &source_key)
+        .ok_or_else(|| format!("Source not found or disabled: {}", source_key))?;
+    Ok(mirror_tracker().status(&source.endpoints()))
 }
 
 #[tauri::command]
@@ -1549,6 +2009,7 @@ pub async fn get_source_videos_by_type(
     page: Option<u32>,
     storage: State<'_, StorageManager>,
     db: State<'_, crate::db::db_client::Db>,
+    source_manager: State<'_, SourceIntelligenceManager>,
 ) -> Result<Vec<ApiSearchItem>, String> {
     let config = get_config_with_db_sources(&storage, &db)?;
     let source = resolve_enabled_source(&config, &source_key)
@@ -1556,20 +2017,29 @@ pub async fn get_source_videos_by_type(
     let page = page.unwrap_or(1).max(1);
     let encoded_type = urlencoding::encode(type_id.trim());
     let query = format!("?ac=videolist&t={}&pg={}", encoded_type, page);
-    let url = source_url(&source.api, &query);
 
-    let body = get_video_client()
-        .get(url)
-        .send()
-        .await
-        .map_err(|e| e.to_string())?
-        .error_for_status()
-        .map_err(|e| e.to_string())?
-        .text()
-        .await
-        .map_err(|e| e.to_string())?;
+    // 翻页越界时返回空列表属于正常情况，仅第一页为空才记为空响应
+    fetch_source_api(
+        &source_manager,
+        &db,
+        &source,
+        RequestKind::Category,
+        |api| source_url(api, &query),
+        Duration::from_secs(8),
+        parse_source_videos,
+        |videos| page == 1 && videos.is_empty(),
+    )
+    .await
+}
 
-    parse_source_videos(&body)
+/// 随图片一起写入缓存的元数据
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ImageMetadata {
+    pub title: Option<String>,
+    pub source_name: Option<String>,
+    pub year: Option<String>,
+    pub category: Option<String>,
+    pub rating: Option<f64>,
 }
 
 #[tauri::command]
@@ -1584,7 +2054,25 @@ pub async fn proxy_image(
     cache_manager: State<'_, crate::db::image_cache::ImageCacheManager>,
 ) -> Result<Vec<u8>, String> {
     let data = storage.get_data()?;
-    validate_remote_url_against_config(&url, &data.config)?;
+    let metadata = ImageMetadata {
+        title,
+        source_name,
+        year,
+        category,
+        rating,
+    };
+    load_image(&url, &metadata, &data.config, &cache_manager).await
+}
+
+/// 获取并压缩封面图片，结果写入图片缓存；上游失效时回退到过期缓存
+pub(crate) async fn load_image(
+    url: &str,
+    metadata: &ImageMetadata,
+    config: &Value,
+    cache_manager: &crate::db::image_cache::ImageCacheManager,
+) -> Result<Vec<u8>, String> {
+    let url = url.to_string();
+    check_remote_url_against_config(&url, config).await?;
 
     // 1. 先尝试从 SQLite 缓存获取
     match cache_manager.get(&url) {
@@ -1601,10 +2089,9 @@ pub async fn proxy_image(
     }
 
     // 2. 使用全局 Client 获取图片
-    let client = get_video_client();
+    let client = get_video_client(ProxyCategory::Media);
 
     let mut headers = HeaderMap::new();
-    headers.insert(USER_AGENT, HeaderValue::from_static("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/122.0.0.0 Safari/537.36"));
     headers.insert(
         ACCEPT,
         HeaderValue::from_static(
@@ -1615,15 +2102,16 @@ pub async fn proxy_image(
         reqwest::header::ACCEPT_ENCODING,
         HeaderValue::from_static("gzip, deflate, br"),
     );
-
-    if url.contains("doubanio.com") {
-        headers.insert(REFERER, HeaderValue::from_static("https://www.douban.com/"));
-    }
+    apply_media_headers(&mut headers, &url);
 
     let fetch_result = async {
+        let _permit = outbound_scheduler()
+            .acquire(&url, None, MEDIA_REQUEST_TIMEOUT)
+            .await;
         let resp = client
             .get(&url)
             .headers(headers)
+            .timeout(MEDIA_REQUEST_TIMEOUT)
             .send()
             .await
             .map_err(|e| e.to_string())?;
@@ -1695,11 +2183,11 @@ pub async fn proxy_image(
     if let Err(e) = cache_manager.set_with_metadata(
         &url,
         &compressed_bytes,
-        title.as_deref(),
-        source_name.as_deref(),
-        year.as_deref(),
-        category.as_deref(),
-        rating,
+        metadata.title.as_deref(),
+        metadata.source_name.as_deref(),
+        metadata.year.as_deref(),
+        metadata.category.as_deref(),
+        metadata.rating,
     ) {
         eprintln!("Failed to save image to cache: {}", e);
     }
@@ -1713,7 +2201,7 @@ async fn fetch_with_retry(
     method: reqwest::Method,
     headers: HeaderMap,
 ) -> Result<reqwest::Response, String> {
-    let client = get_video_client();
+    let client = get_video_client(ProxyCategory::Media);
     let mut retries = 3; // 增加到3次
     let mut delay_ms = 300; // 初始延迟300ms
 
@@ -1721,7 +2209,7 @@ async fn fetch_with_retry(
         let req = client
             .request(method.clone(), url)
             .headers(headers.clone())
-            .timeout(std::time::Duration::from_secs(20)); // 增加到20秒超时
+            .timeout(MEDIA_REQUEST_TIMEOUT);
 
         match req.send().await {
             Ok(resp) => {
@@ -1763,26 +2251,53 @@ pub async fn fetch_binary(
     headers_opt: Option<std::collections::HashMap<String, String>>,
     storage: State<'_, StorageManager>,
     cache_manager: State<'_, VideoCacheManager>,
+    source_manager: State<'_, SourceIntelligenceManager>,
 ) -> Result<FetchBinaryResponse, String> {
     let data = storage.get_data()?;
-    validate_remote_url_against_config(&url, &data.config)?;
+    let method = method.unwrap_or_else(|| "GET".to_string());
+    let (status, body) = load_media(
+        &url,
+        &method,
+        headers_opt,
+        &data.config,
+        &cache_manager,
+        &source_manager,
+    )
+    .await?;
+    Ok(FetchBinaryResponse { status, body })
+}
+
+/// 获取分片等二进制内容：通知预取器、优先读视频缓存、去除伪装前缀，成功的 GET 写入缓存
+pub(crate) async fn load_media(
+    url: &str,
+    method_str: &str,
+    headers_opt: Option<HashMap<String, String>>,
+    config: &Value,
+    cache_manager: &VideoCacheManager,
+    source_manager: &SourceIntelligenceManager,
+) -> Result<(u16, Vec<u8>), String> {
+    let url = url.to_string();
+    check_remote_url_against_config(&url, config).await?;
 
-    let method_str = method.unwrap_or_else(|| "GET".to_string());
     let is_get = method_str.to_uppercase() == "GET";
 
+    // 以该分片为播放位置，预取其后的分片（沿用本次请求头）；不在当前播放列表中的地址会被忽略
+    if is_get {
+        *PREFETCH_HEADERS.lock().unwrap() = headers_opt.clone();
+        segment_prefetcher(&cache_manager.cache, source_manager)
+            .on_segment_request(PREFETCH_CLIENT, &url);
+    }
+
     // 1. 尝试从缓存获取
     if is_get {
         if let Some(cached_data) = cache_manager.get(&url).await {
-            return Ok(FetchBinaryResponse {
-                status: 200,
-                body: cached_data,
-            });
+            return Ok((200, cached_data));
         }
     }
 
     // 2. 准备 Headers
     let mut final_headers = HeaderMap::new();
-    if let Some(h) = headers_opt.clone() {
+    if let Some(h) = headers_opt {
         for (k, v) in h {
             if let Ok(name) = reqwest::header::HeaderName::from_bytes(k.as_bytes()) {
                 if let Ok(value) = HeaderValue::from_str(&v) {
@@ -1791,12 +2306,7 @@ pub async fn fetch_binary(
             }
         }
     }
-    if !final_headers.contains_key(USER_AGENT) {
-        final_headers.insert(USER_AGENT, HeaderValue::from_static("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/122.0.0.0 Safari/537.36"));
-    }
-    if url.contains("doubanio.com") || url.contains("douban.com") {
-        final_headers.insert(REFERER, HeaderValue::from_static("https://www.douban.com/"));
-    }
+    apply_media_headers(&mut final_headers, &url);
     if url.contains(".ts") {
         // 添加 Range 头
         final_headers.insert(RANGE, HeaderValue::from_static("bytes=0-"));
@@ -1806,28 +2316,26 @@ pub async fn fetch_binary(
         "HEAD" => reqwest::Method::HEAD,
         _ => reqwest::Method::GET,
     };
-    // 3. 执行带重试的网络请求
+    // 3. 经调度器执行带重试的网络请求，许可持有到响应体读取完毕
+    let permit = outbound_scheduler()
+        .acquire(&url, None, MEDIA_REQUEST_TIMEOUT)
+        .await;
     let resp = fetch_with_retry(&url, req_method, final_headers).await?;
     let status = resp.status().as_u16();
     let body_bytes = resp.bytes().await.map_err(|e| e.to_string())?;
-    let body = body_bytes.to_vec();
+    let body = if is_get && status == 200 {
+        unwrap_segment(source_manager, None, &url, body_bytes.to_vec())
+    } else {
+        body_bytes.to_vec()
+    };
+    drop(permit);
 
-    // 4. 只有成功的 GET 请求才存入缓存并触发预取
+    // 4. 只有成功的 GET 请求才存入缓存
     if is_get && status == 200 {
         cache_manager.set(url.clone(), body.clone()).await;
-
-        if url.contains(".ts") {
-            let cache_clone = cache_manager.cache.clone();
-            let semaphore_clone = cache_manager.semaphore.clone();
-            let headers_clone = headers_opt.clone();
-
-            tokio::spawn(async move {
-                prefetch_next_segments(url, headers_clone, cache_clone, semaphore_clone).await;
-            });
-        }
     }
 
-    Ok(FetchBinaryResponse { status, body })
+    Ok((status, body))
 }
 
 /// 获取 M3U8 内容并可选地进行去广告处理
@@ -1845,9 +2353,32 @@ pub async fn fetch_m3u8(
     enable_ad_block: Option<bool>,
     headers_opt: Option<std::collections::HashMap<String, String>>,
     storage: State<'_, StorageManager>,
+    cache_manager: State<'_, VideoCacheManager>,
+    source_manager: State<'_, SourceIntelligenceManager>,
 ) -> Result<String, String> {
     let data = storage.get_data()?;
-    validate_remote_url_against_config(&url, &data.config)?;
+    load_playlist(
+        &url,
+        enable_ad_block.unwrap_or(false),
+        headers_opt,
+        &data.config,
+        &cache_manager,
+        &source_manager,
+    )
+    .await
+}
+
+/// 获取 M3U8 并登记到请求配置与分片预取器，按需去广告
+pub(crate) async fn load_playlist(
+    url: &str,
+    enable_ad_block: bool,
+    headers_opt: Option<HashMap<String, String>>,
+    config: &Value,
+    cache_manager: &VideoCacheManager,
+    source_manager: &SourceIntelligenceManager,
+) -> Result<String, String> {
+    let url = url.to_string();
+    check_remote_url_against_config(&url, config).await?;
 
     // 准备 HTTP 请求头
     let mut final_headers = HeaderMap::new();
@@ -1861,29 +2392,30 @@ pub async fn fetch_m3u8(
         }
     }
 
-    // 添加默认 User-Agent
-    if !final_headers.contains_key(USER_AGENT) {
-        final_headers.insert(
-            USER_AGENT,
-            HeaderValue::from_static("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/122.0.0.0 Safari/537.36")
-        );
-    }
-
-    // 为特定域名添加 Referer
-    if url.contains("doubanio.com") || url.contains("douban.com") {
-        final_headers.insert(REFERER, HeaderValue::from_static("https://www.douban.com/"));
-    }
+    // 补齐源配置的请求头、内置 Referer 与默认 User-Agent
+    apply_media_headers(&mut final_headers, &url);
 
-    // 执行带重试的 HTTP 请求
+    // 经调度器执行带重试的 HTTP 请求
+    let permit = outbound_scheduler()
+        .acquire(&url, None, MEDIA_REQUEST_TIMEOUT)
+        .await;
     let resp = fetch_with_retry(&url, reqwest::Method::GET, final_headers).await?;
     let body_bytes = resp.bytes().await.map_err(|e| e.to_string())?;
+    drop(permit);
 
     // 解码为 UTF-8 文本
     let content = String::from_utf8(body_bytes.to_vec())
         .map_err(|e| format!("无法将 M3U8 内容解码为 UTF-8: {}", e))?;
+    media_profiles().register_playlist(&url, &content);
+
+    // 媒体播放列表登记到预取器，预取窗口随缓冲模式调整；换清晰度或换源后旧列表的预取会被取消
+    let prefetcher = segment_prefetcher(&cache_manager.cache, source_manager);
+    let preferences = crate::commands::config::user_preferences_from_config(config);
+    prefetcher.set_buffer_mode(BufferMode::from_preference(&preferences.player_buffer_mode));
+    prefetcher.register_playlist(PREFETCH_CLIENT, &url, &content);
 
     // 如果启用了去广告，则调用 core 中的过滤函数
-    let result = if enable_ad_block.unwrap_or(false) {
+    let result = if enable_ad_block {
         quantumtv_core::filter_ads_from_m3_u8(&content)
     } else {
         content
@@ -1892,122 +2424,20 @@ pub async fn fetch_m3u8(
     Ok(result)
 }
 
-// 预测并预取后续分片（优化版：更多并发+更多预取）
-async fn prefetch_next_segments(
-    current_url: String,
-    headers: Option<HashMap<String, String>>,
-    cache: Cache<String, Vec<u8>>,
-    semaphore: Arc<Semaphore>,
-) {
-    // 简单的数字预测 logic: 查找末尾连续的数字
-    // 如 segment_01.ts -> segment_02.ts
-    let re = Regex::new(r"(\d+)(\.ts.*)$").unwrap();
-    let Some(caps) = re.captures(&current_url) else {
-        return;
-    };
-
-    let num_str = caps.get(1).unwrap().as_str();
-    let suffix = caps.get(2).unwrap().as_str();
-    let prefix = &current_url[..caps.get(1).unwrap().start()];
-
-    let Ok(current_num) = num_str.parse::<u64>() else {
-        return;
-    };
-    let padding = num_str.len();
-
-    // 使用全局 Client
-    let client = get_video_client();
-
-    // 优化的 HTTP 客户端配置
-    let mut final_headers = HeaderMap::new();
-    if let Some(h) = headers {
-        for (k, v) in h {
-            if let Ok(name) = reqwest::header::HeaderName::from_bytes(k.as_bytes()) {
-                if let Ok(value) = HeaderValue::from_str(&v) {
-                    final_headers.insert(name, value);
-                }
-            }
-        }
-    }
-    if !final_headers.contains_key(USER_AGENT) {
-        final_headers.insert(USER_AGENT, HeaderValue::from_static("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/122.0.0.0 Safari/537.36"));
-    }
-    // 直接加上 Range
-    final_headers.insert(RANGE, HeaderValue::from_static("bytes=0-"));
-
-    // 预取接下来的 25 个分片（从15增加到25）
-    let mut handles = Vec::new();
-    for i in 1..=25 {
-        let next_num = current_num + i;
-        let next_num_str = format!("{:0width$}", next_num, width = padding);
-        let next_url = format!("{}{}{}", prefix, next_num_str, suffix);
-
-        // 已有缓存，跳过
-        if cache.contains_key(&next_url) {
-            continue;
-        }
-
-        // 并发下载（使用信号量控制）
-        let client_clone = client.clone();
-        let request_headers = final_headers.clone();
-        let cache_clone = cache.clone();
-        let next_url_clone = next_url.clone();
-        let semaphore_clone = semaphore.clone();
-
-        let handle = tokio::spawn(async move {
-            // 获取信号量许可
-            let _permit = semaphore_clone.acquire().await.ok();
-
-            // 增强的重试逻辑：3次重试
-            let mut retries = 3;
-            let mut delay_ms = 200;
-
-            while retries > 0 {
-                let resp = timeout(
-                    Duration::from_secs(15), // 15秒超时
-                    client_clone
-                        .get(&next_url_clone)
-                        .headers(request_headers.clone())
-                        .send(),
-                )
-                .await;
-
-                match resp {
-                    Ok(Ok(r)) if r.status().is_success() => {
-                        if let Ok(data) = r.bytes().await {
-                            // moka 会自动处理 LRU 淘汰和 TTL 过期
-                            cache_clone
-                                .insert(next_url_clone.clone(), data.to_vec())
-                                .await;
-                            log::debug!("✅ 预取成功: {} ({} bytes)", next_url_clone, data.len());
-                        }
-                        break;
-                    }
-                    Ok(Ok(r)) if r.status().as_u16() == 404 => {
-                        // 404说明后续片段不存在，停止预取
-                        log::debug!("⏹️ 预取停止（404）: {}", next_url_clone);
-                        break;
-                    }
-                    _ => {
-                        retries -= 1;
-                        if retries > 0 {
-                            tokio::time::sleep(Duration::from_millis(delay_ms)).await;
-                            delay_ms = (delay_ms * 2).min(2000);
-                        } else {
-                            log::debug!("❌ 预取失败: {}", next_url_clone);
-                        }
-                    }
-                }
-            }
-        });
-
-        handles.push(handle);
-    }
-
-    // 等待所有预取任务完成
-    for handle in handles {
-        let _ = handle.await;
+/// 已在视频缓存中的地址，返回本地媒体服务上的对应地址，可直接交给播放器
+#[tauri::command]
+pub async fn get_cached_media_url(
+    url: String,
+    cache_manager: State<'_, VideoCacheManager>,
+    app_handle: tauri::AppHandle,
+) -> Result<Option<String>, String> {
+    let server = app_handle
+        .try_state::<crate::media_server::MediaServer>()
+        .ok_or_else(|| "本地媒体服务未启动".to_string())?;
+    if !cache_manager.cache.contains_key(&url) {
+        return Ok(None);
     }
+    Ok(Some(server.cache_url(&url)))
 }
 
 #[tauri::command]
@@ -2018,7 +2448,7 @@ pub async fn get_douban_data(
     count: Option<i32>,
 ) -> Result<Value, String> {
     // 使用全局 Client 请求
-    let client = get_video_client();
+    let client = get_video_client(ProxyCategory::Metadata);
     let url = if data_type == "comments" {
         format!(
             "https://movie.douban.com/subject/{}/comments?status=P&sort=new_score&start={}&count={}",
@@ -2031,7 +2461,7 @@ pub async fn get_douban_data(
     };
 
     let mut headers = HeaderMap::new();
-    headers.insert(USER_AGENT, HeaderValue::from_static("Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/122.0.0.0 Safari/537.36"));
+    headers.insert(USER_AGENT, HeaderValue::from_static(DEFAULT_USER_AGENT));
     headers.insert(ACCEPT, HeaderValue::from_static("text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,image/apng,*/*;q=0.8"));
     headers.insert(
         ACCEPT_LANGUAGE,
@@ -2365,8 +2795,8 @@ pub async fn prefer_best_source_command(
     db: State<'_, crate::db::db_client::Db>,
     source_manager: State<'_, SourceIntelligenceManager>,
 ) -> Result<PreferBestSourceResponse, String> {
-    let client = get_video_client();
-    let (best_source, test_results) = prefer_best_source(client, sources.clone()).await?;
+    let client = get_video_client(ProxyCategory::Media);
+    let (best_source, test_results) = prefer_best_source(&client, sources.clone()).await?;
     persist_source_test_results(&source_manager, &db, &sources, &test_results);
 
     Ok(PreferBestSourceResponse {
@@ -2383,8 +2813,8 @@ pub async fn test_video_source_command(
     db: State<'_, crate::db::db_client::Db>,
     source_manager: State<'_, SourceIntelligenceManager>,
 ) -> Result<CoreSourceTestResult, String> {
-    let client = get_video_client();
-    let result = test_video_source(client, &m3u8_url).await?;
+    let client = get_video_client(ProxyCategory::Media);
+    let result = test_video_source(&client, &m3u8_url).await?;
 
     if let Some(source_key) = source_key.filter(|key| !key.trim().is_empty()) {
         let _ = source_manager.record_runtime_test_result_persisted(
@@ -2441,8 +2871,8 @@ pub async fn initialize_player_by_query(
         && filtered.len() > 1
         && !has_source_intelligence(&filtered, &source_manager)
     {
-        let client = get_video_client();
-        let (best, tests) = prefer_best_source(client, filtered.clone()).await?;
+        let client = get_video_client(ProxyCategory::Media);
+        let (best, tests) = prefer_best_source(&client, filtered.clone()).await?;
         persist_source_test_results(&source_manager, &db, &filtered, &tests);
         test_results = tests;
         filtered = reorder_results_with_best(&best, filtered);
@@ -2460,6 +2890,10 @@ pub async fn change_play_source(
     db: State<'_, crate::db::db_client::Db>,
     source_manager: State<'_, SourceIntelligenceManager>,
 ) -> Result<ChangePlaySourceResponse, String> {
+    // 旧源的分片不再需要预取
+    if let Some(prefetcher) = SEGMENT_PREFETCHER.get() {
+        prefetcher.cancel(PREFETCH_CLIENT);
+    }
     let ordered_sources =
         reorder_results_with_source_intelligence(request.available_sources, &source_manager);
     let requested_is_degraded = source_manager.should_skip_source(&request.new_source);
@@ -2805,8 +3239,8 @@ pub async fn initialize_player_view(
             .cloned();
 
         if let Some(url) = probe_url {
-            let client = get_video_client();
-            Some(test_video_source(client, &url).await)
+            let client = get_video_client(ProxyCategory::Media);
+            Some(test_video_source(&client, &url).await)
         } else {
             None
         }
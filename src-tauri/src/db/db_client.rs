@@ -24,10 +24,16 @@ struct VideoSourceExport {
     disabled: i32,
     is_adult: i32,
     sort_order: i32,
+    #[serde(default = "default_options_json")]
+    options_json: String,
     created_at: i64,
     updated_at: i64,
 }
 
+fn default_options_json() -> String {
+    "{}".to_string()
+}
+
 #[derive(Serialize, Deserialize)]
 struct SourceIntelligenceStatsExport {
     source_key: String,
@@ -40,9 +46,19 @@ struct SourceIntelligenceStatsExport {
     consecutive_failures: i32,
     auto_degraded: i32,
     recent_results_json: String,
+    #[serde(default = "default_circuit_state")]
+    circuit_state: String,
+    #[serde(default)]
+    circuit_trips: i32,
+    #[serde(default)]
+    next_retry_time: Option<i64>,
     updated_at: i64,
 }
 
+fn default_circuit_state() -> String {
+    "closed".to_string()
+}
+
 pub struct Db {
     conn: Arc<Mutex<Connection>>,
 }
@@ -81,13 +97,15 @@ impl Db {
                 let mut search_history = conn.prepare("SELECT * FROM search_history")?;
                 let mut skip_configs = conn.prepare("SELECT * FROM skip_configs")?;
                 let mut video_sources = conn.prepare(
-                    "SELECT source_key, name, api, detail, from_type, disabled, is_adult, sort_order, created_at, updated_at
+                    "SELECT source_key, name, api, detail, from_type, disabled, is_adult, sort_order, created_at, updated_at,
+                            options_json
                      FROM video_sources
                      ORDER BY sort_order ASC, updated_at DESC, source_key ASC",
                 )?;
                 let mut source_stats = conn.prepare(
                     "SELECT source_key, total_tests, successful_tests, total_response_time_ms, last_success_time, last_failure_time,
-                            last_available_time, consecutive_failures, auto_degraded, recent_results_json, updated_at
+                            last_available_time, consecutive_failures, auto_degraded, recent_results_json,
+                            circuit_state, circuit_trips, next_retry_time, updated_at
                      FROM source_intelligence_stats",
                 )?;
                 let play_records_iter = play_records.query_map([], |row| {
@@ -144,6 +162,7 @@ impl Db {
                         sort_order: row.get(7)?,
                         created_at: row.get(8)?,
                         updated_at: row.get(9)?,
+                        options_json: row.get(10)?,
                     })
                 })?;
                 let source_stats_iter = source_stats.query_map([], |row| {
@@ -158,7 +177,10 @@ impl Db {
                         consecutive_failures: row.get(7)?,
                         auto_degraded: row.get(8)?,
                         recent_results_json: row.get(9)?,
-                        updated_at: row.get(10)?,
+                        circuit_state: row.get(10)?,
+                        circuit_trips: row.get(11)?,
+                        next_retry_time: row.get(12)?,
+                        updated_at: row.get(13)?,
                     })
                 })?;
                 let play_records =
@@ -205,8 +227,9 @@ impl Db {
             {
                 let mut source_stmt = tx.prepare(
                     "INSERT OR REPLACE INTO video_sources
-                     (source_key, name, api, detail, from_type, disabled, is_adult, sort_order, created_at, updated_at)
-                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                     (source_key, name, api, detail, from_type, disabled, is_adult, sort_order, created_at, updated_at,
+                      options_json)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
                 )?;
                 for source in video_sources_data {
                     source_stmt.execute(params![
@@ -220,6 +243,7 @@ impl Db {
                         source.sort_order,
                         source.created_at,
                         source.updated_at,
+                        source.options_json,
                     ])?;
                 }
             }
@@ -285,8 +309,9 @@ impl Db {
                 let mut stats_stmt = tx.prepare(
                     "INSERT OR REPLACE INTO source_intelligence_stats
                      (source_key, total_tests, successful_tests, total_response_time_ms, last_success_time, last_failure_time,
-                      last_available_time, consecutive_failures, auto_degraded, recent_results_json, updated_at)
-                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                      last_available_time, consecutive_failures, auto_degraded, recent_results_json,
+                      circuit_state, circuit_trips, next_retry_time, updated_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
                 )?;
                 for stat in source_stats_data {
                     stats_stmt.execute(params![
@@ -300,6 +325,9 @@ impl Db {
                         stat.consecutive_failures,
                         stat.auto_degraded,
                         stat.recent_results_json,
+                        stat.circuit_state,
+                        stat.circuit_trips,
+                        stat.next_retry_time,
                         stat.updated_at,
                     ])?;
                 }
@@ -319,6 +347,7 @@ impl Db {
             conn.execute("DELETE FROM skip_configs", [])?;
             conn.execute("DELETE FROM content_pool", [])?;
             conn.execute("DELETE FROM source_intelligence_stats", [])?;
+            conn.execute("DELETE FROM search_result_cache", [])?;
             Ok(())
         })
     }
@@ -382,6 +411,7 @@ mod tests {
               disabled INTEGER NOT NULL DEFAULT 0,
               is_adult INTEGER NOT NULL DEFAULT 0,
               sort_order INTEGER NOT NULL DEFAULT 0,
+              options_json TEXT NOT NULL DEFAULT '{}',
               created_at INTEGER NOT NULL,
               updated_at INTEGER NOT NULL
             );
@@ -397,6 +427,9 @@ mod tests {
               consecutive_failures INTEGER NOT NULL DEFAULT 0,
               auto_degraded INTEGER NOT NULL DEFAULT 0,
               recent_results_json TEXT NOT NULL DEFAULT '[]',
+              circuit_state TEXT NOT NULL DEFAULT 'closed',
+              circuit_trips INTEGER NOT NULL DEFAULT 0,
+              next_retry_time INTEGER,
               updated_at INTEGER NOT NULL
             );
 
@@ -404,6 +437,16 @@ mod tests {
               id TEXT PRIMARY KEY,
               data TEXT
             );
+
+            CREATE TABLE search_result_cache (
+              query_key TEXT PRIMARY KEY,
+              source_fingerprint TEXT NOT NULL,
+              results_json TEXT NOT NULL,
+              result_count INTEGER NOT NULL DEFAULT 0,
+              size_bytes INTEGER NOT NULL DEFAULT 0,
+              cached_at INTEGER NOT NULL,
+              last_accessed INTEGER NOT NULL
+            );
             "#,
         )
         .expect("init schema");
@@ -45,6 +45,23 @@ pub struct SkipConfig {
     pub outro_time: f64,
 }
 
+/// 节目按（节目单地址，频道 id，开始时间）区分，不同节目单的同名频道互不覆盖
+const EPG_PROGRAMMES_SCHEMA: &str = r#"
+    CREATE TABLE IF NOT EXISTS epg_programmes (
+        channel_id TEXT NOT NULL,
+        start INTEGER NOT NULL,
+        stop INTEGER NOT NULL,
+        title TEXT NOT NULL,
+        description TEXT,
+        category TEXT,
+        epg_url TEXT NOT NULL,
+        PRIMARY KEY (epg_url, channel_id, start)
+    );
+
+    CREATE INDEX IF NOT EXISTS idx_epg_programmes_expiry
+        ON epg_programmes(stop);
+"#;
+
 pub fn init_db(app: &tauri::AppHandle) -> Connection {
     let app_dir = app
         .path()
@@ -189,6 +206,7 @@ pub fn init_db(app: &tauri::AppHandle) -> Connection {
             disabled INTEGER NOT NULL DEFAULT 0,
             is_adult INTEGER NOT NULL DEFAULT 0,
             sort_order INTEGER NOT NULL DEFAULT 0,
+            options_json TEXT NOT NULL DEFAULT '{}',
             created_at INTEGER NOT NULL,
             updated_at INTEGER NOT NULL
         );
@@ -215,6 +233,9 @@ pub fn init_db(app: &tauri::AppHandle) -> Connection {
             consecutive_failures INTEGER NOT NULL DEFAULT 0,
             auto_degraded INTEGER NOT NULL DEFAULT 0,
             recent_results_json TEXT NOT NULL DEFAULT '[]',
+            circuit_state TEXT NOT NULL DEFAULT 'closed',
+            circuit_trips INTEGER NOT NULL DEFAULT 0,
+            next_retry_time INTEGER,
             updated_at INTEGER NOT NULL
         );
 
@@ -227,6 +248,115 @@ pub fn init_db(app: &tauri::AppHandle) -> Connection {
     )
     .expect("failed to create source_intelligence_stats table");
 
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS search_result_cache (
+            query_key TEXT PRIMARY KEY,
+            source_fingerprint TEXT NOT NULL,
+            results_json TEXT NOT NULL,
+            result_count INTEGER NOT NULL DEFAULT 0,
+            size_bytes INTEGER NOT NULL DEFAULT 0,
+            cached_at INTEGER NOT NULL,
+            last_accessed INTEGER NOT NULL
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_search_result_cache_last_accessed
+            ON search_result_cache(last_accessed);
+        "#,
+    )
+    .expect("failed to create search_result_cache table");
+
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS downloads (
+            id TEXT PRIMARY KEY,
+            record_key TEXT NOT NULL,
+            source TEXT NOT NULL,
+            video_id TEXT NOT NULL,
+            episode_index INTEGER NOT NULL,
+            title TEXT NOT NULL,
+            episode_title TEXT NOT NULL DEFAULT '',
+            cover TEXT NOT NULL DEFAULT '',
+            episode_url TEXT NOT NULL,
+            status TEXT NOT NULL DEFAULT 'queued',
+            total_segments INTEGER NOT NULL DEFAULT 0,
+            completed_segments INTEGER NOT NULL DEFAULT 0,
+            downloaded_bytes INTEGER NOT NULL DEFAULT 0,
+            file_path TEXT,
+            error TEXT,
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL,
+            UNIQUE(source, video_id, episode_index)
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_downloads_status
+            ON downloads(status, created_at);
+
+        CREATE INDEX IF NOT EXISTS idx_downloads_record_key
+            ON downloads(record_key);
+        "#,
+    )
+    .expect("failed to create downloads table");
+
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS live_sources (
+            key TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            url TEXT NOT NULL,
+            ua TEXT,
+            epg TEXT,
+            disabled INTEGER NOT NULL DEFAULT 0,
+            sort_order INTEGER NOT NULL DEFAULT 0,
+            created_at INTEGER NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS live_favorites (
+            key TEXT PRIMARY KEY,
+            source_key TEXT NOT NULL,
+            channel_name TEXT NOT NULL,
+            group_name TEXT NOT NULL DEFAULT '',
+            logo TEXT NOT NULL DEFAULT '',
+            save_time INTEGER NOT NULL
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_live_favorites_source_key
+            ON live_favorites(source_key);
+        "#,
+    )
+    .expect("failed to create live tables");
+
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS epg_channels (
+            alias TEXT PRIMARY KEY,
+            channel_id TEXT NOT NULL,
+            icon TEXT,
+            epg_url TEXT NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS epg_guides (
+            epg_url TEXT PRIMARY KEY,
+            refreshed_at INTEGER NOT NULL
+        );
+        "#,
+    )
+    .expect("failed to create epg tables");
+
+    conn.execute_batch(EPG_PROGRAMMES_SCHEMA)
+        .expect("failed to create epg_programmes table");
+
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS subscription_cache (
+            subscription_key TEXT PRIMARY KEY,
+            raw_json TEXT NOT NULL,
+            fetched_at INTEGER NOT NULL
+        );
+        "#,
+    )
+    .expect("failed to create subscription cache table");
+
     if user_version < 1 {
         let has_title_column: bool = conn
             .query_row(
@@ -269,6 +399,88 @@ pub fn init_db(app: &tauri::AppHandle) -> Connection {
             .expect("failed to update database version");
     }
 
+    if user_version < 3 {
+        let has_circuit_column: bool = conn
+            .query_row(
+                "SELECT COUNT(*) FROM pragma_table_info('source_intelligence_stats') WHERE name='circuit_state'",
+                [],
+                |row| {
+                    let count: i32 = row.get(0)?;
+                    Ok(count > 0)
+                },
+            )
+            .unwrap_or(false);
+
+        if !has_circuit_column {
+            conn.execute_batch(
+                r#"
+                ALTER TABLE source_intelligence_stats ADD COLUMN circuit_state TEXT NOT NULL DEFAULT 'closed';
+                ALTER TABLE source_intelligence_stats ADD COLUMN circuit_trips INTEGER NOT NULL DEFAULT 0;
+                ALTER TABLE source_intelligence_stats ADD COLUMN next_retry_time INTEGER;
+                "#,
+            )
+            .expect("failed to add circuit columns to source_intelligence_stats table");
+
+            // 旧版本已降级的源视为熔断，冷却时间未知，下次请求即可半开试探
+            conn.execute(
+                "UPDATE source_intelligence_stats SET circuit_state = 'open', circuit_trips = 1 WHERE auto_degraded = 1",
+                [],
+            )
+            .expect("failed to migrate degraded sources");
+        }
+
+        conn.execute("PRAGMA user_version = 3", [])
+            .expect("failed to update database version");
+    }
+
+    if user_version < 4 {
+        let has_options_column: bool = conn
+            .query_row(
+                "SELECT COUNT(*) FROM pragma_table_info('video_sources') WHERE name='options_json'",
+                [],
+                |row| {
+                    let count: i32 = row.get(0)?;
+                    Ok(count > 0)
+                },
+            )
+            .unwrap_or(false);
+
+        if !has_options_column {
+            conn.execute(
+                "ALTER TABLE video_sources ADD COLUMN options_json TEXT NOT NULL DEFAULT '{}'",
+                [],
+            )
+            .expect("failed to add options_json column to video_sources table");
+        }
+
+        conn.execute("PRAGMA user_version = 4", [])
+            .expect("failed to update database version");
+    }
+
+    if user_version < 5 {
+        // 旧版节目表的主键不含 epg_url，节目单可重新拉取，直接重建
+        let keyed_by_url: bool = conn
+            .query_row(
+                "SELECT COUNT(*) FROM pragma_table_info('epg_programmes') WHERE name='epg_url' AND pk > 0",
+                [],
+                |row| {
+                    let count: i32 = row.get(0)?;
+                    Ok(count > 0)
+                },
+            )
+            .unwrap_or(false);
+
+        if !keyed_by_url {
+            conn.execute_batch("DROP TABLE IF EXISTS epg_programmes; DELETE FROM epg_guides;")
+                .expect("failed to drop epg_programmes table");
+            conn.execute_batch(EPG_PROGRAMMES_SCHEMA)
+                .expect("failed to recreate epg_programmes table");
+        }
+
+        conn.execute("PRAGMA user_version = 5", [])
+            .expect("failed to update database version");
+    }
+
     conn
 }
 
@@ -0,0 +1,340 @@
+// 离线下载任务
+use crate::db::db_client::Db;
+use rusqlite::{params, OptionalExtension, Row};
+use serde::Serialize;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const STATUS_QUEUED: &str = "queued";
+pub const STATUS_DOWNLOADING: &str = "downloading";
+pub const STATUS_PAUSED: &str = "paused";
+pub const STATUS_COMPLETED: &str = "completed";
+pub const STATUS_FAILED: &str = "failed";
+
+const DOWNLOAD_COLUMNS: &str = "id, record_key, source, video_id, episode_index, title, episode_title, cover, episode_url, status, total_segments, completed_segments, downloaded_bytes, file_path, error, created_at, updated_at";
+
+/// 一集视频的下载任务，`record_key` 与播放记录的键一致（`source+id`）
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadItem {
+    pub id: String,
+    pub record_key: String,
+    pub source: String,
+    pub video_id: String,
+    pub episode_index: i64,
+    pub title: String,
+    pub episode_title: String,
+    pub cover: String,
+    pub episode_url: String,
+    pub status: String,
+    pub total_segments: i64,
+    pub completed_segments: i64,
+    pub downloaded_bytes: i64,
+    /// 完成后的本地文件（单个视频文件或本地播放列表）
+    pub file_path: Option<String>,
+    pub error: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+/// 新建下载任务所需的信息
+pub struct NewDownload {
+    pub source: String,
+    pub video_id: String,
+    pub episode_index: i64,
+    pub title: String,
+    pub episode_title: String,
+    pub cover: String,
+    pub episode_url: String,
+}
+
+fn current_timestamp() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
+
+fn read_item(row: &Row) -> rusqlite::Result<DownloadItem> {
+    Ok(DownloadItem {
+        id: row.get(0)?,
+        record_key: row.get(1)?,
+        source: row.get(2)?,
+        video_id: row.get(3)?,
+        episode_index: row.get(4)?,
+        title: row.get(5)?,
+        episode_title: row.get(6)?,
+        cover: row.get(7)?,
+        episode_url: row.get(8)?,
+        status: row.get(9)?,
+        total_segments: row.get(10)?,
+        completed_segments: row.get(11)?,
+        downloaded_bytes: row.get(12)?,
+        file_path: row.get(13)?,
+        error: row.get(14)?,
+        created_at: row.get(15)?,
+        updated_at: row.get(16)?,
+    })
+}
+
+/// 加入下载队列；同一集已有任务时重新排队（已完成的保持不变）
+pub fn enqueue_download(db: &Db, new: &NewDownload) -> Result<DownloadItem, String> {
+    let now = current_timestamp();
+    let id = db.with_conn(|conn| {
+        let existing: Option<(String, String)> = conn
+            .query_row(
+                "SELECT id, status FROM downloads WHERE source = ?1 AND video_id = ?2 AND episode_index = ?3",
+                params![new.source, new.video_id, new.episode_index],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        match existing {
+            Some((id, status)) if status == STATUS_COMPLETED => Ok(id),
+            Some((id, status)) => {
+                // 正在下载的任务只更新地址，避免重复启动
+                let status = if status == STATUS_DOWNLOADING {
+                    STATUS_DOWNLOADING
+                } else {
+                    STATUS_QUEUED
+                };
+                conn.execute(
+                    "UPDATE downloads SET episode_url = ?1, status = ?2, error = NULL, updated_at = ?3 WHERE id = ?4",
+                    params![new.episode_url, status, now, id],
+                )?;
+                Ok(id)
+            }
+            None => {
+                let id = uuid::Uuid::new_v4().to_string();
+                conn.execute(
+                    "INSERT INTO downloads (id, record_key, source, video_id, episode_index, title, episode_title, cover, episode_url, status, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?11)",
+                    params![
+                        id,
+                        format!("{}+{}", new.source, new.video_id),
+                        new.source,
+                        new.video_id,
+                        new.episode_index,
+                        new.title,
+                        new.episode_title,
+                        new.cover,
+                        new.episode_url,
+                        STATUS_QUEUED,
+                        now,
+                    ],
+                )?;
+                Ok(id)
+            }
+        }
+    })?;
+    get_download(db, &id)?.ok_or_else(|| "下载任务不存在".to_string())
+}
+
+pub fn get_download(db: &Db, id: &str) -> Result<Option<DownloadItem>, String> {
+    db.with_conn(|conn| {
+        conn.query_row(
+            &format!("SELECT {} FROM downloads WHERE id = ?1", DOWNLOAD_COLUMNS),
+            params![id],
+            read_item,
+        )
+        .optional()
+    })
+}
+
+/// 按创建时间倒序列出下载任务，可只列出某个播放记录的
+pub fn list_downloads(db: &Db, record_key: Option<&str>) -> Result<Vec<DownloadItem>, String> {
+    db.with_conn(|conn| {
+        let sql = format!(
+            "SELECT {} FROM downloads WHERE ?1 IS NULL OR record_key = ?1 ORDER BY created_at DESC, episode_index ASC",
+            DOWNLOAD_COLUMNS
+        );
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map(params![record_key], read_item)?;
+        rows.collect()
+    })
+}
+
+/// 最早加入队列的若干任务
+pub fn queued_downloads(db: &Db, limit: usize) -> Result<Vec<String>, String> {
+    db.with_conn(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id FROM downloads WHERE status = ?1 ORDER BY created_at ASC, episode_index ASC LIMIT ?2",
+        )?;
+        let rows = stmt.query_map(params![STATUS_QUEUED, limit as i64], |row| row.get(0))?;
+        rows.collect()
+    })
+}
+
+pub fn set_download_status(
+    db: &Db,
+    id: &str,
+    status: &str,
+    error: Option<&str>,
+) -> Result<(), String> {
+    db.with_conn(|conn| {
+        conn.execute(
+            "UPDATE downloads SET status = ?1, error = ?2, updated_at = ?3 WHERE id = ?4",
+            params![status, error, current_timestamp(), id],
+        )?;
+        Ok(())
+    })
+}
+
+pub fn update_download_progress(
+    db: &Db,
+    id: &str,
+    total_segments: usize,
+    completed_segments: usize,
+    downloaded_bytes: u64,
+) -> Result<(), String> {
+    db.with_conn(|conn| {
+        conn.execute(
+            "UPDATE downloads SET total_segments = ?1, completed_segments = ?2, downloaded_bytes = ?3, updated_at = ?4 WHERE id = ?5",
+            params![
+                total_segments as i64,
+                completed_segments as i64,
+                downloaded_bytes as i64,
+                current_timestamp(),
+                id
+            ],
+        )?;
+        Ok(())
+    })
+}
+
+pub fn complete_download(db: &Db, id: &str, file_path: &str) -> Result<(), String> {
+    db.with_conn(|conn| {
+        conn.execute(
+            "UPDATE downloads SET status = ?1, file_path = ?2, error = NULL, updated_at = ?3 WHERE id = ?4",
+            params![STATUS_COMPLETED, file_path, current_timestamp(), id],
+        )?;
+        Ok(())
+    })
+}
+
+pub fn delete_download(db: &Db, id: &str) -> Result<(), String> {
+    db.with_conn(|conn| {
+        conn.execute("DELETE FROM downloads WHERE id = ?1", params![id])?;
+        Ok(())
+    })
+}
+
+/// 上次退出时未完成的下载重新排队，返回数量
+pub fn requeue_interrupted_downloads(db: &Db) -> Result<usize, String> {
+    db.with_conn(|conn| {
+        conn.execute(
+            "UPDATE downloads SET status = ?1 WHERE status = ?2",
+            params![STATUS_QUEUED, STATUS_DOWNLOADING],
+        )
+    })
+}
+
+/// 已下载完成的某一集
+pub fn find_completed_download(
+    db: &Db,
+    source: &str,
+    video_id: &str,
+    episode_index: i64,
+) -> Result<Option<DownloadItem>, String> {
+    db.with_conn(|conn| {
+        conn.query_row(
+            &format!(
+                "SELECT {} FROM downloads WHERE source = ?1 AND video_id = ?2 AND episode_index = ?3 AND status = ?4",
+                DOWNLOAD_COLUMNS
+            ),
+            params![source, video_id, episode_index, STATUS_COMPLETED],
+            read_item,
+        )
+        .optional()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::Connection;
+
+    fn setup_test_db() -> Db {
+        let conn = Connection::open_in_memory().expect("open in-memory db");
+        conn.execute_batch(
+            r#"
+            CREATE TABLE downloads (
+                id TEXT PRIMARY KEY,
+                record_key TEXT NOT NULL,
+                source TEXT NOT NULL,
+                video_id TEXT NOT NULL,
+                episode_index INTEGER NOT NULL,
+                title TEXT NOT NULL,
+                episode_title TEXT NOT NULL DEFAULT '',
+                cover TEXT NOT NULL DEFAULT '',
+                episode_url TEXT NOT NULL,
+                status TEXT NOT NULL DEFAULT 'queued',
+                total_segments INTEGER NOT NULL DEFAULT 0,
+                completed_segments INTEGER NOT NULL DEFAULT 0,
+                downloaded_bytes INTEGER NOT NULL DEFAULT 0,
+                file_path TEXT,
+                error TEXT,
+                created_at INTEGER NOT NULL,
+                updated_at INTEGER NOT NULL,
+                UNIQUE(source, video_id, episode_index)
+            );
+            "#,
+        )
+        .expect("init schema");
+        Db::new(conn)
+    }
+
+    fn episode(index: i64) -> NewDownload {
+        NewDownload {
+            source: "s1".to_string(),
+            video_id: "42".to_string(),
+            episode_index: index,
+            title: "示例".to_string(),
+            episode_title: format!("第{}集", index + 1),
+            cover: String::new(),
+            episode_url: format!("https://cdn.example.com/{}/index.m3u8", index),
+        }
+    }
+
+    #[test]
+    fn enqueue_is_idempotent_and_requeues_failed_tasks() {
+        let db = setup_test_db();
+        let first = enqueue_download(&db, &episode(0)).unwrap();
+        assert_eq!(first.record_key, "s1+42");
+        assert_eq!(first.status, STATUS_QUEUED);
+
+        set_download_status(&db, &first.id, STATUS_FAILED, Some("HTTP 404")).unwrap();
+        let again = enqueue_download(&db, &episode(0)).unwrap();
+        assert_eq!(again.id, first.id);
+        assert_eq!(again.status, STATUS_QUEUED);
+        assert_eq!(again.error, None);
+
+        complete_download(&db, &first.id, "/tmp/video.ts").unwrap();
+        let completed = enqueue_download(&db, &episode(0)).unwrap();
+        assert_eq!(completed.status, STATUS_COMPLETED);
+        assert!(find_completed_download(&db, "s1", "42", 0)
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn interrupted_downloads_are_requeued_in_order() {
+        let db = setup_test_db();
+        let first = enqueue_download(&db, &episode(0)).unwrap();
+        let second = enqueue_download(&db, &episode(1)).unwrap();
+        set_download_status(&db, &first.id, STATUS_DOWNLOADING, None).unwrap();
+        update_download_progress(&db, &first.id, 10, 4, 4096).unwrap();
+
+        assert_eq!(queued_downloads(&db, 5).unwrap(), vec![second.id.clone()]);
+        assert_eq!(requeue_interrupted_downloads(&db).unwrap(), 1);
+        assert_eq!(
+            queued_downloads(&db, 5).unwrap(),
+            vec![first.id.clone(), second.id]
+        );
+
+        let listed = list_downloads(&db, Some("s1+42")).unwrap();
+        assert_eq!(listed.len(), 2);
+        let resumed = get_download(&db, &first.id).unwrap().unwrap();
+        assert_eq!(
+            (resumed.completed_segments, resumed.downloaded_bytes),
+            (4, 4096)
+        );
+    }
+}
@@ -0,0 +1,302 @@
+// 直播节目单（XMLTV）
+use crate::db::db_client::Db;
+use quantumtv_core::{normalize_channel_name, EpgGuide, EpgProgramme};
+use rusqlite::{params, OptionalExtension, Row};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 已结束超过该时长的节目在刷新时清理
+const PROGRAMME_RETENTION_SECS: i64 = 7 * 24 * 3600;
+
+fn current_timestamp() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
+
+fn read_programme(row: &Row) -> rusqlite::Result<EpgProgramme> {
+    Ok(EpgProgramme {
+        channel: row.get(0)?,
+        start: row.get(1)?,
+        stop: row.get(2)?,
+        title: row.get(3)?,
+        description: row.get(4)?,
+        category: row.get(5)?,
+    })
+}
+
+/// 节目单上次成功写入的时间（秒），从未写入时为 `None`
+pub fn epg_guide_refreshed_at(db: &Db, epg_url: &str) -> Result<Option<i64>, String> {
+    db.with_conn(|conn| {
+        conn.query_row(
+            "SELECT refreshed_at FROM epg_guides WHERE epg_url = ?1",
+            params![epg_url],
+            |row| row.get(0),
+        )
+        .optional()
+    })
+}
+
+/// 用一份节目单替换该地址之前写入的全部数据并记录刷新时间，返回写入的节目数
+///
+/// 频道 id 与各显示名称归一化后都作为别名，多份节目单中同名频道以先写入的为准
+pub fn replace_epg_guide(db: &Db, epg_url: &str, guide: &EpgGuide) -> Result<usize, String> {
+    let now = current_timestamp();
+    db.with_conn(|conn| {
+        let tx = conn.unchecked_transaction()?;
+        tx.execute(
+            "INSERT OR REPLACE INTO epg_guides (epg_url, refreshed_at) VALUES (?1, ?2)",
+            params![epg_url, now],
+        )?;
+        tx.execute("DELETE FROM epg_channels WHERE epg_url = ?1", params![epg_url])?;
+        tx.execute(
+            "DELETE FROM epg_programmes WHERE epg_url = ?1 OR stop < ?2",
+            params![epg_url, now - PROGRAMME_RETENTION_SECS],
+        )?;
+        {
+            let mut alias_stmt = tx.prepare(
+                "INSERT OR IGNORE INTO epg_channels (alias, channel_id, icon, epg_url) VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            for channel in &guide.channels {
+                let aliases = std::iter::once(&channel.id).chain(channel.names.iter());
+                for alias in aliases {
+                    let alias = normalize_channel_name(alias);
+                    if !alias.is_empty() {
+                        alias_stmt.execute(params![alias, channel.id, channel.icon, epg_url])?;
+                    }
+                }
+            }
+            // 未在 <channel> 中声明的节目频道也按 id 登记
+            for programme in &guide.programmes {
+                let alias = normalize_channel_name(&programme.channel);
+                if !alias.is_empty() {
+                    alias_stmt.execute(params![
+                        alias,
+                        programme.channel,
+                        Option::<String>::None,
+                        epg_url
+                    ])?;
+                }
+            }
+        }
+        let mut written = 0;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT OR REPLACE INTO epg_programmes (channel_id, start, stop, title, description, category, epg_url) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )?;
+            for programme in &guide.programmes {
+                if programme.stop < now - PROGRAMME_RETENTION_SECS {
+                    continue;
+                }
+                stmt.execute(params![
+                    programme.channel,
+                    programme.start,
+                    programme.stop,
+                    programme.title,
+                    programme.description,
+                    programme.category,
+                    epg_url
+                ])?;
+                written += 1;
+            }
+        }
+        tx.commit()?;
+        Ok(written)
+    })
+}
+
+/// 按 tvg-id、频道名依次匹配节目单中的频道，返回（节目单地址，频道 id）
+pub fn resolve_epg_channel(
+    db: &Db,
+    tvg_id: Option<&str>,
+    name: &str,
+) -> Result<Option<(String, String)>, String> {
+    let candidates: Vec<String> = tvg_id
+        .into_iter()
+        .chain(std::iter::once(name))
+        .map(normalize_channel_name)
+        .filter(|alias| !alias.is_empty())
+        .collect();
+    db.with_conn(|conn| {
+        for alias in &candidates {
+            let found = conn
+                .query_row(
+                    "SELECT epg_url, channel_id FROM epg_channels WHERE alias = ?1",
+                    params![alias],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .optional()?;
+            if found.is_some() {
+                return Ok(found);
+            }
+        }
+        Ok(None)
+    })
+}
+
+/// 某份节目单中与 `[from, to)` 有交集的节目，按开始时间排序
+pub fn epg_programmes_between(
+    db: &Db,
+    epg_url: &str,
+    channel_id: &str,
+    from: i64,
+    to: i64,
+) -> Result<Vec<EpgProgramme>, String> {
+    db.with_conn(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT channel_id, start, stop, title, description, category FROM epg_programmes WHERE epg_url = ?1 AND channel_id = ?2 AND stop > ?3 AND start < ?4 ORDER BY start ASC",
+        )?;
+        let rows = stmt.query_map(params![epg_url, channel_id, from, to], read_programme)?;
+        rows.collect()
+    })
+}
+
+/// 某份节目单中正在播出与下一个节目
+pub fn epg_now_next(
+    db: &Db,
+    epg_url: &str,
+    channel_id: &str,
+    now: i64,
+) -> Result<(Option<EpgProgramme>, Option<EpgProgramme>), String> {
+    let upcoming = db.with_conn(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT channel_id, start, stop, title, description, category FROM epg_programmes WHERE epg_url = ?1 AND channel_id = ?2 AND stop > ?3 ORDER BY start ASC LIMIT 2",
+        )?;
+        let rows = stmt.query_map(params![epg_url, channel_id, now], read_programme)?;
+        rows.collect::<rusqlite::Result<Vec<_>>>()
+    })?;
+    let mut upcoming = upcoming.into_iter();
+    Ok(match upcoming.next() {
+        Some(first) if first.start <= now => (Some(first), upcoming.next()),
+        first => (None, first),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quantumtv_core::EpgChannel;
+    use rusqlite::Connection;
+
+    fn setup_test_db() -> Db {
+        let conn = Connection::open_in_memory().expect("open in-memory db");
+        conn.execute_batch(
+            r#"
+            CREATE TABLE epg_channels (
+                alias TEXT PRIMARY KEY,
+                channel_id TEXT NOT NULL,
+                icon TEXT,
+                epg_url TEXT NOT NULL
+            );
+            CREATE TABLE epg_programmes (
+                channel_id TEXT NOT NULL,
+                start INTEGER NOT NULL,
+                stop INTEGER NOT NULL,
+                title TEXT NOT NULL,
+                description TEXT,
+                category TEXT,
+                epg_url TEXT NOT NULL,
+                PRIMARY KEY (epg_url, channel_id, start)
+            );
+            CREATE TABLE epg_guides (
+                epg_url TEXT PRIMARY KEY,
+                refreshed_at INTEGER NOT NULL
+            );
+            "#,
+        )
+        .expect("init schema");
+        Db::new(conn)
+    }
+
+    fn programme(channel: &str, start: i64, stop: i64, title: &str) -> EpgProgramme {
+        EpgProgramme {
+            channel: channel.to_string(),
+            start,
+            stop,
+            title: title.to_string(),
+            description: None,
+            category: None,
+        }
+    }
+
+    fn guide(base: i64) -> EpgGuide {
+        EpgGuide {
+            channels: vec![EpgChannel {
+                id: "cctv1".to_string(),
+                names: vec!["CCTV-1 综合".to_string()],
+                icon: None,
+            }],
+            programmes: vec![
+                programme("cctv1", base, base + 1800, "新闻联播"),
+                programme("cctv1", base + 1800, base + 3600, "焦点访谈"),
+                programme("hunan", base, base + 3600, "快乐大本营"),
+            ],
+        }
+    }
+
+    #[test]
+    fn channels_resolve_by_id_or_display_name() {
+        let db = setup_test_db();
+        let base = current_timestamp();
+        let url = "https://epg.example.com/e.xml";
+        assert_eq!(epg_guide_refreshed_at(&db, url).unwrap(), None);
+        replace_epg_guide(&db, url, &guide(base)).unwrap();
+        assert!(epg_guide_refreshed_at(&db, url).unwrap().is_some());
+
+        let by_name = resolve_epg_channel(&db, None, "cctv-1 综合").unwrap();
+        assert_eq!(by_name, Some((url.to_string(), "cctv1".to_string())));
+        let by_id = resolve_epg_channel(&db, Some("HUNAN"), "湖南卫视").unwrap();
+        assert_eq!(by_id, Some((url.to_string(), "hunan".to_string())));
+        assert!(resolve_epg_channel(&db, None, "未知频道")
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn now_next_and_day_ranges() {
+        let db = setup_test_db();
+        let base = current_timestamp();
+        let url = "https://epg.example.com/e.xml";
+        assert_eq!(replace_epg_guide(&db, url, &guide(base)).unwrap(), 3);
+        // 重复刷新不会产生重复节目
+        assert_eq!(replace_epg_guide(&db, url, &guide(base)).unwrap(), 3);
+
+        let (now, next) = epg_now_next(&db, url, "cctv1", base + 60).unwrap();
+        assert_eq!(now.unwrap().title, "新闻联播");
+        assert_eq!(next.unwrap().title, "焦点访谈");
+
+        let (now, next) = epg_now_next(&db, url, "cctv1", base - 60).unwrap();
+        assert!(now.is_none());
+        assert_eq!(next.unwrap().title, "新闻联播");
+
+        let range = epg_programmes_between(&db, url, "cctv1", base + 1000, base + 2000).unwrap();
+        assert_eq!(range.len(), 2);
+    }
+
+    #[test]
+    fn guides_with_the_same_channel_id_do_not_mix() {
+        let db = setup_test_db();
+        let base = current_timestamp();
+        let first = "https://a.example.com/e.xml";
+        let second = "https://b.example.com/e.xml";
+        replace_epg_guide(&db, first, &guide(base)).unwrap();
+        let mut other = guide(base);
+        other.programmes = vec![
+            programme("cctv1", base, base + 900, "另一份节目单"),
+            programme("cctv1", base + 900, base + 1800, "另一份节目单 2"),
+        ];
+        replace_epg_guide(&db, second, &other).unwrap();
+
+        let (now, next) = epg_now_next(&db, first, "cctv1", base + 60).unwrap();
+        assert_eq!(now.unwrap().title, "新闻联播");
+        assert_eq!(next.unwrap().title, "焦点访谈");
+        let (now, _) = epg_now_next(&db, second, "cctv1", base + 60).unwrap();
+        assert_eq!(now.unwrap().title, "另一份节目单");
+
+        // 别名以先写入的节目单为准
+        let (url, _) = resolve_epg_channel(&db, None, "CCTV-1 综合")
+            .unwrap()
+            .unwrap();
+        assert_eq!(url, first);
+    }
+}
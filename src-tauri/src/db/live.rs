@@ -0,0 +1,239 @@
+// 直播订阅与收藏频道
+use crate::db::db_client::Db;
+use rusqlite::{params, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 直播订阅：一个 M3U 或 TXT 频道列表地址
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LiveSource {
+    pub key: String,
+    pub name: String,
+    pub url: String,
+    /// 拉取列表与播放时使用的 User-Agent
+    #[serde(default)]
+    pub ua: Option<String>,
+    /// 手动指定的 EPG 地址，优先于列表头声明的
+    #[serde(default)]
+    pub epg: Option<String>,
+    #[serde(default)]
+    pub disabled: bool,
+}
+
+/// 收藏的频道，`key` 为 `订阅标识+频道名`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LiveFavorite {
+    pub key: String,
+    pub source_key: String,
+    pub channel_name: String,
+    pub group_name: String,
+    pub logo: String,
+    pub save_time: i64,
+}
+
+fn current_timestamp() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
+
+pub fn live_favorite_key(source_key: &str, channel_name: &str) -> String {
+    format!("{}+{}", source_key, channel_name)
+}
+
+fn read_source(row: &Row) -> rusqlite::Result<LiveSource> {
+    Ok(LiveSource {
+        key: row.get(0)?,
+        name: row.get(1)?,
+        url: row.get(2)?,
+        ua: row.get(3)?,
+        epg: row.get(4)?,
+        disabled: row.get(5)?,
+    })
+}
+
+/// 按排序列出全部直播订阅
+pub fn list_live_sources(db: &Db) -> Result<Vec<LiveSource>, String> {
+    db.with_conn(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT key, name, url, ua, epg, disabled FROM live_sources ORDER BY sort_order ASC, created_at ASC",
+        )?;
+        let rows = stmt.query_map([], read_source)?;
+        rows.collect()
+    })
+}
+
+pub fn get_live_source(db: &Db, key: &str) -> Result<Option<LiveSource>, String> {
+    db.with_conn(|conn| {
+        conn.query_row(
+            "SELECT key, name, url, ua, epg, disabled FROM live_sources WHERE key = ?1",
+            params![key],
+            read_source,
+        )
+        .optional()
+    })
+}
+
+/// 新增或更新直播订阅，新增的排在最后
+pub fn upsert_live_source(db: &Db, source: &LiveSource) -> Result<(), String> {
+    db.with_conn(|conn| {
+        conn.execute(
+            "INSERT INTO live_sources (key, name, url, ua, epg, disabled, sort_order, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, (SELECT COALESCE(MAX(sort_order), -1) + 1 FROM live_sources), ?7)
+             ON CONFLICT(key) DO UPDATE SET name = excluded.name, url = excluded.url, ua = excluded.ua, epg = excluded.epg, disabled = excluded.disabled",
+            params![
+                source.key,
+                source.name,
+                source.url,
+                source.ua,
+                source.epg,
+                source.disabled,
+                current_timestamp()
+            ],
+        )?;
+        Ok(())
+    })
+}
+
+/// 删除直播订阅及其收藏频道
+pub fn delete_live_source(db: &Db, key: &str) -> Result<(), String> {
+    db.with_conn(|conn| {
+        conn.execute("DELETE FROM live_sources WHERE key = ?1", params![key])?;
+        conn.execute(
+            "DELETE FROM live_favorites WHERE source_key = ?1",
+            params![key],
+        )?;
+        Ok(())
+    })
+}
+
+pub fn list_live_favorites(db: &Db) -> Result<Vec<LiveFavorite>, String> {
+    db.with_conn(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT key, source_key, channel_name, group_name, logo, save_time FROM live_favorites ORDER BY save_time DESC",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(LiveFavorite {
+                key: row.get(0)?,
+                source_key: row.get(1)?,
+                channel_name: row.get(2)?,
+                group_name: row.get(3)?,
+                logo: row.get(4)?,
+                save_time: row.get(5)?,
+            })
+        })?;
+        rows.collect()
+    })
+}
+
+/// 切换频道收藏状态，返回切换后是否已收藏
+pub fn toggle_live_favorite(
+    db: &Db,
+    source_key: &str,
+    channel_name: &str,
+    group_name: &str,
+    logo: &str,
+) -> Result<bool, String> {
+    let key = live_favorite_key(source_key, channel_name);
+    db.with_conn(|conn| {
+        let removed = conn.execute("DELETE FROM live_favorites WHERE key = ?1", params![key])?;
+        if removed > 0 {
+            return Ok(false);
+        }
+        conn.execute(
+            "INSERT INTO live_favorites (key, source_key, channel_name, group_name, logo, save_time) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                key,
+                source_key,
+                channel_name,
+                group_name,
+                logo,
+                current_timestamp()
+            ],
+        )?;
+        Ok(true)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::Connection;
+
+    fn setup_test_db() -> Db {
+        let conn = Connection::open_in_memory().expect("open in-memory db");
+        conn.execute_batch(
+            r#"
+            CREATE TABLE live_sources (
+                key TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                url TEXT NOT NULL,
+                ua TEXT,
+                epg TEXT,
+                disabled INTEGER NOT NULL DEFAULT 0,
+                sort_order INTEGER NOT NULL DEFAULT 0,
+                created_at INTEGER NOT NULL
+            );
+            CREATE TABLE live_favorites (
+                key TEXT PRIMARY KEY,
+                source_key TEXT NOT NULL,
+                channel_name TEXT NOT NULL,
+                group_name TEXT NOT NULL DEFAULT '',
+                logo TEXT NOT NULL DEFAULT '',
+                save_time INTEGER NOT NULL
+            );
+            "#,
+        )
+        .expect("init schema");
+        Db::new(conn)
+    }
+
+    fn source(key: &str) -> LiveSource {
+        LiveSource {
+            key: key.to_string(),
+            name: format!("订阅{}", key),
+            url: format!("https://live.example.com/{}.m3u", key),
+            ua: None,
+            epg: None,
+            disabled: false,
+        }
+    }
+
+    #[test]
+    fn sources_keep_insertion_order_and_update_in_place() {
+        let db = setup_test_db();
+        upsert_live_source(&db, &source("b")).unwrap();
+        upsert_live_source(&db, &source("a")).unwrap();
+
+        let mut edited = source("b");
+        edited.disabled = true;
+        edited.ua = Some("okhttp/3.15".to_string());
+        upsert_live_source(&db, &edited).unwrap();
+
+        let sources = list_live_sources(&db).unwrap();
+        let keys: Vec<&str> = sources.iter().map(|s| s.key.as_str()).collect();
+        assert_eq!(keys, vec!["b", "a"]);
+        assert!(sources[0].disabled);
+        assert_eq!(sources[0].ua.as_deref(), Some("okhttp/3.15"));
+    }
+
+    #[test]
+    fn favorites_toggle_and_follow_their_source() {
+        let db = setup_test_db();
+        upsert_live_source(&db, &source("a")).unwrap();
+        assert!(toggle_live_favorite(&db, "a", "CCTV-1", "央视", "").unwrap());
+        assert!(toggle_live_favorite(&db, "a", "湖南卫视", "卫视", "").unwrap());
+        assert!(!toggle_live_favorite(&db, "a", "湖南卫视", "卫视", "").unwrap());
+
+        let favorites = list_live_favorites(&db).unwrap();
+        assert_eq!(favorites.len(), 1);
+        assert_eq!(favorites[0].key, "a+CCTV-1");
+
+        delete_live_source(&db, "a").unwrap();
+        assert!(list_live_favorites(&db).unwrap().is_empty());
+        assert!(get_live_source(&db, "a").unwrap().is_none());
+    }
+}
@@ -1,9 +1,14 @@
 pub mod db_client;
 pub mod db_handlers;
 pub mod db_init;
+pub mod download;
+pub mod epg;
 pub mod image_cache;
+pub mod live;
 pub mod page_cache;
 pub mod play_favorite;
 pub mod play_record;
 pub mod play_skip;
 pub mod search_history;
+pub mod search_result_cache;
+pub mod subscription;
@@ -0,0 +1,264 @@
+// 搜索结果持久化缓存
+use crate::db::db_client::Db;
+use quantumtv_core::types::SearchResult;
+use rusqlite::{params, OptionalExtension};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 最多保留的查询条数
+const MAX_ENTRIES: i64 = 300;
+/// 所有缓存结果 JSON 的总字节上限
+const MAX_TOTAL_BYTES: i64 = 50 * 1024 * 1024;
+/// 超过该时长的条目不再使用（7 天）
+const MAX_AGE_SECS: i64 = 7 * 24 * 3600;
+
+/// 持久化的搜索结果
+pub struct PersistedSearch {
+    pub results: Vec<SearchResult>,
+    /// 距写入时的秒数
+    pub age_secs: i64,
+}
+
+fn current_timestamp() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
+
+fn normalize_key(query: &str) -> String {
+    query.trim().to_lowercase()
+}
+
+/// 根据启用源集合计算指纹，源集合或过滤设置变化时缓存随之失效
+pub fn source_fingerprint<'a>(
+    sources: impl IntoIterator<Item = (&'a str, &'a str)>,
+    adult_filter_enabled: bool,
+) -> String {
+    let mut entries: Vec<String> = sources
+        .into_iter()
+        .map(|(key, api)| format!("{}={}", key, api))
+        .collect();
+    entries.sort();
+
+    // FNV-1a 64 位，结果在不同版本间保持稳定
+    let mut hash: u64 = 0xcbf29ce484222325;
+    let input = format!("{}|{}", adult_filter_enabled, entries.join("\n"));
+    for byte in input.as_bytes() {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    format!("{:016x}", hash)
+}
+
+/// 读取缓存；源指纹不一致或超过最大时长的条目会被删除并视为未命中
+pub fn load_cached_search(
+    db: &Db,
+    query: &str,
+    fingerprint: &str,
+) -> Result<Option<PersistedSearch>, String> {
+    let key = normalize_key(query);
+    let now = current_timestamp();
+
+    let row: Option<(String, String, i64)> = db.with_conn(|conn| {
+        conn.query_row(
+            "SELECT source_fingerprint, results_json, cached_at
+             FROM search_result_cache WHERE query_key = ?1",
+            params![key],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()
+    })?;
+
+    let Some((stored_fingerprint, results_json, cached_at)) = row else {
+        return Ok(None);
+    };
+
+    let age_secs = (now - cached_at).max(0);
+    let results = serde_json::from_str::<Vec<SearchResult>>(&results_json).ok();
+    if stored_fingerprint != fingerprint || age_secs > MAX_AGE_SECS || results.is_none() {
+        db.with_conn(|conn| {
+            conn.execute(
+                "DELETE FROM search_result_cache WHERE query_key = ?1",
+                params![key],
+            )?;
+            Ok(())
+        })?;
+        return Ok(None);
+    }
+
+    db.with_conn(|conn| {
+        conn.execute(
+            "UPDATE search_result_cache SET last_accessed = ?1 WHERE query_key = ?2",
+            params![now, key],
+        )?;
+        Ok(())
+    })?;
+
+    Ok(results.map(|results| PersistedSearch { results, age_secs }))
+}
+
+/// 写入缓存，同时清理其他指纹的条目并按 LRU 控制总量
+pub fn save_cached_search(
+    db: &Db,
+    query: &str,
+    fingerprint: &str,
+    results: &[SearchResult],
+) -> Result<(), String> {
+    let key = normalize_key(query);
+    if key.is_empty() {
+        return Ok(());
+    }
+    let results_json = serde_json::to_string(results).map_err(|e| e.to_string())?;
+    let now = current_timestamp();
+
+    db.with_conn(|conn| {
+        conn.execute(
+            "DELETE FROM search_result_cache WHERE source_fingerprint != ?1",
+            params![fingerprint],
+        )?;
+        conn.execute(
+            "INSERT OR REPLACE INTO search_result_cache
+                (query_key, source_fingerprint, results_json, result_count, size_bytes, cached_at, last_accessed)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?6)",
+            params![
+                key,
+                fingerprint,
+                results_json,
+                results.len() as i64,
+                results_json.len() as i64,
+                now
+            ],
+        )?;
+        enforce_size_cap(conn)?;
+        Ok(())
+    })
+}
+
+fn enforce_size_cap(conn: &rusqlite::Connection) -> Result<(), rusqlite::Error> {
+    loop {
+        let (count, total_bytes): (i64, i64) = conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(size_bytes), 0) FROM search_result_cache",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        if (count <= MAX_ENTRIES && total_bytes <= MAX_TOTAL_BYTES) || count <= 1 {
+            return Ok(());
+        }
+        conn.execute(
+            "DELETE FROM search_result_cache WHERE query_key = (
+                SELECT query_key FROM search_result_cache ORDER BY last_accessed ASC LIMIT 1
+             )",
+            [],
+        )?;
+    }
+}
+
+/// 清空全部持久化搜索缓存
+pub fn clear_cached_searches(db: &Db) -> Result<(), String> {
+    db.with_conn(|conn| {
+        conn.execute("DELETE FROM search_result_cache", [])?;
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::Connection;
+
+    fn setup_test_db() -> Db {
+        let conn = Connection::open_in_memory().expect("open in-memory db");
+        conn.execute_batch(
+            r#"
+            CREATE TABLE search_result_cache (
+                query_key TEXT PRIMARY KEY,
+                source_fingerprint TEXT NOT NULL,
+                results_json TEXT NOT NULL,
+                result_count INTEGER NOT NULL DEFAULT 0,
+                size_bytes INTEGER NOT NULL DEFAULT 0,
+                cached_at INTEGER NOT NULL,
+                last_accessed INTEGER NOT NULL
+            );
+            "#,
+        )
+        .expect("init search cache schema");
+        Db::new(conn)
+    }
+
+    fn result(id: &str) -> SearchResult {
+        SearchResult {
+            id: id.to_string(),
+            source: "s1".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn fingerprint_ignores_source_order() {
+        let a = source_fingerprint([("a", "https://a"), ("b", "https://b")], true);
+        let b = source_fingerprint([("b", "https://b"), ("a", "https://a")], true);
+        let c = source_fingerprint([("a", "https://a")], true);
+        let d = source_fingerprint([("a", "https://a"), ("b", "https://b")], false);
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_ne!(a, d);
+    }
+
+    #[test]
+    fn save_and_load_roundtrip() {
+        let db = setup_test_db();
+        save_cached_search(&db, " Query ", "fp1", &[result("1"), result("2")]).unwrap();
+
+        let cached = load_cached_search(&db, "query", "fp1").unwrap().unwrap();
+        assert_eq!(cached.results.len(), 2);
+        assert!(cached.age_secs <= 1);
+    }
+
+    #[test]
+    fn fingerprint_change_invalidates_entries() {
+        let db = setup_test_db();
+        save_cached_search(&db, "q1", "fp1", &[result("1")]).unwrap();
+
+        assert!(load_cached_search(&db, "q1", "fp2").unwrap().is_none());
+        // 不匹配的条目已被删除
+        assert!(load_cached_search(&db, "q1", "fp1").unwrap().is_none());
+
+        save_cached_search(&db, "q2", "fp1", &[result("1")]).unwrap();
+        save_cached_search(&db, "q3", "fp2", &[result("1")]).unwrap();
+        assert!(load_cached_search(&db, "q2", "fp1").unwrap().is_none());
+        assert!(load_cached_search(&db, "q3", "fp2").unwrap().is_some());
+    }
+
+    #[test]
+    fn size_cap_evicts_least_recently_used() {
+        let db = setup_test_db();
+        for i in 0..(MAX_ENTRIES + 5) {
+            save_cached_search(&db, &format!("q{}", i), "fp", &[result("1")]).unwrap();
+        }
+
+        let count: i64 = db
+            .with_conn(|conn| {
+                conn.query_row("SELECT COUNT(*) FROM search_result_cache", [], |row| {
+                    row.get(0)
+                })
+            })
+            .unwrap();
+        assert_eq!(count, MAX_ENTRIES);
+    }
+
+    #[test]
+    fn expired_entries_are_dropped() {
+        let db = setup_test_db();
+        save_cached_search(&db, "old", "fp", &[result("1")]).unwrap();
+        db.with_conn(|conn| {
+            conn.execute(
+                "UPDATE search_result_cache SET cached_at = cached_at - ?1",
+                params![MAX_AGE_SECS + 10],
+            )?;
+            Ok(())
+        })
+        .unwrap();
+
+        assert!(load_cached_search(&db, "old", "fp").unwrap().is_none());
+    }
+}
@@ -0,0 +1,82 @@
+// 各订阅最近一次拉取到的原始配置，合并源时无需重新请求
+use crate::db::db_client::Db;
+use rusqlite::params;
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn current_timestamp() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
+
+pub fn save_subscription_cache(
+    db: &Db,
+    subscription_key: &str,
+    raw_json: &str,
+) -> Result<(), String> {
+    db.with_conn(|conn| {
+        conn.execute(
+            "INSERT OR REPLACE INTO subscription_cache (subscription_key, raw_json, fetched_at) VALUES (?1, ?2, ?3)",
+            params![subscription_key, raw_json, current_timestamp()],
+        )?;
+        Ok(())
+    })
+}
+
+/// 全部订阅的原始配置，键为订阅标识
+pub fn load_subscription_cache(db: &Db) -> Result<HashMap<String, String>, String> {
+    db.with_conn(|conn| {
+        let mut stmt = conn.prepare("SELECT subscription_key, raw_json FROM subscription_cache")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect()
+    })
+}
+
+pub fn delete_subscription_cache(db: &Db, subscription_key: &str) -> Result<(), String> {
+    db.with_conn(|conn| {
+        conn.execute(
+            "DELETE FROM subscription_cache WHERE subscription_key = ?1",
+            params![subscription_key],
+        )?;
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::Connection;
+
+    fn setup_test_db() -> Db {
+        let conn = Connection::open_in_memory().expect("open in-memory db");
+        conn.execute_batch(
+            r#"
+            CREATE TABLE subscription_cache (
+                subscription_key TEXT PRIMARY KEY,
+                raw_json TEXT NOT NULL,
+                fetched_at INTEGER NOT NULL
+            );
+            "#,
+        )
+        .expect("init schema");
+        Db::new(conn)
+    }
+
+    #[test]
+    fn cache_is_replaced_and_deleted_per_subscription() {
+        let db = setup_test_db();
+        save_subscription_cache(&db, "a", "{\"sites\":[]}").unwrap();
+        save_subscription_cache(&db, "b", "{}").unwrap();
+        save_subscription_cache(&db, "a", "{\"api_site\":{}}").unwrap();
+
+        let cached = load_subscription_cache(&db).unwrap();
+        assert_eq!(cached.len(), 2);
+        assert_eq!(cached["a"], "{\"api_site\":{}}");
+
+        delete_subscription_cache(&db, "a").unwrap();
+        let cached = load_subscription_cache(&db).unwrap();
+        assert_eq!(cached.keys().collect::<Vec<_>>(), vec!["b"]);
+    }
+}
@@ -1,5 +1,8 @@
 mod commands;
 mod db;
+mod media_protocol;
+mod media_server;
+mod network;
 mod scheduler;
 mod storage;
 
@@ -40,6 +43,16 @@ pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_http::init())
         .plugin(tauri_plugin_opener::init())
+        // 分片、播放列表与封面经自定义协议直接交给 WebView，不走 IPC 字节数组
+        .register_asynchronous_uri_scheme_protocol(
+            media_protocol::MEDIA_PROTOCOL,
+            |ctx, request, responder| {
+                let app = ctx.app_handle().clone();
+                tauri::async_runtime::spawn(async move {
+                    responder.respond(media_protocol::handle_request(&app, &request).await);
+                });
+            },
+        )
         .setup(|app| {
             // 主窗口在 tauri.conf.json 中声明为 create:false，
             // 在此重建以挂载导航守卫（配置本身仍从 conf 读取，尺寸/标题不变）。
@@ -96,6 +109,11 @@ pub fn run() {
                 app.global_shortcut().register(boss_key_shortcut)?;
             }
             app.manage(StorageManager::new(app.handle()));
+            if let Err(error) =
+                commands::config::apply_saved_network_settings(&app.state::<StorageManager>())
+            {
+                log::warn!("应用网络设置失败: {}", error);
+            }
             app.manage(commands::video::VideoCacheManager::new());
             app.manage(commands::video::SearchCacheManager::new());
             app.manage(commands::search::SearchResultCache::new());
@@ -128,6 +146,14 @@ pub fn run() {
             }
 
             app.manage(db);
+            app.manage(commands::download::DownloadManager::new());
+            app.manage(commands::live::LiveManager::new());
+            match media_server::start(app.handle()) {
+                Ok(server) => {
+                    app.manage(server);
+                }
+                Err(error) => log::warn!("{}", error),
+            }
 
             // 初始化图片缓存管理器（共享连接）
             let image_cache_manager = ImageCacheManager::from_shared(shared_conn.clone());
@@ -147,6 +173,7 @@ pub fn run() {
 
             // 启动所有后台任务
             scheduler::start_background_tasks(app.handle().clone());
+            commands::download::restore_downloads(app.handle());
 
             Ok(())
         })
@@ -162,6 +189,10 @@ pub fn run() {
             commands::config::update_custom_categories,
             commands::config::save_admin_config_from_json,
             commands::config::update_subscription_settings,
+            commands::subscription::get_subscriptions,
+            commands::subscription::save_subscription,
+            commands::subscription::delete_subscription,
+            commands::subscription::refresh_subscriptions,
             commands::config::get_config_with_defaults,
             commands::config::admin_apply_source_config,
             commands::config::admin_apply_custom_category,
@@ -177,6 +208,7 @@ pub fn run() {
             commands::config::set_user_preferences,
             commands::config::update_user_preferences,
             commands::search::get_search_suggestions,
+            commands::search::get_spell_suggestions,
             commands::search::build_search_page_state,
             commands::search::get_search_page_bootstrap,
             commands::search::search_page_query,
@@ -201,12 +233,34 @@ pub fn run() {
             commands::video::proxy_image,
             commands::video::fetch_binary,
             commands::video::fetch_m3u8,
+            commands::video::get_cached_media_url,
             commands::video::get_douban_data,
             commands::video::get_source_categories,
+            commands::video::get_source_mirror_status,
             commands::video::get_source_videos_by_type,
             commands::video::prefer_best_source_command,
             commands::video::test_video_source_command,
             commands::video::player_tick,
+            // 离线下载
+            commands::download::enqueue_download,
+            commands::download::get_downloads,
+            commands::download::pause_download,
+            commands::download::resume_download,
+            commands::download::delete_download,
+            commands::download::get_offline_episode,
+            commands::download::get_offline_play_url,
+            commands::live::get_live_sources,
+            commands::live::save_live_source,
+            commands::live::toggle_live_source,
+            commands::live::delete_live_source,
+            commands::live::get_live_channels,
+            commands::live::report_live_url,
+            commands::live::get_live_url_status,
+            commands::live::get_live_favorites,
+            commands::live::toggle_live_favorite,
+            commands::epg::get_live_epg_now_next,
+            commands::epg::get_live_epg_day,
+            commands::epg::refresh_live_epg,
             // 版本
             commands::version::get_current_version,
             commands::version::version_for_updates,
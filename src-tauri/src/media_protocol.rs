@@ -0,0 +1,305 @@
+//! `quantumtv://` 协议：WebView 按 URL 直接读取分片、播放列表与封面，不再经 IPC 传输字节数组
+//!
+//! 地址形如 `quantumtv://localhost/segment?url=...`，Windows 与 Android 上为
+//! `http://quantumtv.localhost/segment?url=...`，前端用 `convertFileSrc(route, 'quantumtv')` 生成
+
+use crate::commands::source_intelligence::SourceIntelligenceManager;
+use crate::commands::video::{
+    load_image, load_media, load_playlist, ImageMetadata, VideoCacheManager,
+};
+use crate::db::image_cache::ImageCacheManager;
+use crate::media_server::{content_type_for, PLAYLIST_CONTENT_TYPE};
+use crate::storage::StorageManager;
+use quantumtv_core::{
+    image_content_type, parse_range, rewrite_playlist_uris, sniff_segment_format, PlaylistUriKind,
+};
+use serde_json::Value;
+use std::collections::HashMap;
+use tauri::http::{header, Method, Request, Response, StatusCode};
+use tauri::{AppHandle, Manager};
+
+pub const MEDIA_PROTOCOL: &str = "quantumtv";
+
+/// 协议返回的完整内容
+struct MediaBody {
+    data: Vec<u8>,
+    content_type: &'static str,
+    cache_control: &'static str,
+}
+
+/// 处理一次协议请求：`/segment`、`/playlist` 与 `/image`，原始地址放在 `url` 参数中
+pub async fn handle_request(app: &AppHandle, request: &Request<Vec<u8>>) -> Response<Vec<u8>> {
+    if request.method() == Method::OPTIONS {
+        return preflight();
+    }
+    let query: HashMap<String, String> =
+        url::form_urlencoded::parse(request.uri().query().unwrap_or_default().as_bytes())
+            .into_owned()
+            .collect();
+    let Some(target) = query.get("url") else {
+        return error_response(StatusCode::BAD_REQUEST, "缺少 url 参数".to_string());
+    };
+    let config = match app.state::<StorageManager>().get_data() {
+        Ok(data) => data.config,
+        Err(e) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, e),
+    };
+
+    let result = match request.uri().path().trim_matches('/') {
+        "segment" => load_segment(app, target, &config).await,
+        "playlist" => {
+            let ad_block = query.get("adblock").is_some_and(|value| value == "1");
+            let base = protocol_base(request);
+            load_protocol_playlist(app, target, ad_block, &base, &config).await
+        }
+        "image" => load_cover(app, target, &query, &config).await,
+        _ => return error_response(StatusCode::NOT_FOUND, "未知的媒体路径".to_string()),
+    };
+    match result {
+        Ok(media) => media_response(request, media),
+        Err(e) => {
+            log::debug!("媒体协议请求失败 {}: {}", target, e);
+            error_response(StatusCode::BAD_GATEWAY, e)
+        }
+    }
+}
+
+async fn load_segment(app: &AppHandle, url: &str, config: &Value) -> Result<MediaBody, String> {
+    let cache = app.state::<VideoCacheManager>();
+    let source_manager = app.state::<SourceIntelligenceManager>();
+    let (status, data) = load_media(url, "GET", None, config, &cache, &source_manager).await?;
+    if !(200..300).contains(&status) {
+        return Err(format!("HTTP {}: {}", status, url));
+    }
+    // 按内容判断类型：伪装成 .jpg 等扩展名的分片去掉前缀后可能是 fMP4
+    let content_type = match sniff_segment_format(&data) {
+        Some(format) => format.content_type(),
+        None => {
+            let path = url::Url::parse(url)
+                .map(|url| url.path().to_string())
+                .unwrap_or_default();
+            content_type_for(&path)
+        }
+    };
+    Ok(MediaBody {
+        data,
+        content_type,
+        cache_control: "private, max-age=1200",
+    })
+}
+
+async fn load_protocol_playlist(
+    app: &AppHandle,
+    url: &str,
+    ad_block: bool,
+    base: &str,
+    config: &Value,
+) -> Result<MediaBody, String> {
+    let cache = app.state::<VideoCacheManager>();
+    let source_manager = app.state::<SourceIntelligenceManager>();
+    let content = load_playlist(url, ad_block, None, config, &cache, &source_manager).await?;
+    Ok(MediaBody {
+        data: rewrite_for_protocol(&content, url, base, ad_block).into_bytes(),
+        content_type: PLAYLIST_CONTENT_TYPE,
+        cache_control: "no-cache",
+    })
+}
+
+async fn load_cover(
+    app: &AppHandle,
+    url: &str,
+    query: &HashMap<String, String>,
+    config: &Value,
+) -> Result<MediaBody, String> {
+    let metadata = ImageMetadata {
+        title: query.get("title").cloned(),
+        source_name: query.get("source_name").cloned(),
+        year: query.get("year").cloned(),
+        category: query.get("category").cloned(),
+        rating: query.get("rating").and_then(|value| value.parse().ok()),
+    };
+    let cache = app.state::<ImageCacheManager>();
+    let data = load_image(url, &metadata, config, &cache).await?;
+    Ok(MediaBody {
+        content_type: image_content_type(&data).unwrap_or("image/jpeg"),
+        data,
+        cache_control: "public, max-age=86400",
+    })
+}
+
+/// 当前请求使用的协议地址前缀，Windows 与 Android 上为 `http://quantumtv.localhost`
+fn protocol_base(request: &Request<Vec<u8>>) -> String {
+    let uri = request.uri();
+    format!(
+        "{}://{}",
+        uri.scheme_str().unwrap_or(MEDIA_PROTOCOL),
+        uri.authority().map(|a| a.as_str()).unwrap_or("localhost")
+    )
+}
+
+/// 播放列表中的子列表、分片、密钥和初始化分片都改为经本协议读取，普通播放器也能直接播放
+fn rewrite_for_protocol(content: &str, playlist_url: &str, base: &str, ad_block: bool) -> String {
+    rewrite_playlist_uris(content, playlist_url, |url, kind| match kind {
+        PlaylistUriKind::Playlist => format!(
+            "{}/playlist?url={}{}",
+            base,
+            urlencoding::encode(url),
+            if ad_block { "&adblock=1" } else { "" }
+        ),
+        PlaylistUriKind::Media => format!("{}/segment?url={}", base, urlencoding::encode(url)),
+    })
+}
+
+/// 完整内容或 Range 区间的响应，HEAD 请求只返回响应头
+fn media_response(request: &Request<Vec<u8>>, media: MediaBody) -> Response<Vec<u8>> {
+    let MediaBody {
+        mut data,
+        content_type,
+        cache_control,
+    } = media;
+    let total = data.len() as u64;
+    let range = match request
+        .headers()
+        .get(header::RANGE)
+        .and_then(|v| v.to_str().ok())
+    {
+        Some(value) => match parse_range(value, total) {
+            Ok(range) => range,
+            Err(e) => {
+                return build_response(
+                    Response::builder()
+                        .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                        .header(header::CONTENT_RANGE, format!("bytes */{}", total)),
+                    e.into_bytes(),
+                )
+            }
+        },
+        None => None,
+    };
+
+    let mut builder = Response::builder()
+        .header(header::CONTENT_TYPE, content_type)
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(header::CACHE_CONTROL, cache_control);
+    match range {
+        Some(range) => {
+            data.truncate(range.end as usize + 1);
+            data.drain(..range.start as usize);
+            builder = builder
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(header::CONTENT_RANGE, range.content_range(total));
+        }
+        None => builder = builder.status(StatusCode::OK),
+    }
+    builder = builder.header(header::CONTENT_LENGTH, data.len());
+    if request.method() == Method::HEAD {
+        data = Vec::new();
+    }
+    build_response(builder, data)
+}
+
+fn preflight() -> Response<Vec<u8>> {
+    build_response(
+        Response::builder()
+            .status(StatusCode::NO_CONTENT)
+            .header(header::ACCESS_CONTROL_ALLOW_METHODS, "GET, HEAD, OPTIONS")
+            .header(header::ACCESS_CONTROL_ALLOW_HEADERS, "Range")
+            .header(header::ACCESS_CONTROL_MAX_AGE, "86400"),
+        Vec::new(),
+    )
+}
+
+fn error_response(status: StatusCode, message: String) -> Response<Vec<u8>> {
+    build_response(
+        Response::builder()
+            .status(status)
+            .header(header::CONTENT_TYPE, "text/plain; charset=utf-8"),
+        message.into_bytes(),
+    )
+}
+
+/// 页面来源（`tauri://localhost`）与本协议不同，统一允许跨域读取
+fn build_response(builder: tauri::http::response::Builder, body: Vec<u8>) -> Response<Vec<u8>> {
+    builder
+        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+        .header(
+            header::ACCESS_CONTROL_EXPOSE_HEADERS,
+            "Content-Length, Content-Range, Accept-Ranges",
+        )
+        .body(body)
+        .unwrap_or_else(|e| {
+            let mut response = Response::new(e.to_string().into_bytes());
+            *response.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+            response
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: Method, range: Option<&str>) -> Request<Vec<u8>> {
+        let mut builder = Request::builder()
+            .method(method)
+            .uri("quantumtv://localhost/segment?url=https%3A%2F%2Fa.example.com%2Fs.ts");
+        if let Some(range) = range {
+            builder = builder.header(header::RANGE, range);
+        }
+        builder.body(Vec::new()).unwrap()
+    }
+
+    fn segment() -> MediaBody {
+        MediaBody {
+            data: (0u8..100).collect(),
+            content_type: "video/mp2t",
+            cache_control: "no-cache",
+        }
+    }
+
+    #[test]
+    fn ranges_and_head_requests_are_honoured() {
+        let full = media_response(&request(Method::GET, None), segment());
+        assert_eq!(full.status(), StatusCode::OK);
+        assert_eq!(full.body().len(), 100);
+
+        let partial = media_response(&request(Method::GET, Some("bytes=10-19")), segment());
+        assert_eq!(partial.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(partial.body(), &(10u8..20).collect::<Vec<_>>());
+        assert_eq!(partial.headers()[header::CONTENT_RANGE], "bytes 10-19/100");
+
+        let head = media_response(&request(Method::HEAD, None), segment());
+        assert!(head.body().is_empty());
+        assert_eq!(head.headers()[header::CONTENT_LENGTH], "100");
+
+        let invalid = media_response(&request(Method::GET, Some("bytes=200-")), segment());
+        assert_eq!(invalid.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(invalid.headers()[header::CONTENT_RANGE], "bytes */100");
+    }
+
+    #[test]
+    fn playlists_point_back_at_the_protocol() {
+        let master = "#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=1\nhd/index.m3u8\n";
+        let rewritten = rewrite_for_protocol(
+            master,
+            "https://a.example.com/v/master.m3u8",
+            "http://quantumtv.localhost",
+            true,
+        );
+        assert!(rewritten.contains(
+            "http://quantumtv.localhost/playlist?url=https%3A%2F%2Fa.example.com%2Fv%2Fhd%2Findex.m3u8&adblock=1"
+        ));
+
+        let media = "#EXTM3U\n#EXT-X-KEY:METHOD=AES-128,URI=\"key.bin\"\n#EXTINF:4,\ns0.ts\n";
+        let rewritten = rewrite_for_protocol(
+            media,
+            "https://a.example.com/v/index.m3u8",
+            "quantumtv://localhost",
+            false,
+        );
+        assert!(rewritten.contains(
+            "URI=\"quantumtv://localhost/segment?url=https%3A%2F%2Fa.example.com%2Fv%2Fkey.bin\""
+        ));
+        assert!(rewritten.contains(
+            "\nquantumtv://localhost/segment?url=https%3A%2F%2Fa.example.com%2Fv%2Fs0.ts"
+        ));
+    }
+}
@@ -0,0 +1,317 @@
+//! 本地媒体服务：在回环地址上以普通 URL 提供离线下载与视频缓存中的内容，支持 Range
+
+use crate::commands::download::downloads_dir;
+use crate::commands::video::VideoCacheManager;
+use axum::body::{Body, Bytes};
+use axum::extract::{Path, Query, State};
+use axum::http::{header, HeaderMap, HeaderValue, Method, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use quantumtv_core::{parse_range, ByteRange};
+use serde::Deserialize;
+use std::io::SeekFrom;
+use tauri::{AppHandle, Manager};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+const READ_CHUNK_SIZE: u64 = 64 * 1024;
+pub(crate) const PLAYLIST_CONTENT_TYPE: &str = "application/vnd.apple.mpegurl";
+
+/// 已启动的本地媒体服务
+///
+/// 路径以每次启动随机生成的令牌开头，其他本地程序和网页无法猜到
+pub struct MediaServer {
+    base_url: String,
+}
+
+impl MediaServer {
+    /// 离线下载目录中的文件
+    pub fn download_url(&self, id: &str, file: &str) -> String {
+        format!("{}/downloads/{}/{}", self.base_url, id, file)
+    }
+
+    /// 视频缓存中的条目，按原始地址查找
+    pub fn cache_url(&self, url: &str) -> String {
+        format!("{}/cache?url={}", self.base_url, urlencoding::encode(url))
+    }
+}
+
+/// 在 127.0.0.1 的随机端口上启动服务
+pub fn start(app: &AppHandle) -> Result<MediaServer, String> {
+    let listener = std::net::TcpListener::bind(("127.0.0.1", 0))
+        .and_then(|listener| listener.set_nonblocking(true).map(|_| listener))
+        .map_err(|e| format!("本地媒体服务启动失败: {}", e))?;
+    let port = listener
+        .local_addr()
+        .map_err(|e| format!("本地媒体服务启动失败: {}", e))?
+        .port();
+    let token = uuid::Uuid::new_v4().simple().to_string();
+
+    let routes = Router::new()
+        .route(
+            "/downloads/:id/:file",
+            get(serve_download).options(preflight),
+        )
+        .route("/cache", get(serve_cache).options(preflight))
+        .with_state(app.clone());
+    let router = Router::new().nest(&format!("/{}", token), routes);
+
+    tauri::async_runtime::spawn(async move {
+        let listener = match tokio::net::TcpListener::from_std(listener) {
+            Ok(listener) => listener,
+            Err(error) => {
+                log::warn!("本地媒体服务启动失败: {}", error);
+                return;
+            }
+        };
+        if let Err(error) = axum::serve(listener, router).await {
+            log::warn!("本地媒体服务已停止: {}", error);
+        }
+    });
+
+    log::info!("本地媒体服务已启动: 127.0.0.1:{}", port);
+    Ok(MediaServer {
+        base_url: format!("http://127.0.0.1:{}/{}", port, token),
+    })
+}
+
+/// 下载任务 ID 与文件名只允许简单字符，防止路径穿越
+fn is_safe_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+pub(crate) fn content_type_for(path: &str) -> &'static str {
+    let extension = path.rsplit('.').next().unwrap_or_default();
+    match extension.to_ascii_lowercase().as_str() {
+        "m3u8" => PLAYLIST_CONTENT_TYPE,
+        "mp4" | "m4s" => "video/mp4",
+        "key" => "application/octet-stream",
+        _ => "video/mp2t",
+    }
+}
+
+/// WebView 的页面来源与本服务不同，需要允许跨域读取
+fn insert_cors_headers(headers: &mut HeaderMap) {
+    headers.insert(
+        header::ACCESS_CONTROL_ALLOW_ORIGIN,
+        HeaderValue::from_static("*"),
+    );
+    headers.insert(
+        header::ACCESS_CONTROL_EXPOSE_HEADERS,
+        HeaderValue::from_static("Content-Length, Content-Range, Accept-Ranges"),
+    );
+}
+
+async fn preflight() -> Response {
+    let mut headers = HeaderMap::new();
+    insert_cors_headers(&mut headers);
+    headers.insert(
+        header::ACCESS_CONTROL_ALLOW_METHODS,
+        HeaderValue::from_static("GET, HEAD, OPTIONS"),
+    );
+    headers.insert(
+        header::ACCESS_CONTROL_ALLOW_HEADERS,
+        HeaderValue::from_static("Range"),
+    );
+    headers.insert(
+        header::ACCESS_CONTROL_MAX_AGE,
+        HeaderValue::from_static("86400"),
+    );
+    (StatusCode::NO_CONTENT, headers).into_response()
+}
+
+fn not_found() -> Response {
+    let mut headers = HeaderMap::new();
+    insert_cors_headers(&mut headers);
+    (StatusCode::NOT_FOUND, headers).into_response()
+}
+
+/// 按 Range 请求头确定响应区间，区间无效时返回错误
+fn requested_range(request: &HeaderMap, total: u64) -> Result<Option<ByteRange>, String> {
+    match request.get(header::RANGE).and_then(|v| v.to_str().ok()) {
+        Some(value) => parse_range(value, total),
+        None => Ok(None),
+    }
+}
+
+fn range_not_satisfiable(total: u64, message: String) -> Response {
+    let mut headers = HeaderMap::new();
+    insert_cors_headers(&mut headers);
+    if let Ok(value) = HeaderValue::from_str(&format!("bytes */{}", total)) {
+        headers.insert(header::CONTENT_RANGE, value);
+    }
+    (StatusCode::RANGE_NOT_SATISFIABLE, headers, message).into_response()
+}
+
+/// 完整内容或 Range 区间的响应头
+fn media_headers(content_type: &'static str, range: Option<ByteRange>, total: u64) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    insert_cors_headers(&mut headers);
+    headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+    headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+    let cache_control = if content_type == PLAYLIST_CONTENT_TYPE {
+        "no-cache"
+    } else {
+        "private, max-age=3600"
+    };
+    headers.insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_static(cache_control),
+    );
+    let length = match range {
+        Some(range) => {
+            if let Ok(value) = HeaderValue::from_str(&range.content_range(total)) {
+                headers.insert(header::CONTENT_RANGE, value);
+            }
+            range.end - range.start + 1
+        }
+        None => total,
+    };
+    headers.insert(header::CONTENT_LENGTH, HeaderValue::from(length));
+    headers
+}
+
+fn status_for(range: Option<ByteRange>) -> StatusCode {
+    if range.is_some() {
+        StatusCode::PARTIAL_CONTENT
+    } else {
+        StatusCode::OK
+    }
+}
+
+async fn serve_download(
+    State(app): State<AppHandle>,
+    Path((id, file)): Path<(String, String)>,
+    method: Method,
+    request: HeaderMap,
+) -> Response {
+    if !is_safe_name(&id) || !is_safe_name(&file) {
+        return not_found();
+    }
+    let Ok(dir) = downloads_dir(&app) else {
+        return not_found();
+    };
+    let Ok(handle) = tokio::fs::File::open(dir.join(&id).join(&file)).await else {
+        return not_found();
+    };
+    let total = match handle.metadata().await {
+        Ok(meta) if meta.is_file() => meta.len(),
+        _ => return not_found(),
+    };
+    let range = match requested_range(&request, total) {
+        Ok(range) => range,
+        Err(e) => return range_not_satisfiable(total, e),
+    };
+    let headers = media_headers(content_type_for(&file), range, total);
+    if method == Method::HEAD {
+        return (status_for(range), headers).into_response();
+    }
+
+    let (start, length) = match range {
+        Some(range) => (range.start, range.end - range.start + 1),
+        None => (0, total),
+    };
+    (status_for(range), headers, file_body(handle, start, length)).into_response()
+}
+
+/// 从文件中按块读取指定区间，大文件不会整个读入内存
+fn file_body(mut file: tokio::fs::File, start: u64, length: u64) -> Body {
+    let (tx, rx) = tokio::sync::mpsc::channel::<Result<Bytes, std::io::Error>>(4);
+
+    tokio::spawn(async move {
+        if let Err(e) = file.seek(SeekFrom::Start(start)).await {
+            let _ = tx.send(Err(e)).await;
+            return;
+        }
+        let mut remaining = length;
+        while remaining > 0 {
+            let mut buffer = vec![0; remaining.min(READ_CHUNK_SIZE) as usize];
+            match file.read(&mut buffer).await {
+                Ok(0) => return,
+                Ok(read) => {
+                    buffer.truncate(read);
+                    remaining -= read as u64;
+                    if tx.send(Ok(Bytes::from(buffer))).await.is_err() {
+                        return;
+                    }
+                }
+                Err(e) => {
+                    let _ = tx.send(Err(e)).await;
+                    return;
+                }
+            }
+        }
+    });
+
+    Body::from_stream(futures_util::stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|item| (item, rx))
+    }))
+}
+
+#[derive(Deserialize)]
+struct CacheQuery {
+    url: String,
+}
+
+async fn serve_cache(
+    State(app): State<AppHandle>,
+    Query(query): Query<CacheQuery>,
+    method: Method,
+    request: HeaderMap,
+) -> Response {
+    let Some(data) = app.state::<VideoCacheManager>().get(&query.url).await else {
+        return not_found();
+    };
+    let data = Bytes::from(data);
+    let total = data.len() as u64;
+    let range = match requested_range(&request, total) {
+        Ok(range) => range,
+        Err(e) => return range_not_satisfiable(total, e),
+    };
+    let content_type = if data.starts_with(b"#EXTM3U") {
+        PLAYLIST_CONTENT_TYPE
+    } else {
+        let path = url::Url::parse(&query.url)
+            .map(|url| url.path().to_string())
+            .unwrap_or_default();
+        content_type_for(&path)
+    };
+    let headers = media_headers(content_type, range, total);
+    if method == Method::HEAD {
+        return (status_for(range), headers).into_response();
+    }
+
+    let body = match range {
+        Some(range) => data.slice(range.start as usize..=range.end as usize),
+        None => data,
+    };
+    (status_for(range), headers, body).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_plain_names_are_served() {
+        assert!(is_safe_name("00012.ts"));
+        assert!(is_safe_name("5f0c2d3e-7a1b-4c1e-9d2f-0a1b2c3d4e5f"));
+        assert!(!is_safe_name(""));
+        assert!(!is_safe_name(".."));
+        assert!(!is_safe_name("..%2Fconfig.json"));
+        assert!(!is_safe_name("a/b.ts"));
+        assert!(!is_safe_name("a\\b.ts"));
+    }
+
+    #[test]
+    fn content_type_follows_extension() {
+        assert_eq!(content_type_for("index.m3u8"), PLAYLIST_CONTENT_TYPE);
+        assert_eq!(content_type_for("video.MP4"), "video/mp4");
+        assert_eq!(content_type_for("/hls/seg-1.ts"), "video/mp2t");
+        assert_eq!(content_type_for("/hls/seg-1"), "video/mp2t");
+    }
+}
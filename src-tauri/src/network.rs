@@ -0,0 +1,70 @@
+//! 出站 HTTP 客户端的网络设置：代理（UserPreferences.proxy）与域名解析（UserPreferences.dns）
+
+use quantumtv_core::url_safety::{redirect_policy, DEFAULT_MAX_REDIRECTS};
+use quantumtv_core::{DnsResolver, DnsSettings, ProxyCategory, ProxySettings};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, RwLock};
+
+static PROXY_SETTINGS: RwLock<Option<ProxySettings>> = RwLock::new(None);
+static SETTINGS_GENERATION: AtomicU64 = AtomicU64::new(0);
+static DNS_RESOLVER: LazyLock<Arc<DnsResolver>> = LazyLock::new(|| Arc::new(DnsResolver::new()));
+
+/// 更新代理设置，共享客户端会在下次获取时按新设置重建
+pub fn set_proxy_settings(settings: ProxySettings) -> Result<(), String> {
+    settings.validate()?;
+    dns_resolver().set_exempt_hosts(settings.proxy_hosts());
+    if let Ok(mut guard) = PROXY_SETTINGS.write() {
+        *guard = Some(settings);
+    }
+    SETTINGS_GENERATION.fetch_add(1, Ordering::SeqCst);
+    Ok(())
+}
+
+pub fn proxy_settings() -> ProxySettings {
+    PROXY_SETTINGS
+        .read()
+        .ok()
+        .and_then(|guard| guard.clone())
+        .unwrap_or_default()
+}
+
+/// 设置版本号，每次更新设置后递增，供缓存客户端的地方判断是否需要重建
+pub fn settings_generation() -> u64 {
+    SETTINGS_GENERATION.load(Ordering::SeqCst)
+}
+
+pub fn dns_resolver() -> &'static Arc<DnsResolver> {
+    &DNS_RESOLVER
+}
+
+/// 更新域名解析设置，解析器由所有客户端共享，无需重建客户端
+pub fn set_dns_settings(settings: DnsSettings) {
+    dns_resolver().configure(settings);
+}
+
+/// 同步“允许局域网源”设置，关闭时源接口与媒体请求会拦截解析到内网的域名
+pub fn set_allow_lan_sources(allow: bool) {
+    dns_resolver().set_allow_private(allow);
+}
+
+/// 按流量分类配置好代理与域名解析的客户端构建器，所有出站客户端都应由此创建
+///
+/// 源接口与媒体请求的地址来自订阅或用户输入，解析结果与每次重定向都按局域网设置检查
+pub fn client_builder(category: ProxyCategory) -> reqwest::ClientBuilder {
+    // 代理设置在 set_proxy_settings 中已校验，不会走到出错分支
+    let builder = proxy_settings()
+        .apply(reqwest::Client::builder(), category)
+        .unwrap_or_else(|e| {
+            log::error!("代理设置无效: {}", e);
+            reqwest::Client::builder()
+        });
+    if category == ProxyCategory::Metadata {
+        return builder.dns_resolver(dns_resolver().handle(false));
+    }
+    builder
+        .dns_resolver(dns_resolver().handle(true))
+        .redirect(redirect_policy(
+            Arc::clone(dns_resolver()),
+            DEFAULT_MAX_REDIRECTS,
+        ))
+}
@@ -2,8 +2,10 @@ use crate::commands::config::{
     fetch_subscription_text, format_rfc3339_utc_now, persist_source_config_values,
     strip_source_config, sync_source_intelligence_cache, validate_subscription_json,
 };
+use crate::commands::epg::{refresh_epg, EPG_REFRESH_INTERVAL};
 use crate::commands::recommendation::RecommendationEngine;
 use crate::commands::source_intelligence::SourceIntelligenceManager;
+use crate::commands::subscription::update_due_subscriptions;
 use crate::db::db_client::Db;
 use crate::db::image_cache::ImageCacheManager;
 use crate::db::page_cache::PageCacheManager;
@@ -17,24 +19,42 @@ use tauri::{Emitter, Manager};
 
 /// Spawn all background interval tasks. Call once from `.setup()`.
 pub fn start_background_tasks(app: tauri::AppHandle) {
-    eprintln!("[调度器] 已启动: 配置订阅(24h), 图像缓存(7*24h), 页面缓存(3*24h), 推荐预热(24h)");
+    eprintln!("[调度器] 已启动: 配置订阅(1h 检查), 图像缓存(7*24h), 页面缓存(3*24h), 推荐预热(24h), 直播节目单(6h)");
 
     spawn_subscription_auto_update(app.clone());
     spawn_image_cache_cleanup(app.clone());
     spawn_page_cache_cleanup(app.clone());
-    spawn_recommendation_preheat(app);
+    spawn_recommendation_preheat(app.clone());
+    spawn_epg_refresh(app);
 }
 
-/// 任务 1 — 订阅自动更新（每 24 小时）
+/// 任务 1 — 订阅自动更新（每小时检查各订阅的更新间隔，旧版单订阅每 24 小时）
 fn spawn_subscription_auto_update(app: tauri::AppHandle) {
     tauri::async_runtime::spawn(async move {
-        let mut interval = tokio::time::interval(Duration::from_secs(24 * 3600));
+        let mut interval = tokio::time::interval(Duration::from_secs(3600));
         interval.tick().await;
+        let mut hours: u64 = 0;
 
         loop {
             interval.tick().await;
-            if let Err(e) = run_subscription_update(&app).await {
-                log::warn!("[调度器:配置订阅] error: {}", e);
+            hours += 1;
+            let storage = app.state::<StorageManager>();
+            let db = app.state::<Db>();
+            let source_manager = app.state::<SourceIntelligenceManager>();
+            match update_due_subscriptions(&storage, &db, &source_manager).await {
+                Ok(Some(true)) => {
+                    let _ = app.emit("configUpdated", ());
+                    log::info!("[调度器:配置订阅] 配置更新成功");
+                }
+                Ok(Some(false)) => {}
+                Ok(None) => {
+                    if hours % 24 == 0 {
+                        if let Err(e) = run_subscription_update(&app).await {
+                            log::warn!("[调度器:配置订阅] error: {}", e);
+                        }
+                    }
+                }
+                Err(e) => log::warn!("[调度器:配置订阅] error: {}", e),
             }
         }
     });
@@ -174,3 +194,18 @@ fn spawn_recommendation_preheat(app: tauri::AppHandle) {
         }
     });
 }
+
+/// 任务 5 — 直播节目单刷新（启动后立即执行一次，之后每 6 小时；仍新鲜的节目单跳过）
+fn spawn_epg_refresh(app: tauri::AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(EPG_REFRESH_INTERVAL);
+
+        loop {
+            interval.tick().await;
+            match refresh_epg(&app, false).await {
+                Ok(written) => log::info!("[调度器:直播节目单] 写入了 {} 个节目", written),
+                Err(e) => log::warn!("[调度器:直播节目单] 错误: {}", e),
+            }
+        }
+    });
+}
@@ -33,6 +33,7 @@ import {
   Database,
   FolderOpen,
   GripVertical,
+  Layers,
   Plus,
   RefreshCw,
   Settings,
@@ -43,7 +44,7 @@ import {
 import { Suspense, useCallback, useEffect, useState } from 'react';
 import { createPortal } from 'react-dom';
 
-import { AdminConfig } from '@/lib/admin.types';
+import { AdminConfig, Subscription } from '@/lib/admin.types';
 import { appLayoutClasses } from '@/lib/ui-layout';
 
 import AnalyticsDashboard from '@/components/AnalyticsDashboard';
@@ -399,6 +400,11 @@ interface SourceHealthStats {
   last_available_time?: number | null;
   consecutive_failures: number;
   auto_degraded: boolean;
+  circuit_state?: 'closed' | 'open' | 'half_open';
+  circuit_trips?: number;
+  next_retry_time?: number | null;
+  unwrapped_segments?: number;
+  unwrapped_bytes?: number;
   recent_results: {
     success: boolean;
     response_time_ms: number;
@@ -466,14 +472,32 @@ const SortableSourceItem = ({
                   : 'bg-emerald-100 text-emerald-700 dark:bg-emerald-900/30 dark:text-emerald-300'
               }`}
             >
-              {stats.auto_degraded ? '已自动降级' : '健康'}
+              {stats.circuit_state === 'half_open'
+                ? '试探恢复中'
+                : stats.auto_degraded
+                  ? '已自动降级'
+                  : '健康'}
             </span>
+            {stats.circuit_state === 'open' && stats.next_retry_time && (
+              <span className='px-1.5 py-0.5 rounded bg-amber-100 text-amber-700 dark:bg-amber-900/30 dark:text-amber-300'>
+                {new Date(stats.next_retry_time * 1000).toLocaleTimeString()}{' '}
+                后重试
+              </span>
+            )}
             <span className='px-1.5 py-0.5 rounded bg-gray-100 text-gray-600 dark:bg-gray-800 dark:text-gray-300'>
               成功率 {stats.success_rate.toFixed(0)}%
             </span>
             <span className='px-1.5 py-0.5 rounded bg-blue-100 text-blue-700 dark:bg-blue-900/30 dark:text-blue-300'>
               均响 {stats.avg_response_time_ms || '--'}ms
             </span>
+            {!!stats.unwrapped_segments && (
+              <span
+                className='px-1.5 py-0.5 rounded bg-purple-100 text-purple-700 dark:bg-purple-900/30 dark:text-purple-300'
+                title={`已去除 ${stats.unwrapped_bytes ?? 0} 字节伪装前缀`}
+              >
+                伪装分片 {stats.unwrapped_segments}
+              </span>
+            )}
           </div>
         )}
       </div>
@@ -1158,6 +1182,344 @@ const CategoryConfig = ({
   );
 };
 
+// 订阅列表组件
+interface SubscriptionListProps {
+  config: AdminConfig | null;
+  onUpdate: (config: AdminConfig) => void;
+  showAlert: (
+    type: 'success' | 'error' | 'warning',
+    title: string,
+    message?: string,
+  ) => void;
+}
+
+const emptySubscription: Subscription = {
+  key: '',
+  name: '',
+  url: '',
+  priority: 0,
+  enabled: true,
+  updateIntervalHours: 24,
+  lastUpdate: 0,
+};
+
+const SubscriptionList = ({
+  config,
+  onUpdate,
+  showAlert,
+}: SubscriptionListProps) => {
+  const [subscriptions, setSubscriptions] = useState<Subscription[]>([]);
+  const [editingSubscription, setEditingSubscription] =
+    useState<Subscription | null>(null);
+  const [busyKey, setBusyKey] = useState<string | null>(null);
+
+  // 配置变化（包括旧版订阅面板保存）后重新读取订阅列表
+  useEffect(() => {
+    let cancelled = false;
+
+    void invoke<Subscription[]>('get_subscriptions')
+      .then((list) => {
+        if (!cancelled) {
+          setSubscriptions(list);
+        }
+      })
+      .catch((error) => {
+        console.error('读取订阅列表失败:', error);
+      });
+
+    return () => {
+      cancelled = true;
+    };
+  }, [config]);
+
+  const sortedSubscriptions = [...subscriptions].sort(
+    (a, b) => b.priority - a.priority,
+  );
+
+  const runAction = async (
+    key: string,
+    action: () => Promise<AdminConfig>,
+    successTitle: string,
+  ) => {
+    setBusyKey(key);
+    try {
+      const newConfig = await action();
+      onUpdate(newConfig);
+      showAlert(
+        'success',
+        successTitle,
+        `共 ${newConfig.SourceConfig?.length || 0} 个视频源`,
+      );
+      return true;
+    } catch (error) {
+      console.error('订阅操作失败:', error);
+      const message =
+        error instanceof Error
+          ? error.message
+          : typeof error === 'string'
+            ? error
+            : '操作失败';
+      showAlert('error', '操作失败', message);
+      return false;
+    } finally {
+      setBusyKey(null);
+    }
+  };
+
+  const handleSave = async () => {
+    if (!editingSubscription) return;
+    if (!editingSubscription.name.trim() || !editingSubscription.url.trim()) {
+      showAlert('error', '请填写订阅名称和订阅URL');
+      return;
+    }
+    const saved = await runAction(
+      editingSubscription.key || 'new',
+      () =>
+        invoke<AdminConfig>('save_subscription', {
+          subscription: editingSubscription,
+        }),
+      '保存成功',
+    );
+    if (saved) {
+      setEditingSubscription(null);
+    }
+  };
+
+  const handleToggle = (subscription: Subscription) => {
+    void runAction(
+      subscription.key,
+      () =>
+        invoke<AdminConfig>('save_subscription', {
+          subscription: { ...subscription, enabled: !subscription.enabled },
+        }),
+      subscription.enabled ? '已停用' : '已启用',
+    );
+  };
+
+  const handleRefresh = (keys?: string[]) => {
+    void runAction(
+      keys?.[0] || 'all',
+      () => invoke<AdminConfig>('refresh_subscriptions', { keys }),
+      '更新成功',
+    );
+  };
+
+  const handleDelete = (key: string) => {
+    void runAction(
+      key,
+      () => invoke<AdminConfig>('delete_subscription', { key }),
+      '删除成功',
+    );
+  };
+
+  return (
+    <div className='space-y-4'>
+      <div className='flex justify-between items-center'>
+        <p className='text-sm text-gray-600 dark:text-gray-400'>
+          共 {subscriptions.length} 个订阅，同一接口以优先级高的订阅为准
+        </p>
+        <div className='flex gap-2'>
+          <button
+            onClick={() => handleRefresh()}
+            disabled={busyKey !== null || subscriptions.length === 0}
+            className='flex items-center gap-1.5 px-3 py-1.5 bg-blue-600 hover:bg-blue-700 disabled:bg-gray-300 dark:disabled:bg-gray-600 text-white text-sm rounded-lg transition-colors'
+          >
+            <RefreshCw
+              className={`w-4 h-4 ${busyKey === 'all' ? 'animate-spin' : ''}`}
+            />
+            全部更新
+          </button>
+          <button
+            onClick={() => setEditingSubscription({ ...emptySubscription })}
+            className='flex items-center gap-1.5 px-3 py-1.5 bg-green-600 hover:bg-green-700 text-white text-sm rounded-lg transition-colors'
+          >
+            <Plus className='w-4 h-4' />
+            添加订阅
+          </button>
+        </div>
+      </div>
+
+      <div className='space-y-2'>
+        {sortedSubscriptions.map((subscription) => (
+          <div
+            key={subscription.key}
+            className='flex items-center gap-3 p-3 bg-gray-50 dark:bg-gray-700/50 rounded-lg'
+          >
+            <div className='flex-1 min-w-0'>
+              <div className='flex items-center gap-2'>
+                <span className='text-sm font-medium text-gray-900 dark:text-gray-100'>
+                  {subscription.name}
+                </span>
+                <span className='px-1.5 py-0.5 text-xs rounded bg-cyan-100 text-cyan-600 dark:bg-cyan-900/30 dark:text-cyan-400'>
+                  优先级 {subscription.priority}
+                </span>
+              </div>
+              <p className='text-xs text-gray-500 dark:text-gray-400 truncate'>
+                {subscription.url}
+              </p>
+              <p className='text-xs text-gray-500 dark:text-gray-400'>
+                {subscription.updateIntervalHours > 0
+                  ? `每 ${subscription.updateIntervalHours} 小时自动更新`
+                  : '不自动更新'}
+                {' · 最后更新: '}
+                {subscription.lastUpdate
+                  ? new Date(subscription.lastUpdate).toLocaleString('zh-CN')
+                  : '从未更新'}
+              </p>
+            </div>
+            <button
+              onClick={() => handleToggle(subscription)}
+              disabled={busyKey !== null}
+              className={`relative w-10 h-5 rounded-full transition-colors ${subscription.enabled ? 'bg-green-500' : 'bg-gray-300 dark:bg-gray-600'}`}
+            >
+              <span
+                className={`absolute top-0.5 w-4 h-4 bg-white rounded-full transition-transform ${subscription.enabled ? 'left-5' : 'left-0.5'}`}
+              />
+            </button>
+            <button
+              onClick={() => handleRefresh([subscription.key])}
+              disabled={busyKey !== null || !subscription.enabled}
+              className='p-1.5 text-gray-500 hover:text-blue-600 dark:hover:text-blue-400 transition-colors'
+            >
+              <RefreshCw
+                className={`w-4 h-4 ${busyKey === subscription.key ? 'animate-spin' : ''}`}
+              />
+            </button>
+            <button
+              onClick={() => setEditingSubscription({ ...subscription })}
+              className='p-1.5 text-gray-500 hover:text-blue-600 dark:hover:text-blue-400 transition-colors'
+            >
+              <Settings className='w-4 h-4' />
+            </button>
+            <button
+              onClick={() => handleDelete(subscription.key)}
+              disabled={busyKey !== null}
+              className='p-1.5 text-gray-500 hover:text-red-600 dark:hover:text-red-400 transition-colors'
+            >
+              <Trash2 className='w-4 h-4' />
+            </button>
+          </div>
+        ))}
+      </div>
+
+      {subscriptions.length === 0 && (
+        <div className='text-center py-8 text-gray-500 dark:text-gray-400'>
+          <Cloud className='w-12 h-12 mx-auto mb-3 opacity-50' />
+          <p>暂无订阅</p>
+        </div>
+      )}
+
+      {/* 添加/编辑订阅弹窗 */}
+      {editingSubscription && (
+        <div className='fixed inset-0 bg-black/50 backdrop-blur-sm z-50 flex items-center justify-center p-4'>
+          <div className='bg-white dark:bg-gray-800 rounded-lg shadow-xl max-w-md w-full p-6'>
+            <div className='flex justify-between items-center mb-4'>
+              <h3 className='text-lg font-semibold text-gray-900 dark:text-gray-100'>
+                {editingSubscription.key ? '编辑订阅' : '添加订阅'}
+              </h3>
+              <button onClick={() => setEditingSubscription(null)}>
+                <X className='w-5 h-5 text-gray-500' />
+              </button>
+            </div>
+            <div className='space-y-4'>
+              <div>
+                <label className='block text-sm font-medium text-gray-700 dark:text-gray-300 mb-1'>
+                  订阅名称
+                </label>
+                <input
+                  type='text'
+                  value={editingSubscription.name}
+                  onChange={(e) =>
+                    setEditingSubscription({
+                      ...editingSubscription,
+                      name: e.target.value,
+                    })
+                  }
+                  className='w-full px-3 py-2 border border-gray-300 dark:border-gray-600 rounded-lg bg-white dark:bg-gray-700 text-gray-900 dark:text-gray-100'
+                  placeholder='例如: 主订阅'
+                />
+              </div>
+              <div>
+                <label className='block text-sm font-medium text-gray-700 dark:text-gray-300 mb-1'>
+                  订阅URL
+                </label>
+                <input
+                  type='url'
+                  value={editingSubscription.url}
+                  onChange={(e) =>
+                    setEditingSubscription({
+                      ...editingSubscription,
+                      url: e.target.value,
+                    })
+                  }
+                  className='w-full px-3 py-2 border border-gray-300 dark:border-gray-600 rounded-lg bg-white dark:bg-gray-700 text-gray-900 dark:text-gray-100'
+                  placeholder='https://example.com/config.json'
+                />
+              </div>
+              <div className='flex gap-3'>
+                <div className='flex-1'>
+                  <label className='block text-sm font-medium text-gray-700 dark:text-gray-300 mb-1'>
+                    优先级
+                  </label>
+                  <input
+                    type='number'
+                    value={editingSubscription.priority}
+                    onChange={(e) =>
+                      setEditingSubscription({
+                        ...editingSubscription,
+                        priority: Number(e.target.value) || 0,
+                      })
+                    }
+                    className='w-full px-3 py-2 border border-gray-300 dark:border-gray-600 rounded-lg bg-white dark:bg-gray-700 text-gray-900 dark:text-gray-100'
+                  />
+                </div>
+                <div className='flex-1'>
+                  <label className='block text-sm font-medium text-gray-700 dark:text-gray-300 mb-1'>
+                    更新间隔（小时）
+                  </label>
+                  <input
+                    type='number'
+                    min={0}
+                    value={editingSubscription.updateIntervalHours}
+                    onChange={(e) =>
+                      setEditingSubscription({
+                        ...editingSubscription,
+                        updateIntervalHours: Math.max(
+                          0,
+                          Math.floor(Number(e.target.value) || 0),
+                        ),
+                      })
+                    }
+                    className='w-full px-3 py-2 border border-gray-300 dark:border-gray-600 rounded-lg bg-white dark:bg-gray-700 text-gray-900 dark:text-gray-100'
+                  />
+                </div>
+              </div>
+              <p className='text-xs text-gray-500 dark:text-gray-400'>
+                数值越大越优先；更新间隔为 0 时不自动更新
+              </p>
+            </div>
+            <div className='flex justify-end gap-2 mt-6'>
+              <button
+                onClick={() => setEditingSubscription(null)}
+                className='px-4 py-2 text-gray-600 dark:text-gray-400 hover:bg-gray-100 dark:hover:bg-gray-700 rounded-lg transition-colors'
+              >
+                取消
+              </button>
+              <button
+                onClick={() => void handleSave()}
+                disabled={busyKey !== null}
+                className='px-4 py-2 bg-green-600 hover:bg-green-700 text-white rounded-lg transition-colors'
+              >
+                {busyKey !== null ? '保存中' : '保存'}
+              </button>
+            </div>
+          </div>
+        </div>
+      )}
+    </div>
+  );
+};
+
 // 配置订阅组件
 interface ConfigSubscriptionProps {
   config: AdminConfig | null;
@@ -1339,6 +1701,7 @@ const ConfigSubscription = ({
         </div>
         <p className='mt-2 text-xs text-gray-500 dark:text-gray-400'>
           输入配置文件的订阅地址，要求 JSON 格式
+          {config?.Subscriptions && '；已使用订阅列表，此处修改的是默认订阅'}
         </p>
       </div>
 
@@ -1423,6 +1786,7 @@ function AdminPageContent() {
   const [expandedTabs, setExpandedTabs] = useState({
     version: true,
     configSubscription: false,
+    subscriptions: false,
     videoSource: false,
     categoryConfig: false,
     liveSource: false,
@@ -1521,6 +1885,20 @@ function AdminPageContent() {
           />
         </CollapsibleTab>
 
+        {/* 订阅列表 */}
+        <CollapsibleTab
+          title='订阅列表'
+          icon={<Layers className='w-5 h-5 text-teal-500' />}
+          isExpanded={expandedTabs.subscriptions}
+          onToggle={() => toggleTab('subscriptions')}
+        >
+          <SubscriptionList
+            config={config}
+            onUpdate={replaceConfig}
+            showAlert={showAlert}
+          />
+        </CollapsibleTab>
+
         {/* 视频源配置 */}
         <CollapsibleTab
           title='视频源配置'
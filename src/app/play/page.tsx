@@ -18,6 +18,7 @@ import {
   SearchResult,
   SourceHealthStats,
 } from '@/lib/types';
+import { fetchMedia } from '@/lib/mediaProtocol';
 import { appLayoutClasses } from '@/lib/ui-layout';
 import { cn, generateStorageKey, subscribeToDataUpdates } from '@/lib/utils';
 import { useProxyImage } from '@/hooks/useProxyImage';
@@ -96,7 +97,7 @@ function PlayPageClient() {
   const [videoCover, setVideoCover] = useState('');
   const [, setVideoDoubanId] = useState(0);
 
-  // 使用 Tauri proxy_image 命令加载封面图片
+  // 经 quantumtv:// 媒体协议加载封面图片
   const { url: proxiedCoverUrl } = useProxyImage(videoCover);
   // 当前源和ID
   const [currentSource, setCurrentSource] = useState(
@@ -507,7 +508,7 @@ function PlayPageClient() {
     return container.querySelector<HTMLElement>('.plyr--fullscreen-fallback');
   };
 
-  // 使用 Tauri fetch_binary 的 HLS.js Loader（带缓存和预取）
+  // 分片经 quantumtv:// 媒体协议读取、播放列表经 fetch_m3u8 命令的 HLS.js Loader（带缓存和预取）
   class TauriHlsJsLoader {
     context: any;
     config: any;
@@ -602,20 +603,15 @@ function PlayPageClient() {
             callbacks.onError({ code: 0, text: error.toString() }, context);
           });
       } else {
-        // 对于 TS 分片等二进制内容，继续使用 fetch_binary
-        invoke<{ status: number; body: number[] }>('fetch_binary', {
-          url,
-          method: 'GET',
-          headersOpt: null,
-        })
-          .then((result) => {
+        // 对于 TS 分片等二进制内容，经 quantumtv:// 协议读取（同样走视频缓存和预取）
+        fetchMedia('segment', url)
+          .then((data) => {
             // 先检查 this.stats 是否为 null (即 loader 是否已被销毁)
             if (!this.stats || this.stats.aborted) return;
 
             this.stats.loading.end = performance.now();
             this.stats.loading.first = this.stats.loading.start;
 
-            const data = new Uint8Array(result.body);
             this.stats.loaded = data.byteLength;
             this.stats.total = data.byteLength;
             const duration = this.stats.loading.end - this.stats.loading.start;
@@ -30,6 +30,15 @@ type SearchPageOpenResponse = {
   cacheHit: boolean;
   filterCategoriesAll: SearchFilterCategory[];
   filterCategoriesAgg: SearchFilterCategory[];
+  suggestions: SpellSuggestion[];
+  correctedQuery: string | null;
+};
+
+type SpellSuggestion = {
+  text: string;
+  score: number;
+  distance: number;
+  homophone: boolean;
 };
 
 function SearchPageClient() {
@@ -51,6 +60,11 @@ function SearchPageClient() {
   const [totalSources, setTotalSources] = useState(0);
   const [completedSources, setCompletedSources] = useState(0);
   const [useFluidSearch, setUseFluidSearch] = useState(true);
+  // 纠错建议与自动纠错后的搜索词
+  const [spellSuggestions, setSpellSuggestions] = useState<SpellSuggestion[]>(
+    [],
+  );
+  const [correctedQuery, setCorrectedQuery] = useState<string | null>(null);
   const focusScrollTimerRef = useRef<ReturnType<typeof setTimeout> | null>(
     null,
   );
@@ -270,8 +284,19 @@ function SearchPageClient() {
     setCompletedSources(0);
 
     // 调用搜索命令
-    invoke<SearchPageOpenResponse>('search_page_open', { query: qParam })
+    setSpellSuggestions([]);
+    setCorrectedQuery(null);
+
+    invoke<SearchPageOpenResponse>('search_page_open', {
+      query: qParam,
+      autoCorrect: true,
+    })
       .then((response) => {
+        // 自动纠错后，后续过滤以实际搜索词为准
+        const effectiveQuery = response?.correctedQuery || qParam;
+        currentQueryRef.current = effectiveQuery;
+        setCorrectedQuery(response?.correctedQuery || null);
+        setSpellSuggestions(response?.suggestions || []);
         setSearchHistory(response?.searchHistory || []);
         setUseFluidSearch(response?.fluidSearch ?? true);
         setFilterOptions({
@@ -284,7 +309,7 @@ function SearchPageClient() {
           aggregatedEntries: Array<[string, AggregatedGroup]>;
           filteredResults: SearchResult[];
         }>('apply_search_filter', {
-          query: qParam,
+          query: effectiveQuery,
           filterAgg: {
             source: 'all',
             title: 'all',
@@ -379,6 +404,56 @@ function SearchPageClient() {
     };
   }, [qParam, useFluidSearch, filterAgg, filterAll]);
 
+  // 缓存结果过期后由后台刷新，有差异时重新应用过滤
+  useEffect(() => {
+    if (!qParam) return;
+
+    let unlistenRefreshed: (() => void) | null = null;
+    listen<{ query: string }>('search-cache-refreshed', (event) => {
+      const query = currentQueryRef.current;
+      if (
+        !query ||
+        event.payload.query.trim().toLowerCase() !== query.trim().toLowerCase()
+      ) {
+        return;
+      }
+
+      invoke<{
+        aggregatedEntries: Array<[string, AggregatedGroup]>;
+        filteredResults: SearchResult[];
+      }>('apply_search_filter', {
+        query,
+        filterAgg: {
+          source: filterAgg.source,
+          title: filterAgg.title,
+          year: filterAgg.year,
+          year_order: filterAgg.yearOrder,
+        },
+        filterAll: {
+          source: filterAll.source,
+          title: filterAll.title,
+          year: filterAll.year,
+          year_order: filterAll.yearOrder,
+        },
+      })
+        .then((filterResponse) => {
+          setAggregatedGroups(new Map(filterResponse.aggregatedEntries));
+          setFilteredAllResults(filterResponse.filteredResults);
+        })
+        .catch(console.error);
+    })
+      .then((unlisten) => {
+        unlistenRefreshed = unlisten;
+      })
+      .catch((err) => {
+        console.error('Failed to setup cache refresh listener:', err);
+      });
+
+    return () => {
+      if (unlistenRefreshed) unlistenRefreshed();
+    };
+  }, [qParam, filterAgg, filterAll]);
+
   // eslint-disable-next-line no-undef
   const scrollPageToTop = (behavior: ScrollBehavior = 'auto') => {
     // eslint-disable-next-line no-undef
@@ -564,6 +639,11 @@ function SearchPageClient() {
                     </span>
                   )}
                 </h2>
+                {correctedQuery && (
+                  <p className='mt-1 text-sm text-gray-500 dark:text-gray-400'>
+                    未找到「{qParam}」，已为你显示「{correctedQuery}」的结果
+                  </p>
+                )}
               </div>
               {/* 筛选器 + 聚合开关 同行 */}
               <div className='mb-8 flex items-center justify-between gap-3 max-[375px]:flex-col max-[375px]:items-start min-[834px]:mb-9'>
@@ -613,6 +693,25 @@ function SearchPageClient() {
                 ) : (
                   <div className='text-center text-gray-500 py-8 dark:text-gray-400'>
                     未找到相关结果
+                    {spellSuggestions.length > 0 && (
+                      <div className='mt-3 flex flex-wrap justify-center items-center gap-2 text-sm'>
+                        <span>你是不是要找：</span>
+                        {spellSuggestions.map((item) => (
+                          <button
+                            key={item.text}
+                            type='button'
+                            className='px-2 py-1 rounded-full bg-gray-100 text-green-600 hover:bg-green-50 dark:bg-gray-800 dark:text-green-400'
+                            onClick={() =>
+                              router.push(
+                                `/search?q=${encodeURIComponent(item.text)}`,
+                              )
+                            }
+                          >
+                            {item.text}
+                          </button>
+                        ))}
+                      </div>
+                    )}
                   </div>
                 )
               ) : (
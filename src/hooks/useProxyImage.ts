@@ -1,6 +1,7 @@
-import { invoke } from '@tauri-apps/api/core';
 import { useEffect, useRef, useState } from 'react';
 
+import { fetchMedia } from '@/lib/mediaProtocol';
+
 export interface ImageMetadata {
   title?: string;
   source_name?: string;
@@ -26,18 +27,16 @@ async function getImageData(
     return pending;
   }
 
-  // 创建新请求（proxy_image 内部会自动处理 SQLite 缓存）
+  // 创建新请求（图片协议内部会自动处理 SQLite 缓存）
   const request = (async () => {
     try {
-      const imageData = await invoke<number[]>('proxy_image', {
-        url: originalUrl,
-        title: metadata?.title || null,
-        sourceName: metadata?.source_name || null,
-        year: metadata?.year || null,
-        category: metadata?.category || null,
-        rating: metadata?.rating || null,
+      const data = await fetchMedia('image', originalUrl, {
+        title: metadata?.title,
+        source_name: metadata?.source_name,
+        year: metadata?.year,
+        category: metadata?.category,
+        rating: metadata?.rating,
       });
-      const data = new Uint8Array(imageData);
       pendingRequests.delete(originalUrl);
       return data;
     } catch (err) {
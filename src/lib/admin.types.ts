@@ -4,6 +4,8 @@ export interface AdminConfig {
     AutoUpdate: boolean;
     LastCheck: string;
   };
+  // 多订阅，保存后取代 ConfigSubscribtion
+  Subscriptions?: Subscription[];
   ConfigFile: string;
   UserPreferences: {
     // 应用基础设置
@@ -22,6 +24,20 @@ export interface AdminConfig {
     fluid_search: boolean;
     player_buffer_mode: string;
     has_seen_announcement: string;
+    // 网络设置
+    proxy?: {
+      sources: string;
+      metadata: string;
+      media: string;
+      remote_dns: boolean;
+      bypass_lan: boolean;
+      bypass: string[];
+    };
+    dns?: {
+      doh: string[];
+      hosts: Record<string, string[]>;
+      cache_ttl_secs: number;
+    };
   };
   UserConfig: {
     Users: {
@@ -30,6 +46,7 @@ export interface AdminConfig {
       banned?: boolean;
       enabledApis?: string[]; // 优先级高于tags限制
       tags?: string[]; // 多 tags 取并集限制
+      allowAdult?: boolean; // false 始终过滤成人源，true 默认返回
     }[];
     Tags?: {
       name: string;
@@ -44,6 +61,19 @@ export interface AdminConfig {
     from: 'config' | 'custom';
     disabled?: boolean;
     is_adult?: boolean; // 标记是否为成人资源
+    mirrors?: string[]; // 与 api 等价的镜像地址
+    aliases?: string[]; // 合并镜像时并入的源 key，仍解析到该源
+    subscription?: string; // 来源订阅的 key，手动添加的源没有
+    mirror_strategy?: 'failover' | 'hedged';
+    request_profile?: {
+      user_agent?: string;
+      referer?: string;
+      origin?: string;
+      cookie?: string;
+      headers?: Record<string, string>;
+      cookie_hosts?: string[]; // 源站之外也发送 Cookie 与 User-Agent 的主机
+    };
+    hosts?: Record<string, string | string[]> | string[]; // 静态解析，兼容 TVBox 的 host=ip 写法
   }[];
   CustomCategories: {
     name?: string;
@@ -53,3 +83,13 @@ export interface AdminConfig {
     disabled?: boolean;
   }[];
 }
+
+export interface Subscription {
+  key: string;
+  name: string;
+  url: string;
+  priority: number; // 越大越优先，同一接口以优先级高的订阅为准
+  enabled: boolean;
+  updateIntervalHours: number; // 0 表示不自动更新
+  lastUpdate: number; // 毫秒时间戳
+}
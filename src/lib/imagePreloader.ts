@@ -1,4 +1,4 @@
-import { invoke } from '@tauri-apps/api/core';
+import { fetchMedia } from '@/lib/mediaProtocol';
 
 // 预加载队列
 const preloadQueue = new Set<string>();
@@ -58,7 +58,7 @@ async function processPreloadQueue(): Promise<void> {
     preloadInProgress.add(url);
 
     // 异步预加载，不阻塞
-    invoke<number[]>('proxy_image', { url })
+    fetchMedia('image', url)
       .then(() => {
         preloadInProgress.delete(url);
         // 继续处理队列
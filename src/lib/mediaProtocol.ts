@@ -0,0 +1,39 @@
+import { convertFileSrc } from '@tauri-apps/api/core';
+
+// Rust 端注册的 quantumtv:// 协议，分片、播放列表与封面按 URL 直接读取，不经 IPC 传输字节数组
+const MEDIA_PROTOCOL = 'quantumtv';
+
+export type MediaRoute = 'segment' | 'playlist' | 'image';
+
+type MediaParams = Record<string, string | number | boolean | null | undefined>;
+
+/**
+ * 生成媒体协议地址（Windows / Android 上为 http://quantumtv.localhost/...）
+ * @example mediaProtocolUrl('playlist', m3u8Url, { adblock: 1 })
+ */
+export function mediaProtocolUrl(
+  route: MediaRoute,
+  url: string,
+  params: MediaParams = {},
+): string {
+  const search = new URLSearchParams({ url });
+  for (const [key, value] of Object.entries(params)) {
+    if (value !== null && value !== undefined && value !== '') {
+      search.set(key, String(value));
+    }
+  }
+  return `${convertFileSrc(route, MEDIA_PROTOCOL)}?${search.toString()}`;
+}
+
+// 经媒体协议读取完整内容，失败时抛出 Rust 端返回的错误信息
+export async function fetchMedia(
+  route: MediaRoute,
+  url: string,
+  params?: MediaParams,
+): Promise<Uint8Array> {
+  const response = await fetch(mediaProtocolUrl(route, url, params));
+  if (!response.ok) {
+    throw new Error((await response.text()) || `HTTP ${response.status}`);
+  }
+  return new Uint8Array(await response.arrayBuffer());
+}
@@ -6,6 +6,8 @@ export interface ApiSite {
   name: string;
   detail?: string;
   is_adult?: boolean;
+  mirrors?: string[];
+  mirror_strategy?: 'failover' | 'hedged';
 }
 // 播放记录数据结构
 export interface PlayRecord {
@@ -47,6 +49,38 @@ export interface UserPreferences {
   fluid_search: boolean;
   player_buffer_mode: string;
   has_seen_announcement: string;
+
+  // 网络设置
+  proxy?: ProxySettings;
+  dns?: DnsSettings;
+
+  // 离线下载设置
+  download?: DownloadSettings;
+}
+
+// 出站代理设置，地址留空表示直连
+export interface ProxySettings {
+  sources: string;
+  metadata: string;
+  media: string;
+  remote_dns: boolean;
+  bypass_lan: boolean;
+  bypass: string[];
+}
+
+// 离线下载设置：同时下载的任务数、每个任务的分片并发、总限速（KB/s，0 为不限）与保存形式
+export interface DownloadSettings {
+  max_tasks: number;
+  segment_concurrency: number;
+  bandwidth_limit_kbps: number;
+  output: 'ts' | 'hls';
+}
+
+// 域名解析设置：DoH 上游、静态解析（域名 → IP 或别名，支持 * 通配）与缓存时间
+export interface DnsSettings {
+  doh: string[];
+  hosts: Record<string, string[]>;
+  cache_ttl_secs: number;
 }
 
 export interface RuntimeCustomCategory {
@@ -101,6 +135,11 @@ export interface SourceHealthStats {
   last_available_time?: number | null;
   consecutive_failures: number;
   auto_degraded: boolean;
+  circuit_state?: 'closed' | 'open' | 'half_open';
+  circuit_trips?: number;
+  next_retry_time?: number | null;
+  unwrapped_segments?: number;
+  unwrapped_bytes?: number;
   recent_results: {
     success: boolean;
     response_time_ms: number;
@@ -374,3 +413,64 @@ export interface PreferBestSourceResponse {
   best_source: SearchResult;
   test_results: Array<[string, SourceTestResult]>;
 }
+
+// 直播
+export interface LiveSource {
+  key: string;
+  name: string;
+  url: string;
+  ua?: string | null;
+  epg?: string | null;
+  disabled: boolean;
+}
+
+export interface LiveCatchup {
+  mode: string;
+  source?: string | null;
+  days?: number | null;
+}
+
+export interface LiveChannel {
+  name: string;
+  tvgId?: string | null;
+  tvgName?: string | null;
+  logo?: string | null;
+  /** 按线路健康度排序，第一个为首选线路，其余为备用线路 */
+  urls: string[];
+  catchup?: LiveCatchup | null;
+}
+
+export interface LiveGroup {
+  name: string;
+  channels: LiveChannel[];
+}
+
+export interface LivePlaylist {
+  epgUrls: string[];
+  groups: LiveGroup[];
+}
+
+export interface LiveFavorite {
+  key: string;
+  sourceKey: string;
+  channelName: string;
+  groupName: string;
+  logo: string;
+  saveTime: number;
+}
+
+export interface EpgProgramme {
+  channel: string;
+  /** UTC 秒级时间戳 */
+  start: number;
+  stop: number;
+  title: string;
+  description?: string | null;
+  category?: string | null;
+}
+
+export interface EpgNowNext {
+  name: string;
+  now?: EpgProgramme | null;
+  next?: EpgProgramme | null;
+}